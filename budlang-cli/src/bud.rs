@@ -2,11 +2,16 @@ use std::{
     borrow::Cow,
     collections::{hash_map::Entry, HashMap},
     io::{stdin, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use ariadne::{Label, Report, ReportKind};
-use budlang::{parser::ParseError, vm::Value, Bud, Error};
+use budlang::{
+    loader::FileLoader,
+    parser::ParseError,
+    vm::{bytecode, ir::optimizer::OptimizationLevel, CancellationToken, Value},
+    Bud, Error,
+};
 use clap::Parser;
 use crossterm::tty::IsTty;
 use reedline::{
@@ -18,6 +23,13 @@ use reedline::{
 struct Args {
     #[clap(short('f'), long)]
     source_file: Option<PathBuf>,
+    /// Compiles the source file to bytecode at this path instead of running
+    /// it.
+    #[clap(short('o'), long, requires("source_file"))]
+    output: Option<PathBuf>,
+    /// Optimizes compiled code.
+    #[clap(short('O'), long)]
+    optimize: bool,
     eval: Option<String>,
 }
 
@@ -46,28 +58,67 @@ macro_rules! unwrap_or_print_error {
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut bud = Bud::empty();
-
     let args = Args::parse();
-    let mut source_cache = SourceCache::default();
+
+    // Imports are resolved relative to the source file being executed, or the
+    // current directory otherwise.
+    let import_root = args
+        .source_file
+        .as_ref()
+        .and_then(|file| file.parent())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let mut bud = Bud::empty().with_loader(FileLoader::new(&import_root));
+    if args.optimize {
+        bud.set_optimization_level(OptimizationLevel::Full);
+    }
+
+    let mut source_cache = SourceCache::new(import_root);
     if let Some(file) = args.source_file {
-        let source = std::fs::read_to_string(&file)?;
-        let source_id = SourceId::File(file);
-        source_cache.register(&source_id, &source);
-        let value = unwrap_or_print_error_and_exit!(
-            bud.run_source::<Value>(&source),
-            &source_id,
-            source_cache
-        );
+        let contents = std::fs::read(&file)?;
+        let value = if bytecode::is_bytecode(&contents) {
+            // Bytecode records the names of the sources it was compiled from,
+            // which are resolved when reporting errors.
+            let source_id = SourceId::File(file);
+            unwrap_or_print_error_and_exit!(
+                bud.run_bytecode::<Value>(&contents),
+                &source_id,
+                source_cache
+            )
+        } else {
+            let source = String::from_utf8(contents)?;
+            if let Some(output) = &args.output {
+                // Bytecode may be executed from another directory, so sources
+                // are named relative to the import root.
+                let name = file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let source_id = SourceId::File(file);
+                let bytecode = unwrap_or_print_error_and_exit!(
+                    bud.compile_to_bytecode_named(name.as_str(), &source),
+                    &source_id,
+                    source_cache
+                );
+                std::fs::write(output, bytecode)?;
+                return Ok(());
+            }
+            let source_id = SourceId::File(file);
+            let name = source_cache.register(&source_id, &source);
+            unwrap_or_print_error_and_exit!(
+                bud.run_source_named::<Value>(name.as_str(), &source),
+                &source_id,
+                source_cache
+            )
+        };
         print_value(false, &value);
     }
 
     let is_interactive = stdin().is_tty();
 
     if let Some(eval) = args.eval {
-        source_cache.register(&SourceId::CommandLine, &eval);
+        let name = source_cache.register(&SourceId::CommandLine, &eval);
         let value = unwrap_or_print_error_and_exit!(
-            bud.run_source::<Value>(&eval),
+            bud.run_source_named::<Value>(name.as_str(), &eval),
             &SourceId::CommandLine,
             source_cache
         );
@@ -97,19 +148,33 @@ fn main() -> anyhow::Result<()> {
             .with_validator(Box::new(BudValidator));
         let mut counter = 1;
 
+        // While a line is being edited, Ctrl-C is handled by the line editor.
+        // While code is executing, it cancels the execution rather than
+        // exiting the process.
+        let cancellation = CancellationToken::new();
+        bud.set_cancellation(Some(cancellation.clone()));
+        ctrlc::set_handler({
+            let cancellation = cancellation.clone();
+            move || cancellation.cancel()
+        })?;
+
         loop {
             let sig = line_editor.read_line(&BudPrompt(counter));
             match sig {
                 Ok(Signal::Success(buffer)) => {
                     let source_id = SourceId::Counter(counter);
                     counter += 1;
-                    source_cache.register(&source_id, &buffer);
+                    let name = source_cache.register(&source_id, &buffer);
+                    cancellation.reset();
                     // let source = unwrap_or_print_error!(
                     //     Source::parse(source_id, &buffer, runtime.pool()),
                     //     source_cache
                     // );
-                    let result =
-                        unwrap_or_print_error!(bud.evaluate(&buffer), &source_id, source_cache);
+                    let result = unwrap_or_print_error!(
+                        bud.evaluate_named(name.as_str(), &buffer),
+                        &source_id,
+                        source_cache
+                    );
 
                     print_value(true, &result);
                 }
@@ -166,15 +231,48 @@ enum SourceId {
     CommandLine,
 }
 
-#[derive(Default)]
+impl SourceId {
+    fn name(&self) -> String {
+        match self {
+            SourceId::Counter(number) => format!("({number})"),
+            SourceId::File(path) => path.display().to_string(),
+            SourceId::CommandLine => String::from("(cli)"),
+        }
+    }
+}
+
 struct SourceCache {
     entries: HashMap<SourceId, ariadne::Source>,
+    names: HashMap<String, SourceId>,
+    import_root: PathBuf,
 }
 
 impl SourceCache {
-    pub fn register(&mut self, source: &SourceId, contents: &str) {
+    pub fn new(import_root: PathBuf) -> Self {
+        Self {
+            entries: HashMap::new(),
+            names: HashMap::new(),
+            import_root,
+        }
+    }
+
+    /// Registers `contents` as the source of `source`, returning the name to
+    /// compile the source with.
+    pub fn register(&mut self, source: &SourceId, contents: &str) -> String {
         self.entries
             .insert(source.clone(), ariadne::Source::from(contents));
+        let name = source.name();
+        self.names.insert(name.clone(), source.clone());
+        name
+    }
+
+    /// Returns the id of the source compiled with `name`. Sources that weren't
+    /// registered were loaded by an `import`.
+    pub fn resolve(&self, name: &str) -> SourceId {
+        self.names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| SourceId::File(self.import_root.join(name)))
     }
 }
 
@@ -195,11 +293,7 @@ impl ariadne::Cache<SourceId> for SourceCache {
     }
 
     fn display<'a>(&self, id: &'a SourceId) -> Option<Box<dyn std::fmt::Display + 'a>> {
-        match id {
-            SourceId::Counter(number) => Some(Box::new(format!("({number})"))),
-            SourceId::File(path) => Some(Box::new(path.display())),
-            SourceId::CommandLine => Some(Box::new("(cli)")),
-        }
+        Some(Box::new(id.name()))
     }
 }
 
@@ -208,9 +302,55 @@ fn print_error(
     cache: &mut SourceCache,
     error: Error<'_, (), Value>,
 ) -> anyhow::Result<()> {
-    if let Some(range) = error.location() {
+    // Faults are reported at the location of each stack frame that has one.
+    let located_frames = error
+        .stack_trace()
+        .iter()
+        .filter_map(|frame| {
+            let location = frame.location.as_ref()?;
+            let source = frame
+                .source
+                .as_ref()
+                .map_or_else(|| source.clone(), |name| cache.resolve(name));
+            Some((source, location.range.clone()))
+        })
+        .collect::<Vec<_>>();
+    if let Some(((source, range), callers)) = located_frames.split_last() {
+        let trace = error
+            .stack_trace()
+            .iter()
+            .rev()
+            .filter(|frame| frame.function.is_some())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut report = Report::build(ReportKind::Error, source.clone(), range.start);
+        report
+            .add_label(Label::new((source.clone(), range.clone())).with_message(error.to_string()));
+        for (source, range) in callers {
+            report.add_label(
+                Label::new((source.clone(), range.clone())).with_message("called from here"),
+            );
+        }
+        report
+            .with_note(trace)
+            .with_config(
+                ariadne::Config::default()
+                    .with_label_attach(ariadne::LabelAttach::Start)
+                    .with_underlines(false),
+            )
+            .finish()
+            .eprint(cache)?;
+    } else if let Some(range) = error.location() {
+        // Compilation errors can be located in an imported module.
+        let source = match &error {
+            Error::Compilation(err) => err
+                .source_name()
+                .map_or_else(|| source.clone(), |name| cache.resolve(name)),
+            _ => source.clone(),
+        };
         let mut report = Report::build(ReportKind::Error, source.clone(), range.start);
-        report.add_label(Label::new((source.clone(), range)).with_message(error.to_string()));
+        report.add_label(Label::new((source, range)).with_message(error.to_string()));
         report
             .with_config(
                 ariadne::Config::default()
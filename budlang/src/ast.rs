@@ -1,9 +1,11 @@
 #![allow(missing_docs, clippy::missing_panics_doc)] // TODO docs and panics for ast
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     fmt::{Debug, Display},
+    ops::Range,
+    rc::Rc,
 };
 
 use budvm::{
@@ -11,14 +13,28 @@ use budvm::{
         self, CodeBlockBuilder, CompareAction, Destination, Instruction, Label, LinkError, Literal,
         LiteralOrSource, LoopScope, Module, Scope, ScopeSymbol, ScopeSymbolKind,
     },
-    Comparison, Symbol, ValueKind,
+    Comparison, SourceLocation, Symbol, ValueKind,
 };
 
-use crate::Intrinsic;
+use crate::{
+    loader::{ImportError, SourceLoader},
+    parser::parse_module,
+    Intrinsic,
+};
 
 pub struct ExpressionTree {
     nodes: Vec<Node>,
+    locations: Vec<Option<SourceLocation>>,
     root: NodeId,
+    lambdas: RefCell<Vec<ir::Function<Intrinsic>>>,
+    lambda_captures: RefCell<Vec<Vec<Symbol>>>,
+    // True while generating the body of a function, where calls in tail
+    // position can replace the current frame.
+    tail_calls: Cell<bool>,
+    record_types: RecordTypes,
+    // The named function this tree is the body of. Within lambdas, `this`
+    // refers to this function.
+    function: Option<Symbol>,
 }
 
 impl ExpressionTree {
@@ -32,15 +48,163 @@ impl ExpressionTree {
         self.nodes.get(id.0).expect("invalid node id")
     }
 
-    fn root(&self) -> &Node {
-        self.node(self.root)
+    pub fn generate_code(
+        &self,
+        block: &mut CodeBlockBuilder<Intrinsic>,
+    ) -> Result<(), CompilationError> {
+        self.generate_node(self.root, Destination::Return, block)
     }
 
-    pub fn generate_code(
+    /// Generates the code for the body of a function. Unlike
+    /// [`Self::generate_code()`], calls in tail position replace the
+    /// function's frame instead of returning to it.
+    pub fn generate_function_code(
         &self,
         block: &mut CodeBlockBuilder<Intrinsic>,
     ) -> Result<(), CompilationError> {
-        self.root().generate_code(Destination::Return, block, self)
+        self.copy_assigned_arguments(block);
+        let previous = self.tail_calls.replace(true);
+        let result = self.generate_code(block);
+        self.tail_calls.set(previous);
+        result
+    }
+
+    /// Copies each argument that is assigned to within this tree into a
+    /// variable of the same name. Otherwise, assigning to an argument within a
+    /// block would introduce a variable that is only visible within it.
+    fn copy_assigned_arguments(&self, block: &mut CodeBlockBuilder<Intrinsic>) {
+        for node in &self.nodes {
+            if let Node::Assign(assign) = node {
+                if let Node::Identifier(name) = self.node(assign.target) {
+                    if let Some(ScopeSymbol::Argument(arg)) = block.lookup(name) {
+                        let value = LiteralOrSource::Argument(arg.clone());
+                        let variable = block.variable_index_from_name(name);
+                        block.push(Instruction::Load { value, variable });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns true if a call storing its result in `destination` can replace
+    /// the current frame. Calls within a `try` block are never tail calls,
+    /// because the frame's exception handlers must remain registered.
+    fn is_tail_call(
+        &self,
+        destination: &Destination,
+        operations: &CodeBlockBuilder<Intrinsic>,
+    ) -> bool {
+        self.tail_calls.get()
+            && matches!(destination, Destination::Return)
+            && operations.try_depth() == 0
+    }
+
+    /// Generates the code for node `id`, recording the node's source location
+    /// for each instruction generated.
+    fn generate_node(
+        &self,
+        id: NodeId,
+        destination: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+    ) -> Result<(), CompilationError> {
+        let previous_location = self.enter_node(id, operations);
+        let result = self
+            .node(id)
+            .generate_code(destination, operations, self)
+            .map_err(|err| self.locate_error(id, err, operations));
+        operations.set_location(previous_location);
+        result
+    }
+
+    /// Generates the code for node `id` within its own block scope. Variables
+    /// first assigned within the node aren't visible afterwards.
+    fn generate_scoped_node(
+        &self,
+        id: NodeId,
+        destination: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+    ) -> Result<(), CompilationError> {
+        operations.begin_scope();
+        let result = self.generate_node(id, destination, operations);
+        operations.end_scope();
+        result
+    }
+
+    /// Converts node `id` into a [`LiteralOrSource`], recording the node's
+    /// source location for each instruction generated.
+    fn node_value_or_source(
+        &self,
+        id: NodeId,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+    ) -> Result<LiteralOrSource, CompilationError> {
+        let previous_location = self.enter_node(id, operations);
+        let result = self
+            .node(id)
+            .to_value_or_source(operations, self)
+            .map_err(|err| self.locate_error(id, err, operations));
+        operations.set_location(previous_location);
+        result
+    }
+
+    /// Returns the source range of node `id`, if known.
+    #[must_use]
+    pub fn location(&self, id: NodeId) -> Option<Range<usize>> {
+        self.locations
+            .get(id.0)
+            .and_then(Option::as_ref)
+            .map(|location| location.range.clone())
+    }
+
+    /// Attaches the location of node `id` to `err` if it doesn't already have
+    /// one. Errors are located by the innermost node that has a location.
+    fn locate_error(
+        &self,
+        id: NodeId,
+        err: CompilationError,
+        operations: &CodeBlockBuilder<Intrinsic>,
+    ) -> CompilationError {
+        match self.location(id) {
+            Some(range) => err.located(operations.source(), range),
+            None => err,
+        }
+    }
+
+    fn enter_node(
+        &self,
+        id: NodeId,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+    ) -> Option<SourceLocation> {
+        match self.locations.get(id.0).and_then(Option::as_ref) {
+            Some(location) => operations.set_location(Some(location.clone())),
+            None => operations.location().cloned(),
+        }
+    }
+
+    /// Returns the functions generated for each lambda encountered by
+    /// [`Self::generate_code()`]. They must be linked in the same module as
+    /// the function this tree was generated into.
+    pub fn take_lambdas(&self) -> Vec<ir::Function<Intrinsic>> {
+        std::mem::take(&mut *self.lambdas.borrow_mut())
+    }
+
+    /// Returns true if `path` names an enum variant that has no fields.
+    fn is_unit_variant(&self, path: &Symbol) -> bool {
+        self.record_types
+            .resolve(path)
+            .map_or(false, RecordType::is_unit_variant)
+    }
+
+    fn lift_lambda(&self, body: ir::CodeBlock<Intrinsic>) -> Symbol {
+        let mut lambdas = self.lambdas.borrow_mut();
+        // Lambdas are named after the function they are declared within, as
+        // every function of a module is resolvable while linking.
+        let name = match &self.function {
+            Some(function) => format!("{function}.__lambda{}", lambdas.len()),
+            None => format!("__lambda{}", lambdas.len()),
+        };
+        let name = Symbol::from(name.as_str());
+        lambdas.push(ir::Function::new(name.clone(), body));
+        name
     }
 }
 
@@ -73,6 +237,7 @@ impl<'a> ExpressionTreeNode<'a> {
 }
 
 impl<'a> Debug for ExpressionTreeNode<'a> {
+    #[allow(clippy::too_many_lines)] // One arm per node.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.tree.node(self.id) {
             Node::If(if_op) => f
@@ -91,6 +256,10 @@ impl<'a> Debug for ExpressionTreeNode<'a> {
                 .debug_struct("Not")
                 .field("expr", &self.node(op.expr))
                 .finish(),
+            Node::Negate(expr) => f
+                .debug_struct("Negate")
+                .field("expr", &self.node(*expr))
+                .finish(),
             Node::Convert(op) => f
                 .debug_struct("Convert")
                 .field("expr", &self.node(op.expr))
@@ -133,6 +302,11 @@ impl<'a> Debug for ExpressionTreeNode<'a> {
             }
             Node::Literal(literal) => Debug::fmt(literal, f),
             Node::Identifier(identifier) => Debug::fmt(identifier, f),
+            Node::Index(index) => f
+                .debug_struct("Index")
+                .field("target", &self.node(index.target))
+                .field("index", &self.node(index.index))
+                .finish(),
             Node::Call(call) => {
                 let target = call.target.map(|node| self.node(node));
                 let name = if let Some(symbol) = &call.name {
@@ -155,6 +329,11 @@ impl<'a> Debug for ExpressionTreeNode<'a> {
                 let value = self.node(*value);
                 f.debug_struct("Return").field("value", &value).finish()
             }
+            Node::Lambda(lambda) => f
+                .debug_struct("Lambda")
+                .field("args", &lambda.args)
+                .field("body", &self.node(lambda.body))
+                .finish(),
             Node::Break(value) => {
                 let mut s = f.debug_struct("Break");
                 if let Some(name) = &value.name {
@@ -172,6 +351,40 @@ impl<'a> Debug for ExpressionTreeNode<'a> {
                 }
                 s.finish()
             }
+            Node::Try(try_expr) => f
+                .debug_struct("Try")
+                .field("body", &self.node(try_expr.body))
+                .field(
+                    "catch",
+                    &try_expr
+                        .catch
+                        .as_ref()
+                        .map(|catch| (&catch.name, self.node(catch.body))),
+                )
+                .field("finally", &try_expr.finally.map(|node| self.node(node)))
+                .finish(),
+            Node::Throw(value) => f
+                .debug_struct("Throw")
+                .field("value", &self.node(*value))
+                .finish(),
+            Node::Match(match_expr) => {
+                let arms = match_expr
+                    .arms
+                    .iter()
+                    .map(|arm| {
+                        (
+                            &arm.pattern,
+                            arm.guard.map(|guard| self.node(guard)),
+                            self.node(arm.body),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                f.debug_struct("Match")
+                    .field("subject", &self.node(match_expr.subject))
+                    .field("arms", &arms)
+                    .field("else", &match_expr.else_block.map(|node| self.node(node)))
+                    .finish()
+            }
         }
     }
 }
@@ -189,12 +402,18 @@ enum Node {
     Identifier(Symbol),
     Map(Map),
     List(List),
+    Index(Index),
     // Lookup(Lookup),
     Call(Call),
+    Lambda(Lambda),
     Return(NodeId),
     Loop(Loop),
     Break(Break),
     Continue(Continue),
+    Try(Try),
+    Throw(NodeId),
+    Match(Match),
+    Negate(NodeId),
 }
 
 impl Node {
@@ -210,14 +429,25 @@ impl Node {
             Node::Not(not) => not.generate_code(result, operations, tree),
             Node::Convert(convert) => convert.generate_code(result, operations, tree),
             Node::Block(statements) => {
+                let statements = &statements.0;
+                // The last statement of a block being returned is in tail
+                // position, and can return its value directly.
+                if let (Destination::Return, Some((last, rest))) =
+                    (&result, statements.split_last())
+                {
+                    for statement in rest {
+                        let result = operations.new_temporary_variable();
+                        tree.generate_node(*statement, Destination::Variable(result), operations)?;
+                    }
+                    return tree.generate_node(*last, result, operations);
+                }
                 let mut last_result_var = None;
-                for statement in &statements.0 {
-                    let statement = tree.node(*statement);
+                for statement in statements {
                     let result = operations.new_temporary_variable();
-                    statement.generate_code(
+                    tree.generate_node(
+                        *statement,
                         Destination::Variable(result.clone()),
                         operations,
-                        tree,
                     )?;
                     last_result_var = Some(result);
                 }
@@ -233,17 +463,66 @@ impl Node {
                     .store_into_destination(LiteralOrSource::Literal(literal.clone()), result);
                 Ok(())
             }
-            Node::Identifier(identifier) => operations
-                .load_from_symbol(identifier, result)
-                .map_err(CompilationError::from),
+            Node::Identifier(identifier) => {
+                if tree.is_unit_variant(identifier) {
+                    // Variants without fields are values rather than
+                    // constructors.
+                    operations.push(Instruction::Call {
+                        function: Some(identifier.clone()),
+                        arg_count: 0,
+                        destination: result,
+                    });
+                    Ok(())
+                } else if let Some(ScopeSymbol::Function(function)) = operations.lookup(identifier)
+                {
+                    // Taking a function as a value creates a closure that
+                    // doesn't capture anything.
+                    operations.push(Instruction::NewClosure {
+                        function: function.clone(),
+                        capture_count: 0,
+                        destination: result,
+                    });
+                    Ok(())
+                } else if let Some((target, fields)) = field_access(identifier, operations) {
+                    let (field, fields) = fields.split_last().expect("at least one field");
+                    let target = load_fields(target, fields, operations);
+                    operations.push(Instruction::GetIndex {
+                        target,
+                        index: LiteralOrSource::Literal(Literal::String((*field).to_string())),
+                        destination: result,
+                    });
+                    Ok(())
+                } else {
+                    operations
+                        .load_from_symbol(identifier, result)
+                        .map_err(CompilationError::from)
+                }
+            }
             Node::Map(map) => map.generate_code(result, operations, tree),
             Node::List(list) => list.generate_code(result, operations, tree),
+            Node::Index(index) => index.generate_code(result, operations, tree),
             // Node::Lookup(lookup) => lookup.generate_code(operations, tree),
             Node::Call(call) => call.generate_code(result, operations, tree),
+            Node::Lambda(lambda) => lambda.generate_code(result, operations, tree),
             Node::Return(value) => Self::generate_return(*value, operations, tree),
             Node::Loop(l) => l.generate_code(result, operations, tree),
             Node::Break(l) => l.generate_code(operations, tree),
             Node::Continue(l) => l.generate_code(operations, tree),
+            Node::Try(try_expr) => try_expr.generate_code(result, operations, tree),
+            Node::Throw(value) => {
+                let value = tree.node_value_or_source(*value, operations)?;
+                operations.push(Instruction::Throw(value));
+                Ok(())
+            }
+            Node::Match(match_expr) => match_expr.generate_code(result, operations, tree),
+            Node::Negate(value) => {
+                let value = tree.node_value_or_source(*value, operations)?;
+                operations.push(Instruction::Negate {
+                    value,
+                    destination: result,
+                });
+                Ok(())
+            }
         }
     }
 
@@ -257,8 +536,28 @@ impl Node {
             Node::Identifier(identifier) => match operations.lookup(identifier) {
                 Some(ScopeSymbol::Argument(arg)) => Ok(LiteralOrSource::Argument(arg.clone())),
                 Some(ScopeSymbol::Variable(var)) => Ok(LiteralOrSource::Variable(var.clone())),
-                Some(ScopeSymbol::Function { .. }) => todo!("attempt to take function as value"),
-                None => Err(CompilationError::UndefinedIdentifier(identifier.clone())),
+                Some(ScopeSymbol::Function { .. }) => {
+                    let variable = operations.new_temporary_variable();
+                    self.generate_code(Destination::Variable(variable.clone()), operations, tree)?;
+                    Ok(LiteralOrSource::Variable(variable))
+                }
+                None if field_access(identifier, operations).is_some() => {
+                    let variable = operations.new_temporary_variable();
+                    self.generate_code(Destination::Variable(variable.clone()), operations, tree)?;
+                    Ok(LiteralOrSource::Variable(variable))
+                }
+                None if operations.is_out_of_scope(identifier) => {
+                    Err(CompilationError::OutOfScope {
+                        name: identifier.clone(),
+                        source: None,
+                        range: None,
+                    })
+                }
+                None => Err(CompilationError::UndefinedIdentifier {
+                    name: identifier.clone(),
+                    source: None,
+                    range: None,
+                }),
             },
             // Node::Lookup(lookup) => lookup.generate_code(operations, tree),
             // Node::Call(call) => call.generate_code(result, operations, tree),
@@ -278,10 +577,147 @@ impl Node {
         operations: &mut CodeBlockBuilder<Intrinsic>,
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
-        let value_to_return = tree.node(value_to_return);
-        value_to_return.generate_code(Destination::Return, operations, tree)?;
+        tree.generate_node(value_to_return, Destination::Return, operations)?;
+        // Not every value stored in the return destination exits the
+        // function, such as the result of a call that isn't a tail call.
+        operations.push(Instruction::Return(None));
         Ok(())
     }
+
+    /// Appends every identifier referenced by this node and its children to
+    /// `identifiers`.
+    #[allow(clippy::too_many_lines)] // One arm per node.
+    fn collect_identifiers(&self, tree: &ExpressionTree, identifiers: &mut Vec<Symbol>) {
+        let visit = |id: NodeId, identifiers: &mut Vec<Symbol>| {
+            tree.node(id).collect_identifiers(tree, identifiers);
+        };
+        match self {
+            Node::If(if_expr) => {
+                visit(if_expr.condition, identifiers);
+                visit(if_expr.true_block, identifiers);
+                if let Some(else_block) = if_expr.else_block {
+                    visit(else_block, identifiers);
+                }
+            }
+            Node::BinOp(bin_op) => {
+                visit(bin_op.left, identifiers);
+                visit(bin_op.right, identifiers);
+            }
+            Node::Not(Not { expr, .. })
+            | Node::Convert(Convert { expr, .. })
+            | Node::Negate(expr) => {
+                visit(*expr, identifiers);
+            }
+            Node::Assign(assign) => {
+                visit(assign.target, identifiers);
+                visit(assign.value, identifiers);
+            }
+            Node::Block(block) => {
+                for statement in &block.0 {
+                    visit(*statement, identifiers);
+                }
+            }
+            Node::Literal(_) | Node::Continue(_) => {}
+            Node::Identifier(identifier) => {
+                // Accessing a field references the value the path begins with.
+                if let Some((base, _)) = identifier.split_once('.') {
+                    identifiers.push(Symbol::from(base));
+                }
+                identifiers.push(identifier.clone());
+            }
+            Node::Map(map) => {
+                for mapping in &map.mappings {
+                    visit(mapping.key, identifiers);
+                    visit(mapping.value, identifiers);
+                }
+            }
+            Node::List(list) => {
+                for value in &list.values {
+                    visit(*value, identifiers);
+                }
+            }
+            Node::Index(index) => {
+                visit(index.target, identifiers);
+                visit(index.index, identifiers);
+            }
+            Node::Call(call) => {
+                match (call.target, &call.name) {
+                    (Some(target), _) => visit(target, identifiers),
+                    // A global call may be calling a function stored in a
+                    // variable.
+                    (None, Some(name)) => identifiers.push(name.clone()),
+                    (None, None) => {}
+                }
+                for arg in &call.args {
+                    visit(*arg, identifiers);
+                }
+            }
+            Node::Lambda(lambda) => visit(lambda.body, identifiers),
+            Node::Return(value) | Node::Throw(value) => visit(*value, identifiers),
+            Node::Loop(l) => {
+                match &l.parameters {
+                    Some(LoopParameters::Until(condition) | LoopParameters::While(condition)) => {
+                        visit(*condition, identifiers);
+                    }
+                    Some(LoopParameters::For {
+                        var_name,
+                        initial_value,
+                        stop_value,
+                        step,
+                        ..
+                    }) => {
+                        identifiers.push(var_name.clone());
+                        if let Some(initial_value) = initial_value {
+                            visit(*initial_value, identifiers);
+                        }
+                        visit(*stop_value, identifiers);
+                        if let Some(step) = step {
+                            visit(*step, identifiers);
+                        }
+                    }
+                    Some(LoopParameters::ForIn {
+                        var_name,
+                        value_name,
+                        iterable,
+                    }) => {
+                        identifiers.push(var_name.clone());
+                        identifiers.extend(value_name.iter().cloned());
+                        visit(*iterable, identifiers);
+                    }
+                    None => {}
+                }
+                visit(l.body, identifiers);
+            }
+            Node::Break(b) => {
+                if let Some(value) = b.value {
+                    visit(value, identifiers);
+                }
+            }
+            Node::Try(try_expr) => {
+                visit(try_expr.body, identifiers);
+                if let Some(catch) = &try_expr.catch {
+                    identifiers.extend(catch.name.iter().cloned());
+                    visit(catch.body, identifiers);
+                }
+                if let Some(finally) = try_expr.finally {
+                    visit(finally, identifiers);
+                }
+            }
+            Node::Match(match_expr) => {
+                visit(match_expr.subject, identifiers);
+                for arm in &match_expr.arms {
+                    arm.pattern.collect_bindings(identifiers);
+                    if let Some(guard) = arm.guard {
+                        visit(guard, identifiers);
+                    }
+                    visit(arm.body, identifiers);
+                }
+                if let Some(else_block) = match_expr.else_block {
+                    visit(else_block, identifiers);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -328,8 +764,8 @@ impl If {
             // The if statement is a result of the comparison. Use the special
             // form of the comparison operator to branch instead of using an if
             // operation
-            let left = tree.node(*left).to_value_or_source(operations, tree)?;
-            let right = tree.node(*right).to_value_or_source(operations, tree)?;
+            let left = tree.node_value_or_source(*left, operations)?;
+            let right = tree.node_value_or_source(*right, operations)?;
             operations.push(Instruction::Compare {
                 comparison: *comparison,
                 left,
@@ -339,23 +775,21 @@ impl If {
         } else {
             // The if statement is a result of something more complex
             let condition_result = operations.new_temporary_variable();
-            condition.generate_code(
+            tree.generate_node(
+                self.condition,
                 Destination::Variable(condition_result.clone()),
                 operations,
-                tree,
             )?;
             operations.push(Instruction::If {
                 condition: LiteralOrSource::Variable(condition_result),
                 false_jump_to,
             });
         }
-        let true_block = tree.node(self.true_block);
-        true_block.generate_code(result.clone(), operations, tree)?;
+        tree.generate_scoped_node(self.true_block, result.clone(), operations)?;
         if let (Some(else_block), Some(if_false_label)) = (self.else_block, if_false_label) {
             operations.push(Instruction::JumpTo(after_false_label.clone()));
             operations.label(if_false_label);
-            let else_block = tree.node(else_block);
-            else_block.generate_code(result, operations, tree)?;
+            tree.generate_scoped_node(else_block, result, operations)?;
         }
         operations.label(after_false_label);
         Ok(())
@@ -390,6 +824,9 @@ pub enum BinOpKind {
     Sub,
     Multiply,
     Divide,
+    IntegerDivide,
+    Remainder,
+    Power,
     LogicalAnd,
     LogicalOr,
     LogicalXor,
@@ -410,9 +847,7 @@ impl BinOpKind {
         operations: &mut CodeBlockBuilder<Intrinsic>,
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
-        let left = tree.node(left);
-        let right = tree.node(right);
-        let left = left.to_value_or_source(operations, tree)?;
+        let left = tree.node_value_or_source(left, operations)?;
 
         if matches!(self, BinOpKind::LogicalAnd | BinOpKind::LogicalOr) {
             // These operators short circuit, so we delay evaluating right until
@@ -421,7 +856,7 @@ impl BinOpKind {
         } else {
             // None of these operators short circuit, so we can evaluate right
             // immediately.
-            let right = right.to_value_or_source(operations, tree)?;
+            let right = tree.node_value_or_source(right, operations)?;
             match self {
                 BinOpKind::Add => operations.push(Instruction::Add {
                     left,
@@ -443,6 +878,21 @@ impl BinOpKind {
                     right,
                     destination,
                 }),
+                BinOpKind::IntegerDivide => operations.push(Instruction::IntegerDivide {
+                    left,
+                    right,
+                    destination,
+                }),
+                BinOpKind::Remainder => operations.push(Instruction::Remainder {
+                    left,
+                    right,
+                    destination,
+                }),
+                BinOpKind::Power => operations.push(Instruction::Power {
+                    left,
+                    right,
+                    destination,
+                }),
                 BinOpKind::BitwiseAnd => operations.push(Instruction::BitwiseAnd {
                     left,
                     right,
@@ -498,7 +948,7 @@ impl BinOpKind {
     fn generate_short_circuit_op(
         &self,
         left: LiteralOrSource,
-        right: &Node,
+        right: NodeId,
         destination: Destination,
         operations: &mut CodeBlockBuilder<Intrinsic>,
         tree: &ExpressionTree,
@@ -512,7 +962,7 @@ impl BinOpKind {
                     false_jump_to: store_false.clone(),
                 });
                 // If left was false, we jump over the evaluation of right.
-                let right = right.to_value_or_source(operations, tree)?;
+                let right = tree.node_value_or_source(right, operations)?;
                 operations.push(Instruction::If {
                     condition: right,
                     false_jump_to: store_false.clone(),
@@ -540,7 +990,7 @@ impl BinOpKind {
                 operations.push(Instruction::JumpTo(store_true.clone()));
 
                 operations.label(check_right);
-                let right = right.to_value_or_source(operations, tree)?;
+                let right = tree.node_value_or_source(right, operations)?;
                 let store_false = operations.new_label();
                 operations.push(Instruction::If {
                     condition: right,
@@ -580,32 +1030,29 @@ impl Not {
         operations: &mut CodeBlockBuilder<Intrinsic>,
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
-        match tree.node(self.expr) {
-            Node::Literal(literal) => {
-                let expr = LiteralOrSource::Literal(match literal {
-                    Literal::Void => Literal::Boolean(true),
-                    Literal::Integer(value) => Literal::Integer(!value),
-                    Literal::Real(value) => Literal::Boolean(value.abs() < f64::EPSILON),
-                    Literal::Boolean(value) => Literal::Boolean(!value),
-                    Literal::String(value) => Literal::Boolean(value.is_empty()),
+        if let Node::Literal(literal) = tree.node(self.expr) {
+            let expr = LiteralOrSource::Literal(match literal {
+                Literal::Void => Literal::Boolean(true),
+                Literal::Integer(value) => Literal::Integer(!value),
+                Literal::Real(value) => Literal::Boolean(value.abs() < f64::EPSILON),
+                Literal::Boolean(value) => Literal::Boolean(!value),
+                Literal::String(value) => Literal::Boolean(value.is_empty()),
+            });
+            operations.store_into_destination(expr, result);
+        } else {
+            let value = operations.new_temporary_variable();
+            tree.generate_node(self.expr, Destination::Variable(value.clone()), operations)?;
+            let value = LiteralOrSource::Variable(value);
+            if self.bitwise {
+                operations.push(Instruction::BitwiseNot {
+                    value,
+                    destination: result,
+                });
+            } else {
+                operations.push(Instruction::LogicalNot {
+                    value,
+                    destination: result,
                 });
-                operations.store_into_destination(expr, result);
-            }
-            other => {
-                let value = operations.new_temporary_variable();
-                other.generate_code(Destination::Variable(value.clone()), operations, tree)?;
-                let value = LiteralOrSource::Variable(value);
-                if self.bitwise {
-                    operations.push(Instruction::BitwiseNot {
-                        value,
-                        destination: result,
-                    });
-                } else {
-                    operations.push(Instruction::LogicalNot {
-                        value,
-                        destination: result,
-                    });
-                }
             }
         }
         Ok(())
@@ -626,11 +1073,7 @@ impl Convert {
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
         let value = operations.new_temporary_variable();
-        tree.node(self.expr).generate_code(
-            Destination::Variable(value.clone()),
-            operations,
-            tree,
-        )?;
+        tree.generate_node(self.expr, Destination::Variable(value.clone()), operations)?;
         let value = LiteralOrSource::Variable(value);
         operations.push(Instruction::Convert {
             value,
@@ -678,6 +1121,15 @@ impl Call {
         }
     }
 
+    pub fn value<Args: IntoIterator<Item = NodeId>>(target: NodeId, args: Args) -> Self {
+        Self {
+            target: Some(target),
+            name: None,
+            args: args.into_iter().collect(),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)] // One arm per kind of call.
     fn generate_code(
         &self,
         destination: Destination,
@@ -688,65 +1140,143 @@ impl Call {
             (None, None) => {
                 // Recursive call
                 for &arg in &self.args {
-                    let arg = tree.node(arg);
-                    arg.generate_code(Destination::Stack, operations, tree)?;
+                    tree.generate_node(arg, Destination::Stack, operations)?;
                 }
 
-                operations.push(Instruction::Call {
-                    function: None,
-                    arg_count: self.args.len(),
-                    destination,
-                });
+                let mut function = None;
+                let mut arg_count = self.args.len();
+                if let Some(captures) = tree.lambda_captures.borrow().last() {
+                    if let Some(name) = &tree.function {
+                        // Within a lambda declared in a function, `this` still
+                        // refers to the enclosing function.
+                        function = Some(name.clone());
+                    } else {
+                        // Lambdas receive their captured values as trailing
+                        // arguments, which need to be passed along when
+                        // recursing.
+                        for capture in captures {
+                            operations.load_from_symbol(capture, Destination::Stack)?;
+                        }
+                        arg_count += captures.len();
+                    }
+                }
+
+                if tree.is_tail_call(&destination, operations) {
+                    operations.push(Instruction::TailCall {
+                        function,
+                        arg_count,
+                    });
+                } else {
+                    operations.push(Instruction::Call {
+                        function,
+                        arg_count,
+                        destination,
+                    });
+                }
             }
             (None, Some(symbol)) => {
                 // Global call
                 for &arg in &self.args {
-                    let arg = tree.node(arg);
-                    arg.generate_code(Destination::Stack, operations, tree)?;
+                    tree.generate_node(arg, Destination::Stack, operations)?;
                 }
 
                 match operations.lookup(symbol) {
-                    Some(ScopeSymbol::Argument(_) | ScopeSymbol::Variable(_)) => {
-                        todo!("calling a lambda function in an argument")
+                    Some(ScopeSymbol::Argument(arg)) => {
+                        operations.push(Instruction::CallValue {
+                            target: LiteralOrSource::Argument(arg.clone()),
+                            arg_count: self.args.len(),
+                            destination,
+                        });
+                    }
+                    Some(ScopeSymbol::Variable(var)) => {
+                        operations.push(Instruction::CallValue {
+                            target: LiteralOrSource::Variable(var.clone()),
+                            arg_count: self.args.len(),
+                            destination,
+                        });
                     }
                     Some(ScopeSymbol::Function(function)) => {
-                        operations.push(Instruction::Call {
-                            function: Some(function.clone()),
+                        if let Some(record) = tree.record_types.resolve(symbol) {
+                            if record.field_count != self.args.len() {
+                                return Err(CompilationError::ArgumentCountMismatch {
+                                    name: symbol.clone(),
+                                    expected: record.field_count,
+                                    received: self.args.len(),
+                                    source: None,
+                                    range: None,
+                                });
+                            }
+                        }
+                        if tree.is_tail_call(&destination, operations) {
+                            operations.push(Instruction::TailCall {
+                                function: Some(function.clone()),
+                                arg_count: self.args.len(),
+                            });
+                        } else {
+                            operations.push(Instruction::Call {
+                                function: Some(function.clone()),
+                                arg_count: self.args.len(),
+                                destination,
+                            });
+                        }
+                    }
+                    None if symbol == "format" => {
+                        operations.push(Instruction::CallIntrinsic {
+                            intrinsic: Intrinsic::Format,
                             arg_count: self.args.len(),
                             destination,
                         });
                     }
-                    None => operations.push(Instruction::Call {
-                        function: Some(symbol.clone()),
-                        arg_count: self.args.len(),
-                        destination,
-                    }),
+                    None => {
+                        return Err(CompilationError::UndefinedFunction {
+                            name: symbol.clone(),
+                            source: None,
+                            range: None,
+                        })
+                    }
                 }
             }
-            (Some(target), Some(name)) => {
+            (Some(target_id), Some(name)) => {
                 // Evaluate the target expression
-                let target = tree.node(target);
-                let target = if let Node::Identifier(name) = target {
-                    match operations.lookup(name) {
-                        Some(ScopeSymbol::Argument(arg)) => LiteralOrSource::Argument(arg.clone()),
-                        Some(ScopeSymbol::Variable(var)) => LiteralOrSource::Variable(var.clone()),
-                        Some(ScopeSymbol::Function { .. }) => todo!("can't invoke on a function"),
-                        None => todo!("unknown identifier"),
+                let target = match tree.node(target_id) {
+                    Node::Identifier(path)
+                        if field_access(path, operations).is_none()
+                            && !tree.is_unit_variant(path) =>
+                    {
+                        match operations.lookup(path) {
+                            Some(ScopeSymbol::Argument(arg)) => {
+                                LiteralOrSource::Argument(arg.clone())
+                            }
+                            Some(ScopeSymbol::Variable(var)) => {
+                                LiteralOrSource::Variable(var.clone())
+                            }
+                            Some(ScopeSymbol::Function { .. }) => {
+                                todo!("can't invoke on a function")
+                            }
+                            None => {
+                                // The target isn't a value, so it is a module
+                                // path. The function is resolved through the
+                                // module tree when linking.
+                                let function = Symbol::from(format!("{path}.{name}").as_str());
+                                return Call::global(function, self.args.iter().copied())
+                                    .generate_code(destination, operations, tree);
+                            }
+                        }
+                    }
+                    _ => {
+                        let target_result = operations.new_temporary_variable();
+                        tree.generate_node(
+                            target_id,
+                            Destination::Variable(target_result.clone()),
+                            operations,
+                        )?;
+                        LiteralOrSource::Variable(target_result)
                     }
-                } else {
-                    let target_result = operations.new_temporary_variable();
-                    target.generate_code(
-                        Destination::Variable(target_result.clone()),
-                        operations,
-                        tree,
-                    )?;
-                    LiteralOrSource::Variable(target_result)
                 };
 
                 // Push the arguments
                 for &arg in &self.args {
-                    let arg = tree.node(arg);
-                    arg.generate_code(Destination::Stack, operations, tree)?;
+                    tree.generate_node(arg, Destination::Stack, operations)?;
                 }
 
                 // Invoke the call
@@ -757,8 +1287,90 @@ impl Call {
                     destination,
                 });
             }
-            (Some(_), None) => todo!("invalid instruction"),
+            (Some(target), None) => {
+                // Calling the result of an expression
+                let target = tree.node_value_or_source(target, operations)?;
+
+                for &arg in &self.args {
+                    tree.generate_node(arg, Destination::Stack, operations)?;
+                }
+
+                operations.push(Instruction::CallValue {
+                    target,
+                    arg_count: self.args.len(),
+                    destination,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Lambda {
+    args: Vec<Symbol>,
+    body: NodeId,
+}
+
+impl Lambda {
+    #[must_use]
+    pub fn new(args: Vec<Symbol>, body: NodeId) -> Self {
+        Self { args, body }
+    }
+
+    fn generate_code(
+        &self,
+        result: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        // Any arguments or variables of the enclosing function referenced by
+        // the body are captured by value when the closure is created.
+        let mut referenced = Vec::new();
+        tree.node(self.body)
+            .collect_identifiers(tree, &mut referenced);
+        let mut captures = Vec::<Symbol>::new();
+        for name in referenced {
+            if !self.args.contains(&name)
+                && !captures.contains(&name)
+                && matches!(
+                    operations.lookup(&name),
+                    Some(ScopeSymbol::Argument(_) | ScopeSymbol::Variable(_))
+                )
+            {
+                captures.push(name);
+            }
+        }
+
+        let mut block = CodeBlockBuilder::default();
+        block.set_source(operations.source().cloned());
+        for function in operations.functions() {
+            block.add_function(function.clone());
+        }
+        for arg in &self.args {
+            block.new_argument(arg.clone());
         }
+        for capture in &captures {
+            block.new_argument(capture.clone());
+        }
+
+        tree.copy_assigned_arguments(&mut block);
+        tree.lambda_captures.borrow_mut().push(captures.clone());
+        let tail_calls = tree.tail_calls.replace(true);
+        let body_result = tree.generate_node(self.body, Destination::Return, &mut block);
+        tree.tail_calls.set(tail_calls);
+        tree.lambda_captures.borrow_mut().pop();
+        body_result?;
+
+        let function = tree.lift_lambda(block.finish());
+        for capture in &captures {
+            operations.load_from_symbol(capture, Destination::Stack)?;
+        }
+        operations.push(Instruction::NewClosure {
+            function,
+            capture_count: captures.len(),
+            destination: result,
+        });
         Ok(())
     }
 }
@@ -778,10 +1390,42 @@ impl Assign {
     ) -> Result<(), CompilationError> {
         match tree.node(self.target) {
             Node::Identifier(name) => {
-                let variable = operations.variable_index_from_name(name);
-                let value = tree.node(self.value);
-                value.generate_code(Destination::Variable(variable.clone()), operations, tree)?;
-                operations.store_into_destination(LiteralOrSource::Variable(variable), result);
+                if let Some((target, fields)) = field_access(name, operations) {
+                    let (field, fields) = fields.split_last().expect("at least one field");
+                    let target = load_fields(target, fields, operations);
+                    let value = operations.new_temporary_variable();
+                    tree.generate_node(
+                        self.value,
+                        Destination::Variable(value.clone()),
+                        operations,
+                    )?;
+                    operations.push(Instruction::SetIndex {
+                        target,
+                        index: LiteralOrSource::Literal(Literal::String((*field).to_string())),
+                        value: LiteralOrSource::Variable(value.clone()),
+                    });
+                    operations.store_into_destination(LiteralOrSource::Variable(value), result);
+                } else {
+                    let variable = operations.variable_index_from_name(name);
+                    tree.generate_node(
+                        self.value,
+                        Destination::Variable(variable.clone()),
+                        operations,
+                    )?;
+                    operations.store_into_destination(LiteralOrSource::Variable(variable), result);
+                }
+            }
+            Node::Index(index) => {
+                let target = tree.node_value_or_source(index.target, operations)?;
+                let key = tree.node_value_or_source(index.index, operations)?;
+                let value = operations.new_temporary_variable();
+                tree.generate_node(self.value, Destination::Variable(value.clone()), operations)?;
+                operations.push(Instruction::SetIndex {
+                    target,
+                    index: key,
+                    value: LiteralOrSource::Variable(value.clone()),
+                });
+                operations.store_into_destination(LiteralOrSource::Variable(value), result);
             }
             _ => todo!("not a variable name"),
         }
@@ -789,6 +1433,44 @@ impl Assign {
     }
 }
 
+/// Splits `path` into the argument or variable it begins with and the names of
+/// the fields it accesses, if `path` is a field access such as `point.x`.
+fn field_access<'a>(
+    path: &'a Symbol,
+    operations: &CodeBlockBuilder<Intrinsic>,
+) -> Option<(LiteralOrSource, Vec<&'a str>)> {
+    if operations.lookup(path).is_some() {
+        return None;
+    }
+    let mut segments = path.split('.');
+    let base = match operations.lookup(&Symbol::from(segments.next()?))? {
+        ScopeSymbol::Argument(arg) => LiteralOrSource::Argument(arg.clone()),
+        ScopeSymbol::Variable(var) => LiteralOrSource::Variable(var.clone()),
+        ScopeSymbol::Function(_) => return None,
+    };
+    let fields = segments.collect::<Vec<_>>();
+    (!fields.is_empty()).then_some((base, fields))
+}
+
+/// Reads each field in `fields` in turn, starting with `target`, returning the
+/// location of the last value read.
+fn load_fields(
+    mut target: LiteralOrSource,
+    fields: &[&str],
+    operations: &mut CodeBlockBuilder<Intrinsic>,
+) -> LiteralOrSource {
+    for field in fields {
+        let value = operations.new_temporary_variable();
+        operations.push(Instruction::GetIndex {
+            target,
+            index: LiteralOrSource::Literal(Literal::String((*field).to_string())),
+            destination: Destination::Variable(value.clone()),
+        });
+        target = LiteralOrSource::Variable(value);
+    }
+    target
+}
+
 #[derive(Debug, Clone)]
 pub struct Map {
     pub mappings: Vec<Mapping>,
@@ -802,10 +1484,8 @@ impl Map {
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
         for mapping in &self.mappings {
-            tree.node(mapping.key)
-                .generate_code(Destination::Stack, operations, tree)?;
-            tree.node(mapping.value)
-                .generate_code(Destination::Stack, operations, tree)?;
+            tree.generate_node(mapping.key, Destination::Stack, operations)?;
+            tree.generate_node(mapping.value, Destination::Stack, operations)?;
         }
 
         operations.push(Instruction::CallIntrinsic {
@@ -836,8 +1516,7 @@ impl List {
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
         for value in &self.values {
-            tree.node(*value)
-                .generate_code(Destination::Stack, operations, tree)?;
+            tree.generate_node(*value, Destination::Stack, operations)?;
         }
 
         operations.push(Instruction::CallIntrinsic {
@@ -849,6 +1528,30 @@ impl List {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub target: NodeId,
+    pub index: NodeId,
+}
+
+impl Index {
+    fn generate_code(
+        &self,
+        result: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        let target = tree.node_value_or_source(self.target, operations)?;
+        let index = tree.node_value_or_source(self.index, operations)?;
+        operations.push(Instruction::GetIndex {
+            target,
+            index,
+            destination: result,
+        });
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct Loop {
     pub name: Option<Symbol>,
@@ -857,13 +1560,23 @@ pub struct Loop {
 }
 
 impl Loop {
+    #[allow(clippy::too_many_lines)] // One arm per kind of loop.
     fn generate_code(
         &self,
         result: Destination,
         operations: &mut CodeBlockBuilder<Intrinsic>,
         tree: &ExpressionTree,
     ) -> Result<(), CompilationError> {
-        let mut scope = operations.begin_loop(self.name.clone(), result.clone());
+        // The loop's body isn't in tail position, so a loop whose value is
+        // returned stores its result in a variable first.
+        if let Destination::Return = result {
+            let variable = operations.new_temporary_variable();
+            self.generate_code(Destination::Variable(variable.clone()), operations, tree)?;
+            operations.store_into_destination(LiteralOrSource::Variable(variable), result);
+            return Ok(());
+        }
+
+        let mut scope = operations.begin_loop(self.name.clone(), result.clone());
 
         let continue_label = scope.continue_label.clone();
         let break_label = scope.break_label.clone();
@@ -875,6 +1588,20 @@ impl Loop {
             Some(LoopParameters::While(expr)) => {
                 Self::generate_while_preamble(*expr, break_label, &mut scope, tree)?;
             }
+            Some(LoopParameters::ForIn {
+                var_name,
+                value_name,
+                iterable,
+            }) => {
+                Self::generate_for_in_preamble(
+                    var_name,
+                    value_name.as_ref(),
+                    *iterable,
+                    break_label,
+                    &mut scope,
+                    tree,
+                )?;
+            }
             Some(LoopParameters::For {
                 var_name,
                 initial_value,
@@ -886,10 +1613,10 @@ impl Loop {
                 // Initialize the variable
                 let variable = scope.variable_index_from_name(var_name);
                 if let Some(initial_value) = *initial_value {
-                    tree.node(initial_value).generate_code(
+                    tree.generate_node(
+                        initial_value,
                         Destination::Variable(variable.clone()),
                         &mut scope,
-                        tree,
                     )?;
                 }
 
@@ -899,25 +1626,24 @@ impl Loop {
 
                 // We evaluate the value of the step once at the start of the loop, not on each iteration.
 
-                let stop_value = match tree.node(*stop_value) {
-                    Node::Literal(literal) => LiteralOrSource::Literal(literal.clone()),
-                    other => {
-                        let stop_result = scope.new_temporary_variable();
-                        other.generate_code(
-                            Destination::Variable(stop_result.clone()),
-                            &mut scope,
-                            tree,
-                        )?;
-                        LiteralOrSource::Variable(stop_result)
-                    }
+                let stop_value = if let Node::Literal(literal) = tree.node(*stop_value) {
+                    LiteralOrSource::Literal(literal.clone())
+                } else {
+                    let stop_result = scope.new_temporary_variable();
+                    tree.generate_node(
+                        *stop_value,
+                        Destination::Variable(stop_result.clone()),
+                        &mut scope,
+                    )?;
+                    LiteralOrSource::Variable(stop_result)
                 };
 
                 let step = if let Some(step) = step {
                     let step_result = scope.new_temporary_variable();
-                    tree.node(*step).generate_code(
+                    tree.generate_node(
+                        *step,
                         Destination::Variable(step_result.clone()),
                         &mut scope,
-                        tree,
                     )?;
                     LiteralOrSource::Variable(step_result)
                 } else if *ascending {
@@ -969,8 +1695,7 @@ impl Loop {
             }
         }
 
-        let body = tree.node(self.body);
-        body.generate_code(result, &mut scope, tree)?;
+        tree.generate_node(self.body, result, &mut scope)?;
         scope.push(Instruction::JumpTo(continue_label));
         scope.label_break();
 
@@ -987,10 +1712,10 @@ impl Loop {
 
         let condition_result = scope.new_temporary_variable();
         let continue_evaluation = scope.new_label();
-        tree.node(condition).generate_code(
+        tree.generate_node(
+            condition,
             Destination::Variable(condition_result.clone()),
             scope,
-            tree,
         )?;
         scope.push(Instruction::If {
             condition: LiteralOrSource::Variable(condition_result),
@@ -1011,10 +1736,10 @@ impl Loop {
         scope.label_continue();
 
         let condition_result = scope.new_temporary_variable();
-        tree.node(condition).generate_code(
+        tree.generate_node(
+            condition,
             Destination::Variable(condition_result.clone()),
             scope,
-            tree,
         )?;
         scope.push(Instruction::If {
             condition: LiteralOrSource::Variable(condition_result),
@@ -1022,6 +1747,54 @@ impl Loop {
         });
         Ok(())
     }
+
+    fn generate_for_in_preamble(
+        var_name: &Symbol,
+        value_name: Option<&Symbol>,
+        iterable: NodeId,
+        break_label: Label,
+        scope: &mut LoopScope<'_, CodeBlockBuilder<Intrinsic>, Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        // The iterator is created once, before the loop begins.
+        let iterable = tree.node_value_or_source(iterable, scope)?;
+        let iterator = scope.new_temporary_variable();
+        scope.push(Instruction::Iterate {
+            source: iterable,
+            destination: Destination::Variable(iterator.clone()),
+        });
+
+        scope.label_continue();
+        let variable = scope.variable_index_from_name(var_name);
+        if let Some(value_name) = value_name {
+            // Each item is destructured into its first and second values,
+            // such as the key and value of a map entry.
+            let item = scope.new_temporary_variable();
+            scope.push(Instruction::IterateNext {
+                iterator: LiteralOrSource::Variable(iterator),
+                destination: Destination::Variable(item.clone()),
+                done_jump_to: break_label,
+            });
+            scope.push(Instruction::GetIndex {
+                target: LiteralOrSource::Variable(item.clone()),
+                index: LiteralOrSource::Literal(Literal::Integer(0)),
+                destination: Destination::Variable(variable),
+            });
+            let value = scope.variable_index_from_name(value_name);
+            scope.push(Instruction::GetIndex {
+                target: LiteralOrSource::Variable(item),
+                index: LiteralOrSource::Literal(Literal::Integer(1)),
+                destination: Destination::Variable(value),
+            });
+        } else {
+            scope.push(Instruction::IterateNext {
+                iterator: LiteralOrSource::Variable(iterator),
+                destination: Destination::Variable(variable),
+                done_jump_to: break_label,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -1036,6 +1809,11 @@ pub enum LoopParameters {
         ascending: bool,
         inclusive: bool,
     },
+    ForIn {
+        var_name: Symbol,
+        value_name: Option<Symbol>,
+        iterable: NodeId,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -1052,11 +1830,12 @@ impl Break {
     ) -> Result<(), CompilationError> {
         if let Some(loop_info) = operations.loop_info(self.name.as_ref()) {
             let break_label = loop_info.break_label.clone();
+            let try_depth = loop_info.try_depth;
             if let Some(value) = self.value {
                 let result = loop_info.loop_result.clone();
-                let value = tree.node(value);
-                value.generate_code(result, operations, tree)?;
+                tree.generate_node(value, result, operations)?;
             }
+            exit_tries(try_depth, operations);
             operations.push(Instruction::JumpTo(break_label));
             Ok(())
         } else {
@@ -1078,6 +1857,7 @@ impl Continue {
     ) -> Result<(), CompilationError> {
         if let Some(loop_info) = operations.loop_info(self.name.as_ref()) {
             let continue_label = loop_info.continue_label.clone();
+            exit_tries(loop_info.try_depth, operations);
             operations.push(Instruction::JumpTo(continue_label));
             Ok(())
         } else {
@@ -1086,22 +1866,437 @@ impl Continue {
     }
 }
 
+/// Removes the exception handlers registered since `try_depth`, allowing
+/// control to jump out of the `try` expressions that registered them.
+fn exit_tries(try_depth: usize, operations: &mut CodeBlockBuilder<Intrinsic>) {
+    for _ in try_depth..operations.try_depth() {
+        operations.push(Instruction::ExitTry);
+    }
+}
+
+#[derive(Debug)]
+pub struct Try {
+    pub body: NodeId,
+    pub catch: Option<Catch>,
+    pub finally: Option<NodeId>,
+}
+
+impl Try {
+    fn generate_code(
+        &self,
+        result: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        let value = operations.new_temporary_variable();
+        let caught = operations.new_temporary_variable();
+        let handler = operations.new_label();
+        let after_body = operations.new_label();
+        let rethrow = if self.finally.is_some() {
+            let rethrow = operations.new_temporary_variable();
+            operations.push(Instruction::Load {
+                value: LiteralOrSource::Literal(Literal::Boolean(false)),
+                variable: rethrow.clone(),
+            });
+            Some(rethrow)
+        } else {
+            None
+        };
+
+        operations.enter_try(handler.clone(), Destination::Variable(caught.clone()));
+        tree.generate_scoped_node(self.body, Destination::Variable(value.clone()), operations)?;
+        operations.exit_try();
+        operations.push(Instruction::JumpTo(after_body.clone()));
+
+        operations.label(handler);
+        if let Some(catch) = &self.catch {
+            // When there is a finally block, anything raised by the catch
+            // block must still execute the finally block before propagating.
+            let rethrow_handler = self.finally.map(|_| {
+                let rethrow_handler = operations.new_label();
+                operations.enter_try(
+                    rethrow_handler.clone(),
+                    Destination::Variable(caught.clone()),
+                );
+                rethrow_handler
+            });
+            operations.begin_scope();
+            if let Some(name) = &catch.name {
+                let variable = operations.declare_variable(name);
+                operations.push(Instruction::Load {
+                    value: LiteralOrSource::Variable(caught.clone()),
+                    variable,
+                });
+            }
+            let body =
+                tree.generate_node(catch.body, Destination::Variable(value.clone()), operations);
+            operations.end_scope();
+            body?;
+            if let Some(rethrow_handler) = rethrow_handler {
+                operations.exit_try();
+                operations.push(Instruction::JumpTo(after_body.clone()));
+                operations.label(rethrow_handler);
+            }
+        }
+        if let Some(rethrow) = &rethrow {
+            operations.push(Instruction::Load {
+                value: LiteralOrSource::Literal(Literal::Boolean(true)),
+                variable: rethrow.clone(),
+            });
+        }
+
+        operations.label(after_body);
+        if let (Some(finally), Some(rethrow)) = (self.finally, rethrow) {
+            let finally_result = operations.new_temporary_variable();
+            tree.generate_scoped_node(finally, Destination::Variable(finally_result), operations)?;
+            let done = operations.new_label();
+            operations.push(Instruction::If {
+                condition: LiteralOrSource::Variable(rethrow),
+                false_jump_to: done.clone(),
+            });
+            operations.push(Instruction::Throw(LiteralOrSource::Variable(caught)));
+            operations.label(done);
+        }
+
+        operations.store_into_destination(LiteralOrSource::Variable(value), result);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Catch {
+    pub name: Option<Symbol>,
+    pub body: NodeId,
+}
+
+#[derive(Debug)]
+pub struct Match {
+    pub subject: NodeId,
+    pub arms: Vec<MatchArm>,
+    pub else_block: Option<NodeId>,
+}
+
+impl Match {
+    fn generate_code(
+        &self,
+        result: Destination,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        // The subject is copied so that bindings can't change it between arms.
+        let subject = operations.new_temporary_variable();
+        tree.generate_node(
+            self.subject,
+            Destination::Variable(subject.clone()),
+            operations,
+        )?;
+        let subject = LiteralOrSource::Variable(subject);
+        let value = operations.new_temporary_variable();
+        let end = operations.new_label();
+
+        for arm in &self.arms {
+            let next_arm = operations.new_label();
+            // Bindings are only visible within their arm.
+            operations.begin_scope();
+            let generated = arm.generate_code(
+                subject.clone(),
+                Destination::Variable(value.clone()),
+                &next_arm,
+                operations,
+                tree,
+            );
+            operations.end_scope();
+            generated?;
+            operations.push(Instruction::JumpTo(end.clone()));
+            operations.label(next_arm);
+        }
+
+        if let Some(else_block) = self.else_block {
+            tree.generate_scoped_node(
+                else_block,
+                Destination::Variable(value.clone()),
+                operations,
+            )?;
+        } else {
+            operations.push(Instruction::Push(subject));
+            operations.push(Instruction::CallIntrinsic {
+                intrinsic: Intrinsic::NoMatch,
+                arg_count: 1,
+                destination: Destination::Variable(value.clone()),
+            });
+        }
+
+        operations.label(end);
+        operations.store_into_destination(LiteralOrSource::Variable(value), result);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<NodeId>,
+    pub body: NodeId,
+}
+
+impl MatchArm {
+    fn generate_code(
+        &self,
+        subject: LiteralOrSource,
+        result: Destination,
+        next_arm: &Label,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        self.pattern
+            .generate_test(subject, next_arm, operations, tree)?;
+        if let Some(guard) = self.guard {
+            let condition = tree.node_value_or_source(guard, operations)?;
+            operations.push(Instruction::If {
+                condition,
+                false_jump_to: next_arm.clone(),
+            });
+        }
+        tree.generate_node(self.body, result, operations)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches any value: `_`.
+    Wildcard,
+    /// Matches any value, storing it in a variable.
+    Binding(Symbol),
+    /// Matches values equal to a literal.
+    Literal(Literal),
+    /// Matches lists containing exactly one value per pattern.
+    List(Vec<Pattern>),
+    /// Matches maps containing each key, whose values match the paired
+    /// pattern.
+    Map(Vec<(Literal, Pattern)>),
+    /// Matches records created from the struct or enum variant named by
+    /// `path`, whose fields match each pattern in order.
+    Record { path: Symbol, fields: Vec<Pattern> },
+}
+
+impl Pattern {
+    /// Generates code that jumps to `no_match` if `subject` doesn't match this
+    /// pattern.
+    fn generate_test(
+        &self,
+        subject: LiteralOrSource,
+        no_match: &Label,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        match self {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Binding(name) => {
+                let variable = operations.declare_variable(name);
+                operations.push(Instruction::Load {
+                    value: subject,
+                    variable,
+                });
+                Ok(())
+            }
+            Pattern::Literal(literal) => {
+                operations.push(Instruction::Compare {
+                    comparison: Comparison::Equal,
+                    left: subject,
+                    right: LiteralOrSource::Literal(literal.clone()),
+                    action: CompareAction::JumpIfFalse(no_match.clone()),
+                });
+                Ok(())
+            }
+            Pattern::List(elements) => {
+                Self::test_shape(
+                    Intrinsic::MatchList,
+                    &subject,
+                    [Literal::Integer(len_as_i64(elements.len()))],
+                    no_match,
+                    operations,
+                );
+                Self::test_elements(
+                    &subject,
+                    elements
+                        .iter()
+                        .enumerate()
+                        .map(|(index, pattern)| (Literal::Integer(len_as_i64(index)), pattern)),
+                    no_match,
+                    operations,
+                    tree,
+                )
+            }
+            Pattern::Map(entries) => {
+                Self::test_shape(
+                    Intrinsic::MatchMap,
+                    &subject,
+                    entries.iter().map(|(key, _)| key.clone()),
+                    no_match,
+                    operations,
+                );
+                Self::test_elements(
+                    &subject,
+                    entries.iter().map(|(key, pattern)| (key.clone(), pattern)),
+                    no_match,
+                    operations,
+                    tree,
+                )
+            }
+            Pattern::Record { path, fields } => {
+                let record_type = tree.record_types.resolve(path).ok_or_else(|| {
+                    CompilationError::UndefinedIdentifier {
+                        name: path.clone(),
+                        source: None,
+                        range: None,
+                    }
+                })?;
+                let variant = record_type
+                    .variant
+                    .as_ref()
+                    .map_or(Literal::Void, |variant| {
+                        Literal::String(variant.to_string())
+                    });
+                Self::test_shape(
+                    Intrinsic::MatchRecord,
+                    &subject,
+                    [
+                        Literal::String(record_type.kind.to_string()),
+                        variant,
+                        Literal::Integer(len_as_i64(fields.len())),
+                    ],
+                    no_match,
+                    operations,
+                );
+                Self::test_elements(
+                    &subject,
+                    fields
+                        .iter()
+                        .enumerate()
+                        .map(|(index, pattern)| (Literal::Integer(len_as_i64(index)), pattern)),
+                    no_match,
+                    operations,
+                    tree,
+                )
+            }
+        }
+    }
+
+    /// Calls `intrinsic` with `subject` and `args`, jumping to `no_match` if
+    /// it returns false.
+    fn test_shape(
+        intrinsic: Intrinsic,
+        subject: &LiteralOrSource,
+        args: impl IntoIterator<Item = Literal>,
+        no_match: &Label,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+    ) {
+        operations.push(Instruction::Push(subject.clone()));
+        let mut arg_count = 1;
+        for arg in args {
+            operations.push(Instruction::Push(LiteralOrSource::Literal(arg)));
+            arg_count += 1;
+        }
+        let matched = operations.new_temporary_variable();
+        operations.push(Instruction::CallIntrinsic {
+            intrinsic,
+            arg_count,
+            destination: Destination::Variable(matched.clone()),
+        });
+        operations.push(Instruction::If {
+            condition: LiteralOrSource::Variable(matched),
+            false_jump_to: no_match.clone(),
+        });
+    }
+
+    /// Tests each value contained in `subject` at the paired index against its
+    /// pattern.
+    fn test_elements<'a>(
+        subject: &LiteralOrSource,
+        elements: impl IntoIterator<Item = (Literal, &'a Pattern)>,
+        no_match: &Label,
+        operations: &mut CodeBlockBuilder<Intrinsic>,
+        tree: &ExpressionTree,
+    ) -> Result<(), CompilationError> {
+        for (index, pattern) in elements {
+            if let Pattern::Wildcard = pattern {
+                continue;
+            }
+            let element = operations.new_temporary_variable();
+            operations.push(Instruction::GetIndex {
+                target: subject.clone(),
+                index: LiteralOrSource::Literal(index),
+                destination: Destination::Variable(element.clone()),
+            });
+            pattern.generate_test(
+                LiteralOrSource::Variable(element),
+                no_match,
+                operations,
+                tree,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn collect_bindings(&self, identifiers: &mut Vec<Symbol>) {
+        match self {
+            Pattern::Binding(name) => identifiers.push(name.clone()),
+            Pattern::List(patterns)
+            | Pattern::Record {
+                fields: patterns, ..
+            } => {
+                for pattern in patterns {
+                    pattern.collect_bindings(identifiers);
+                }
+            }
+            Pattern::Map(entries) => {
+                for (_, pattern) in entries {
+                    pattern.collect_bindings(identifiers);
+                }
+            }
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+        }
+    }
+}
+
+fn len_as_i64(len: usize) -> i64 {
+    i64::try_from(len).unwrap_or(i64::MAX)
+}
+
 #[derive(Debug, Default)]
-pub struct SyntaxTreeBuilder(RefCell<Vec<Node>>);
+pub struct SyntaxTreeBuilder {
+    nodes: RefCell<Vec<Node>>,
+    locations: RefCell<Vec<Option<SourceLocation>>>,
+}
 
 impl SyntaxTreeBuilder {
     #[must_use]
     pub const fn new() -> Self {
-        Self(RefCell::new(Vec::new()))
+        Self {
+            nodes: RefCell::new(Vec::new()),
+            locations: RefCell::new(Vec::new()),
+        }
     }
 
     fn push(&self, node: Node) -> NodeId {
-        let mut nodes = self.0.borrow_mut();
+        let mut nodes = self.nodes.borrow_mut();
         let id = NodeId(nodes.len());
         nodes.push(node);
+        self.locations.borrow_mut().push(None);
         id
     }
 
+    /// Sets the source location of `node`, unless it already has one. Nested
+    /// expressions are located before the expressions that contain them, which
+    /// keeps the most precise location.
+    pub fn set_location(&self, node: NodeId, location: SourceLocation) {
+        let mut locations = self.locations.borrow_mut();
+        let slot = locations.get_mut(node.0).expect("invalid node id");
+        if slot.is_none() {
+            *slot = Some(location);
+        }
+    }
+
     pub fn if_node(&self, node: If) -> NodeId {
         self.push(Node::If(node))
     }
@@ -1118,6 +2313,22 @@ impl SyntaxTreeBuilder {
         self.push(Node::Continue(node))
     }
 
+    pub fn try_node(&self, node: Try) -> NodeId {
+        self.push(Node::Try(node))
+    }
+
+    pub fn throw_node(&self, value: NodeId) -> NodeId {
+        self.push(Node::Throw(value))
+    }
+
+    pub fn match_node(&self, node: Match) -> NodeId {
+        self.push(Node::Match(node))
+    }
+
+    pub fn negate_node(&self, expr: NodeId) -> NodeId {
+        self.push(Node::Negate(expr))
+    }
+
     pub fn binop_node(&self, kind: BinOpKind, left: NodeId, right: NodeId) -> NodeId {
         self.push(Node::BinOp(BinOp { kind, left, right }))
     }
@@ -1158,6 +2369,10 @@ impl SyntaxTreeBuilder {
         self.push(Node::Literal(Literal::Boolean(boolean)))
     }
 
+    pub fn void(&self) -> NodeId {
+        self.push(Node::Literal(Literal::Void))
+    }
+
     pub fn not_node(&self, expr: NodeId, bitwise: bool) -> NodeId {
         self.push(Node::Not(Not { expr, bitwise }))
     }
@@ -1166,6 +2381,10 @@ impl SyntaxTreeBuilder {
         self.push(Node::Call(call))
     }
 
+    pub fn lambda(&self, lambda: Lambda) -> NodeId {
+        self.push(Node::Lambda(lambda))
+    }
+
     pub fn return_node(&self, value: NodeId) -> NodeId {
         self.push(Node::Return(value))
     }
@@ -1178,10 +2397,20 @@ impl SyntaxTreeBuilder {
         self.push(Node::List(List { values }))
     }
 
+    pub fn index_node(&self, target: NodeId, index: NodeId) -> NodeId {
+        self.push(Node::Index(Index { target, index }))
+    }
+
     pub fn finish(self, root: NodeId) -> ExpressionTree {
         ExpressionTree {
-            nodes: self.0.into_inner(),
+            nodes: self.nodes.into_inner(),
+            locations: self.locations.into_inner(),
             root,
+            lambdas: RefCell::default(),
+            lambda_captures: RefCell::default(),
+            tail_calls: Cell::new(false),
+            record_types: RecordTypes::default(),
+            function: None,
         }
     }
 }
@@ -1192,9 +2421,13 @@ pub struct CodeUnit {
     declarations_by_symbol: HashMap<Symbol, usize>,
     declarations: Vec<DeclaredSymbol>,
     vtable: Vec<Function>,
-    modules: Vec<CodeUnit>,
+    structs: Vec<Struct>,
+    enums: Vec<Enum>,
+    modules: Vec<(Symbol, CodeUnit)>,
+    imports: Vec<(Symbol, String)>,
     init_statements: Vec<NodeId>,
     init_tree: SyntaxTreeBuilder,
+    source_name: Option<Symbol>,
 }
 
 impl CodeUnit {
@@ -1203,12 +2436,24 @@ impl CodeUnit {
             declarations_by_symbol: HashMap::new(),
             declarations: Vec::new(),
             modules: Vec::new(),
+            imports: Vec::new(),
             vtable: Vec::new(),
+            structs: Vec::new(),
+            enums: Vec::new(),
             init_statements,
             init_tree,
+            source_name: None,
         }
     }
 
+    /// Sets the name of the source this unit was parsed from, such as a file
+    /// path. The name is included in the debug information of each compiled
+    /// function, including the functions of any modules that don't have their
+    /// own source name.
+    pub fn set_source_name(&mut self, name: impl Into<Symbol>) {
+        self.source_name = Some(name.into());
+    }
+
     pub fn new(init: impl FnOnce(&mut SyntaxTreeBuilder) -> Vec<NodeId>) -> Self {
         let mut init_tree = SyntaxTreeBuilder::default();
         let init_statements = init(&mut init_tree);
@@ -1216,27 +2461,149 @@ impl CodeUnit {
     }
 
     pub fn with(mut self, name: impl Into<Symbol>, declaration: impl Into<Declaration>) -> Self {
+        let name = name.into();
         let declaration_index = self.declarations.len();
         let declaration = match declaration.into() {
             Declaration::Function(function) => {
                 let vtable_index = self.vtable.len();
                 self.vtable.push(function);
-                self.declarations_by_symbol
-                    .insert(name.into(), declaration_index);
+                self.declarations_by_symbol.insert(name, declaration_index);
                 DeclaredSymbol::Function(vtable_index)
             }
             Declaration::Module(module) => {
                 let module_index = self.modules.len();
-                self.modules.push(module);
-                self.declarations_by_symbol
-                    .insert(name.into(), declaration_index);
+                self.modules.push((name.clone(), module));
+                self.declarations_by_symbol.insert(name, declaration_index);
                 DeclaredSymbol::Module(module_index)
             }
+            Declaration::Import(path) => {
+                self.imports.push((name.clone(), path));
+                self.declarations_by_symbol.insert(name, declaration_index);
+                DeclaredSymbol::Import
+            }
+            Declaration::Struct(mut definition) => {
+                // Methods are compiled as functions named `Struct.method`.
+                for mut method in std::mem::take(&mut definition.methods) {
+                    method.name =
+                        Symbol::from(format!("{}.{}", definition.name, method.name).as_str());
+                    self.vtable.push(method);
+                }
+                self.structs.push(definition);
+                self.declarations_by_symbol.insert(name, declaration_index);
+                DeclaredSymbol::Struct
+            }
+            Declaration::Enum(mut definition) => {
+                // Methods are compiled as functions named `Enum.method`.
+                for mut method in std::mem::take(&mut definition.methods) {
+                    method.name =
+                        Symbol::from(format!("{}.{}", definition.name, method.name).as_str());
+                    self.vtable.push(method);
+                }
+                self.enums.push(definition);
+                self.declarations_by_symbol.insert(name, declaration_index);
+                DeclaredSymbol::Enum
+            }
         };
         self.declarations.push(declaration);
         self
     }
 
+    /// Returns true if this unit or any of its modules contain imports that
+    /// have not been resolved.
+    #[must_use]
+    pub fn has_imports(&self) -> bool {
+        !self.imports.is_empty() || self.modules.iter().any(|(_, module)| module.has_imports())
+    }
+
+    /// Loads each imported module using `loader`, including any modules they
+    /// import. Each import becomes a module declaration in this unit.
+    pub fn resolve_imports(&mut self, loader: &mut dyn SourceLoader) -> Result<(), ImportError> {
+        self.resolve_imports_within(loader, &mut Vec::new())
+    }
+
+    fn resolve_imports_within(
+        &mut self,
+        loader: &mut dyn SourceLoader,
+        importing: &mut Vec<String>,
+    ) -> Result<(), ImportError> {
+        for (_, module) in &mut self.modules {
+            module.resolve_imports_within(loader, importing)?;
+        }
+
+        for (name, path) in std::mem::take(&mut self.imports) {
+            if importing.contains(&path) {
+                return Err(ImportError::Cycle(path));
+            }
+
+            let source = loader.load(&path)?;
+            let mut module = parse_module(&source).map_err(|error| ImportError::Parse {
+                path: path.clone(),
+                error,
+            })?;
+            module.set_source_name(path.as_str());
+            importing.push(path);
+            module.resolve_imports_within(loader, importing)?;
+            importing.pop();
+
+            let module_index = self.modules.len();
+            self.modules.push((name.clone(), module));
+            if let Some(declaration_index) = self.declarations_by_symbol.get(&name) {
+                self.declarations[*declaration_index] = DeclaredSymbol::Module(module_index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Invokes `callback` with the name of each function declared by this
+    /// unit's modules, qualified by the module path relative to this unit.
+    fn map_each_module_function(&self, prefix: &str, callback: &mut impl FnMut(Symbol)) {
+        for (name, module) in &self.modules {
+            let prefix = format!("{prefix}{name}.");
+            for function in &module.vtable {
+                callback(Symbol::from(format!("{prefix}{}", function.name).as_str()));
+            }
+            for definition in &module.structs {
+                callback(Symbol::from(
+                    format!("{prefix}{}", definition.name).as_str(),
+                ));
+            }
+            for definition in &module.enums {
+                for constructor in definition.constructor_names() {
+                    callback(Symbol::from(format!("{prefix}{constructor}").as_str()));
+                }
+            }
+            module.map_each_module_function(&prefix, callback);
+        }
+    }
+
+    /// Adds the record types declared by this unit and its modules to `types`.
+    fn collect_record_types(&self, module_path: &str, types: &mut Vec<RecordType>) {
+        for definition in &self.structs {
+            let name = Symbol::from(format!("{module_path}{}", definition.name).as_str());
+            types.push(RecordType {
+                kind: name.clone(),
+                name,
+                variant: None,
+                field_count: definition.fields.len(),
+            });
+        }
+        for definition in &self.enums {
+            let kind = Symbol::from(format!("{module_path}{}", definition.name).as_str());
+            for variant in &definition.variants {
+                types.push(RecordType {
+                    name: Symbol::from(format!("{kind}.{}", variant.name).as_str()),
+                    kind: kind.clone(),
+                    variant: Some(variant.name.clone()),
+                    field_count: variant.fields.len(),
+                });
+            }
+        }
+        for (name, module) in &self.modules {
+            module.collect_record_types(&format!("{module_path}{name}."), types);
+        }
+    }
+
     pub fn compile<
         InitScope: Scope<Environment = E>,
         E: budvm::Environment<Intrinsic = Intrinsic>,
@@ -1244,26 +2611,99 @@ impl CodeUnit {
         self,
         scope: &mut InitScope,
     ) -> Result<Module<Intrinsic>, CompilationError> {
+        // Every function that is already defined can be referenced as a value.
+        let mut functions = Vec::new();
+        scope.map_each_symbol(&mut |symbol, kind| {
+            if let ScopeSymbolKind::Function = kind {
+                functions.push(symbol);
+            }
+        });
+        let mut types = Vec::new();
+        self.collect_record_types("", &mut types);
+        let record_types = RecordTypes {
+            module_path: String::new(),
+            types: Rc::new(types),
+        };
+        self.compile_with_functions(scope, functions, None, &record_types)
+    }
+
+    fn compile_with_functions<
+        InitScope: Scope<Environment = E>,
+        E: budvm::Environment<Intrinsic = Intrinsic>,
+    >(
+        self,
+        scope: &mut InitScope,
+        mut functions: Vec<Symbol>,
+        source_name: Option<Symbol>,
+        record_types: &RecordTypes,
+    ) -> Result<Module<Intrinsic>, CompilationError> {
+        let source_name = self.source_name.clone().or(source_name);
+        if let Some((_, path)) = self.imports.first() {
+            return Err(CompilationError::UnresolvedImport(path.clone()));
+        }
+
+        // Functions being defined by this unit and its modules can also be
+        // referenced as values.
+        functions.extend(self.vtable.iter().map(|f| f.name.clone()));
+        functions.extend(self.structs.iter().map(|s| s.name.clone()));
+        functions.extend(self.enums.iter().flat_map(Enum::constructor_names));
+        self.map_each_module_function("", &mut |function| functions.push(function));
+
         let init = match self.init_statements.len() {
             0 => None,
             1 => Some(self.init_statements[0]),
             _ => Some(self.init_tree.statements(self.init_statements)),
         };
-        let vtable = self
-            .vtable
-            .into_iter()
-            .map(|f| {
-                let mut block = CodeBlockBuilder::default();
-                for arg in &f.args {
-                    block.new_argument(arg.clone());
-                }
-                f.body.generate_code(&mut block)?;
-                Ok(ir::Function::new(f.name, block.finish()))
-            })
-            .collect::<Result<_, CompilationError>>()?;
+
+        let mut vtable = Vec::with_capacity(self.vtable.len() + self.structs.len());
+        // Constructors are linked first, as they don't call any other
+        // functions.
+        for definition in &self.structs {
+            // Records are identified by the struct's name qualified by the
+            // path of the module it is declared in.
+            let kind = format!("{}{}", record_types.module_path, definition.name);
+            vtable.push(record_constructor(
+                definition.name.clone(),
+                &kind,
+                None,
+                &definition.fields,
+                source_name.clone(),
+            ));
+        }
+        for definition in &self.enums {
+            let kind = format!("{}{}", record_types.module_path, definition.name);
+            for variant in &definition.variants {
+                vtable.push(record_constructor(
+                    Symbol::from(format!("{}.{}", definition.name, variant.name).as_str()),
+                    &kind,
+                    Some(&variant.name),
+                    &variant.fields,
+                    source_name.clone(),
+                ));
+            }
+        }
+        for mut f in self.vtable {
+            f.body.record_types = record_types.clone();
+            f.body.function = Some(f.name.clone());
+            let mut block = CodeBlockBuilder::default();
+            block.set_source(source_name.clone());
+            for function in &functions {
+                block.add_function(function.clone());
+            }
+            for arg in &f.args {
+                block.new_argument(arg.clone());
+            }
+            f.body.generate_function_code(&mut block)?;
+            vtable.append(&mut f.body.take_lambdas());
+            vtable.push(ir::Function::new(f.name, block.finish()));
+        }
 
         let init = if let Some(body) = init {
             let mut block = CodeBlockBuilder::default();
+            block.set_source(source_name.clone());
+            for function in &functions {
+                block.add_function(function.clone());
+            }
             // Define any existing variables
             scope.map_each_symbol(&mut |symbol, kind| match kind {
                 ScopeSymbolKind::Variable => {
@@ -1271,10 +2711,19 @@ impl CodeUnit {
                 }
                 ScopeSymbolKind::Function | ScopeSymbolKind::Argument => {}
             });
-            self.init_tree.finish(body).generate_code(&mut block)?;
+            let mut tree = self.init_tree.finish(body);
+            tree.record_types = record_types.clone();
+            tree.generate_code(&mut block)?;
+            vtable.append(&mut tree.take_lambdas());
             for (symbol, variable) in block.variables() {
                 scope.define_persistent_variable(symbol.clone(), variable.clone());
             }
+            // Variables scoped to blocks aren't persisted, but the storage
+            // they used must still be reserved for the indexes above to
+            // remain valid.
+            for variable in block.unused_variables() {
+                scope.define_persistent_variable(variable.name().clone(), variable);
+            }
 
             Some(ir::Function::new("__init", block.finish()))
         } else {
@@ -1285,7 +2734,15 @@ impl CodeUnit {
             vtable,
             self.modules
                 .into_iter()
-                .map(|unit| unit.compile(scope))
+                .map(|(name, unit)| {
+                    unit.compile_with_functions(
+                        scope,
+                        functions.clone(),
+                        source_name.clone(),
+                        &record_types.within(&name),
+                    )
+                    .map(|module| module.with_name(name))
+                })
                 .collect::<Result<_, CompilationError>>()?,
             init,
         ))
@@ -1296,6 +2753,13 @@ impl CodeUnit {
 pub enum Declaration {
     Function(Function),
     Module(CodeUnit),
+    /// A module that must be loaded using
+    /// [`CodeUnit::resolve_imports`] before compiling.
+    Import(String),
+    /// A record type declared with `struct`.
+    Struct(Struct),
+    /// A record type declared with `enum`.
+    Enum(Enum),
 }
 
 impl From<Function> for Declaration {
@@ -1310,10 +2774,26 @@ impl From<CodeUnit> for Declaration {
     }
 }
 
+impl From<Struct> for Declaration {
+    fn from(definition: Struct) -> Self {
+        Self::Struct(definition)
+    }
+}
+
+impl From<Enum> for Declaration {
+    fn from(definition: Enum) -> Self {
+        Self::Enum(definition)
+    }
+}
+
 #[derive(Debug)]
 enum DeclaredSymbol {
     Function(usize),
     Module(usize),
+    // Replaced by a module once the import is resolved.
+    Import,
+    Struct,
+    Enum,
 }
 
 #[derive(Debug)]
@@ -1338,18 +2818,268 @@ impl Function {
     }
 }
 
+/// A record type declared with `struct`.
+///
+/// Declaring a struct defines a constructor function with the struct's name
+/// that accepts one argument per field. Each method is a [`Function`] whose
+/// first argument is the record the method was invoked on.
+#[derive(Debug)]
+pub struct Struct {
+    name: Symbol,
+    fields: Vec<Symbol>,
+    methods: Vec<Function>,
+}
+
+impl Struct {
+    pub fn new(name: impl Into<Symbol>, fields: Vec<Symbol>, methods: Vec<Function>) -> Self {
+        Self {
+            name: name.into(),
+            fields,
+            methods,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+}
+
+/// A tagged record type declared with `enum`.
+///
+/// Declaring an enum defines a constructor function named `Enum.Variant` for
+/// each variant that accepts one argument per field. Referring to a variant
+/// without fields creates a record without calling its constructor. Each
+/// method is a [`Function`] whose first argument is the record the method was
+/// invoked on.
+#[derive(Debug)]
+pub struct Enum {
+    name: Symbol,
+    variants: Vec<Variant>,
+    methods: Vec<Function>,
+}
+
+impl Enum {
+    pub fn new(name: impl Into<Symbol>, variants: Vec<Variant>, methods: Vec<Function>) -> Self {
+        Self {
+            name: name.into(),
+            variants,
+            methods,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    fn constructor_names(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.variants
+            .iter()
+            .map(|variant| Symbol::from(format!("{}.{}", self.name, variant.name).as_str()))
+    }
+}
+
+#[derive(Debug)]
+pub struct Variant {
+    name: Symbol,
+    fields: Vec<Symbol>,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<Symbol>, fields: Vec<Symbol>) -> Self {
+        Self {
+            name: name.into(),
+            fields,
+        }
+    }
+}
+
+/// Returns a function named `name` that creates a record of type `kind`
+/// containing one field per argument.
+fn record_constructor(
+    name: Symbol,
+    kind: &str,
+    variant: Option<&Symbol>,
+    fields: &[Symbol],
+    source_name: Option<Symbol>,
+) -> ir::Function<Intrinsic> {
+    let mut block = CodeBlockBuilder::default();
+    block.set_source(source_name);
+    block.push(Instruction::Push(LiteralOrSource::Literal(
+        Literal::String(kind.to_string()),
+    )));
+    block.push(Instruction::Push(LiteralOrSource::Literal(
+        variant.map_or(Literal::Void, |variant| {
+            Literal::String(variant.to_string())
+        }),
+    )));
+    for field in fields {
+        let argument = block.new_argument(field.clone());
+        block.push(Instruction::Push(LiteralOrSource::Literal(
+            Literal::String(field.to_string()),
+        )));
+        block.push(Instruction::Push(LiteralOrSource::Argument(argument)));
+    }
+    block.push(Instruction::CallIntrinsic {
+        intrinsic: Intrinsic::NewRecord,
+        arg_count: 2 + fields.len() * 2,
+        destination: Destination::Return,
+    });
+    ir::Function::new(name, block.finish())
+}
+
+/// The struct types and enum variants known while compiling, used to resolve
+/// the paths used in patterns and to variants without fields.
+#[derive(Debug, Default, Clone)]
+struct RecordTypes {
+    /// The path of the module being compiled, such as `outer.inner.`.
+    module_path: String,
+    types: Rc<Vec<RecordType>>,
+}
+
+impl RecordTypes {
+    /// Returns the types as seen from within the module `name`.
+    fn within(&self, name: &Symbol) -> Self {
+        Self {
+            module_path: format!("{}{name}.", self.module_path),
+            types: self.types.clone(),
+        }
+    }
+
+    /// Resolves `path` by searching the current module first, followed by
+    /// each enclosing module.
+    fn resolve(&self, path: &Symbol) -> Option<&RecordType> {
+        let mut prefix = self.module_path.as_str();
+        loop {
+            let qualified = format!("{prefix}{path}");
+            if let Some(found) = self.types.iter().find(|ty| ty.name == qualified.as_str()) {
+                return Some(found);
+            }
+            if prefix.is_empty() {
+                return None;
+            }
+            // Remove the innermost module from the prefix.
+            let parent = &prefix[..prefix.len() - 1];
+            prefix = parent.rfind('.').map_or("", |dot| &prefix[..=dot]);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RecordType {
+    /// The fully-qualified name of the struct or variant.
+    name: Symbol,
+    kind: Symbol,
+    variant: Option<Symbol>,
+    field_count: usize,
+}
+
+impl RecordType {
+    fn is_unit_variant(&self) -> bool {
+        self.variant.is_some() && self.field_count == 0
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum CompilationError {
-    UndefinedFunction(Symbol),
-    UndefinedIdentifier(Symbol),
+    UndefinedFunction {
+        name: Symbol,
+        /// The name of the source the call is in, if known.
+        source: Option<Symbol>,
+        /// The range of the call within the source, if known.
+        range: Option<Range<usize>>,
+    },
+    UndefinedIdentifier {
+        name: Symbol,
+        /// The name of the source the identifier is in, if known.
+        source: Option<Symbol>,
+        /// The range of the identifier within the source, if known.
+        range: Option<Range<usize>>,
+    },
+    /// A variable was referenced after the block it was assigned in ended.
+    OutOfScope {
+        name: Symbol,
+        /// The name of the source the identifier is in, if known.
+        source: Option<Symbol>,
+        /// The range of the identifier within the source, if known.
+        range: Option<Range<usize>>,
+    },
+    /// A struct or enum variant was constructed with the wrong number of
+    /// fields.
+    ArgumentCountMismatch {
+        name: Symbol,
+        expected: usize,
+        received: usize,
+        /// The name of the source the call is in, if known.
+        source: Option<Symbol>,
+        /// The range of the call within the source, if known.
+        range: Option<Range<usize>>,
+    },
     InvalidScope,
+    UnresolvedImport(String),
+}
+
+impl CompilationError {
+    /// Returns the source range for this error, if available.
+    #[must_use]
+    pub fn location(&self) -> Option<Range<usize>> {
+        match self {
+            CompilationError::UndefinedFunction { range, .. }
+            | CompilationError::UndefinedIdentifier { range, .. }
+            | CompilationError::OutOfScope { range, .. }
+            | CompilationError::ArgumentCountMismatch { range, .. } => range.clone(),
+            CompilationError::InvalidScope | CompilationError::UnresolvedImport(_) => None,
+        }
+    }
+
+    /// Returns the name of the source [`Self::location()`] is in, if known.
+    #[must_use]
+    pub fn source_name(&self) -> Option<&Symbol> {
+        match self {
+            CompilationError::UndefinedFunction { source, .. }
+            | CompilationError::UndefinedIdentifier { source, .. }
+            | CompilationError::OutOfScope { source, .. }
+            | CompilationError::ArgumentCountMismatch { source, .. } => source.as_ref(),
+            CompilationError::InvalidScope | CompilationError::UnresolvedImport(_) => None,
+        }
+    }
+
+    fn located(mut self, source_name: Option<&Symbol>, location: Range<usize>) -> Self {
+        match &mut self {
+            CompilationError::UndefinedFunction { source, range, .. }
+            | CompilationError::UndefinedIdentifier { source, range, .. }
+            | CompilationError::OutOfScope { source, range, .. }
+            | CompilationError::ArgumentCountMismatch { source, range, .. } => {
+                if range.is_none() {
+                    *source = source_name.cloned();
+                    *range = Some(location);
+                }
+            }
+            CompilationError::InvalidScope | CompilationError::UnresolvedImport(_) => {}
+        }
+        self
+    }
 }
 
 impl From<LinkError> for CompilationError {
     fn from(err: LinkError) -> Self {
         match err {
-            LinkError::UndefinedFunction(name) => CompilationError::UndefinedFunction(name),
-            LinkError::UndefinedIdentifier(name) => CompilationError::UndefinedIdentifier(name),
+            LinkError::UndefinedFunction(name) => CompilationError::UndefinedFunction {
+                name,
+                source: None,
+                range: None,
+            },
+            LinkError::UndefinedIdentifier(name) => CompilationError::UndefinedIdentifier {
+                name,
+                source: None,
+                range: None,
+            },
+            LinkError::OutOfScope(name) => CompilationError::OutOfScope {
+                name,
+                source: None,
+                range: None,
+            },
             LinkError::InvalidScopeOperation => CompilationError::InvalidScope,
             LinkError::InvalidLabel(_label) => unreachable!("invalid label encountered"),
         }
@@ -1361,14 +3091,34 @@ impl std::error::Error for CompilationError {}
 impl Display for CompilationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CompilationError::UndefinedFunction(symbol) => {
-                write!(f, "undefined function: {symbol}")
+            CompilationError::UndefinedFunction { name, .. } => {
+                write!(f, "undefined function: {name}")
             }
             CompilationError::InvalidScope => {
                 write!(f, "the scope used did not support a required operation")
             }
-            CompilationError::UndefinedIdentifier(symbol) => {
-                write!(f, "undefined identifier: {symbol}")
+            CompilationError::UndefinedIdentifier { name, .. } => {
+                write!(f, "undefined identifier: {name}")
+            }
+            CompilationError::OutOfScope { name, .. } => {
+                write!(
+                    f,
+                    "variable out of scope: {name} is only defined within a block that has ended"
+                )
+            }
+            CompilationError::UnresolvedImport(path) => {
+                write!(f, "import has not been loaded: {path}")
+            }
+            CompilationError::ArgumentCountMismatch {
+                name,
+                expected,
+                received,
+                ..
+            } => {
+                write!(
+                    f,
+                    "{name} expects {expected} field(s), but {received} were passed"
+                )
             }
         }
     }
@@ -0,0 +1,239 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use budvm::{allocate, charge, Environment, FaultKind, PoppedValues, Symbol, Value, ValueKind};
+
+/// The largest width or precision a placeholder may request.
+const MAX_WIDTH: usize = u16::MAX as usize;
+
+/// Evaluates the `format` intrinsic: the first argument is a template whose
+/// placeholders are replaced with the remaining arguments, in order.
+///
+/// Each placeholder is written as `{}` or `{:<spec>}`, where `<spec>` is
+/// `[[fill]align][0][width][.precision]`. `{{` and `}}` insert a literal
+/// brace. Widths and precisions above 65,535 fault with
+/// [`FaultKind::ValueOutOfRange`].
+pub fn format<Env>(environment: &Env, mut args: PoppedValues<'_>) -> Result<Value, FaultKind>
+where
+    Env: Environment,
+    Env::String: Display,
+{
+    let template = args.next_argument("template")?;
+    let template_contents = match template.as_dynamic::<Env::String>() {
+        Some(template) => template.to_string(),
+        None => {
+            return Err(FaultKind::invalid_type(
+                "template must be a string. Found `@received-value` (@received-kind)",
+                template,
+            ))
+        }
+    };
+
+    charge(template_contents.len());
+    allocate(template_contents.len())?;
+    let mut formatted = String::with_capacity(template_contents.len());
+    let mut chars = template_contents.chars().peekable();
+    let mut argument_index = 0;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                formatted.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                formatted.push('}');
+            }
+            '{' => {
+                let spec = Spec::parse(&mut chars).ok_or_else(|| invalid_template(&template))?;
+                argument_index += 1;
+                let value = args.next_argument(&argument_index.to_string())?;
+                spec.write(&value, environment, &mut formatted)?;
+            }
+            '}' => return Err(invalid_template(&template)),
+            ch => formatted.push(ch),
+        }
+    }
+    args.verify_empty()?;
+
+    Ok(Value::dynamic(<Env::String as From<String>>::from(
+        formatted,
+    )))
+}
+
+fn invalid_template(template: &Value) -> FaultKind {
+    FaultKind::invalid_type("invalid format template: @received-value", template.clone())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '<' => Some(Self::Left),
+            '^' => Some(Self::Center),
+            '>' => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+/// The specification of how to format a single placeholder.
+#[derive(Debug, Default)]
+struct Spec {
+    fill: Option<char>,
+    alignment: Option<Alignment>,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Parses the placeholder whose opening brace has been read, consuming its
+    /// closing brace.
+    fn parse(chars: &mut Peekable<Chars<'_>>) -> Option<Self> {
+        let mut spec = Self::default();
+        match chars.next()? {
+            '}' => return Some(spec),
+            ':' => {}
+            _ => return None,
+        }
+
+        // The fill character is only present when followed by an alignment.
+        let mut lookahead = chars.clone();
+        match (lookahead.next(), lookahead.next()) {
+            (Some(fill), Some(alignment)) if Alignment::from_char(alignment).is_some() => {
+                spec.fill = Some(fill);
+                spec.alignment = Alignment::from_char(alignment);
+                chars.next();
+                chars.next();
+            }
+            (Some(alignment), _) if Alignment::from_char(alignment).is_some() => {
+                spec.alignment = Alignment::from_char(alignment);
+                chars.next();
+            }
+            _ => {}
+        }
+
+        if chars.peek() == Some(&'0') {
+            spec.zero_pad = true;
+            chars.next();
+        }
+        spec.width = parse_number(chars).unwrap_or_default();
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = Some(parse_number(chars)?);
+        }
+
+        (chars.next()? == '}').then_some(spec)
+    }
+
+    fn write<Env>(
+        &self,
+        value: &Value,
+        environment: &Env,
+        formatted: &mut String,
+    ) -> Result<(), FaultKind>
+    where
+        Env: Environment,
+        Env::String: Display,
+    {
+        if self.width > MAX_WIDTH {
+            return Err(FaultKind::ValueOutOfRange("width"));
+        }
+        if self
+            .precision
+            .map_or(false, |precision| precision > MAX_WIDTH)
+        {
+            return Err(FaultKind::ValueOutOfRange("precision"));
+        }
+
+        let is_numeric = matches!(value, Value::Integer(_) | Value::Real(_));
+        let mut contents = match (value, self.precision) {
+            (Value::Real(value), Some(precision)) => {
+                charge(precision);
+                allocate(precision)?;
+                format!("{value:.precision$}")
+            }
+            (value, precision) => {
+                let converted =
+                    value.convert(&ValueKind::Dynamic(Symbol::from("String")), environment)?;
+                let contents = string_contents::<Env>(&converted);
+                match precision {
+                    Some(precision) if !is_numeric => contents.chars().take(precision).collect(),
+                    _ => contents,
+                }
+            }
+        };
+
+        charge(contents.len());
+        allocate(contents.len())?;
+        let length = contents.chars().count();
+        if length >= self.width {
+            formatted.push_str(&contents);
+            return Ok(());
+        }
+        let padding = self.width - length;
+
+        if self.zero_pad && is_numeric && self.alignment.is_none() {
+            // Zeroes are inserted between the sign and the digits.
+            if contents.starts_with('-') {
+                formatted.push('-');
+                contents.remove(0);
+            }
+            charge(padding);
+            allocate(padding)?;
+            formatted.extend(std::iter::repeat('0').take(padding));
+            formatted.push_str(&contents);
+            return Ok(());
+        }
+
+        let alignment = self.alignment.unwrap_or(if is_numeric {
+            Alignment::Right
+        } else {
+            Alignment::Left
+        });
+        let (before, after) = match alignment {
+            Alignment::Left => (0, padding),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+            Alignment::Right => (padding, 0),
+        };
+        let fill = self.fill.unwrap_or(' ');
+        charge(padding);
+        allocate(padding.saturating_mul(fill.len_utf8()))?;
+        formatted.extend(std::iter::repeat(fill).take(before));
+        formatted.push_str(&contents);
+        formatted.extend(std::iter::repeat(fill).take(after));
+        Ok(())
+    }
+}
+
+/// Returns the text of a value that has been converted to a String.
+fn string_contents<Env>(value: &Value) -> String
+where
+    Env: Environment,
+    Env::String: Display,
+{
+    // Custom types may convert to the built-in String type rather than the
+    // environment's String type.
+    if let Some(string) = value.as_dynamic::<Env::String>() {
+        string.to_string()
+    } else if let Some(string) = value.as_dynamic::<String>() {
+        string.clone()
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars<'_>>) -> Option<usize> {
+    let mut number = None;
+    while let Some(digit) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+        chars.next();
+        number = Some(number.unwrap_or(0_usize).checked_mul(10)? + digit as usize);
+    }
+    number
+}
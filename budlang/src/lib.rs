@@ -18,25 +18,34 @@ use std::{
     fmt::Display,
     ops::{Deref, DerefMut, Range},
     str::FromStr,
+    time::Instant,
 };
 
 use budvm::{
-    ir::{LinkError, Scope},
-    Fault, FaultKind, FromStack, NativeFunction, Symbol, Value,
+    bytecode::BytecodeError,
+    ir::{optimizer::OptimizationLevel, LinkError, Module, Scope},
+    DebugInfo, Fault, FaultKind, FaultStackFrame, FromStack, NativeFunction, Symbol, Value,
 };
 
 pub use budvm as vm;
 use vm::{
-    Budgeted, DynamicValue, ExecutionBehavior, Function, HashMap, List, PoppedValues,
-    VirtualMachine,
+    AllocationBudget, ArithmeticMode, Budgeted, CancellationToken, DynamicValue, ExecutionBehavior,
+    Function, HashMap, List, PoppedValues, Record, VirtualMachine,
 };
 
-use crate::parser::parse;
+use crate::{
+    ast::CodeUnit,
+    loader::{ImportError, SourceLoader},
+    parser::parse,
+};
 
 /// The abstract syntax tree Bud uses.
 pub mod ast;
 
-// mod optimizer;
+mod format;
+/// Loading of imported modules.
+pub mod loader;
+mod matching;
 /// The interface for parsing Bud code.
 pub mod parser;
 
@@ -50,6 +59,10 @@ where
     Parse(parser::ParseError),
     /// An error occurred while compiling [`CodeUnit`](ast::CodeUnit).
     Compilation(ast::CompilationError),
+    /// An error occurred while importing a module.
+    Import(loader::ImportError),
+    /// Bytecode could not be decoded.
+    Bytecode(BytecodeError),
     /// A fault occurred while running the virtual machine.
     Vm(budvm::Error<'a, BudEnvironment<Env>, ReturnType>),
 }
@@ -62,6 +75,8 @@ where
         match self {
             Self::Parse(arg0) => Self::Parse(arg0.clone()),
             Self::Compilation(arg0) => Self::Compilation(arg0.clone()),
+            Self::Import(arg0) => Self::Import(arg0.clone()),
+            Self::Bytecode(arg0) => Self::Bytecode(arg0.clone()),
             Self::Vm(arg0) => Self::Vm(arg0.clone()),
         }
     }
@@ -84,16 +99,44 @@ where
         match self {
             Error::Parse(parse) => Error::Parse(parse),
             Error::Compilation(compilation) => Error::Compilation(compilation),
+            Error::Import(import) => Error::Import(import),
+            Error::Bytecode(bytecode) => Error::Bytecode(bytecode),
             Error::Vm(err) => Error::Vm(err.expect_no_pause()),
         }
     }
 
     /// Returns the source range for this error, if available.
+    ///
+    /// For faults raised by the virtual machine, this is the location of the
+    /// innermost stack frame that has debug information. Use
+    /// [`Error::stack_trace()`] to determine which source the location is in.
+    /// For compilation errors, use
+    /// [`CompilationError::source_name()`](ast::CompilationError::source_name).
     #[must_use]
     pub fn location(&self) -> Option<Range<usize>> {
         match self {
             Error::Parse(err) => err.location(),
-            Error::Compilation(_) | Error::Vm(_) => None,
+            Error::Compilation(err) => err.location(),
+            Error::Vm(_) => self
+                .stack_trace()
+                .iter()
+                .rev()
+                .find_map(|frame| frame.location.as_ref())
+                .map(|location| location.range.clone()),
+            Error::Import(_) | Error::Bytecode(_) => None,
+        }
+    }
+
+    /// Returns the stack trace of the virtual machine when a fault was
+    /// raised. The first entry is the outermost frame, and the last entry is
+    /// the frame that raised the fault.
+    ///
+    /// Returns an empty slice if this error is not a fault.
+    #[must_use]
+    pub fn stack_trace(&self) -> &[FaultStackFrame] {
+        match self {
+            Error::Vm(budvm::Error::Fault(fault)) => &fault.stack,
+            _ => &[],
         }
     }
 }
@@ -113,6 +156,8 @@ where
         match self {
             Error::Parse(err) => write!(f, "parse error: {err}"),
             Error::Compilation(err) => write!(f, "compilation error: {err}"),
+            Error::Import(err) => write!(f, "import error: {err}"),
+            Error::Bytecode(err) => write!(f, "bytecode error: {err}"),
             Error::Vm(err) => write!(f, "vm error: {err}"),
         }
     }
@@ -136,6 +181,24 @@ where
     }
 }
 
+impl<'a, Env, ReturnType> From<loader::ImportError> for Error<'a, Env, ReturnType>
+where
+    Env: Environment,
+{
+    fn from(err: loader::ImportError) -> Self {
+        Self::Import(err)
+    }
+}
+
+impl<'a, Env, ReturnType> From<BytecodeError> for Error<'a, Env, ReturnType>
+where
+    Env: Environment,
+{
+    fn from(err: BytecodeError) -> Self {
+        Self::Bytecode(err)
+    }
+}
+
 impl<'a, Env, ReturnType> From<LinkError> for Error<'a, Env, ReturnType>
 where
     Env: Environment,
@@ -178,9 +241,17 @@ where
 /// Each instance of this type has its own sandboxed environment. Its stack
 /// space, function declarations, and [`Environment`] are unique from all other
 /// instances of Bud with the exception that [`Symbol`]s are tracked globally.
-pub struct Bud<Env>(VirtualMachine<BudEnvironment<Env>>)
+///
+/// Modules imported using `import "path"` are loaded using the instance's
+/// [`SourceLoader`]. By default, no loader is installed and all imports fail.
+pub struct Bud<Env>
 where
-    Env: Environment;
+    Env: Environment,
+{
+    vm: VirtualMachine<BudEnvironment<Env>>,
+    loader: Option<Box<dyn SourceLoader>>,
+    optimization_level: OptimizationLevel,
+}
 
 impl Bud<()> {
     /// Returns a default instance of Bud with no custom [`Environment`]
@@ -196,7 +267,11 @@ where
 {
     /// Returns a new instance with the provided virtual machine.
     pub const fn new(vm: VirtualMachine<BudEnvironment<Env>>) -> Self {
-        Self(vm)
+        Self {
+            vm,
+            loader: None,
+            optimization_level: OptimizationLevel::None,
+        }
     }
 
     /// Returns a new instance with the provided environment.
@@ -204,6 +279,129 @@ where
         Self::new(VirtualMachine::default_for(BudEnvironment(environment)))
     }
 
+    /// Sets the loader used to load imported modules and returns self. This
+    /// is a builder-style function.
+    #[must_use]
+    pub fn with_loader(mut self, loader: impl SourceLoader + 'static) -> Self {
+        self.set_loader(loader);
+        self
+    }
+
+    /// Sets the loader used to load imported modules.
+    pub fn set_loader(&mut self, loader: impl SourceLoader + 'static) {
+        self.loader = Some(Box::new(loader));
+    }
+
+    /// Sets the level of optimization applied to code compiled by this
+    /// instance and returns self. This is a builder-style function.
+    ///
+    /// By default, no optimizations are performed.
+    #[must_use]
+    pub fn with_optimization_level(mut self, level: OptimizationLevel) -> Self {
+        self.set_optimization_level(level);
+        self
+    }
+
+    /// Sets the level of optimization applied to code compiled by this
+    /// instance.
+    pub fn set_optimization_level(&mut self, level: OptimizationLevel) {
+        self.optimization_level = level;
+    }
+
+    /// Returns the level of optimization applied to code compiled by this
+    /// instance.
+    #[must_use]
+    pub const fn optimization_level(&self) -> OptimizationLevel {
+        self.optimization_level
+    }
+
+    /// Sets how integer overflow and division by zero are handled and returns
+    /// self. This is a builder-style function.
+    ///
+    /// By default, [`ArithmeticMode::Void`] is used.
+    #[must_use]
+    pub fn with_arithmetic_mode(mut self, mode: ArithmeticMode) -> Self {
+        self.set_arithmetic_mode(mode);
+        self
+    }
+
+    /// Sets how integer overflow and division by zero are handled.
+    pub fn set_arithmetic_mode(&mut self, mode: ArithmeticMode) {
+        self.vm.set_arithmetic_mode(mode);
+    }
+
+    /// Returns how integer overflow and division by zero are handled.
+    #[must_use]
+    pub const fn arithmetic_mode(&self) -> ArithmeticMode {
+        self.vm.arithmetic_mode()
+    }
+
+    /// Sets the maximum number of nested function calls and returns self.
+    /// This is a builder-style function.
+    ///
+    /// Calling a function while this many calls are already executing raises
+    /// [`FaultKind::CallDepthExceeded`]. By default,
+    /// [`DEFAULT_MAXIMUM_CALL_DEPTH`](vm::DEFAULT_MAXIMUM_CALL_DEPTH) is used.
+    #[must_use]
+    pub fn with_maximum_call_depth(mut self, maximum_call_depth: usize) -> Self {
+        self.set_maximum_call_depth(maximum_call_depth);
+        self
+    }
+
+    /// Sets the maximum number of nested function calls.
+    pub fn set_maximum_call_depth(&mut self, maximum_call_depth: usize) {
+        self.vm.set_maximum_call_depth(maximum_call_depth);
+    }
+
+    /// Returns the maximum number of nested function calls.
+    #[must_use]
+    pub const fn maximum_call_depth(&self) -> usize {
+        self.vm.maximum_call_depth()
+    }
+
+    /// Sets the token that can cancel execution and returns self. This is a
+    /// builder-style function.
+    ///
+    /// After [`CancellationToken::cancel()`] is called, executing code raises
+    /// [`FaultKind::Cancelled`].
+    #[must_use]
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.set_cancellation(Some(cancellation));
+        self
+    }
+
+    /// Sets the token that can cancel execution.
+    pub fn set_cancellation(&mut self, cancellation: Option<CancellationToken>) {
+        self.vm.set_cancellation(cancellation);
+    }
+
+    /// Returns the token that can cancel execution.
+    #[must_use]
+    pub const fn cancellation(&self) -> Option<&CancellationToken> {
+        self.vm.cancellation()
+    }
+
+    /// Sets the time at which execution is stopped and returns self. This is
+    /// a builder-style function.
+    ///
+    /// Code executing after `deadline` raises [`FaultKind::TimedOut`].
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.set_deadline(Some(deadline));
+        self
+    }
+
+    /// Sets the time at which execution is stopped.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.vm.set_deadline(deadline);
+    }
+
+    /// Returns the time at which execution is stopped.
+    #[must_use]
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.vm.deadline()
+    }
+
     /// Registers a function with the provided name and returns self. This is a
     /// builder-style function.
     #[must_use]
@@ -239,22 +437,38 @@ where
     pub fn evaluate<'a, ReturnType: FromStack>(
         &'a mut self,
         source: &str,
+    ) -> Result<ReturnType, Error<'a, Env, ReturnType>> {
+        self.evaluate_source(None, source)
+    }
+
+    /// Evaluates `source` interactively and returns the provided result. This
+    /// function behaves the same as [`Bud::evaluate()`], except that `name` is
+    /// recorded as the source of each function compiled, allowing
+    /// [`Error::stack_trace()`] to identify where faults occurred.
+    pub fn evaluate_named<'a, ReturnType: FromStack>(
+        &'a mut self,
+        name: impl Into<Symbol>,
+        source: &str,
+    ) -> Result<ReturnType, Error<'a, Env, ReturnType>> {
+        self.evaluate_source(Some(name.into()), source)
+    }
+
+    fn evaluate_source<'a, ReturnType: FromStack>(
+        &'a mut self,
+        name: Option<Symbol>,
+        source: &str,
     ) -> Result<ReturnType, Error<'a, Env, ReturnType>> {
         let previous_variable_count = self.persistent_variables().len();
-        let unit = parse(source)?.compile(&mut self.0)?;
-        for function in unit.vtable {
-            if env::var("PRINT_IR").is_ok() {
-                println!("function {}", function.name);
-            }
-            function.link_into(&mut self.0)?;
-        }
+        let mut unit = self.parse(name, source)?.compile(&mut self.vm)?;
+        unit.optimize(self.optimization_level);
+        unit.link_into(&mut self.vm)?;
 
         if let Some(init) = &unit.init {
             if env::var("PRINT_IR").is_ok() {
                 println!("function __init");
             }
 
-            let function = init.link(&mut self.0)?;
+            let function = init.link(&mut self.vm)?;
             let variable_count = self.persistent_variables().len();
             let new_variables = variable_count - previous_variable_count;
             if new_variables > 0 {
@@ -277,11 +491,99 @@ where
         &mut self,
         source: &str,
     ) -> Result<Output, Error<'_, Env, Output>> {
-        let unit = parse(source)?;
-        unit.compile(&mut self.0)?
-            .load_into(self)
+        self.run_source_internal(None, source)
+    }
+
+    /// Compiles `source` and executes it in this context. This function
+    /// behaves the same as [`Bud::run_source()`], except that `name` is
+    /// recorded as the source of each function compiled, allowing
+    /// [`Error::stack_trace()`] to identify where faults occurred.
+    pub fn run_source_named<Output: FromStack>(
+        &mut self,
+        name: impl Into<Symbol>,
+        source: &str,
+    ) -> Result<Output, Error<'_, Env, Output>> {
+        self.run_source_internal(Some(name.into()), source)
+    }
+
+    fn run_source_internal<Output: FromStack>(
+        &mut self,
+        name: Option<Symbol>,
+        source: &str,
+    ) -> Result<Output, Error<'_, Env, Output>> {
+        let mut unit = self.parse(name, source)?.compile(&mut self.vm)?;
+        unit.optimize(self.optimization_level);
+        unit.load_into(self).map_err(Error::from)
+    }
+
+    /// Compiles `source` and returns the resulting module encoded as
+    /// [bytecode](budvm::bytecode). The bytecode can be executed later using
+    /// [`Bud::run_bytecode()`], including by another process.
+    ///
+    /// Functions defined in this context are resolved by name when the
+    /// bytecode is executed, and must be defined in the context executing it.
+    pub fn compile_to_bytecode<ReturnType>(
+        &mut self,
+        source: &str,
+    ) -> Result<Vec<u8>, Error<'static, Env, ReturnType>> {
+        self.compile_to_bytecode_internal(None, source)
+    }
+
+    /// Compiles `source` and returns the resulting module encoded as
+    /// bytecode. This function behaves the same as
+    /// [`Bud::compile_to_bytecode()`], except that `name` is recorded as the
+    /// source of each function compiled, allowing [`Error::stack_trace()`] to
+    /// identify where faults occurred.
+    pub fn compile_to_bytecode_named<ReturnType>(
+        &mut self,
+        name: impl Into<Symbol>,
+        source: &str,
+    ) -> Result<Vec<u8>, Error<'static, Env, ReturnType>> {
+        self.compile_to_bytecode_internal(Some(name.into()), source)
+    }
+
+    fn compile_to_bytecode_internal<ReturnType>(
+        &mut self,
+        name: Option<Symbol>,
+        source: &str,
+    ) -> Result<Vec<u8>, Error<'static, Env, ReturnType>> {
+        let mut unit = self.parse(name, source)?.compile(&mut self.vm)?;
+        unit.optimize(self.optimization_level);
+        Ok(unit.to_bytecode())
+    }
+
+    /// Validates and executes bytecode produced by
+    /// [`Bud::compile_to_bytecode()`] in this context.
+    ///
+    /// The functions in the bytecode are statically verified before any of
+    /// them are executed. If verification fails,
+    /// [`budvm::Error::Verification`] is returned and none of the functions
+    /// are defined.
+    pub fn run_bytecode<Output: FromStack>(
+        &mut self,
+        bytecode: &[u8],
+    ) -> Result<Output, Error<'_, Env, Output>> {
+        Module::<Intrinsic>::from_bytecode(bytecode)?
+            .load_verified_into(self)
             .map_err(Error::from)
     }
+
+    /// Parses `source` and loads all of its imports.
+    fn parse<ReturnType>(
+        &mut self,
+        name: Option<Symbol>,
+        source: &str,
+    ) -> Result<CodeUnit, Error<'static, Env, ReturnType>> {
+        let mut unit = parse(source)?;
+        if let Some(name) = name {
+            unit.set_source_name(name);
+        }
+        if unit.has_imports() {
+            let loader = self.loader.as_deref_mut().ok_or(ImportError::NoLoader)?;
+            unit.resolve_imports(loader)?;
+        }
+        Ok(unit)
+    }
 }
 
 impl<Env> Scope for Bud<Env>
@@ -291,22 +593,30 @@ where
     type Environment = BudEnvironment<Env>;
 
     fn resolve_function_vtable_index(&self, name: &Symbol) -> Option<usize> {
-        self.0.resolve_function_vtable_index(name)
+        self.vm.resolve_function_vtable_index(name)
     }
 
     fn map_each_symbol(&self, callback: &mut impl FnMut(Symbol, vm::ir::ScopeSymbolKind)) {
-        self.0.map_each_symbol(callback);
+        self.vm.map_each_symbol(callback);
     }
 
     fn define_function(
         &mut self,
         function: vm::Function<<Self::Environment as vm::Environment>::Intrinsic>,
     ) -> Option<usize> {
-        self.0.define_function(function)
+        self.vm.define_function(function)
+    }
+
+    fn next_vtable_index(&self) -> Option<usize> {
+        self.vm.next_vtable_index()
     }
 
     fn define_persistent_variable(&mut self, name: Symbol, variable: vm::ir::Variable) {
-        self.0.define_persistent_variable(name, variable);
+        self.vm.define_persistent_variable(name, variable);
+    }
+
+    fn define_debug_info(&mut self, vtable_index: usize, debug_info: DebugInfo) {
+        self.vm.define_debug_info(vtable_index, debug_info);
     }
 }
 
@@ -317,7 +627,7 @@ where
     type Target = VirtualMachine<BudEnvironment<Env>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.vm
     }
 }
 
@@ -326,14 +636,14 @@ where
     Env: Environment,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.vm
     }
 }
 
 /// Customizes the behavior of a virtual machine instance.
 pub trait Environment: 'static {
     /// The string type for this environment.
-    type String: DynamicValue + for<'a> From<&'a str> + From<String>;
+    type String: DynamicValue + Display + for<'a> From<&'a str> + From<String>;
     /// The map type for this environment.
     type Map: DynamicValue + for<'a> TryFrom<PoppedValues<'a>, Error = FaultKind>;
     /// The list (array) type for this environment.
@@ -351,6 +661,36 @@ pub trait Environment: 'static {
     /// [`ExecutionBehavior::Pause`] was called.
     fn step(&mut self) -> ExecutionBehavior;
 
+    /// Called once before each instruction is executed with the instruction
+    /// that is about to be executed. The instruction is None when the current
+    /// function is about to return because it has no more instructions.
+    ///
+    /// The returned behavior is handled the same as [`step()`](Self::step).
+    /// The provided implementation calls [`step()`](Self::step).
+    fn step_instruction(
+        &mut self,
+        _instruction: Option<&vm::Instruction<Intrinsic>>,
+    ) -> ExecutionBehavior {
+        self.step()
+    }
+
+    /// Called after an instruction is executed with the total cost charged
+    /// using [`vm::charge()`] while executing it.
+    ///
+    /// The provided implementation does nothing.
+    fn consume(&mut self, _cost: usize) {}
+
+    /// Returns the budget that allocations made by the built-in `String`,
+    /// `List`, and `Map` types are charged against while executing. When the
+    /// budget is exhausted, [`FaultKind::OutOfMemory`] is raised. Memory that
+    /// is released is not credited back to the budget.
+    ///
+    /// The provided implementation returns None, which does not limit
+    /// allocations.
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        None
+    }
+
     /// Converts `value` to a custom type supported by the runtime.
     fn convert(&self, value: &Value, kind: &Symbol) -> Result<Value, FaultKind> {
         Err(FaultKind::invalid_type(
@@ -382,6 +722,21 @@ where
         T::step(&mut self.0)
     }
 
+    fn step_instruction(
+        &mut self,
+        instruction: Option<&vm::Instruction<Self::Intrinsic>>,
+    ) -> ExecutionBehavior {
+        T::step_instruction(&mut self.0, instruction)
+    }
+
+    fn consume(&mut self, cost: usize) {
+        T::consume(&mut self.0, cost);
+    }
+
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        T::allocation_budget(&self.0)
+    }
+
     fn intrinsic(
         &mut self,
         intrinsic: &Self::Intrinsic,
@@ -391,7 +746,16 @@ where
             Intrinsic::NewMap => Ok(Value::dynamic(
                 <T::Map as TryFrom<PoppedValues<'_>>>::try_from(args)?,
             )),
-            Intrinsic::NewList => Ok(Value::dynamic(args.collect::<T::List>())),
+            Intrinsic::NewList => {
+                vm::allocate(args.len().saturating_mul(std::mem::size_of::<Value>()))?;
+                Ok(Value::dynamic(args.collect::<T::List>()))
+            }
+            Intrinsic::Format => format::format(self, args),
+            Intrinsic::NewRecord => new_record::<Self>(args),
+            Intrinsic::MatchList => matching::match_list::<T>(args),
+            Intrinsic::MatchMap => matching::match_map::<T>(args),
+            Intrinsic::MatchRecord => matching::match_record::<T>(args),
+            Intrinsic::NoMatch => matching::no_match(args),
         }
     }
 
@@ -434,6 +798,26 @@ where
             ExecutionBehavior::Pause
         }
     }
+
+    fn step_instruction(
+        &mut self,
+        instruction: Option<&vm::Instruction<Intrinsic>>,
+    ) -> ExecutionBehavior {
+        if self.charge_instruction(instruction) {
+            self.env.step_instruction(instruction)
+        } else {
+            ExecutionBehavior::Pause
+        }
+    }
+
+    fn consume(&mut self, cost: usize) {
+        self.spend(cost);
+        self.env.consume(cost);
+    }
+
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        self.env.allocation_budget()
+    }
 }
 
 impl Environment for () {
@@ -461,6 +845,21 @@ where
     fn step(&mut self) -> ExecutionBehavior {
         vm::Environment::step(&mut self.env)
     }
+
+    fn step_instruction(
+        &mut self,
+        instruction: Option<&vm::Instruction<Intrinsic>>,
+    ) -> ExecutionBehavior {
+        vm::Environment::step_instruction(&mut self.env, instruction)
+    }
+
+    fn consume(&mut self, cost: usize) {
+        vm::Environment::consume(&mut self.env, cost);
+    }
+
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        vm::Environment::allocation_budget(&self.env)
+    }
 }
 
 /// A runtime intrinsic function.
@@ -470,6 +869,21 @@ pub enum Intrinsic {
     NewMap,
     /// Creates a new List with the given arguments.
     NewList,
+    /// Formats a template string with the given arguments.
+    Format,
+    /// Creates a new Record whose kind is the first argument and whose enum
+    /// variant is the second argument, followed by pairs of field names and
+    /// values.
+    NewRecord,
+    /// Checks whether a value is a List with a given length.
+    MatchList,
+    /// Checks whether a value is a Map containing all of the given keys.
+    MatchMap,
+    /// Checks whether a value is a Record with a given kind, variant, and
+    /// number of fields.
+    MatchRecord,
+    /// Raises a fault because a value did not match any pattern.
+    NoMatch,
 }
 
 impl Display for Intrinsic {
@@ -477,6 +891,12 @@ impl Display for Intrinsic {
         match self {
             Intrinsic::NewMap => f.write_str("NewMap"),
             Intrinsic::NewList => f.write_str("NewList"),
+            Intrinsic::Format => f.write_str("Format"),
+            Intrinsic::NewRecord => f.write_str("NewRecord"),
+            Intrinsic::MatchList => f.write_str("MatchList"),
+            Intrinsic::MatchMap => f.write_str("MatchMap"),
+            Intrinsic::MatchRecord => f.write_str("MatchRecord"),
+            Intrinsic::NoMatch => f.write_str("NoMatch"),
         }
     }
 }
@@ -488,10 +908,49 @@ impl FromStr for Intrinsic {
         match s {
             "NewMap" => Ok(Self::NewMap),
             "NewList" => Ok(Self::NewList),
+            "Format" => Ok(Self::Format),
+            "NewRecord" => Ok(Self::NewRecord),
+            "MatchList" => Ok(Self::MatchList),
+            "MatchMap" => Ok(Self::MatchMap),
+            "MatchRecord" => Ok(Self::MatchRecord),
+            "NoMatch" => Ok(Self::NoMatch),
             _ => Err(()),
         }
     }
 }
 
+fn new_record<Env>(mut args: PoppedValues<'_>) -> Result<Value, FaultKind>
+where
+    Env: vm::Environment,
+    Env::String: Display,
+{
+    let symbol = |value: Value| {
+        value
+            .as_dynamic::<Env::String>()
+            .map(|name| Symbol::from(name.to_string().as_str()))
+            .ok_or_else(|| {
+                FaultKind::invalid_type(
+                    "expected a string. Found `@received-value` (@received-kind)",
+                    value,
+                )
+            })
+    };
+    let kind = symbol(args.next_argument("kind")?)?;
+    let variant = match args.next_argument("variant")? {
+        Value::Void => None,
+        variant => Some(symbol(variant)?),
+    };
+    let mut fields = Vec::new();
+    while let Some(name) = args.next() {
+        let value = args.next_argument("value")?;
+        fields.push((symbol(name)?, value));
+    }
+    let record = Record::new(kind, fields);
+    Ok(Value::dynamic(match variant {
+        Some(variant) => record.with_variant(variant),
+        None => record,
+    }))
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::parser::ParseError;
+
+/// Loads the source code of modules imported using `import "path"`.
+///
+/// Implementing this trait allows embedders to serve imported sources from
+/// any location: memory, disk, a database, or anything else. Bud includes
+/// [`MemoryLoader`] and [`FileLoader`].
+pub trait SourceLoader {
+    /// Returns the source code for the module at `path`.
+    ///
+    /// `path` is passed exactly as written in the `import` statement.
+    fn load(&mut self, path: &str) -> Result<String, ImportError>;
+}
+
+/// A [`SourceLoader`] that serves sources from memory.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryLoader {
+    sources: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    /// Registers `source` as the contents of `path` and returns self. This is
+    /// a builder-style function.
+    #[must_use]
+    pub fn with_source(mut self, path: impl Into<String>, source: impl Into<String>) -> Self {
+        self.insert(path, source);
+        self
+    }
+
+    /// Registers `source` as the contents of `path`, replacing any previously
+    /// registered source.
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.sources.insert(path.into(), source.into());
+    }
+}
+
+impl SourceLoader for MemoryLoader {
+    fn load(&mut self, path: &str) -> Result<String, ImportError> {
+        self.sources
+            .get(path)
+            .cloned()
+            .ok_or_else(|| ImportError::NotFound(path.to_string()))
+    }
+}
+
+/// A [`SourceLoader`] that reads sources from the filesystem.
+///
+/// Paths are resolved relative to the root directory this loader was created
+/// with. No attempt is made to prevent imports from reaching outside of the
+/// root directory.
+#[derive(Debug, Clone)]
+pub struct FileLoader {
+    root: PathBuf,
+}
+
+impl FileLoader {
+    /// Returns a loader that resolves paths relative to `root`.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl SourceLoader for FileLoader {
+    fn load(&mut self, path: &str) -> Result<String, ImportError> {
+        std::fs::read_to_string(self.root.join(path)).map_err(|err| {
+            if err.kind() == ErrorKind::NotFound {
+                ImportError::NotFound(path.to_string())
+            } else {
+                ImportError::Io {
+                    path: path.to_string(),
+                    message: err.to_string(),
+                }
+            }
+        })
+    }
+}
+
+/// An error that occurred while importing a module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    /// No [`SourceLoader`] has been provided.
+    NoLoader,
+    /// The module could not be found.
+    NotFound(String),
+    /// An error occurred while reading the module's source.
+    Io {
+        /// The path being imported.
+        path: String,
+        /// A description of the error.
+        message: String,
+    },
+    /// The module's source could not be parsed.
+    Parse {
+        /// The path being imported.
+        path: String,
+        /// The error encountered.
+        error: ParseError,
+    },
+    /// The module imports itself, either directly or indirectly.
+    Cycle(String),
+}
+
+impl std::error::Error for ImportError {}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::NoLoader => f.write_str("no source loader is available"),
+            ImportError::NotFound(path) => write!(f, "module not found: {path}"),
+            ImportError::Io { path, message } => write!(f, "error reading {path}: {message}"),
+            ImportError::Parse { path, error } => write!(f, "error parsing {path}: {error}"),
+            ImportError::Cycle(path) => write!(f, "{path} imports itself"),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use budvm::{DynamicValue, FaultKind, PoppedValues, Record, Value};
+
+use crate::Environment;
+
+/// Evaluates the `MatchList` intrinsic: returns true if the first argument is
+/// a list containing the number of values specified by the second argument.
+pub fn match_list<Env>(mut args: PoppedValues<'_>) -> Result<Value, FaultKind>
+where
+    Env: Environment,
+{
+    let value = args.next_argument("value")?;
+    let length = args.next_argument("length")?;
+    args.verify_empty()?;
+
+    if value.as_dynamic::<Env::List>().is_none() {
+        return Ok(Value::Boolean(false));
+    }
+    let items = value.iterate()?;
+    let mut count = 0;
+    while items.next_item()?.is_some() {
+        count += 1;
+    }
+    Ok(Value::Boolean(Value::Integer(count) == length))
+}
+
+/// Evaluates the `MatchMap` intrinsic: returns true if the first argument is a
+/// map containing every key passed as the remaining arguments.
+pub fn match_map<Env>(mut args: PoppedValues<'_>) -> Result<Value, FaultKind>
+where
+    Env: Environment,
+{
+    let value = args.next_argument("value")?;
+    if value.as_dynamic::<Env::Map>().is_none() {
+        return Ok(Value::Boolean(false));
+    }
+
+    // Maps iterate over `[key, value]` entries.
+    let entries = value.iterate()?;
+    let mut keys = Vec::new();
+    while let Some(entry) = entries.next_item()? {
+        keys.push(entry.get_index(&Value::Integer(0))?);
+    }
+    Ok(Value::Boolean(args.all(|key| keys.contains(&key))))
+}
+
+/// Evaluates the `MatchRecord` intrinsic: returns true if the first argument
+/// is a [`Record`] whose kind, variant, and number of fields match the
+/// remaining arguments. A variant of `Void` matches records created from a
+/// struct.
+pub fn match_record<Env>(mut args: PoppedValues<'_>) -> Result<Value, FaultKind>
+where
+    Env: Environment,
+{
+    let value = args.next_argument("value")?;
+    let kind = args.next_argument("kind")?;
+    let variant = args.next_argument("variant")?;
+    let field_count = args.next_argument("field count")?;
+    args.verify_empty()?;
+
+    let record = match value.as_dynamic::<Record>() {
+        Some(record) => record,
+        None => return Ok(Value::Boolean(false)),
+    };
+    let matches_kind =
+        |expected: &Value, name: &str| string_contents::<Env>(expected).as_deref() == Some(name);
+    let matches_variant = match record.variant() {
+        Some(record_variant) => matches_kind(&variant, record_variant),
+        None => variant == Value::Void,
+    };
+    Ok(Value::Boolean(
+        matches_kind(&kind, &record.kind())
+            && matches_variant
+            && Value::Integer(i64::try_from(record.len()).unwrap_or(i64::MAX)) == field_count,
+    ))
+}
+
+/// Evaluates the `NoMatch` intrinsic: raises [`FaultKind::NoMatch`] for the
+/// value that no pattern matched.
+pub fn no_match(mut args: PoppedValues<'_>) -> Result<Value, FaultKind> {
+    let value = args.next_argument("value")?;
+    args.verify_empty()?;
+    Err(FaultKind::NoMatch(value))
+}
+
+fn string_contents<Env>(value: &Value) -> Option<String>
+where
+    Env: Environment,
+{
+    value.as_dynamic::<Env::String>().map(ToString::to_string)
+}
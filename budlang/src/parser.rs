@@ -6,21 +6,24 @@
 
 use std::{
     fmt::{Display, Write},
+    iter::Peekable,
     ops::Range,
     str::CharIndices,
 };
 
 use crate::ast::{
-    BinOpKind, Break, Call, CodeUnit, Continue, Function, If, Loop, LoopParameters, Mapping,
-    NodeId, SyntaxTreeBuilder,
+    BinOpKind, Break, Call, Catch, CodeUnit, Continue, Declaration, Enum, Function, If, Lambda,
+    Loop, LoopParameters, Mapping, Match, MatchArm, NodeId, Pattern, Struct, SyntaxTreeBuilder,
+    Try, Variant,
 };
 
 use budvm::{
+    ir::Literal,
     lexer_util::{
         decode_numeric_literal, decode_string_literal_contents, DecodeNumericError,
         DecodeStringError, DoublePeekable, Numeric,
     },
-    Comparison, Symbol, ValueKind,
+    BigInteger, Comparison, Decimal, SourceLocation, Symbol, ValueKind,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -50,7 +53,10 @@ pub enum TokenKind {
     Identifier(Symbol),
     Integer(i64),
     Real(f64),
+    BigInteger(BigInteger),
+    Decimal(Decimal),
     String(String),
+    InterpolatedString(Vec<StringPart>),
     Assign,
     Comment(String),
     Comparison(Comparison),
@@ -59,6 +65,8 @@ pub enum TokenKind {
     Sub,
     Multiply,
     Divide,
+    Remainder,
+    Power,
     ShiftLeft,
     ShiftRight,
     Open(BracketType),
@@ -81,7 +89,18 @@ impl Display for TokenKind {
             TokenKind::Identifier(value) => Display::fmt(value, f),
             TokenKind::Integer(value) => Display::fmt(value, f),
             TokenKind::Real(value) => Display::fmt(value, f),
+            TokenKind::BigInteger(value) => write!(f, "{value}n"),
+            TokenKind::Decimal(value) => write!(f, "{value}d"),
             TokenKind::String(value) => Display::fmt(value, f),
+            TokenKind::InterpolatedString(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => f.write_str(text)?,
+                        StringPart::Expression(_) => f.write_str("{..}")?,
+                    }
+                }
+                Ok(())
+            }
             TokenKind::Assign => f.write_str(":="),
             TokenKind::Comparison(value) => Display::fmt(value, f),
             TokenKind::ShiftLeft => f.write_str("<<"),
@@ -91,6 +110,8 @@ impl Display for TokenKind {
             TokenKind::Sub => f.write_char('-'),
             TokenKind::Multiply => f.write_char('*'),
             TokenKind::Divide => f.write_char('/'),
+            TokenKind::Remainder => f.write_char('%'),
+            TokenKind::Power => f.write_str("**"),
             TokenKind::Open(value) => match value {
                 BracketType::Paren => f.write_char('('),
                 BracketType::Square => f.write_char('['),
@@ -116,6 +137,15 @@ impl Display for TokenKind {
     }
 }
 
+/// A portion of a string literal containing interpolated expressions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StringPart {
+    /// Literal text.
+    Text(String),
+    /// The location in the source of an expression whose value is inserted.
+    Expression(Range<usize>),
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum BracketType {
     Paren,
@@ -127,15 +157,51 @@ pub struct Lexer<'a> {
     source: &'a str,
     chars: DoublePeekable<CharIndices<'a>>,
     peeked_token: Option<Result<Token, ParseError>>,
+    line_starts: Vec<usize>,
+    last_token_end: usize,
 }
 
 impl<'a> Lexer<'a> {
     #[must_use]
     pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
         Self {
             source,
             chars: DoublePeekable::new(source.char_indices()),
             peeked_token: None,
+            line_starts,
+            last_token_end: 0,
+        }
+    }
+
+    /// Returns a lexer for the portion of `source` within `range`, such as an
+    /// expression interpolated into a string literal.
+    fn for_range(source: &'a str, range: Range<usize>) -> Self {
+        let mut lexer = Self::new(&source[..range.end]);
+        while matches!(lexer.chars.peek(), Some((offset, _)) if *offset < range.start) {
+            lexer.chars.next();
+        }
+        lexer.last_token_end = range.start;
+        lexer
+    }
+
+    /// Returns the location of the source starting at `start` and ending with
+    /// the last token returned, ignoring comments and line endings.
+    fn location_from(&self, start: usize) -> SourceLocation {
+        self.location_between(start, self.last_token_end)
+    }
+
+    /// Returns the location of the source from `start` to `end`.
+    fn location_between(&self, start: usize, end: usize) -> SourceLocation {
+        let line = match self.line_starts.binary_search(&start) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+        SourceLocation {
+            range: start..end.max(start),
+            line,
         }
     }
 
@@ -168,7 +234,7 @@ impl<'a> Lexer<'a> {
                 Some((_, char)) if char == ' ' || char == '\t' => {
                     continue;
                 }
-                Some((offset, char)) if char.is_alphabetic() => {
+                Some((offset, char)) if char.is_alphabetic() || char == '_' => {
                     let mut end = offset;
                     while self
                         .chars
@@ -193,6 +259,8 @@ impl<'a> Lexer<'a> {
                             kind: match numeric.contents {
                                 Numeric::Integer(value) => TokenKind::Integer(value),
                                 Numeric::Real(value) => TokenKind::Real(value),
+                                Numeric::BigInteger(value) => TokenKind::BigInteger(value),
+                                Numeric::Decimal(value) => TokenKind::Decimal(value),
                             },
                             range: offset..numeric.last_offset,
                         })),
@@ -206,7 +274,15 @@ impl<'a> Lexer<'a> {
                     Some(Ok(Token::at_offset(TokenKind::Sub, offset)))
                 }
                 Some((offset, char)) if char == '*' => {
-                    Some(Ok(Token::at_offset(TokenKind::Multiply, offset)))
+                    if matches!(self.chars.peek().map(|(_, ch)| *ch), Some('*')) {
+                        self.chars.next();
+                        Some(Ok(Token::new(TokenKind::Power, offset..offset + 2)))
+                    } else {
+                        Some(Ok(Token::at_offset(TokenKind::Multiply, offset)))
+                    }
+                }
+                Some((offset, char)) if char == '%' => {
+                    Some(Ok(Token::at_offset(TokenKind::Remainder, offset)))
                 }
                 Some((offset, char)) if char == '/' => {
                     if matches!(self.chars.peek().map(|(_, ch)| *ch), Some('/')) {
@@ -390,13 +466,60 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_string(&mut self, start_offset: usize) -> Result<Token, ParseError> {
-        let literal = decode_string_literal_contents(&mut self.chars, start_offset)?;
+        let mut expressions = Vec::new();
+        let mut chars = self.source[start_offset + 1..]
+            .char_indices()
+            .map(|(offset, ch)| (offset + start_offset + 1, ch))
+            .peekable();
+        let end_quote_offset = match scan_string_literal(&mut chars, Some(&mut expressions)) {
+            Some(end_quote_offset) if !expressions.is_empty() => end_quote_offset,
+            _ => {
+                // Without any interpolated expressions, the literal can be
+                // decoded directly. This also reports any errors.
+                let literal = decode_string_literal_contents(&mut self.chars, start_offset)?;
+                return Ok(Token {
+                    kind: TokenKind::String(literal.contents),
+                    range: start_offset..literal.end_quote_offset + 1,
+                });
+            }
+        };
+
+        let mut parts = Vec::with_capacity(expressions.len() * 2 + 1);
+        let mut text_start = start_offset + 1;
+        for expression in expressions {
+            // Exclude the opening brace.
+            parts.push(StringPart::Text(
+                self.decode_string_text(text_start..expression.start - 1)?,
+            ));
+            // Skip the closing brace.
+            text_start = expression.end + 1;
+            parts.push(StringPart::Expression(expression));
+        }
+        parts.push(StringPart::Text(
+            self.decode_string_text(text_start..end_quote_offset)?,
+        ));
+
+        for (offset, _) in &mut self.chars {
+            if offset == end_quote_offset {
+                break;
+            }
+        }
+
         Ok(Token {
-            kind: TokenKind::String(literal.contents),
-            range: start_offset..literal.end_quote_offset + 1,
+            kind: TokenKind::InterpolatedString(parts),
+            range: start_offset..end_quote_offset + 1,
         })
     }
 
+    /// Decodes the escape sequences within `range` of a string literal.
+    fn decode_string_text(&self, range: Range<usize>) -> Result<String, ParseError> {
+        let mut chars = self.source[range.clone()]
+            .char_indices()
+            .map(|(offset, ch)| (offset + range.start, ch))
+            .chain(std::iter::once((range.end, '"')));
+        Ok(decode_string_literal_contents(&mut chars, range.start - 1)?.contents)
+    }
+
     fn expect_next(&mut self, expected: &str) -> Result<Token, ParseError> {
         self.next()
             .ok_or_else(|| ParseError::UnexpectedEof(expected.to_string()))?
@@ -407,50 +530,351 @@ impl<'a> Iterator for Lexer<'a> {
     type Item = Result<Token, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(token) = self.peeked_token.take() {
+        let token = if let Some(token) = self.peeked_token.take() {
             Some(token)
         } else {
             self.read_token()
+        };
+        if let Some(Ok(token)) = &token {
+            if !matches!(token.kind, TokenKind::EndOfLine | TokenKind::Comment(_)) {
+                self.last_token_end = token.range.end;
+            }
+        }
+        token
+    }
+}
+
+/// Scans a string literal whose opening quote has already been read, returning
+/// the offset of the closing quote. If `expressions` is provided, the ranges of
+/// interpolated expressions are pushed to it.
+///
+/// A `{` begins an interpolated expression unless it is escaped or immediately
+/// followed by `}` or `:`, which allows using `format()` placeholders in
+/// literals. A `{` without a matching `}` is part of the text. Returns None if
+/// the end of the literal cannot be found.
+fn scan_string_literal<I>(
+    chars: &mut Peekable<I>,
+    mut expressions: Option<&mut Vec<Range<usize>>>,
+) -> Option<usize>
+where
+    I: Iterator<Item = (usize, char)> + Clone,
+{
+    loop {
+        match chars.next()? {
+            (_, '\\') => {
+                let (_, escaped) = chars.next()?;
+                if escaped == 'u' {
+                    // Skip the braces of the unicode escape.
+                    while chars.next()?.1 != '}' {}
+                }
+            }
+            (offset, '"') => return Some(offset),
+            (offset, '{') if !matches!(chars.peek(), Some((_, '}' | ':'))) => {
+                let mut expression_chars = chars.clone();
+                if let Some(end) = scan_interpolated_expression(&mut expression_chars) {
+                    *chars = expression_chars;
+                    if let Some(expressions) = &mut expressions {
+                        expressions.push(offset + 1..end);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Scans an interpolated expression whose opening brace has already been
+/// read, returning the offset of its closing brace.
+fn scan_interpolated_expression<I>(chars: &mut Peekable<I>) -> Option<usize>
+where
+    I: Iterator<Item = (usize, char)> + Clone,
+{
+    let mut depth = 0_usize;
+    loop {
+        match chars.next()? {
+            (_, '{') => depth += 1,
+            (offset, '}') => {
+                if depth == 0 {
+                    return Some(offset);
+                }
+                depth -= 1;
+            }
+            (_, '"') => {
+                scan_string_literal(chars, None)?;
+            }
+            _ => {}
         }
     }
 }
 
 pub fn parse(source: &str) -> Result<CodeUnit, ParseError> {
     let mut tokens = Lexer::new(source);
+    parse_unit(&mut tokens, UnitKind::Script)
+}
+
+/// Parses `source` as the contents of a module. Modules may only contain
+/// declarations.
+pub fn parse_module(source: &str) -> Result<CodeUnit, ParseError> {
+    let mut tokens = Lexer::new(source);
+    parse_unit(&mut tokens, UnitKind::Module)
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum UnitKind {
+    Script,
+    Module,
+    ModuleBlock,
+}
+
+fn parse_unit(tokens: &mut Lexer<'_>, kind: UnitKind) -> Result<CodeUnit, ParseError> {
     let mut statements = Vec::new();
-    let mut functions = Vec::new();
+    let mut declarations = Vec::new();
     let init_tree = SyntaxTreeBuilder::new();
-    while let Some(token) = tokens.next().transpose()? {
+    loop {
+        let token = if let Some(token) = tokens.next().transpose()? {
+            token
+        } else if kind == UnitKind::ModuleBlock {
+            return Err(ParseError::UnexpectedEof(String::from("end")));
+        } else {
+            break;
+        };
         match &token.kind {
-            TokenKind::Identifier(ident) if ident == "function" => {
-                let function = parse_function(&mut tokens)?;
-                functions.push(function);
+            TokenKind::Identifier(ident)
+                if ident == "function"
+                    && !matches!(
+                        tokens.peek_token_kind(),
+                        Some(TokenKind::Open(BracketType::Paren))
+                    ) =>
+            {
+                let function = parse_function(tokens, false)?;
+                declarations.push((function.name().clone(), Declaration::from(function)));
+            }
+            TokenKind::Identifier(ident)
+                if ident == "module"
+                    && matches!(tokens.peek_token_kind(), Some(TokenKind::Identifier(_))) =>
+            {
+                declarations.push(parse_module_block(tokens)?);
+            }
+            TokenKind::Identifier(ident)
+                if ident == "struct"
+                    && matches!(tokens.peek_token_kind(), Some(TokenKind::Identifier(_))) =>
+            {
+                declarations.push(parse_struct(tokens)?);
+            }
+            TokenKind::Identifier(ident)
+                if ident == "enum"
+                    && matches!(tokens.peek_token_kind(), Some(TokenKind::Identifier(_))) =>
+            {
+                declarations.push(parse_enum(tokens)?);
+            }
+            TokenKind::Identifier(ident)
+                if ident == "import"
+                    && matches!(tokens.peek_token_kind(), Some(TokenKind::String(_))) =>
+            {
+                declarations.push(parse_import(tokens)?);
+            }
+            TokenKind::Identifier(ident) if ident == "end" && kind == UnitKind::ModuleBlock => {
+                tokens.expect_end_of_line_or_eof()?;
+                break;
             }
 
             TokenKind::Comment(_) | TokenKind::EndOfLine => {}
-            _ => {
-                let expression = parse_expression(token, &init_tree, &mut tokens, None)?;
+            _ if kind == UnitKind::Script => {
+                let expression = parse_expression(token, &init_tree, tokens, None)?;
                 statements.push(expression);
             }
+            _ => return Err(ParseError::Unexpected(token)),
         }
     }
 
     let mut unit = CodeUnit::from_tree(statements, init_tree);
-    for function in functions {
-        unit = unit.with(function.name().clone(), function);
+    for (name, declaration) in declarations {
+        unit = unit.with(name, declaration);
     }
 
     Ok(unit)
 }
 
-fn parse_function(tokens: &mut Lexer<'_>) -> Result<Function, ParseError> {
+fn parse_module_block(tokens: &mut Lexer<'_>) -> Result<(Symbol, Declaration), ParseError> {
+    // module <name>
+    //   <declarations>
+    // end
+    let name = match tokens.expect_next("module name")?.kind {
+        TokenKind::Identifier(name) => name,
+        _ => unreachable!("peeked identifier"),
+    };
+    tokens.expect_end_of_line()?;
+
+    let module = parse_unit(tokens, UnitKind::ModuleBlock)?;
+    Ok((name, Declaration::Module(module)))
+}
+
+fn parse_import(tokens: &mut Lexer<'_>) -> Result<(Symbol, Declaration), ParseError> {
+    // import "<path>" [as <name>]
+    let path = match tokens.expect_next("import path")?.kind {
+        TokenKind::String(path) => path,
+        _ => unreachable!("peeked string"),
+    };
+
+    let name = match tokens.peek_token_kind() {
+        Some(TokenKind::Identifier(keyword)) if keyword == "as" => {
+            let _as = tokens.next();
+            let name = tokens.expect_next("module name")?;
+            match name.kind {
+                TokenKind::Identifier(name) => name,
+                _ => return Err(ParseError::Unexpected(name)),
+            }
+        }
+        _ => {
+            // Without an explicit name, the module is named after the file.
+            let file_name = path.rsplit('/').next().unwrap_or(&path);
+            let stem = file_name.split('.').next().unwrap_or(file_name);
+            Symbol::from(stem)
+        }
+    };
+    tokens.expect_end_of_line_or_eof()?;
+
+    Ok((name, Declaration::Import(path)))
+}
+
+fn parse_struct(tokens: &mut Lexer<'_>) -> Result<(Symbol, Declaration), ParseError> {
+    // struct <name>(<fields>)
+    //   <methods>
+    // end
+    let name = match tokens.expect_next("struct name")?.kind {
+        TokenKind::Identifier(name) => name,
+        _ => unreachable!("peeked identifier"),
+    };
+    let fields = parse_field_list(tokens)?;
+    tokens.expect_end_of_line()?;
+
+    let mut methods = Vec::new();
+    loop {
+        let token = tokens.expect_next("end")?;
+        match &token.kind {
+            TokenKind::Identifier(ident) if ident == "function" => {
+                methods.push(parse_function(tokens, true)?);
+            }
+            TokenKind::Identifier(ident) if ident == "end" => {
+                tokens.expect_end_of_line_or_eof()?;
+                break;
+            }
+            TokenKind::Comment(_) | TokenKind::EndOfLine => {}
+            _ => return Err(ParseError::Unexpected(token)),
+        }
+    }
+
+    Ok((
+        name.clone(),
+        Declaration::from(Struct::new(name, fields, methods)),
+    ))
+}
+
+fn parse_enum(tokens: &mut Lexer<'_>) -> Result<(Symbol, Declaration), ParseError> {
+    // enum <name>
+    //   <variant>[(<fields>)]
+    //   <methods>
+    // end
+    let name = match tokens.expect_next("enum name")?.kind {
+        TokenKind::Identifier(name) => name,
+        _ => unreachable!("peeked identifier"),
+    };
+    tokens.expect_end_of_line()?;
+
+    let mut variants = Vec::new();
+    let mut methods = Vec::new();
+    loop {
+        let token = tokens.expect_next("end")?;
+        match token.kind {
+            TokenKind::Identifier(ident) if ident == "function" => {
+                methods.push(parse_function(tokens, true)?);
+            }
+            TokenKind::Identifier(ident) if ident == "end" => {
+                tokens.expect_end_of_line_or_eof()?;
+                break;
+            }
+            TokenKind::Identifier(variant) => {
+                let fields = match tokens.peek_token_kind() {
+                    Some(TokenKind::Open(BracketType::Paren)) => parse_field_list(tokens)?,
+                    _ => Vec::new(),
+                };
+                tokens.expect_end_of_line()?;
+                variants.push(Variant::new(variant, fields));
+            }
+            TokenKind::Comment(_) | TokenKind::EndOfLine => {}
+            _ => return Err(ParseError::Unexpected(token)),
+        }
+    }
+
+    Ok((
+        name.clone(),
+        Declaration::from(Enum::new(name, variants, methods)),
+    ))
+}
+
+fn parse_function(tokens: &mut Lexer<'_>, is_method: bool) -> Result<Function, ParseError> {
     let name = tokens.expect_next("function name")?;
     let name = match name.kind {
         TokenKind::Identifier(name) => name,
         _ => todo!("error non-identifier for function name"),
     };
 
-    // Parameter list
+    let mut args = parse_parameter_list(tokens)?;
+
+    tokens.expect_end_of_line()?;
+
+    // Methods receive the record they were invoked on as `self`. Calls to a
+    // method's name within its body aren't recursive, as methods must be
+    // invoked through a record.
+    let owning_function_name = if is_method {
+        args.insert(0, Symbol::from("self"));
+        None
+    } else {
+        Some(name.as_str())
+    };
+
+    let body_tree = SyntaxTreeBuilder::new();
+    let body_node = parse_statements(&body_tree, tokens, owning_function_name)?;
+
+    match tokens.expect_next("end")?.kind {
+        TokenKind::Identifier(end) if end == "end" => {}
+        other => todo!("unexpected {other:?}"),
+    }
+    // There needs to be a trailing end of line after end.
+    tokens.expect_end_of_line_or_eof()?;
+
+    Ok(Function::new(name, args, body_tree.finish(body_node)))
+}
+
+fn parse_lambda(tree: &SyntaxTreeBuilder, tokens: &mut Lexer<'_>) -> Result<NodeId, ParseError> {
+    // function(<args>) <body> end
+    let args = parse_parameter_list(tokens)?;
+
+    // The body is parsed without an owning function name, so calls to the
+    // enclosing function by name are resolved when linking. Code generation
+    // determines what `this` refers to.
+    let body = parse_statements(tree, tokens, None)?;
+
+    let end = tokens.expect_next("end")?;
+    match &end.kind {
+        TokenKind::Identifier(sym) if sym == "end" => {}
+        _ => return Err(ParseError::Unexpected(end)),
+    }
+
+    Ok(tree.lambda(Lambda::new(args, body)))
+}
+
+/// Parses the fields of a struct or enum variant. Each field must have a
+/// unique name.
+fn parse_field_list(tokens: &mut Lexer<'_>) -> Result<Vec<Symbol>, ParseError> {
+    parse_names(tokens, true)
+}
+
+fn parse_parameter_list(tokens: &mut Lexer<'_>) -> Result<Vec<Symbol>, ParseError> {
+    parse_names(tokens, false)
+}
+
+fn parse_names(tokens: &mut Lexer<'_>, unique: bool) -> Result<Vec<Symbol>, ParseError> {
     let open_paren = tokens.expect_next("(")?;
     let mut args = Vec::new();
     if let TokenKind::Open(BracketType::Paren) = open_paren.kind {
@@ -461,8 +885,14 @@ fn parse_function(tokens: &mut Lexer<'_>) -> Result<Function, ParseError> {
                 }
                 Some(Ok(Token {
                     kind: TokenKind::Identifier(arg_name),
-                    ..
+                    range,
                 })) => {
+                    if unique && args.contains(&arg_name) {
+                        return Err(ParseError::Unexpected(Token {
+                            kind: TokenKind::Identifier(arg_name),
+                            range,
+                        }));
+                    }
                     args.push(arg_name);
 
                     match tokens.expect_next("comma")? {
@@ -486,19 +916,7 @@ fn parse_function(tokens: &mut Lexer<'_>) -> Result<Function, ParseError> {
         }
     }
 
-    tokens.expect_end_of_line()?;
-
-    let body_tree = SyntaxTreeBuilder::new();
-    let body_node = parse_statements(&body_tree, tokens, Some(&name))?;
-
-    match tokens.expect_next("end")?.kind {
-        TokenKind::Identifier(end) if end == "end" => {}
-        other => todo!("unexpected {other:?}"),
-    }
-    // There needs to be a trailing end of line after end.
-    tokens.expect_end_of_line_or_eof()?;
-
-    Ok(Function::new(name, args, body_tree.finish(body_node)))
+    Ok(args)
 }
 
 fn parse_statements(
@@ -507,9 +925,16 @@ fn parse_statements(
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
     let mut body = Vec::new();
+    let mut start = None;
     while let Some(Ok(token)) = tokens.peek() {
         match &token.kind {
-            TokenKind::Identifier(ident) if ident == "end" || ident == "else" => {
+            TokenKind::Identifier(ident)
+                if ident == "end"
+                    || ident == "else"
+                    || ident == "catch"
+                    || ident == "finally"
+                    || ident == "when" =>
+            {
                 // end of function
                 break;
             }
@@ -518,15 +943,21 @@ fn parse_statements(
             }
             _ => {
                 let first_token = tokens.next().expect("just peeked")?;
+                start.get_or_insert(first_token.range.start);
                 let expression = parse_expression(first_token, tree, tokens, owning_function_name)?;
                 body.push(expression);
             }
         }
     }
 
-    let body_node = match body.len() {
-        1 => body[0],
-        _ => tree.statements(body),
+    let body_node = if body.len() == 1 {
+        body[0]
+    } else {
+        let statements = tree.statements(body);
+        if let Some(start) = start {
+            tree.set_location(statements, tokens.location_from(start));
+        }
+        statements
     };
     Ok(body_node)
 }
@@ -536,6 +967,18 @@ fn parse_expression(
     tree: &SyntaxTreeBuilder,
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
+    let expression = parse_expression_kind(first_token, tree, tokens, owning_function_name)?;
+    tree.set_location(expression, tokens.location_from(start));
+    Ok(expression)
+}
+
+fn parse_expression_kind(
+    first_token: Token,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
     match &first_token.kind {
         TokenKind::Identifier(symbol) if symbol == "if" => {
@@ -583,6 +1026,28 @@ fn parse_expression(
         TokenKind::Identifier(symbol) if symbol == "break" || symbol == "continue" => {
             parse_loop_keyword(symbol, tree, tokens, owning_function_name)
         }
+        TokenKind::Identifier(symbol) if symbol == "try" => {
+            parse_try(first_token.range, tree, tokens, owning_function_name)
+        }
+        TokenKind::Identifier(symbol) if symbol == "return" => {
+            // return [expr]
+            let value = if matches!(
+                tokens.peek_token_kind(),
+                Some(TokenKind::EndOfLine | TokenKind::Comment(_)) | None
+            ) {
+                tree.void()
+            } else {
+                let first_token = tokens.expect_next("end of line or expression")?;
+                parse_expression(first_token, tree, tokens, owning_function_name)?
+            };
+            Ok(tree.return_node(value))
+        }
+        TokenKind::Identifier(symbol) if symbol == "throw" => {
+            // throw <expr>
+            let first_token = tokens.expect_next("value to throw")?;
+            let value = parse_expression(first_token, tree, tokens, owning_function_name)?;
+            Ok(tree.throw_node(value))
+        }
         _ => parse_assign_expression(first_token, tree, tokens, owning_function_name),
     }
 }
@@ -665,6 +1130,15 @@ fn parse_for_loop(
     };
 
     let mut first_token = tokens.expect_next("assigment or keyword")?;
+    match &first_token.kind {
+        TokenKind::Comma => {
+            return parse_for_in_loop(var_name, first_token, tree, tokens, owning_function_name)
+        }
+        TokenKind::Identifier(keyword) if keyword == "in" => {
+            return parse_for_in_loop(var_name, first_token, tree, tokens, owning_function_name)
+        }
+        _ => {}
+    }
 
     let initial_value = if let TokenKind::Assign = &first_token.kind {
         first_token = tokens.expect_next("expression")?;
@@ -727,6 +1201,45 @@ fn parse_for_loop(
     })
 }
 
+fn parse_for_in_loop(
+    var_name: Symbol,
+    mut next_token: Token,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<LoopParameters, ParseError> {
+    // loop [#label] for x[, y] in <expr>
+    let value_name = if let TokenKind::Comma = &next_token.kind {
+        let value_name = tokens.expect_next("variable name")?;
+        let value_name = match &value_name.kind {
+            TokenKind::Identifier(name) => name.clone(),
+            _ => return Err(ParseError::Unexpected(value_name)),
+        };
+        next_token = tokens.expect_next("in")?;
+        Some(value_name)
+    } else {
+        None
+    };
+
+    match &next_token.kind {
+        TokenKind::Identifier(keyword) if keyword == "in" => {}
+        _ => return Err(ParseError::Unexpected(next_token)),
+    }
+
+    let iterable = parse_expression(
+        tokens.expect_next("expression")?,
+        tree,
+        tokens,
+        owning_function_name,
+    )?;
+
+    Ok(LoopParameters::ForIn {
+        var_name,
+        value_name,
+        iterable,
+    })
+}
+
 fn parse_loop_keyword(
     keyword: &Symbol,
     tree: &SyntaxTreeBuilder,
@@ -770,6 +1283,239 @@ fn parse_loop_keyword(
     }
 }
 
+fn parse_try(
+    range: Range<usize>,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    // try
+    //   <body>
+    // [catch [name]
+    //   <body>]
+    // [finally
+    //   <body>]
+    // end
+    tokens.expect_end_of_line()?;
+    let body = parse_statements(tree, tokens, owning_function_name)?;
+
+    let mut next_token = tokens.expect_next("catch, finally, or end")?;
+    let catch = match &next_token.kind {
+        TokenKind::Identifier(keyword) if keyword == "catch" => {
+            let name = match tokens.peek_token_kind() {
+                Some(TokenKind::Identifier(_)) => match tokens.next() {
+                    Some(Ok(Token {
+                        kind: TokenKind::Identifier(name),
+                        ..
+                    })) => Some(name),
+                    _ => unreachable!("just peeked"),
+                },
+                _ => None,
+            };
+            tokens.expect_end_of_line()?;
+            let body = parse_statements(tree, tokens, owning_function_name)?;
+            next_token = tokens.expect_next("finally or end")?;
+            Some(Catch { name, body })
+        }
+        _ => None,
+    };
+
+    let finally = match &next_token.kind {
+        TokenKind::Identifier(keyword) if keyword == "finally" => {
+            tokens.expect_end_of_line()?;
+            let body = parse_statements(tree, tokens, owning_function_name)?;
+            next_token = tokens.expect_next("end")?;
+            Some(body)
+        }
+        _ => None,
+    };
+
+    match &next_token.kind {
+        TokenKind::Identifier(end) if end == "end" => {}
+        _ => return Err(ParseError::Unexpected(next_token)),
+    }
+    if catch.is_none() && finally.is_none() {
+        return Err(ParseError::MissingCatchOrFinally(range));
+    }
+    tokens.expect_end_of_line_or_eof()?;
+
+    Ok(tree.try_node(Try {
+        body,
+        catch,
+        finally,
+    }))
+}
+
+fn parse_match(
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    // match <expr>
+    // when <pattern> [if <guard>]
+    //   <body>
+    // [else
+    //   <body>]
+    // end
+    let first_token = tokens.expect_next("value to match")?;
+    let subject = parse_expression(first_token, tree, tokens, owning_function_name)?;
+    tokens.expect_end_of_line()?;
+
+    let mut arms = Vec::new();
+    let mut else_block = None;
+    loop {
+        let token = tokens.expect_next("when, else, or end")?;
+        match &token.kind {
+            TokenKind::Identifier(keyword) if keyword == "when" && else_block.is_none() => {
+                let pattern = parse_pattern(tokens)?;
+                let guard = match tokens.peek_token_kind() {
+                    Some(TokenKind::Identifier(keyword)) if keyword == "if" => {
+                        let _if = tokens.next();
+                        let first_token = tokens.expect_next("guard")?;
+                        Some(parse_expression(
+                            first_token,
+                            tree,
+                            tokens,
+                            owning_function_name,
+                        )?)
+                    }
+                    _ => None,
+                };
+                tokens.expect_end_of_line()?;
+                let body = parse_statements(tree, tokens, owning_function_name)?;
+                arms.push(MatchArm {
+                    pattern,
+                    guard,
+                    body,
+                });
+            }
+            TokenKind::Identifier(keyword) if keyword == "else" && else_block.is_none() => {
+                tokens.expect_end_of_line()?;
+                else_block = Some(parse_statements(tree, tokens, owning_function_name)?);
+            }
+            TokenKind::Identifier(end) if end == "end" => break,
+            TokenKind::Comment(_) | TokenKind::EndOfLine => {}
+            _ => return Err(ParseError::Unexpected(token)),
+        }
+    }
+
+    Ok(tree.match_node(Match {
+        subject,
+        arms,
+        else_block,
+    }))
+}
+
+fn parse_pattern(tokens: &mut Lexer<'_>) -> Result<Pattern, ParseError> {
+    let token = tokens.expect_next("pattern")?;
+    match token.kind {
+        TokenKind::Identifier(name) => match name.as_str() {
+            "_" => Ok(Pattern::Wildcard),
+            "true" => Ok(Pattern::Literal(Literal::Boolean(true))),
+            "false" => Ok(Pattern::Literal(Literal::Boolean(false))),
+            _ => {
+                let mut path = vec![name];
+                while let Some(TokenKind::Period) = tokens.peek_token_kind() {
+                    let _period = tokens.next();
+                    let segment = tokens.expect_next("name")?;
+                    match segment.kind {
+                        TokenKind::Identifier(segment) => path.push(segment),
+                        _ => return Err(ParseError::Unexpected(segment)),
+                    }
+                }
+
+                if let Some(TokenKind::Open(BracketType::Paren)) = tokens.peek_token_kind() {
+                    let _open = tokens.next();
+                    Ok(Pattern::Record {
+                        path: join_path(&path),
+                        fields: parse_pattern_list(tokens, &BracketType::Paren)?,
+                    })
+                } else if path.len() > 1 {
+                    // A path without fields refers to an enum variant.
+                    Ok(Pattern::Record {
+                        path: join_path(&path),
+                        fields: Vec::new(),
+                    })
+                } else {
+                    Ok(Pattern::Binding(path.remove(0)))
+                }
+            }
+        },
+        TokenKind::Open(BracketType::Square) => Ok(Pattern::List(parse_pattern_list(
+            tokens,
+            &BracketType::Square,
+        )?)),
+        TokenKind::Open(BracketType::Curly) => {
+            let mut entries = Vec::new();
+            loop {
+                let key = tokens.expect_next("key or }")?;
+                if matches!(key.kind, TokenKind::Close(BracketType::Curly)) {
+                    break;
+                }
+                let key = parse_pattern_literal(key)?;
+                let colon = tokens.expect_next(":")?;
+                if !matches!(colon.kind, TokenKind::Colon) {
+                    return Err(ParseError::Unexpected(colon));
+                }
+                entries.push((key, parse_pattern(tokens)?));
+                expect_pattern_separator(tokens, &BracketType::Curly)?;
+            }
+            Ok(Pattern::Map(entries))
+        }
+        _ => parse_pattern_literal(token).map(Pattern::Literal),
+    }
+}
+
+fn parse_pattern_literal(token: Token) -> Result<Literal, ParseError> {
+    let literal = match token.kind {
+        TokenKind::Integer(integer) => Literal::Integer(integer),
+        TokenKind::Real(real) => Literal::Real(real),
+        TokenKind::String(string) => Literal::String(string),
+        TokenKind::Identifier(keyword) if keyword == "true" => Literal::Boolean(true),
+        TokenKind::Identifier(keyword) if keyword == "false" => Literal::Boolean(false),
+        _ => return Err(ParseError::Unexpected(token)),
+    };
+    Ok(literal)
+}
+
+fn parse_pattern_list(
+    tokens: &mut Lexer<'_>,
+    closing: &BracketType,
+) -> Result<Vec<Pattern>, ParseError> {
+    let mut patterns = Vec::new();
+    loop {
+        match tokens.peek_token_kind() {
+            Some(TokenKind::Close(bracket)) if bracket == closing => {
+                let _close = tokens.next();
+                break;
+            }
+            _ => {
+                patterns.push(parse_pattern(tokens)?);
+                expect_pattern_separator(tokens, closing)?;
+            }
+        }
+    }
+    Ok(patterns)
+}
+
+/// Consumes the comma following a pattern, leaving a closing bracket to be
+/// read by the caller.
+fn expect_pattern_separator(
+    tokens: &mut Lexer<'_>,
+    closing: &BracketType,
+) -> Result<(), ParseError> {
+    match tokens.peek_token_kind() {
+        Some(TokenKind::Comma) => {
+            let _comma = tokens.next();
+            Ok(())
+        }
+        Some(TokenKind::Close(bracket)) if bracket == closing => Ok(()),
+        _ => Err(tokens
+            .expect_next(", or closing bracket")
+            .map_or_else(|err| err, ParseError::Unexpected)),
+    }
+}
+
 fn parse_assign_expression(
     first_token: Token,
     tree: &SyntaxTreeBuilder,
@@ -778,20 +1524,23 @@ fn parse_assign_expression(
 ) -> Result<NodeId, ParseError> {
     // This operator groups differently than most of the other operators. a := b
     // := c should result in `(a := (b := c))`, not `((a := b) := c))`.
+    let mut start = first_token.range.start;
     let mut left = parse_logic_expression(first_token, tree, tokens, owning_function_name)?;
 
     let mut stack = Vec::new();
     while let Some(TokenKind::Assign) = tokens.peek_token_kind() {
         tokens.next();
-        stack.push(left);
+        stack.push((left, start));
         let first_token = tokens.expect_next("value to assign")?;
+        start = first_token.range.start;
         left = parse_logic_expression(first_token, tree, tokens, owning_function_name)?;
     }
 
     // Perform the assignments.
     let mut right = left;
-    while let Some(left) = stack.pop() {
+    while let Some((left, start)) = stack.pop() {
         right = tree.assign_node(left, right);
+        tree.set_location(right, tokens.location_from(start));
     }
 
     Ok(right)
@@ -803,6 +1552,7 @@ fn parse_logic_expression(
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
     let mut left = parse_comparison_expression(first_token, tree, tokens, owning_function_name)?;
 
     while let Some(binop_kind) = match tokens.peek_token_kind() {
@@ -815,6 +1565,7 @@ fn parse_logic_expression(
         let next_token = tokens.expect_next("value to compare against")?;
         let right = parse_comparison_expression(next_token, tree, tokens, owning_function_name)?;
         left = tree.binop_node(binop_kind, left, right);
+        tree.set_location(left, tokens.location_from(start));
     }
 
     Ok(left)
@@ -826,6 +1577,7 @@ fn parse_comparison_expression(
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
     let mut left = parse_convert_expression(first_token, tree, tokens, owning_function_name)?;
 
     while let Some(TokenKind::Comparison(comparison)) = tokens.peek_token_kind() {
@@ -834,6 +1586,7 @@ fn parse_comparison_expression(
         let next_token = tokens.expect_next("value to compare against")?;
         let right = parse_convert_expression(next_token, tree, tokens, owning_function_name)?;
         left = tree.compare_node(comparison, left, right);
+        tree.set_location(left, tokens.location_from(start));
     }
 
     Ok(left)
@@ -845,6 +1598,7 @@ fn parse_convert_expression(
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
     let mut expr = parse_bitwise_expression(first_token, tree, tokens, owning_function_name)?;
 
     while matches!(tokens.peek_token_kind(), Some(TokenKind::Identifier(sym)) if sym == "as") {
@@ -857,6 +1611,7 @@ fn parse_convert_expression(
         };
 
         expr = tree.convert_node(expr, kind);
+        tree.set_location(expr, tokens.location_from(start));
     }
 
     Ok(expr)
@@ -868,6 +1623,7 @@ fn parse_bitwise_expression(
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
     let mut left = parse_add_sub(first_token, tree, tokens, owning_function_name)?;
 
     while let Some(binop_kind) = match tokens.peek_token_kind() {
@@ -882,6 +1638,7 @@ fn parse_bitwise_expression(
         let next_token = tokens.expect_next("value to compare against")?;
         let right = parse_add_sub(next_token, tree, tokens, owning_function_name)?;
         left = tree.binop_node(binop_kind, left, right);
+        tree.set_location(left, tokens.location_from(start));
     }
 
     Ok(left)
@@ -898,6 +1655,7 @@ fn parse_add_sub(
         Sub,
     }
 
+    let start = first_token.range.start;
     let mut left = parse_mul_div(first_token, tree, tokens, owning_function_name)?;
 
     loop {
@@ -913,6 +1671,7 @@ fn parse_add_sub(
             Kind::Add => tree.binop_node(BinOpKind::Add, left, right),
             Kind::Sub => tree.binop_node(BinOpKind::Sub, left, right),
         };
+        tree.set_location(left, tokens.location_from(start));
     }
 
     Ok(left)
@@ -924,36 +1683,108 @@ fn parse_mul_div(
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
-    enum Kind {
-        Multiply,
-        Divide,
-    }
-
-    let mut left = parse_term(first_token, tree, tokens, owning_function_name)?;
+    let start = first_token.range.start;
+    let mut left = parse_power(first_token, tree, tokens, owning_function_name)?;
 
     loop {
-        let kind = match tokens.peek() {
-            Some(Ok(token)) if matches!(token.kind, TokenKind::Multiply) => Kind::Multiply,
-            Some(Ok(token)) if matches!(token.kind, TokenKind::Divide) => Kind::Divide,
+        let kind = match tokens.peek_token_kind() {
+            Some(TokenKind::Multiply) => BinOpKind::Multiply,
+            Some(TokenKind::Divide) => BinOpKind::Divide,
+            Some(TokenKind::Remainder) => BinOpKind::Remainder,
+            Some(TokenKind::Identifier(sym)) if sym == "div" => BinOpKind::IntegerDivide,
             _ => break,
         };
         let _op_token = tokens.next();
         let next_token = tokens.expect_next("value to operate against")?;
-        let right = parse_term(next_token, tree, tokens, owning_function_name)?;
-        left = match kind {
-            Kind::Multiply => tree.binop_node(BinOpKind::Multiply, left, right),
-            Kind::Divide => tree.binop_node(BinOpKind::Divide, left, right),
-        };
+        let right = parse_power(next_token, tree, tokens, owning_function_name)?;
+        left = tree.binop_node(kind, left, right);
+        tree.set_location(left, tokens.location_from(start));
     }
 
     Ok(left)
 }
 
+fn parse_power(
+    first_token: Token,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
+    if let TokenKind::Sub = first_token.kind {
+        // Negation applies to the result of raising to a power, so that
+        // `-x ** 2` is `-(x ** 2)`.
+        let next_token = tokens.expect_next("value to negate")?;
+        let value = parse_power(next_token, tree, tokens, owning_function_name)?;
+        let negated = tree.negate_node(value);
+        tree.set_location(negated, tokens.location_from(start));
+        return Ok(negated);
+    }
+    if let Some(TokenKind::Power) = tokens.peek_token_kind() {
+        // A `-` followed by a digit is lexed as part of the numeric literal.
+        // When raised to a power, the literal is negated afterwards so that
+        // `-2 ** 2` is `-(2 ** 2)`, matching `-x ** 2`.
+        if let Some(unsigned) = unsigned_literal(&first_token) {
+            let value = parse_power(unsigned, tree, tokens, owning_function_name)?;
+            let negated = tree.negate_node(value);
+            tree.set_location(negated, tokens.location_from(start));
+            return Ok(negated);
+        }
+    }
+
+    let base = parse_term(first_token, tree, tokens, owning_function_name)?;
+    if let Some(TokenKind::Power) = tokens.peek_token_kind() {
+        let _op_token = tokens.next();
+        // Raising to a power is right-associative.
+        let next_token = tokens.expect_next("exponent")?;
+        let exponent = parse_power(next_token, tree, tokens, owning_function_name)?;
+        let power = tree.binop_node(BinOpKind::Power, base, exponent);
+        tree.set_location(power, tokens.location_from(start));
+        Ok(power)
+    } else {
+        Ok(base)
+    }
+}
+
+/// Returns `token` without its sign if it is a negative numeric literal.
+fn unsigned_literal(token: &Token) -> Option<Token> {
+    let kind = match &token.kind {
+        TokenKind::Integer(value) if *value < 0 => value.checked_neg().map_or_else(
+            || TokenKind::BigInteger(-&BigInteger::from(*value)),
+            TokenKind::Integer,
+        ),
+        TokenKind::Real(value) if value.is_sign_negative() => TokenKind::Real(-value),
+        TokenKind::BigInteger(value) if value.is_negative() => TokenKind::BigInteger(-value),
+        TokenKind::Decimal(value) if value < &Decimal::default() => TokenKind::Decimal(-value),
+        _ => return None,
+    };
+    Some(Token {
+        kind,
+        range: token.range.start + 1..token.range.end,
+    })
+}
+
 fn parse_term(
     first_token: Token,
     tree: &SyntaxTreeBuilder,
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    let start = first_token.range.start;
+    let mut term = parse_term_kind(first_token, tree, tokens, owning_function_name)?;
+    tree.set_location(term, tokens.location_from(start));
+    // Terms such as `(expr)`, `[list]`, and `{map}` can be indexed directly.
+    while let Some(TokenKind::Open(BracketType::Square)) = tokens.peek_token_kind() {
+        term = parse_index(term, start, tree, tokens, owning_function_name)?;
+    }
+    Ok(term)
+}
+
+fn parse_term_kind(
+    first_token: Token,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
     match first_token.kind {
         TokenKind::Identifier(lookup_base) => match lookup_base.as_str() {
@@ -968,7 +1799,15 @@ fn parse_term(
                 )?;
                 Ok(tree.not_node(expr, false))
             }
-            _ => parse_lookup(lookup_base, tree, tokens, owning_function_name),
+            "function" => parse_lambda(tree, tokens),
+            "match" => parse_match(tree, tokens, owning_function_name),
+            _ => parse_lookup(
+                lookup_base,
+                first_token.range.start,
+                tree,
+                tokens,
+                owning_function_name,
+            ),
         },
         TokenKind::Tilde => {
             let expr = parse_term(
@@ -981,7 +1820,20 @@ fn parse_term(
         }
         TokenKind::Integer(integer) => Ok(tree.integer(integer)),
         TokenKind::Real(integer) => Ok(tree.real(integer)),
+        // There are no literals for these types in the virtual machine, so
+        // they are converted from strings instead.
+        TokenKind::BigInteger(integer) => Ok(tree.convert_node(
+            tree.string(integer.to_string()),
+            ValueKind::Dynamic(Symbol::from("BigInteger")),
+        )),
+        TokenKind::Decimal(decimal) => Ok(tree.convert_node(
+            tree.string(decimal.to_string()),
+            ValueKind::Dynamic(Symbol::from("Decimal")),
+        )),
         TokenKind::String(string) => Ok(tree.string(string)),
+        TokenKind::InterpolatedString(parts) => {
+            parse_interpolated_string(parts, tree, tokens, owning_function_name)
+        }
         TokenKind::Open(BracketType::Paren) => {
             let first_token = tokens.expect_next("expression")?;
             let expression = parse_expression(first_token, tree, tokens, owning_function_name)?;
@@ -1001,72 +1853,211 @@ fn parse_term(
     }
 }
 
+fn parse_interpolated_string(
+    parts: Vec<StringPart>,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    // Each interpolated value is converted to a String, and all of the parts
+    // are concatenated.
+    let mut result = None;
+    for part in parts {
+        let node = match part {
+            StringPart::Text(text) if text.is_empty() => continue,
+            StringPart::Text(text) => tree.string(text),
+            StringPart::Expression(range) => {
+                let mut expression_tokens = Lexer::for_range(tokens.source, range);
+                let first_token = expression_tokens.expect_next("expression")?;
+                let expression = parse_expression(
+                    first_token,
+                    tree,
+                    &mut expression_tokens,
+                    owning_function_name,
+                )?;
+                if let Some(token) = expression_tokens.next() {
+                    return Err(ParseError::Unexpected(token?));
+                }
+                tree.convert_node(expression, ValueKind::from(Symbol::from("String")))
+            }
+        };
+        result = Some(match result {
+            Some(left) => tree.binop_node(BinOpKind::Add, left, node),
+            None => node,
+        });
+    }
+    Ok(result.unwrap_or_else(|| tree.string(String::new())))
+}
+
 fn parse_lookup(
-    mut symbol: Symbol,
+    symbol: Symbol,
+    start: usize,
     tree: &SyntaxTreeBuilder,
     tokens: &mut Lexer<'_>,
     owning_function_name: Option<&str>,
 ) -> Result<NodeId, ParseError> {
+    // A dotted path, such as `module.function`, is read in full. Whether the
+    // path refers to a module or to a value is determined while compiling.
+    let mut path = vec![symbol];
+    let mut path_ends = Vec::new();
+    while let Some(TokenKind::Period) = tokens.peek_token_kind() {
+        path_ends.push(tokens.last_token_end);
+        let _period = tokens.next();
+        let token = tokens.expect_next("identifier")?;
+        match token.kind {
+            TokenKind::Identifier(sym) => path.push(sym),
+            _ => return Err(ParseError::Unexpected(token)),
+        }
+    }
+
     let mut base = None;
+    let mut symbol = path.pop();
+    if !path.is_empty() {
+        if let Some(TokenKind::Open(BracketType::Paren)) = tokens.peek_token_kind() {
+            let module = tree.identifier(join_path(&path));
+            tree.set_location(
+                module,
+                tokens.location_between(start, path_ends[path.len() - 1]),
+            );
+            base = Some(module);
+        } else {
+            path.extend(symbol.take());
+            symbol = Some(join_path(&path));
+        }
+    }
     loop {
         base = match tokens.peek_token_kind() {
             Some(TokenKind::Open(BracketType::Paren)) => {
                 // Call
-                let _open_paren = tokens.next();
-                let mut args = Vec::new();
-                let mut next_token = tokens.expect_next("argument or )")?;
-                if !matches!(next_token.kind, TokenKind::Close(BracketType::Paren)) {
-                    loop {
-                        args.push(parse_expression(
-                            next_token,
-                            tree,
-                            tokens,
-                            owning_function_name,
-                        )?);
-
-                        // Expect either end bracket or comma,
-                        let comma_or_end = tokens.expect_next(", or )")?;
-                        match comma_or_end.kind {
-                            TokenKind::Close(BracketType::Paren) => break,
-                            TokenKind::Comma => {}
-                            other => todo!("error: {other:?}"),
-                        }
-                        next_token = tokens.expect_next("argument or )")?;
+                let args = parse_call_args(tree, tokens, owning_function_name)?;
+                match (base, symbol.take()) {
+                    (Some(base), Some(symbol)) => Some(tree.call(Call::on(base, symbol, args))),
+                    // Calling the value returned by the previous call
+                    (Some(base), None) => Some(tree.call(Call::value(base, args))),
+                    (None, Some(symbol))
+                        if symbol == "this" || owning_function_name == Some(&symbol) =>
+                    {
+                        Some(tree.call(Call::recurse(args)))
                     }
-                }
-                if let Some(base) = base {
-                    Some(tree.call(Call::on(base, symbol, args)))
-                } else if symbol == "this" || owning_function_name == Some(&symbol) {
-                    Some(tree.call(Call::recurse(args)))
-                } else {
-                    Some(tree.call(Call::global(symbol, args)))
+                    (None, Some(symbol)) => Some(tree.call(Call::global(symbol, args))),
+                    (None, None) => unreachable!("a lookup always begins with a symbol"),
                 }
             }
             Some(TokenKind::Open(BracketType::Square)) => {
                 // Index
-                todo!()
+                let target = match (base, symbol.take()) {
+                    (Some(base), None) => base,
+                    (None, Some(symbol)) => {
+                        let target = tree.identifier(symbol);
+                        tree.set_location(target, tokens.location_from(start));
+                        target
+                    }
+                    (Some(_), Some(_)) => {
+                        let open_square = tokens.expect_next("[")?;
+                        return Err(ParseError::Unexpected(open_square));
+                    }
+                    (None, None) => unreachable!("a lookup always begins with a symbol"),
+                };
+                Some(parse_index(
+                    target,
+                    start,
+                    tree,
+                    tokens,
+                    owning_function_name,
+                )?)
             }
             _ if base.is_none() => {
                 // Just a solo identifier literal.
-                Some(tree.identifier(symbol))
+                Some(tree.identifier(symbol.take().expect("always at least a base symbol")))
             }
-            _ => break,
+            _ => match (base, symbol.take()) {
+                (Some(base), Some(field)) => {
+                    // Accessing a field of the previous expression's result.
+                    let field = tree.string(field.to_string());
+                    Some(tree.index_node(base, field))
+                }
+                _ => break,
+            },
         };
+        if let Some(base) = base {
+            tree.set_location(base, tokens.location_from(start));
+        }
 
-        if let Some(TokenKind::Period) = tokens.peek_token_kind() {
-            let _period = tokens.next();
-            match tokens.expect_next("identifier")?.kind {
-                TokenKind::Identifier(sym) => symbol = sym,
-                _ => todo!("error: invalid lookup"),
+        match tokens.peek_token_kind() {
+            Some(TokenKind::Period) => {
+                let _period = tokens.next();
+                let token = tokens.expect_next("identifier")?;
+                match token.kind {
+                    TokenKind::Identifier(sym) => symbol = Some(sym),
+                    _ => return Err(ParseError::Unexpected(token)),
+                }
             }
-        } else {
-            break;
+            Some(TokenKind::Open(BracketType::Paren | BracketType::Square)) if symbol.is_none() => {
+                // The result of the previous expression is being called or
+                // indexed.
+            }
+            _ => break,
         }
     }
 
     Ok(base.expect("always at least a base lookup"))
 }
 
+fn parse_call_args(
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<Vec<NodeId>, ParseError> {
+    let _open_paren = tokens.next();
+    let mut args = Vec::new();
+    let mut next_token = tokens.expect_next("argument or )")?;
+    if !matches!(next_token.kind, TokenKind::Close(BracketType::Paren)) {
+        loop {
+            args.push(parse_expression(
+                next_token,
+                tree,
+                tokens,
+                owning_function_name,
+            )?);
+
+            // Expect either end bracket or comma,
+            let comma_or_end = tokens.expect_next(", or )")?;
+            match comma_or_end.kind {
+                TokenKind::Close(BracketType::Paren) => break,
+                TokenKind::Comma => {}
+                other => todo!("error: {other:?}"),
+            }
+            next_token = tokens.expect_next("argument or )")?;
+        }
+    }
+    Ok(args)
+}
+
+fn parse_index(
+    target: NodeId,
+    start: usize,
+    tree: &SyntaxTreeBuilder,
+    tokens: &mut Lexer<'_>,
+    owning_function_name: Option<&str>,
+) -> Result<NodeId, ParseError> {
+    let _open_square = tokens.next();
+    let first_token = tokens.expect_next("index")?;
+    let index = parse_expression(first_token, tree, tokens, owning_function_name)?;
+    let close_square = tokens.expect_next("]")?;
+    if !matches!(close_square.kind, TokenKind::Close(BracketType::Square)) {
+        return Err(ParseError::Unexpected(close_square));
+    }
+
+    let node = tree.index_node(target, index);
+    tree.set_location(node, tokens.location_from(start));
+    Ok(node)
+}
+
+fn join_path(path: &[Symbol]) -> Symbol {
+    let path = path.iter().map(Symbol::as_str).collect::<Vec<_>>();
+    Symbol::from(path.join(".").as_str())
+}
+
 fn parse_map(
     tree: &SyntaxTreeBuilder,
     tokens: &mut Lexer<'_>,
@@ -1146,6 +2137,7 @@ pub enum ParseError {
         offset: usize,
         found: Option<Token>,
     },
+    MissingCatchOrFinally(Range<usize>),
     String(DecodeStringError),
     Numeric(DecodeNumericError),
 }
@@ -1162,6 +2154,7 @@ impl ParseError {
                 ..
             } => Some(*open_offset..error_location + 1),
             ParseError::ExpectedEndOfLine { offset, .. } => Some(*offset..*offset),
+            ParseError::MissingCatchOrFinally(range) => Some(range.clone()),
             ParseError::Numeric(err) => err.location(),
             ParseError::String(err) => err.location(),
         }
@@ -1193,6 +2186,11 @@ impl Display for ParseError {
                     write!(f, "expected end of line at {offset}")
                 }
             }
+            Self::MissingCatchOrFinally(range) => write!(
+                f,
+                "try at offset {} must have a catch or finally block",
+                range.start
+            ),
             Self::String(err) => write!(f, "error parsing string literal: {err}"),
             Self::Numeric(err) => write!(f, "error parsing numeric literal: {err}"),
         }
@@ -1229,6 +2227,27 @@ fn string_parsing() {
             .unwrap(),
         vec![Token::new(TokenKind::String(string), 0..source.len())]
     );
+    assert_eq!(
+        Lexer::new(r#""a{b}c""#)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap(),
+        vec![Token::new(
+            TokenKind::InterpolatedString(vec![
+                StringPart::Text(String::from("a")),
+                StringPart::Expression(3..4),
+                StringPart::Text(String::from("c"))
+            ]),
+            0..7
+        )]
+    );
+    let string = String::from("{a} \"b\" \\");
+    let source = string.to_source().unwrap();
+    assert_eq!(
+        Lexer::new(source.as_str())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap(),
+        vec![Token::new(TokenKind::String(string), 0..source.len())]
+    );
     assert!(matches!(
         Lexer::new(r#"""#)
             .collect::<Result<Vec<_>, _>>()
@@ -1257,3 +2276,27 @@ fn number_parsing() {
     assert_eq!(tokens[4].kind, TokenKind::Integer(1_000));
     assert_eq!(tokens[5].kind, TokenKind::Real(-1_000.000_1));
 }
+
+#[test]
+fn number_suffixes() {
+    let tokens = Lexer::new(r#"10n -1_000n 1.50d 2d 2div 3do"#)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(tokens.len(), 8);
+    assert_eq!(tokens[0].kind, TokenKind::BigInteger(BigInteger::from(10)));
+    assert_eq!(
+        tokens[1].kind,
+        TokenKind::BigInteger(BigInteger::from(-1_000))
+    );
+    assert_eq!(
+        tokens[2].kind,
+        TokenKind::Decimal(Decimal::new(BigInteger::from(150), 2))
+    );
+    assert_eq!(tokens[3].kind, TokenKind::Decimal(Decimal::from(2)));
+    // Suffixes must not be followed by other identifier characters.
+    assert_eq!(tokens[4].kind, TokenKind::Integer(2));
+    assert_eq!(tokens[5].kind, TokenKind::Identifier(Symbol::from("div")));
+    assert_eq!(tokens[6].kind, TokenKind::Integer(3));
+    assert_eq!(tokens[7].kind, TokenKind::Identifier(Symbol::from("do")));
+}
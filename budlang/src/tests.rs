@@ -1,25 +1,42 @@
-use std::{fmt::Display, vec};
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+    vec,
+};
 
 use crate::{
-    parser::{Lexer, TokenKind},
-    Bud, Error,
+    ast::CompilationError,
+    loader::{ImportError, MemoryLoader},
+    parser::{Lexer, ParseError, TokenKind},
+    Bud, BudEnvironment, Error, Intrinsic,
 };
 
 use budvm::{
-    DynamicFault, DynamicValue, Fault, FaultKind, FaultOrPause, HashMap, Instruction, List,
-    PoppedValues, Symbol, Value, ValueOrSource,
+    bytecode::BytecodeError,
+    ir::{optimizer::OptimizationLevel, Module, Scope},
+    AllocationBudget, ArithmeticMode, BigInteger, Budgeted, CancellationToken, Decimal,
+    Destination, DynamicFault, DynamicValue, Fault, FaultKind, FaultOrPause, Function, HashMap,
+    Instruction, InstructionCosts, List, PoppedValues, Record, Symbol, Value, ValueIterator,
+    ValueKind, ValueOrSource, VerificationIssueKind,
 };
 
 macro_rules! assert_run {
     ($source:literal, $result:expr) => {
-        let mut context = Bud::empty();
-        let expected_result = $result;
-        let result: Value = context.run_source($source).unwrap();
-        if result != $result {
-            panic!(
-                "{} returned {result:?}, expected {expected_result:?}",
-                $source
-            );
+        for level in [
+            OptimizationLevel::None,
+            OptimizationLevel::Basic,
+            OptimizationLevel::Full,
+        ] {
+            let mut context = Bud::empty().with_optimization_level(level);
+            let expected_result = $result;
+            let result: Value = context.run_source($source).unwrap();
+            context.verify_functions().unwrap();
+            if result != $result {
+                panic!(
+                    "{} returned {result:?} at {level:?}, expected {expected_result:?}",
+                    $source
+                );
+            }
         }
     };
 }
@@ -69,6 +86,48 @@ fn math() {
     assert_run!("1.1 + 2.0", Value::Real(3.1));
     assert_run!("-1.0 - -10.0", Value::Real(9.));
     assert_run!("-0.0 * 0.0", Value::Real(0.));
+
+    // Integer division rounds towards negative infinity, and the remainder
+    // has the sign of the divisor.
+    assert_run!("7 div 2", 3);
+    assert_run!("-7 div 2", -4);
+    assert_run!("7 % 3", 1);
+    assert_run!("-7 % 3", 2);
+    assert_run!("7 % -3", -2);
+    assert_run!("7.5 div 2.0", Value::Real(3.));
+    assert_run!("-7.5 % 2.0", Value::Real(0.5));
+    assert_run!("1 div 0", Value::Void);
+    assert_run!("1 % 0", Value::Void);
+    assert_run!("1 / 0", Value::Void);
+    assert_run!("(-9223372036854775807 - 1) div -1", Value::Void);
+    assert_run!("(-9223372036854775807 - 1) % -1", 0);
+
+    // Powers are right-associative and bind tighter than negation.
+    assert_run!("2 ** 10", 1024);
+    assert_run!("2 ** 3 ** 2", 512);
+    assert_run!("2 * 3 ** 2", 18);
+    assert_run!("4.0 ** 0.5", Value::Real(2.));
+    assert_run!("2 ** 63", Value::Void);
+    assert_run!("2 ** -1", Value::Void);
+
+    // Unary negation
+    assert_run!("a := 3\n-a", -3);
+    assert_run!("a := 3\n-a ** 2", -9);
+    assert_run!("a := 3\n(-a) ** 2", 9);
+    assert_run!("-2 ** 2", -4);
+    assert_run!("(-2) ** 2", 4);
+    assert_run!("-1.5 ** 2.0", Value::Real(-2.25));
+    assert_run!("a := 1.5\n-a * 2.0", Value::Real(-3.));
+    assert_run!("a := 2\n5 - -a", 7);
+    assert_run!("a := -9223372036854775807 - 1\n-a", Value::Void);
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("a := \"a\"\n-a"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
 }
 
 #[test]
@@ -101,6 +160,10 @@ impl DynamicValue for TestDynamic {
         Symbol::from("TestDynamic")
     }
 
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        (kind == "String").then(|| Value::dynamic(format!("TestDynamic({})", self.0)))
+    }
+
     fn call(&self, name: &Symbol, _args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
         match name.as_ref() {
             "squared" => Ok(Value::dynamic(Self(self.0.pow(2)))),
@@ -109,6 +172,33 @@ impl DynamicValue for TestDynamic {
             )))),
         }
     }
+
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        let index = index
+            .as_i64()
+            .ok_or_else(|| FaultKind::invalid_type("index must be an integer", index.clone()))?;
+        Ok(Value::Integer(i64::from(self.0) * index))
+    }
+
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        Ok(Value::dynamic(ValueIterator::new(
+            (0..i64::from(self.0)).map(Value::Integer),
+        )))
+    }
+
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        match other {
+            Value::Integer(exponent) if !is_reverse => Ok(u32::try_from(*exponent)
+                .ok()
+                .and_then(|exponent| self.0.checked_pow(exponent))
+                .map(|value| Value::dynamic(Self(value)))),
+            _ => Ok(None),
+        }
+    }
+
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        Ok(Some(Value::Integer(-i64::from(self.0))))
+    }
 }
 
 #[test]
@@ -163,6 +253,260 @@ fn dynamic_invoke() {
     assert_eq!(result.0, 4);
 }
 
+fn run_with_mode(mode: ArithmeticMode, source: &str) -> Result<Value, FaultKind> {
+    let mut results = [
+        OptimizationLevel::None,
+        OptimizationLevel::Basic,
+        OptimizationLevel::Full,
+    ]
+    .into_iter()
+    .map(|level| {
+        Bud::empty()
+            .with_optimization_level(level)
+            .with_arithmetic_mode(mode)
+            .run_source::<Value>(source)
+            .map_err(|err| match err {
+                Error::Vm(budvm::Error::Fault(Fault {
+                    kind: FaultOrPause::Fault(kind),
+                    ..
+                })) => kind,
+                other => unreachable!("unexpected error: {other}"),
+            })
+    });
+    let result = results.next().unwrap();
+    for other in results {
+        assert_eq!(
+            result, other,
+            "{source} differed between optimization levels"
+        );
+    }
+    result
+}
+
+#[test]
+fn arithmetic_modes() {
+    const MAX: i64 = i64::MAX;
+    const MIN: i64 = i64::MIN;
+
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Void, "9223372036854775807 + 1"),
+        Ok(Value::Void)
+    );
+
+    let overflow = run_with_mode(ArithmeticMode::Fault, "9223372036854775807 + 1").unwrap_err();
+    assert_eq!(
+        overflow,
+        FaultKind::Overflow {
+            operator: "+",
+            operands: vec![Value::Integer(MAX), Value::Integer(1)]
+        }
+    );
+    assert_eq!(
+        overflow.to_string(),
+        "integer overflow evaluating `9223372036854775807 + 1`"
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Fault, "a := -9223372036854775807 - 1\n-a"),
+        Err(FaultKind::Overflow {
+            operator: "-",
+            operands: vec![Value::Integer(MIN)]
+        })
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Fault, "7 div 0"),
+        Err(FaultKind::DivideByZero(Value::Integer(7)))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Fault, "2 ** -1"),
+        Err(FaultKind::ValueOutOfRange("exponent"))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Fault, "1.0 / 0.0"),
+        Ok(Value::Real(f64::INFINITY))
+    );
+
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Wrapping, "9223372036854775807 + 1"),
+        Ok(Value::Integer(MIN))
+    );
+    assert_eq!(
+        run_with_mode(
+            ArithmeticMode::Wrapping,
+            "(-9223372036854775807 - 1) div -1"
+        ),
+        Ok(Value::Integer(MIN))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Wrapping, "3 ** 41"),
+        Ok(Value::Integer(3_i64.wrapping_pow(41)))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Wrapping, "1 % 0"),
+        Err(FaultKind::DivideByZero(Value::Integer(1)))
+    );
+
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Saturating, "9223372036854775807 * 2"),
+        Ok(Value::Integer(MAX))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Saturating, "-9223372036854775807 - 2"),
+        Ok(Value::Integer(MIN))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::Saturating, "(-3) ** 41"),
+        Ok(Value::Integer(MIN))
+    );
+
+    assert_eq!(
+        run_with_mode(ArithmeticMode::PromoteToReal, "9223372036854775807 + 1"),
+        Ok(Value::Real(9_223_372_036_854_775_808.))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::PromoteToReal, "2 ** -1"),
+        Ok(Value::Real(0.5))
+    );
+    assert_eq!(
+        run_with_mode(ArithmeticMode::PromoteToReal, "1 + 2"),
+        Ok(Value::Integer(3))
+    );
+}
+
+fn big_integer(value: &str) -> Value {
+    Value::dynamic(value.parse::<BigInteger>().unwrap())
+}
+
+fn decimal(value: &str) -> Value {
+    Value::dynamic(value.parse::<Decimal>().unwrap())
+}
+
+#[test]
+fn big_integers() {
+    assert_run!(
+        "123456789012345678901234567890n * 10",
+        big_integer("1234567890123456789012345678900")
+    );
+    assert_run!(
+        "(9223372036854775807 as BigInteger) + 1",
+        big_integer("9223372036854775808")
+    );
+    assert_run!("2n ** 100", big_integer("1267650600228229401496703205376"));
+    assert_run!("1 - 5n", big_integer("-4"));
+    assert_run!("-7n div 2", big_integer("-4"));
+    assert_run!("-7n % 2", big_integer("1"));
+    assert_run!("-7n / 2", big_integer("-3"));
+    assert_run!("a := 5n\n-a", big_integer("-5"));
+    assert_run!("5n + 0.5", Value::Real(5.5));
+    assert_run!("\"-42\" as BigInteger", big_integer("-42"));
+    assert_run!("2.9 as BigInteger", big_integer("2"));
+    assert_run!("42n as Integer", 42);
+    assert_run!("42n as Real", Value::Real(42.));
+    assert_run!(
+        "12345678901234567890n as String",
+        Value::dynamic(String::from("12345678901234567890"))
+    );
+    // Like integers and reals, different kinds of numbers are never equal.
+    assert_run!("1n = 1n", true);
+    assert_run!("1n = 1", false);
+    assert_run!("1n < 2n", true);
+    assert_run!("{1n: 2}[1n]", 2);
+
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("1n div 0"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::DivideByZero(_)),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        bud.run_source::<Value>("\"1.5\" as BigInteger"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    let source = bud.run_source::<Value>("[1n, -2n]").unwrap().to_source();
+    assert_eq!(source.as_deref(), Some("[1n, -2n]"));
+}
+
+#[test]
+fn decimals() {
+    assert_run!("0.1d + 0.2d", decimal("0.3"));
+    assert_run!("0.1d + 0.2d = 0.3d", true);
+    assert_run!("1.10d + 2", decimal("3.10"));
+    assert_run!("(0.1 as Decimal) + 0.2", decimal("0.3"));
+    assert_run!("19.99d * 3", decimal("59.97"));
+    assert_run!("10d / 4", decimal("2.5"));
+    assert_run!("1d / 3", decimal("0.3333333333333333333333333333"));
+    assert_run!("-7.5d div 2", decimal("-4"));
+    assert_run!("-7.5d % 2", decimal("0.5"));
+    assert_run!("1.5d ** 2", decimal("2.25"));
+    assert_run!("2d ** -1", decimal("0.5"));
+    assert_run!("5n + 0.25d", decimal("5.25"));
+    assert_run!("a := 1.5d\n-a", decimal("-1.5"));
+    assert_run!("\"12.50\" as Decimal", decimal("12.50"));
+    assert_run!("12.50d as String", Value::dynamic(String::from("12.50")));
+    assert_run!("12.75d as Integer", 12);
+    assert_run!("-12.75d as BigInteger", big_integer("-12"));
+    assert_run!("0.5d as Real", Value::Real(0.5));
+    assert_run!("1.50d = 1.5d", true);
+    assert_run!("1.5d = 1.5", false);
+    assert_run!("1.25d < 1.5d", true);
+    assert_run!("{1.50d: 2}[1.5d]", 2);
+
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("1d / 0"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::DivideByZero(_)),
+            ..
+        })))
+    ));
+    let source = bud.run_source::<Value>("[1.50d, -2d]").unwrap().to_source();
+    assert_eq!(source.as_deref(), Some("[1.50d, -2d]"));
+}
+
+#[test]
+fn dynamic_operators() {
+    let mut context = Bud::empty();
+    context
+        .run_source::<()>(
+            r#"
+        function power(dynamic)
+            dynamic ** 3
+        end
+
+        function negate(dynamic)
+            -dynamic
+        end
+
+        function reverse_power(dynamic)
+            3 ** dynamic
+        end
+    "#,
+        )
+        .unwrap();
+    let result: TestDynamic = context
+        .call(&Symbol::from("power"), [Value::dynamic(TestDynamic(2))])
+        .unwrap();
+    assert_eq!(result.0, 8);
+    let result: i64 = context
+        .call(&Symbol::from("negate"), [Value::dynamic(TestDynamic(2))])
+        .unwrap();
+    assert_eq!(result, -2);
+    assert!(matches!(
+        context.call::<Value, _, _>(
+            &Symbol::from("reverse_power"),
+            [Value::dynamic(TestDynamic(2))]
+        ),
+        Err(Fault {
+            kind: FaultOrPause::Fault(FaultKind::TypeMismatch { .. }),
+            ..
+        })
+    ));
+}
+
 #[test]
 fn dynamic_error() {
     let mut context = Bud::empty();
@@ -216,6 +560,85 @@ fn strings() {
     assert_eq!(replaced, "1bcd1");
 }
 
+#[test]
+fn string_methods() {
+    fn string(value: &str) -> Value {
+        Value::dynamic(String::from(value))
+    }
+    fn strings(values: &[&str]) -> Value {
+        Value::dynamic(values.iter().map(|value| string(value)).collect::<List>())
+    }
+
+    assert_run!("a := \"héllo\"\na.len()", 5);
+    assert_run!("a := \"hé\"\na.chars()", strings(&["h", "é"]));
+    assert_run!(
+        "a := \"a,b,,c\"\na.split(\",\")",
+        strings(&["a", "b", "", "c"])
+    );
+    assert_run!(
+        "a := \"a\\nb\\r\\nc\\n\"\na.lines()",
+        strings(&["a", "b", "c"])
+    );
+    assert_run!("a := \"  a b  \"\na.trim()", string("a b"));
+    assert_run!("a := \"  a b  \"\na.trim_start()", string("a b  "));
+    assert_run!("a := \"  a b  \"\na.trim_end()", string("  a b"));
+    assert_run!("a := \"aBc\"\na.upper()", string("ABC"));
+    assert_run!("a := \"aBc\"\na.lower()", string("abc"));
+    assert_run!("a := \"abc\"\na.starts_with(\"ab\")", true);
+    assert_run!("a := \"abc\"\na.starts_with(\"bc\")", false);
+    assert_run!("a := \"abc\"\na.ends_with(\"bc\")", true);
+    assert_run!("a := \"abc\"\na.contains(\"b\")", true);
+    assert_run!("a := \"abc\"\na.contains(\"d\")", false);
+    assert_run!("a := \"éabcb\"\na.find(\"b\")", 2);
+    assert_run!("a := \"abc\"\na.find(\"d\")", Value::Void);
+    assert_run!("a := \"héllo\"\na.substring(1, 3)", string("él"));
+    assert_run!("a := \"héllo\"\na.substring(3)", string("lo"));
+    assert_run!("a := \"héllo\"\na.substring(5)", string(""));
+    assert_run!("a := \"ab\"\na.repeat(3)", string("ababab"));
+    assert_run!("a := \" 42 \"\na.parse_integer()", 42);
+    assert_run!("a := \"-1.5\"\na.parse_real()", -1.5);
+
+    // Faults
+    for source in [
+        "a := \"abc\"\na.substring(2, 1)",
+        "a := \"abc\"\na.substring(0, 4)",
+        "a := \"abc\"\na.repeat(-1)",
+    ] {
+        assert!(matches!(
+            Bud::empty().run_source::<()>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange(_)),
+                ..
+            })))
+        ));
+    }
+    for source in [
+        "a := \"abc\"\na.split(1)",
+        "a := \"abc\"\na.split(\"\")",
+        "a := \"abc\"\na.starts_with(true)",
+        "a := \"abc\"\na.substring(\"0\")",
+        "a := \"abc\"\na.repeat(\"2\")",
+        "a := \"abc\"\na.parse_integer()",
+        "a := \"1.5\"\na.parse_integer()",
+        "a := \"abc\"\na.parse_real()",
+    ] {
+        assert!(matches!(
+            Bud::empty().run_source::<()>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+                ..
+            })))
+        ));
+    }
+    let mut context = Bud::empty();
+    let error = context
+        .run_source::<()>("a := \"abc\"\na.ends_with(1)")
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("suffix must be a string. Found `1` (Integer)"));
+}
+
 #[test]
 fn maps() {
     let map = Bud::empty()
@@ -291,90 +714,950 @@ fn lists() {
 }
 
 #[test]
-fn interactive() {
-    // Test variable persistence
-    let mut session = Bud::empty();
-    session.evaluate::<()>("a := 42").unwrap();
-    assert_eq!(session.evaluate::<i64>("a").unwrap(), 42);
+fn list_methods() {
+    assert_run!("a := [1, 2, 3]\na.get(1)", 2);
+    assert_run!(
+        "a := [1, 2, 3]\na.set(0, 5)\na",
+        Value::dynamic(List::from_iter([5, 2, 3].map(Value::Integer)))
+    );
+    assert_run!(
+        "a := [1, 3]\na.insert(1, 2)\na.insert(3, 4)\na",
+        Value::dynamic(List::from_iter([1, 2, 3, 4].map(Value::Integer)))
+    );
+    assert_run!(
+        "a := [1, 2, 3, 4]\na.slice(1, 3)",
+        Value::dynamic(List::from_iter([2, 3].map(Value::Integer)))
+    );
+    assert_run!(
+        "a := [1, 2, 3, 4]\na.slice(2)",
+        Value::dynamic(List::from_iter([3, 4].map(Value::Integer)))
+    );
+    assert_run!("a := [1, 2, 3]\na.contains(2)", true);
+    assert_run!("a := [1, 2, 3]\na.contains(\"2\")", false);
+    assert_run!("a := [1, 2, 3]\na.index_of(3)", 2);
+    assert_run!("a := [1, 2, 3]\na.index_of(4)", Value::Void);
+    assert_run!(
+        "a := [1, 2, 3]\na.reverse()\na",
+        Value::dynamic(List::from_iter([3, 2, 1].map(Value::Integer)))
+    );
+    assert_run!("a := [1, 2, 3]\na.clear()\na.count()", 0);
+    assert_run!(
+        "a := [1, \"b\", true, 1.5]\na.join(\", \")",
+        Value::dynamic(String::from("1, b, true, 1.5"))
+    );
+    assert_run!("a := []\na.join(\", \")", Value::dynamic(String::new()));
+    assert_run!(
+        "a := [1]\nb := a.concat([2])\na.count() * 10 + b.count()",
+        12
+    );
+    assert_run!("a := [1]\na.extend([2, 3])\na.extend({4: 5})\na.count()", 4);
+    assert_run!(
+        "a := [1]\na.extend(a)\na",
+        Value::dynamic(List::from_iter([1, 1].map(Value::Integer)))
+    );
+    assert_run!("a := [1]\nb := a + [2, 3]\na.count() * 10 + b.count()", 13);
+    assert_eq!(
+        Bud::empty()
+            .run_source::<Value>("[1, 2.5, [true, \"a\"]]")
+            .unwrap()
+            .to_string(),
+        "[1, 2.5, [true, \"a\"]]"
+    );
 
-    // Test a new function calling an existing function
-    session
-        .evaluate::<()>(
-            r#"
-                function foo()
-                    42
-                end
-            "#,
-        )
-        .unwrap();
-    session
-        .evaluate::<()>(
-            r#"
-                function bar()
-                    foo()
-                end
-            "#,
-        )
-        .unwrap();
-    assert_eq!(session.evaluate::<i64>("bar()").unwrap(), 42);
+    // Faults
+    for source in [
+        "a := [1, 2, 3]\na.get(3)",
+        "a := [1]\na.set(1, 2)",
+        "a := [1]\na.insert(2, 2)",
+        "a := [1]\na.remove(1)",
+        "a := [1, 2]\na.slice(2, 1)",
+        "a := [1, 2]\na.slice(0, 3)",
+    ] {
+        assert!(matches!(
+            Bud::empty().run_source::<()>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange("index")),
+                ..
+            })))
+        ));
+    }
+    for source in [
+        "a := [1]\na.get(\"0\")",
+        "a := [1]\na.join(1)",
+        "a := [1]\na.concat(1)",
+    ] {
+        assert!(matches!(
+            Bud::empty().run_source::<()>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+                ..
+            })))
+        ));
+    }
 }
 
 #[test]
-fn loops() {
-    // Basic loop with continue and break usage
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                a := 0
-                total := 0
-                loop
-                    a := a + 1
-                    if a = 5
-                        break total
-                    else
-                        if a = 3
-                            continue
-                        end
-                    end
-                    total := total + a
-                end
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 1 + 2 + 4);
+fn list_sorting() {
+    assert_run!(
+        "a := [3, 1, 2]\na.sort()\na",
+        Value::dynamic(List::from_iter([1, 2, 3].map(Value::Integer)))
+    );
+    assert_run!(
+        r#"
+            a := ["b", "c", "a"]
+            a.sort()
+            a.join("")
+        "#,
+        Value::dynamic(String::from("abc"))
+    );
+    // Comparators return a negative number, zero, or a positive number.
+    assert_run!(
+        r#"
+            a := [3, 1, 2, 5, 4]
+            a.sort(function(lhs, rhs) rhs - lhs end)
+            a
+        "#,
+        Value::dynamic(List::from_iter([5, 4, 3, 2, 1].map(Value::Integer)))
+    );
+    // Sorting is stable, and comparators can capture values.
+    assert_run!(
+        r#"
+            key := 0
+            a := [[2, "a"], [1, "b"], [2, "c"], [1, "d"]]
+            a.sort(function(lhs, rhs) lhs[key] - rhs[key] end)
+            a[0][1] + a[1][1] + a[2][1] + a[3][1]
+        "#,
+        Value::dynamic(String::from("bdac"))
+    );
+    // Named functions can be used as comparators.
+    assert_run!(
+        r#"
+            function descending(lhs, rhs)
+                rhs - lhs
+            end
+            a := [1, 3, 2]
+            a.sort(descending)
+            a[0]
+        "#,
+        3
+    );
 
-    // Test labeled break
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                a := 1
-                loop #infinite
-                    loop
-                        if a = 10
-                            break #infinite a
-                        else
-                            a := a + 1
-                        end
-                    end
-                end
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 10);
+    // Faults
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := [1, \"a\"]\na.sort()"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::TypeMismatch { .. }),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := [1, 2]\na.sort(function(lhs, rhs) true end)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := [1, 2]\na.sort(1)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    // Each comparator that sorts consumes native stack space, so the nesting
+    // is limited.
+    let nested = r"
+        function compare(lhs, rhs)
+            depth := lhs[0]
+            if depth > 0
+                nested := [[depth - 1, lhs[1]], [depth - 1, lhs[1]]]
+                nested.sort(lhs[1])
+            end
+            0
+        end
+    ";
+    let mut bud = Bud::empty();
+    bud.run_source::<()>(&format!(
+        "{nested}a := [[10, compare], [10, compare]]\na.sort(compare)"
+    ))
+    .unwrap();
+    assert!(matches!(
+        bud.run_source::<()>("a := [[1000, compare], [1000, compare]]\na.sort(compare)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::CallDepthExceeded(_)),
+            ..
+        })))
+    ));
+}
 
-    // Test labeled continue
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                b := 0
-                c := 0
-                loop #outer
-                    if b = 10
-                        break c
-                    end
-                    b := b + 1
-                    a := 0
-                    loop
+#[test]
+fn map_methods() {
+    fn ints(values: &[i64]) -> Value {
+        Value::dynamic(values.iter().copied().map(Value::Integer).collect::<List>())
+    }
+
+    assert_run!("m := {3: 1, 1: 2, 2: 3}\nm.keys()", ints(&[3, 1, 2]));
+    assert_run!("m := {3: 1, 1: 2, 2: 3}\nm.values()", ints(&[1, 2, 3]));
+    assert_run!(
+        "m := {1: 2, 3: 4}\nm.entries()",
+        Value::dynamic(List::from_iter([ints(&[1, 2]), ints(&[3, 4])]))
+    );
+    assert_run!("m := {1: 2}\nm.contains_key(1)", true);
+    assert_run!("m := {1: 2}\nm.contains_key(2)", false);
+    assert_run!("m := {1: 2}\nm.clear()\nm.count()", 0);
+    assert_run!("m := {1: 2}\nm.get_or(1, 0)", 2);
+    assert_run!("m := {1: 2}\nm.get_or(2, 0)", 0);
+    assert_run!(
+        "m := {1: 1, 2: 2}\nm.merge({2: 3, 4: 5})\nm.values()",
+        ints(&[1, 3, 5])
+    );
+    assert_run!("m := {1: 1}\nm.merge(m)\nm.count()", 1);
+    assert_run!(
+        "m := {1: 1, 2: 2, 3: 3}\nremoved := m.remove_all([1, 3, 5])\nremoved * 10 + m.count()",
+        21
+    );
+
+    // Equality compares contents regardless of order.
+    assert_run!("{1: [1, 2], 2: {3: 4}} = {2: {3: 4}, 1: [1, 2]}", true);
+    assert_run!("{1: [1, 2]} = {1: [1, 3]}", false);
+    assert_run!("m := {1: 2}\nm = m", true);
+    assert_run!("a := [1]\na = a", true);
+
+    // Maps display their contents in insertion order.
+    assert_eq!(
+        Bud::empty()
+            .run_source::<Value>("{\"b\": 1, \"a\": [true, 1.5], 3: {}}")
+            .unwrap()
+            .to_string(),
+        "{\"b\": 1, \"a\": [true, 1.5], 3: {}}"
+    );
+
+    assert!(matches!(
+        Bud::empty().run_source::<()>("m := {}\nm.merge([1])"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+}
+
+#[test]
+fn exceptions() {
+    // Thrown values are caught as-is.
+    assert_run!("try\n  throw 42\ncatch err\n  err + 1\nend", 43);
+    assert_run!("try\n  1\ncatch err\n  2\nend", 1);
+    assert_run!("try\n  throw [1]\ncatch\n  2\nend", 2);
+    // Faults are caught as values that describe the fault.
+    assert_run!(
+        "try\n  a := [1]\n  a.remove(5)\ncatch err\n  err.kind()\nend",
+        Value::dynamic(String::from("ValueOutOfRange"))
+    );
+    assert_run!(
+        "try\n  1 + \"a\"\ncatch err\n  err.message()\nend",
+        Value::dynamic(String::from("can't add String and `1` (Integer)"))
+    );
+    // Faults propagate out of called functions.
+    assert_run!(
+        "function fails(n)\n  throw n * 2\nend\ntry\n  fails(3)\n  0\ncatch err\n  err\nend",
+        6
+    );
+    // The stack is restored when a fault is caught while pushing arguments.
+    assert_run!(
+        "function fails()\n  throw 1\nend\nfunction add(a, b)\n  a + b\nend\ntry\n  add(10, fails())\ncatch err\n  add(err, 1)\nend",
+        2
+    );
+    // Handlers nest, and rethrowing a caught fault preserves its kind.
+    assert_run!(
+        "try\n  try\n    throw 1\n  catch err\n    throw err + 1\n  end\ncatch err\n  err\nend",
+        2
+    );
+    assert_run!(
+        "try\n  try\n    a := [1]\n    a.remove(5)\n  catch err\n    throw err\n  end\ncatch err\n  err.kind()\nend",
+        Value::dynamic(String::from("ValueOutOfRange"))
+    );
+    // Finally blocks always run.
+    assert_run!(
+        "a := [1]\ntry\n  a.push(2)\nfinally\n  a.push(3)\nend\na",
+        Value::dynamic(List::from_iter([
+            Value::Integer(1),
+            Value::Integer(2),
+            Value::Integer(3)
+        ]))
+    );
+    assert_run!(
+        "a := [1]\ntry\n  try\n    throw 2\n  finally\n    a.push(3)\n  end\ncatch err\n  a.push(err)\nend\na",
+        Value::dynamic(List::from_iter([
+            Value::Integer(1),
+            Value::Integer(3),
+            Value::Integer(2)
+        ]))
+    );
+    assert_run!(
+        "a := []\ntry\n  try\n    throw 1\n  catch err\n    throw err + 1\n  finally\n    a.push(0)\n  end\ncatch err\n  a.push(err)\nend\na",
+        Value::dynamic(List::from_iter([Value::Integer(0), Value::Integer(2)]))
+    );
+    // Leaving a loop from within a try removes its handler.
+    assert_run!(
+        "total := 0\nloop for i := 1 to 5\n  try\n    if i = 3\n      continue\n    end\n    if i = 4\n      break\n    end\n    total := total + i\n  catch\n    total := 100\n  end\nend\ntry\n  throw 1\ncatch err\n  total + err * 10\nend",
+        13
+    );
+    // Faults raised by native functions are catchable.
+    let mut bud = Bud::empty().with_native_function("fails", |_args: &mut PoppedValues<'_>| {
+        Err::<Value, _>(FaultKind::dynamic("native failure"))
+    });
+    assert_eq!(
+        bud.run_source::<String>(
+            "try\n  fails()\ncatch err\n  err.kind() + \": \" + err.message()\nend"
+        )
+        .unwrap(),
+        "Dynamic: native failure"
+    );
+    // Throwing a caught fault raises the original error again.
+    let err = bud
+        .run_source::<Value>("try\n  fails()\ncatch err\n  throw err\nend")
+        .unwrap_err();
+    match err {
+        Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::Dynamic(fault)),
+            ..
+        })) => assert_eq!(fault.downcast_ref::<&str>(), Some(&"native failure")),
+        other => unreachable!("unexpected error: {other:?}"),
+    }
+
+    let mut context = Bud::empty();
+    let err = context.run_source::<Value>("throw 1").unwrap_err();
+    assert!(matches!(
+        &err,
+        Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::Thrown(Value::Integer(1))),
+            ..
+        }))
+    ));
+    assert_eq!(
+        err.to_string(),
+        "vm error: vm fault: uncaught value: `1` (Integer)"
+    );
+    assert!(matches!(
+        Bud::empty().run_source::<()>("try\n  1\nend"),
+        Err(Error::Parse(ParseError::MissingCatchOrFinally(_)))
+    ));
+}
+
+#[test]
+fn string_interpolation() {
+    fn string(value: &str) -> Value {
+        Value::dynamic(String::from(value))
+    }
+
+    assert_run!(
+        "name := \"Bud\"\namount := 1.5\n\"Hello, {name}, you owe {amount}!\"",
+        string("Hello, Bud, you owe 1.5!")
+    );
+    assert_run!("a := 2\n\"{a}{a * 3}\"", string("26"));
+    assert_run!(
+        "a := [1, 2]\n\"{true} {a.count()} {a}\"",
+        string("true 2 [1, 2]")
+    );
+    // Expressions may contain strings and maps.
+    assert_run!(
+        "m := {\"a\": 1}\n\"value: {m[\"a\"]}, {{\"b\": 2}[\"b\"]}\"",
+        string("value: 1, 2")
+    );
+    // Escaped braces, empty braces, and format placeholders are not
+    // interpolated.
+    assert_run!("\"\\{a} {} {:>3}\"", string("{a} {} {:>3}"));
+    // Braces without a closing brace are part of the text.
+    assert_run!("n := \"a\"\n\"x{n}y{\"", string("xay{"));
+    assert_run!("n := 1\n\"{{n}\"", string("{1"));
+
+    // Custom dynamic values are converted using the environment.
+    let mut bud = Bud::empty()
+        .with_native_function("test_value", |_args: &mut PoppedValues<'_>| {
+            Ok::<_, FaultKind>(Value::dynamic(TestDynamic(3)))
+        });
+    assert_eq!(
+        bud.run_source::<String>("\"value: {test_value()}\"")
+            .unwrap(),
+        "value: TestDynamic(3)"
+    );
+
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("\"{1 2}\""),
+        Err(Error::Parse(ParseError::Unexpected(_)))
+    ));
+}
+
+#[test]
+fn format() {
+    fn string(value: &str) -> Value {
+        Value::dynamic(String::from(value))
+    }
+
+    assert_run!(
+        "format(\"{} + {} = {}\", 1, 2.5, \"three\")",
+        string("1 + 2.5 = three")
+    );
+    assert_run!("format(\"[{:5}]\", 42)", string("[   42]"));
+    assert_run!("format(\"[{:5}]\", \"ab\")", string("[ab   ]"));
+    assert_run!("format(\"[{:<5}]\", 42)", string("[42   ]"));
+    assert_run!("format(\"[{:^6}]\", \"ab\")", string("[  ab  ]"));
+    assert_run!("format(\"[{:*>5}]\", \"ab\")", string("[***ab]"));
+    assert_run!("format(\"[{:05}]\", -42)", string("[-0042]"));
+    assert_run!("format(\"[{:.2}]\", 3.14159)", string("[3.14]"));
+    assert_run!("format(\"[{:8.3}]\", 2.0)", string("[   2.000]"));
+    assert_run!("format(\"[{:.2}]\", \"abc\")", string("[ab]"));
+    assert_run!("format(\"\\{\\{}}\")", string("{}"));
+    assert_run!("format(\"{}\", [1, 2])", string("[1, 2]"));
+
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("format(\"{} {}\", 1)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ArgumentMissing(_)),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("format(\"{}\", 1, 2)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::TooManyArguments(_)),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("format(\"{:x}\", 1)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("format(\"{:.99999999999}\", 1.5)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange("precision")),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<Value>("format(\"{:4000000000}\", 1)"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange("width")),
+            ..
+        })))
+    ));
+    // Functions named format take precedence over the intrinsic.
+    assert_run!("function format(value)\n  value * 2\nend\nformat(2)", 4);
+}
+
+#[test]
+fn records() {
+    fn string(value: &str) -> Value {
+        Value::dynamic(String::from(value))
+    }
+    const POINT: &str = "struct Point(x, y)\n  function sum()\n    self.x + self.y\n  end\n\n  function scaled(factor)\n    Point(self.x * factor, self.y * factor)\n  end\n\n  function move_by(dx)\n    self.x := self.x + dx\n    self\n  end\nend\n";
+
+    // Fields can be read, written, and reached through call results.
+    assert_run!(
+        "struct Point(x, y)\nend\np := Point(1, 2)\np.x * 10 + p.y",
+        12
+    );
+    assert_run!(
+        "struct Point(x, y)\nend\np := Point(1, 2)\np.y := 5\np.y",
+        5
+    );
+    assert_run!(
+        "struct Point(x, y)\nend\nfunction origin()\n  Point(3, 4)\nend\norigin().y",
+        4
+    );
+    assert_run!(
+        "struct Pair(left, right)\nend\np := Pair(Pair(1, 2), 3)\np.left.right := 9\np.left.right",
+        9
+    );
+    // Methods receive the record as `self`.
+    let source = format!("{POINT}p := Point(1, 2)\np.sum()");
+    let mut bud = Bud::empty();
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 3);
+    let source = format!("{POINT}p := Point(1, 2)\np.scaled(3).sum()");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 9);
+    let source = format!("{POINT}p := Point(1, 2)\np.move_by(4).x + p.x");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 10);
+    // Records are values of their own kind.
+    let source = format!("{POINT}Point(1, 2) = Point(1, 2)");
+    assert!(bud.run_source::<bool>(&source).unwrap());
+    let source = format!("{POINT}Point(1, 2) = Point(2, 1)");
+    assert!(!bud.run_source::<bool>(&source).unwrap());
+    let source = format!("{POINT}p := Point(1, \"a\")\np as String");
+    assert_eq!(
+        bud.run_source::<Value>(&source).unwrap(),
+        string("Point(1, \"a\")")
+    );
+    let source = format!("{POINT}p := Point(1, 2)\np as Point = p");
+    assert!(bud.run_source::<bool>(&source).unwrap());
+    let source = format!("{POINT}p := Point(1, 2)\n\"p is {{p}}\"");
+    assert_eq!(
+        bud.run_source::<Value>(&source).unwrap(),
+        string("p is Point(1, 2)")
+    );
+    let source = format!("{POINT}p := Point(1, 2)\nm := p as Map\nm[\"y\"]");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 2);
+    let record = bud.run_source::<Value>("Point(5, 6)").unwrap();
+    assert_eq!(record.kind(), ValueKind::Dynamic(Symbol::from("Point")));
+    assert_eq!(
+        record.as_dynamic::<Record>().unwrap().get("y"),
+        Some(Value::Integer(6))
+    );
+
+    // Records declared in modules are named by their module path.
+    assert_run!(
+        "module geometry\n  struct Point(x, y)\n    function sum()\n      self.x + self.y\n    end\n  end\nend\np := geometry.Point(1, 2)\np.sum()",
+        3
+    );
+    let mut bud = Bud::empty();
+    assert_eq!(
+        bud.run_source::<Value>(
+            "module geometry\n  struct Point(x, y)\n  end\nend\ngeometry.Point(1, 2) as String"
+        )
+        .unwrap(),
+        string("geometry.Point(1, 2)")
+    );
+
+    // Unknown fields and methods fault.
+    let mut bud = Bud::empty();
+    assert_eq!(
+        bud.run_source::<Value>("struct Point(x, y)\nend\np := Point(1, 2)\np.z")
+            .unwrap_err()
+            .to_string(),
+        "vm error: vm fault: Point has no field \"z\""
+    );
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("struct Point(x, y)\nend\np := Point(1, 2)\np.sum()"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::UnknownFunction { .. }),
+            ..
+        })))
+    ));
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>(&format!("{POINT}p := Point(1, 2)\np.scaled()")),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ArgumentCountMismatch {
+                expected: 1,
+                received: 0
+            }),
+            ..
+        })))
+    ));
+}
+
+#[test]
+fn record_constructors() {
+    // Constructors must be passed every field.
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("struct P(x, y)\nend\nP(1)"),
+        Err(Error::Compilation(
+            CompilationError::ArgumentCountMismatch {
+                expected: 2,
+                received: 1,
+                ..
+            }
+        ))
+    ));
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("enum E\n  A(v, w)\nend\nE.A(1)"),
+        Err(Error::Compilation(
+            CompilationError::ArgumentCountMismatch {
+                expected: 2,
+                received: 1,
+                ..
+            }
+        ))
+    ));
+    // Field names must be unique.
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("struct P(x, x)\nend"),
+        Err(Error::Parse(ParseError::Unexpected(_)))
+    ));
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("enum E\n  A(v, v)\nend"),
+        Err(Error::Parse(ParseError::Unexpected(_)))
+    ));
+}
+
+#[test]
+fn enums_and_match() {
+    const SHAPE: &str = "enum Shape\n  Circle(radius)\n  Rect(width, height)\n  Empty\n\n  function area()\n    match self\n    when Shape.Circle(r)\n      3 * r * r\n    when Shape.Rect(w, h)\n      w * h\n    when Shape.Empty\n      0\n    end\n  end\nend\n";
+
+    // Literal, wildcard, and binding patterns.
+    assert_run!(
+        "function describe(n)\n  match n\n  when 0\n    \"zero\"\n  when -1\n    \"minus one\"\n  when \"one\"\n    \"string\"\n  when true\n    \"bool\"\n  when _\n    \"other\"\n  end\nend\ndescribe(0) + describe(-1) + describe(\"one\") + describe(true) + describe(5)",
+        Value::dynamic(String::from("zerominus onestringboolother"))
+    );
+    assert_run!(
+        "function double(n)\n  match n\n  when x\n    x * 2\n  end\nend\ndouble(21)",
+        42
+    );
+    // List and map patterns, including nesting.
+    assert_run!(
+        "function sum(value)\n  match value\n  when []\n    0\n  when [a]\n    a\n  when [a, [b, c]]\n    a + b + c\n  when [a, b]\n    a + b\n  else\n    -1\n  end\nend\nsum([]) + sum([1]) * 10 + sum([1, 2]) * 100 + sum([1, [2, 3]]) * 1000 + sum([1, 2, 3])",
+        6309
+    );
+    assert_run!(
+        "function lookup(value)\n  match value\n  when {\"x\": x, \"y\": 0}\n    x\n  when {\"x\": x, \"y\": y}\n    x + y\n  else\n    0\n  end\nend\nlookup({\"x\": 1, \"y\": 0}) + lookup({\"x\": 10, \"y\": 20, \"z\": 5}) + lookup({\"y\": 1}) + lookup([1])",
+        31
+    );
+    // Guards are checked after the pattern matches.
+    assert_run!(
+        "function sign(n)\n  match n\n  when x if x < 0\n    -1\n  when 0\n    0\n  else\n    1\n  end\nend\nsign(-5) * 100 + sign(0) * 10 + sign(7)",
+        -99
+    );
+    // Match can be used as a value.
+    assert_run!(
+        "value := match [1, 2]\n  when [a, b]\n    a + b\n  end\nvalue",
+        3
+    );
+
+    // Enum variants are matched by kind and variant.
+    let mut bud = Bud::empty();
+    let source = format!("{SHAPE}Shape.Circle(2).area() + Shape.Rect(3, 4).area()");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 24);
+    let source = format!("{SHAPE}Shape.Empty.area()");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 0);
+    let source = format!("{SHAPE}Shape.Empty = Shape.Empty");
+    assert!(bud.run_source::<bool>(&source).unwrap());
+    let source = format!("{SHAPE}Shape.Circle(1) = Shape.Circle(2)");
+    assert!(!bud.run_source::<bool>(&source).unwrap());
+    let source = format!("{SHAPE}s := Shape.Rect(1, 2)\ns.height");
+    assert_eq!(bud.run_source::<i64>(&source).unwrap(), 2);
+    let source = format!("{SHAPE}(Shape.Rect(1, 2) as String) + (Shape.Empty as String)");
+    assert_eq!(
+        bud.run_source::<Value>(&source).unwrap(),
+        Value::dynamic(String::from("Shape.Rect(1, 2)Shape.Empty"))
+    );
+    let record = bud.run_source::<Value>("Shape.Circle(5)").unwrap();
+    assert_eq!(record.kind(), ValueKind::Dynamic(Symbol::from("Shape")));
+    assert_eq!(
+        record.as_dynamic::<Record>().unwrap().variant(),
+        Some(&Symbol::from("Circle"))
+    );
+    // Structs are matched by kind, and records from other types don't match.
+    assert_run!(
+        "struct Point(x, y)\nend\nstruct Size(x, y)\nend\nfunction x_of(p)\n  match p\n  when Point(x, _)\n    x\n  when _\n    0\n  end\nend\nx_of(Point(4, 5)) + x_of(Size(6, 7))",
+        4
+    );
+    // Types declared in modules are resolved relative to the current module.
+    assert_run!(
+        "module shapes\n  enum Shape\n    Dot\n    Line(length)\n  end\n\n  function length(shape)\n    match shape\n    when Shape.Line(length)\n      length\n    when Shape.Dot\n      0\n    end\n  end\nend\nshapes.length(shapes.Shape.Line(3)) + shapes.length(shapes.Shape.Dot)",
+        3
+    );
+
+    // Without an else arm, unmatched values fault.
+    let mut bud = Bud::empty();
+    let result = bud.run_source::<Value>("match 3\nwhen 1\n  true\nend");
+    assert!(matches!(
+        result,
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::NoMatch(Value::Integer(3))),
+            ..
+        })))
+    ));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "vm error: vm fault: no pattern matched `3` (Integer)"
+    );
+    // Patterns must name a known type.
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("match 3\nwhen Missing(x)\n  x\nend"),
+        Err(Error::Compilation(
+            CompilationError::UndefinedIdentifier { .. }
+        ))
+    ));
+}
+
+#[test]
+fn indexing() {
+    // Reading
+    assert_run!("a := [1, 2, 3]\na[1]", 2);
+    assert_run!("[1, 2, 3][2]", 3);
+    assert_run!("([1, 2, 3])[0]", 1);
+    assert_run!("m := {\"a\": 1}\nm[\"a\"]", 1);
+    assert_run!("m := {\"a\": 1}\nm[\"b\"]", Value::Void);
+    assert_run!("{1: 2}[1]", 2);
+    assert_run!("a := [[1, 2], [3, 4]]\na[1][0]", 3);
+    assert_run!(
+        r"
+            function items()
+                [1, 2, 3]
+            end
+            items()[1] + items()[2]
+        ",
+        5
+    );
+
+    // Writing
+    assert_run!("a := [1, 2, 3]\na[1] := 5\na[0] + a[1] + a[2]", 9);
+    assert_run!("a := [1, 2, 3]\na[1] := 5", 5);
+    assert_run!(
+        "m := {}\nm[\"a\"] := 1\nm[\"a\"] := m[\"a\"] + 1\nm[\"a\"]",
+        2
+    );
+    assert_run!("a := [[1, 2], [3, 4]]\na[1][0] := 5\na[1][0]", 5);
+    assert_run!("a := [0, 0]\ni := 1\na[i] := a[0] := 3\na[0] + a[i]", 6);
+    assert_run!("m := {\"a\": [1]}\nm[\"a\"][0] := 2\nm[\"a\"][0]", 2);
+
+    // Faults
+    for source in ["a := [1, 2, 3]\na[3]", "a := [1, 2, 3]\na[-1]"] {
+        assert!(matches!(
+            Bud::empty().run_source::<()>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange("index")),
+                ..
+            })))
+        ));
+    }
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := [1]\na[1] := 2"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ValueOutOfRange("index")),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := [1]\na[\"b\"]"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<()>("a := 1\na[0]"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::NotIndexable(budvm::ValueKind::Integer)),
+            ..
+        })))
+    ));
+    assert!(matches!(
+        Bud::empty().run_source::<()>("m := {}\nm[{}] := 1"),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ValueCannotBeHashed(_)),
+            ..
+        })))
+    ));
+}
+
+#[test]
+fn dynamic_indexing() {
+    let mut context = Bud::empty();
+    context
+        .run_source::<()>(
+            r"
+        function test(dynamic)
+            dynamic[3]
+        end
+
+        function assign(dynamic)
+            dynamic[0] := 1
+        end
+    ",
+        )
+        .unwrap();
+    let result: i64 = context
+        .call(&Symbol::from("test"), [Value::dynamic(TestDynamic(2))])
+        .unwrap();
+    assert_eq!(result, 6);
+
+    let error = context
+        .call::<(), _, _>(&Symbol::from("assign"), [Value::dynamic(TestDynamic(2))])
+        .unwrap_err();
+    assert!(matches!(
+        error.kind,
+        FaultOrPause::Fault(FaultKind::NotIndexable(budvm::ValueKind::Dynamic(kind))) if kind == "TestDynamic"
+    ));
+}
+
+#[test]
+fn interactive() {
+    // Test variable persistence
+    let mut session = Bud::empty();
+    session.evaluate::<()>("a := 42").unwrap();
+    assert_eq!(session.evaluate::<i64>("a").unwrap(), 42);
+
+    // Test a new function calling an existing function
+    session
+        .evaluate::<()>(
+            r#"
+                function foo()
+                    42
+                end
+            "#,
+        )
+        .unwrap();
+    session
+        .evaluate::<()>(
+            r#"
+                function bar()
+                    foo()
+                end
+            "#,
+        )
+        .unwrap();
+    assert_eq!(session.evaluate::<i64>("bar()").unwrap(), 42);
+}
+
+#[test]
+fn block_scoping() {
+    fn out_of_scope(source: &str) -> Option<Symbol> {
+        match Bud::empty().run_source::<Value>(source) {
+            Err(Error::Compilation(CompilationError::OutOfScope { name, .. })) => Some(name),
+            _ => None,
+        }
+    }
+
+    // Variables introduced within a block are not visible after it ends.
+    assert_eq!(
+        out_of_scope("if true\n  a := 1\nend\na"),
+        Some(Symbol::from("a"))
+    );
+    assert_eq!(
+        out_of_scope("if false\n  1\nelse\n  b := 2\nend\nb"),
+        Some(Symbol::from("b"))
+    );
+    assert_eq!(
+        out_of_scope("loop for i := 1 to 3\n  last := i\nend\nlast"),
+        Some(Symbol::from("last"))
+    );
+    assert_eq!(
+        out_of_scope("loop for i := 1 to 3\n  1\nend\ni"),
+        Some(Symbol::from("i"))
+    );
+    assert_eq!(
+        out_of_scope("try\n  throw 1\ncatch err\n  err\nend\nerr"),
+        Some(Symbol::from("err"))
+    );
+    assert_eq!(
+        out_of_scope("match [1]\nwhen [x]\n  x\nend\nx"),
+        Some(Symbol::from("x"))
+    );
+
+    // Assigning to a variable from an outer scope updates it.
+    assert_run!("a := 1\nif true\n  a := 2\nend\na", 2);
+    assert_run!(
+        "total := 0\nloop for i := 1 to 3\n  total := total + i\nend\ntotal",
+        3
+    );
+    // Match bindings are new variables, even if an outer variable has the
+    // same name.
+    assert_run!("x := 10\nmatch 3\nwhen x\n  x\nend\nx", 10);
+    assert_run!("x := 10\nmatch [1, 2]\nwhen [x, y]\n  x + y\nend", 3);
+    // The caught value is assigned to a new variable.
+    assert_run!("err := 1\ntry\n  throw 2\ncatch err\n  err\nend\nerr", 1);
+    // A variable can be introduced again after its block ends.
+    assert_run!("if true\n  a := 1\nend\na := 2\na", 2);
+    // Reused storage doesn't expose the previous variable's value.
+    assert_run!(
+        "if true\n  a := 1\nend\nif true\n  b := b\nend",
+        Value::Void
+    );
+    // Assigning to an argument within a block updates it.
+    assert_run!(
+        "function f(n)\n  if true\n    n := 0\n  end\n  n\nend\nf(5)",
+        0
+    );
+    assert_run!(
+        "function f(n)\n  if false\n    n := 0\n  end\n  n\nend\nf(5)",
+        5
+    );
+    assert_run!(
+        "function f(n)\n  loop for i := 1 to 3\n    n := n + i\n  end\n  n\nend\nf(5)",
+        8
+    );
+    assert_run!(
+        "g := function(n)\n  if true\n    n := 0\n  end\n  n\nend\ng(5)",
+        0
+    );
+    // Closures capture block-scoped variables.
+    assert_run!(
+        "if true\n  a := 3\n  f := function() a * 2 end\n  f()\nend",
+        6
+    );
+
+    // Block-scoped variables aren't persisted between interactive evaluations,
+    // but top-level variables still are.
+    let mut session = Bud::empty();
+    session
+        .evaluate::<()>("if true\n  hidden := 1\nend\nvisible := 2")
+        .unwrap();
+    assert_eq!(session.evaluate::<i64>("visible").unwrap(), 2);
+    assert!(matches!(
+        session.evaluate::<Value>("hidden"),
+        Err(Error::Compilation(
+            CompilationError::UndefinedIdentifier { .. }
+        ))
+    ));
+    session.evaluate::<()>("other := visible + 1").unwrap();
+    assert_eq!(session.evaluate::<i64>("visible + other").unwrap(), 5);
+}
+
+#[test]
+fn loops() {
+    // Basic loop with continue and break usage
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                a := 0
+                total := 0
+                loop
+                    a := a + 1
+                    if a = 5
+                        break total
+                    else
+                        if a = 3
+                            continue
+                        end
+                    end
+                    total := total + a
+                end
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 1 + 2 + 4);
+
+    // Test labeled break
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                a := 1
+                loop #infinite
+                    loop
+                        if a = 10
+                            break #infinite a
+                        else
+                            a := a + 1
+                        end
+                    end
+                end
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 10);
+
+    // Test labeled continue
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                b := 0
+                c := 0
+                loop #outer
+                    if b = 10
+                        break c
+                    end
+                    b := b + 1
+                    a := 0
+                    loop
                         if a = 10
                             continue #outer
                         end
@@ -385,259 +1668,1494 @@ fn loops() {
                 end
             "#,
         )
-        .unwrap();
-    assert_eq!(result, 100);
+        .unwrap();
+    assert_eq!(result, 100);
+}
+
+#[test]
+fn conditioned_loops() {
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                a := 0
+                loop while a < 10
+                    a := a + 1
+                end
+                a
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 10);
+
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                a := 0
+                loop until a = 10
+                    a := a + 1
+                end
+                a
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 10);
+}
+
+#[test]
+fn for_loops() {
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                sum := 0
+                loop for x := 0 to 5
+                    sum := sum + x
+                end
+                sum
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 1 + 2 + 3 + 4);
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                sum := 0
+                loop for x := 0 to 5 inclusive
+                    sum := sum + x
+                end
+                sum
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 1 + 2 + 3 + 4 + 5);
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                sum := 0
+                loop for x := 5 down to 1
+                    sum := sum + x
+                end
+                sum
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 5 + 4 + 3 + 2);
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                sum := 0
+                loop for x := 5 down to 1 inclusive
+                    sum := sum + x
+                end
+                sum
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 5 + 4 + 3 + 2 + 1);
+}
+
+#[test]
+fn for_in_loops() {
+    assert_run!(
+        r"
+            sum := 0
+            loop for x in [1, 2, 3]
+                sum := sum + x
+            end
+            sum
+        ",
+        6
+    );
+    assert_run!(
+        r#"
+            sum := 0
+            keys := ""
+            loop for key, value in {"a": 1, "b": 2}
+                keys := keys + key
+                sum := sum + value
+            end
+            keys + (sum as String)
+        "#,
+        Value::dynamic(String::from("ab3"))
+    );
+    assert_run!(
+        r"
+            sum := 0
+            loop for a, b in [[1, 2], [3, 4]]
+                sum := sum + a * b
+            end
+            sum
+        ",
+        14
+    );
+    // Loops over an empty collection never assign the variable.
+    assert_run!(
+        r"
+            x := 42
+            loop for x in []
+            end
+            x
+        ",
+        42
+    );
+    // The collection is evaluated once, and modifying it while iterating
+    // doesn't affect the loop.
+    assert_run!(
+        r"
+            list := [1, 2, 3]
+            count := 0
+            loop for x in list
+                list.push(x)
+                count := count + 1
+            end
+            count * 10 + list.count()
+        ",
+        36
+    );
+    // Labels, break, and continue
+    assert_run!(
+        r"
+            sum := 0
+            loop #outer for x in [1, 2, 3, 4]
+                if x = 2
+                    continue
+                end
+                loop for y in [10, 20, 30]
+                    if x = 4
+                        break #outer
+                    end
+                    if y = 30
+                        break
+                    end
+                    sum := sum + x * y
+                end
+            end
+            sum
+        ",
+        120
+    );
+    assert_run!(
+        r"
+            loop for x in [1, 2, 3, 4]
+                if x > 2
+                    break x
+                end
+            end
+        ",
+        3
+    );
+    assert_run!(
+        r"
+            function first_even(list)
+                loop for x in list
+                    if x & 1 = 0
+                        break x
+                    end
+                end
+            end
+            first_even([1, 3, 6, 8])
+        ",
+        6
+    );
+}
+
+#[test]
+fn dynamic_iteration() {
+    let mut context = Bud::empty();
+    context
+        .run_source::<()>(
+            r"
+        function sum(dynamic)
+            total := 0
+            loop for x in dynamic
+                total := total + x
+            end
+            total
+        end
+    ",
+        )
+        .unwrap();
+    let result: i64 = context
+        .call(&Symbol::from("sum"), [Value::dynamic(TestDynamic(5))])
+        .unwrap();
+    assert_eq!(result, 10);
+
+    // Iterators can be advanced manually, and iterating an iterator continues
+    // where it left off.
+    let iterator = Value::dynamic(ValueIterator::new([1, 2, 3].map(Value::Integer)));
+    assert_eq!(
+        iterator.as_dynamic::<ValueIterator>().unwrap().next_value(),
+        Some(Value::Integer(1))
+    );
+    let result: i64 = context.call(&Symbol::from("sum"), [iterator]).unwrap();
+    assert_eq!(result, 5);
+
+    let error = context
+        .call::<(), _, _>(&Symbol::from("sum"), [Value::Integer(1)])
+        .unwrap_err();
+    assert!(matches!(
+        error.kind,
+        FaultOrPause::Fault(FaultKind::NotIterable(budvm::ValueKind::Integer))
+    ));
+}
+
+#[test]
+fn nesting_if() {
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                if (if 1
+                        true
+                    end)
+                    42
+                end
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn comments() {
+    let parsed = Lexer::new("// test")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(parsed.len(), 1);
+    match &parsed[0].kind {
+        TokenKind::Comment(comment) => {
+            assert_eq!(comment, "// test");
+        }
+        _ => unreachable!("unexpected token kind"),
+    }
+
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                // comment alone on line
+                if true // comment after if line
+                    a := 2 // comment after expression
+                    loop // comment after loop
+                        break // comment after break
+                    end
+                    1
+                end // comment after end
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 1);
+}
+
+#[test]
+fn nested_assignments() {
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                a := b := 4
+                a * b
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 16);
+}
+
+#[test]
+fn if_else_if() {
+    let result = Bud::empty()
+        .run_source::<i64>(
+            r#"
+                function test(a)
+                    if a = 0
+                        2
+                    else if a = 1
+                        3
+                    else
+                        4
+                    end
+                end
+
+                test(0) + test(1) + test(2)
+            "#,
+        )
+        .unwrap();
+    assert_eq!(result, 9);
+}
+
+#[test]
+fn logic() {
+    // To verify short circuit behavior, we have register a native function that
+    // panics when called.
+    let mut bud = Bud::empty()
+        .with_native_function("unreachable", |_args: &mut PoppedValues<'_>| {
+            unreachable!("short circuit failed")
+        });
+
+    assert!(!bud
+        .run_source::<bool>(r#"false and unreachable()"#,)
+        .unwrap());
+    assert!(bud.run_source::<bool>(r#"true and true"#,).unwrap());
+    assert!(!bud.run_source::<bool>(r#"true and false"#,).unwrap());
+
+    assert!(bud.run_source::<bool>(r#"true or unreachable()"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"false or true"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"false or false"#,).unwrap());
+
+    assert!(!bud.run_source::<bool>(r#"false xor false"#,).unwrap());
+    assert!(!bud.run_source::<bool>(r#"true xor true"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"true xor false"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"false xor true"#,).unwrap());
+
+    assert!(!bud.run_source::<bool>(r#"not true"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"not false"#,).unwrap());
+    assert_eq!(bud.run_source::<i64>(r#"not 0"#,).unwrap(), !0);
+    assert!(bud.run_source::<bool>(r#"not 0.0"#,).unwrap());
+    assert!(bud.run_source::<bool>(r#"not """#,).unwrap());
+
+    assert!(bud
+        .run_source::<bool>(
+            r#"
+                a := false
+                not a
+            "#,
+        )
+        .unwrap());
+    assert_eq!(
+        bud.run_source::<i64>(
+            r#"
+                a := 0
+                ~a
+            "#,
+        )
+        .unwrap(),
+        !0
+    );
+    assert!(bud
+        .run_source::<bool>(
+            r#"
+                a := 0.0
+                not a
+            "#,
+        )
+        .unwrap());
+    assert!(bud
+        .run_source::<bool>(
+            r#"
+                    a := ""
+                    not a
+                "#,
+        )
+        .unwrap());
+
+    // Verify that this groups `(not true) and true` instead of `not (true and true)`.
+    assert!(!bud.run_source::<bool>(r#"not true and true"#,).unwrap());
 }
 
 #[test]
-fn conditioned_loops() {
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                a := 0
-                loop while a < 10
-                    a := a + 1
+fn bitwise() {
+    assert_run!("1 & 3", 1);
+    assert_run!("1 | 3", 3);
+    assert_run!("1 ^ 3", 2);
+    assert_run!("1 << 3", 8);
+    assert_run!("1 << 64", 0);
+    assert_run!("8 >> 3", 1);
+    assert_run!("1 >> 64", 0);
+}
+
+#[test]
+fn conversions() {
+    assert_run!("1 as String", Value::dynamic(String::from("1")));
+    assert_run!("\"1\" as String", Value::dynamic(String::from("1")));
+    assert_run!("1 as Real", Value::Real(1.0));
+    assert_run!("1 as Integer", Value::Integer(1));
+    assert_run!("1 as Boolean", Value::Boolean(true));
+    assert_run!("0 as Boolean", Value::Boolean(false));
+    assert_run!("1.2 as Integer", Value::Integer(1));
+
+    // Collections that contain themselves can't be converted.
+    for source in [
+        "l := []\nl.push(l)\nl as String",
+        "m := {}\nm[1] := m\nm as String",
+        "l := []\nl.push(l)\n\"{l}\"",
+    ] {
+        let mut bud = Bud::empty();
+        assert!(matches!(
+            bud.run_source::<Value>(source),
+            Err(Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+                ..
+            })))
+        ));
+    }
+}
+
+#[test]
+fn closures() {
+    // Lambdas can be stored and called later.
+    assert_run!(
+        r#"
+            double := function(x) x * 2 end
+            double(21)
+        "#,
+        42
+    );
+    // Variables are captured by value when the closure is created.
+    assert_run!(
+        r#"
+            offset := 10
+            add_offset := function(x)
+                x + offset
+            end
+            offset := 100
+            add_offset(1)
+        "#,
+        11
+    );
+    // Closures can be returned from and passed to functions.
+    assert_run!(
+        r#"
+            function make_adder(amount)
+                function(x) x + amount end
+            end
+
+            function apply(f, value)
+                f(value)
+            end
+
+            add_two := make_adder(2)
+            apply(add_two, 40) + make_adder(1)(0)
+        "#,
+        43
+    );
+    // Named functions can be used as values.
+    assert_run!(
+        r#"
+            function square(x)
+                x * x
+            end
+
+            f := square
+            f(4)
+        "#,
+        16
+    );
+    // `this` refers to the lambda, and captured values are passed along.
+    assert_run!(
+        r#"
+            base := 1
+            factorial := function(n)
+                if n <= 1
+                    base
+                else
+                    n * this(n - 1)
                 end
-                a
-            "#,
-        )
+            end
+            factorial(5)
+        "#,
+        120
+    );
+    // Lambdas nested within lambdas capture through each level.
+    assert_run!(
+        r#"
+            a := 1
+            outer := function(b)
+                function(c) a + b + c end
+            end
+            outer(2)(3)
+        "#,
+        6
+    );
+
+    // Closures persist across interactive evaluations.
+    let mut session = Bud::empty();
+    session
+        .evaluate::<()>("increment := function(x) x + 1 end")
         .unwrap();
-    assert_eq!(result, 10);
+    session
+        .evaluate::<()>("twice := function(x) increment(increment(x)) end")
+        .unwrap();
+    assert_eq!(session.evaluate::<i64>("twice(1)").unwrap(), 3);
 
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                a := 0
-                loop until a = 10
-                    a := a + 1
+    let mut bud = Bud::empty();
+    let result = bud.run_source::<Value>("not_a_function := 1\nnot_a_function()");
+    assert!(matches!(
+        result,
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::InvalidType { .. }),
+            ..
+        })))
+    ));
+    let result = bud.run_source::<Value>("f := function(a, b) a + b end\nf(1)");
+    assert!(matches!(
+        result,
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ArgumentCountMismatch {
+                expected: 2,
+                received: 1
+            }),
+            ..
+        })))
+    ));
+}
+
+#[test]
+fn closures_within_functions() {
+    // Lambdas can call the function they are declared within, as well as
+    // functions declared after it. Within them, `this` refers to the
+    // enclosing function.
+    assert_run!(
+        r#"
+            function countdown(n)
+                if n > 0
+                    step := function(m) countdown(m - 1) end
+                    step(n)
+                else
+                    done := function() finish() end
+                    done()
                 end
-                a
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 10);
+            end
+
+            function sum(n)
+                add := function(m)
+                    if m > 0
+                        m + this(m - 1)
+                    else
+                        0
+                    end
+                end
+                add(n)
+            end
+
+            function finish()
+                100
+            end
+
+            countdown(3) + sum(4)
+        "#,
+        110
+    );
 }
 
 #[test]
-fn for_loops() {
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                sum := 0
-                loop for x := 0 to 5
-                    sum := sum + x
+fn modules() {
+    // Functions within a module are called through the module's name.
+    assert_run!(
+        r#"
+            module math
+                function square(x)
+                    x * x
                 end
-                sum
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 1 + 2 + 3 + 4);
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                sum := 0
-                loop for x := 0 to 5 inclusive
-                    sum := sum + x
+
+                function sum_of_squares(a, b)
+                    square(a) + square(b)
                 end
-                sum
-            "#,
-        )
+            end
+
+            math.sum_of_squares(3, 4)
+        "#,
+        25
+    );
+    // Modules can be nested, and names resolve from the innermost module out.
+    let mut session = Bud::empty();
+    session
+        .evaluate::<()>("function value()\n1\nend\nfunction base()\n1000\nend")
         .unwrap();
-    assert_eq!(result, 1 + 2 + 3 + 4 + 5);
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                sum := 0
-                loop for x := 5 down to 1
-                    sum := sum + x
+    assert_eq!(
+        session
+            .evaluate::<i64>(
+                r#"
+                    module outer
+                        module inner
+                            function value()
+                                100
+                            end
+
+                            function total()
+                                value() + base()
+                            end
+                        end
+
+                        function value()
+                            inner.value() + 10
+                        end
+                    end
+
+                    outer.value() + outer.inner.total() + value()
+                "#
+            )
+            .unwrap(),
+        1211
+    );
+    // Module functions can be used as values.
+    assert_run!(
+        r#"
+            module ops
+                function double(x)
+                    x * 2
                 end
-                sum
-            "#,
+            end
+
+            f := ops.double
+            f(21)
+        "#,
+        42
+    );
+
+    // Imports are loaded through the loader.
+    let loader = MemoryLoader::default()
+        .with_source(
+            "math.bud",
+            "import \"consts.bud\" as c\nfunction tau()\nc.pi() * 2\nend",
         )
-        .unwrap();
-    assert_eq!(result, 5 + 4 + 3 + 2);
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                sum := 0
-                loop for x := 5 down to 1 inclusive
-                    sum := sum + x
+        .with_source("consts.bud", "function pi()\n3\nend")
+        .with_source("cycle.bud", "import \"cycle.bud\"")
+        .with_source("script.bud", "1 + 1");
+    let mut bud = Bud::empty().with_loader(loader);
+    assert_eq!(
+        bud.run_source::<i64>("import \"math.bud\"\nmath.tau()")
+            .unwrap(),
+        6
+    );
+    assert_eq!(
+        bud.evaluate::<i64>("import \"math.bud\" as m\nm.tau() + m.c.pi()")
+            .unwrap(),
+        9
+    );
+    assert_eq!(
+        bud.run_source::<()>("import \"missing.bud\"").unwrap_err(),
+        Error::Import(ImportError::NotFound(String::from("missing.bud")))
+    );
+    assert_eq!(
+        bud.run_source::<()>("import \"cycle.bud\"").unwrap_err(),
+        Error::Import(ImportError::Cycle(String::from("cycle.bud")))
+    );
+    assert!(matches!(
+        bud.run_source::<()>("import \"script.bud\""),
+        Err(Error::Import(ImportError::Parse { .. }))
+    ));
+    assert_eq!(
+        Bud::empty()
+            .run_source::<()>("import \"math.bud\"")
+            .unwrap_err(),
+        Error::Import(ImportError::NoLoader)
+    );
+}
+
+#[test]
+fn module_forward_references() {
+    // Modules can call functions declared after them.
+    assert_run!(
+        r#"
+            module shapes
+                function area(size)
+                    square(size)
                 end
-                sum
-            "#,
+            end
+
+            function square(x)
+                x * x
+            end
+
+            shapes.area(5)
+        "#,
+        25
+    );
+}
+
+#[test]
+fn invalid_lookups() {
+    // Each segment of a path must be an identifier.
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("a := 1\na.1"),
+        Err(Error::Parse(ParseError::Unexpected(_)))
+    ));
+    let mut bud = Bud::empty();
+    assert!(matches!(
+        bud.run_source::<Value>("x := [1]\nx[0].1"),
+        Err(Error::Parse(ParseError::Unexpected(_)))
+    ));
+}
+
+#[test]
+fn stack_traces() {
+    let library = "function fail(value)\n    value()\nend\n";
+    // The call to lib.fail isn't in tail position, keeping outer's frame.
+    let main =
+        "import \"lib.bud\"\n\nfunction outer()\n    lib.fail(1)\n    true\nend\n\nouter()\n";
+    let mut bud = Bud::empty().with_loader(MemoryLoader::default().with_source("lib.bud", library));
+    let error = bud.run_source_named::<()>("main.bud", main).unwrap_err();
+    let trace = error
+        .stack_trace()
+        .iter()
+        .filter(|frame| frame.function.is_some())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        trace,
+        [
+            "at __init (main.bud:8)",
+            "at outer (main.bud:4)",
+            "at lib.fail (lib.bud:2)"
+        ]
+    );
+    assert_eq!(&library[error.location().unwrap()], "value()");
+
+    // Without a name, lines are still reported.
+    let mut bud = Bud::empty();
+    let error = bud
+        .run_source::<()>("function fail()\n    x := 1\n    x()\nend\n\nfail()")
+        .unwrap_err();
+    let innermost = error.stack_trace().last().unwrap();
+    assert_eq!(innermost.to_string(), "at fail (line 3)");
+}
+
+#[test]
+fn call_depth() {
+    // Calls don't recurse on the native stack, so deep recursion is only
+    // limited by the configured maximum call depth.
+    assert_run!(
+        "function count(n)\n  if n = 0\n    0\n  else\n    count(n - 1) + 1\n  end\nend\ncount(50_000)",
+        50_000
+    );
+
+    let recurse = "function recurse(n)\n  recurse(n + 1) + 1\nend\n";
+    let mut bud = Bud::empty().with_maximum_call_depth(10);
+    assert_eq!(bud.maximum_call_depth(), 10);
+    let error = bud
+        .run_source::<Value>(&format!("{recurse}recurse(0)"))
+        .unwrap_err();
+    assert!(matches!(
+        &error,
+        Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::CallDepthExceeded(10)),
+            ..
+        }))
+    ));
+    // Every frame is reported in the stack trace.
+    assert_eq!(error.stack_trace().len(), 11);
+
+    // The fault can be caught by a frame that is still executing.
+    assert_eq!(
+        bud.run_source::<i64>(&format!("{recurse}try\n  recurse(0)\ncatch\n  42\nend"))
+            .unwrap(),
+        42
+    );
+}
+
+#[test]
+fn tail_calls() {
+    // Calls in tail position reuse the caller's frame, allowing recursion
+    // beyond the maximum call depth.
+    let sum = "function sum(n, total)\n  if n = 0\n    return total\n  end\n  sum(n - 1, total + n)\nend\n";
+    let mut bud = Bud::empty().with_maximum_call_depth(10);
+    assert_eq!(
+        bud.run_source::<i64>(&format!("{sum}sum(100_000, 0)"))
+            .unwrap(),
+        5_000_050_000
+    );
+
+    // Recursing with `this` from within an if.
+    let countdown = "function countdown(n)\n  if n > 0\n    return this(n - 1)\n  else\n    \"done\"\n  end\nend\n";
+    assert_eq!(
+        bud.run_source::<String>(&format!("{countdown}countdown(1_000)"))
+            .unwrap(),
+        "done"
+    );
+
+    // Closures can recurse in tail position too.
+    assert_eq!(
+        bud.run_source::<i64>(
+            "f := function(n, total)\n  if n = 0\n    total\n  else\n    this(n - 1, total + n)\n  end\nend\nf(1_000, 0)"
         )
-        .unwrap();
-    assert_eq!(result, 5 + 4 + 3 + 2 + 1);
+        .unwrap(),
+        500_500
+    );
+
+    // Calls within a try block return to the frame so that faults can be
+    // caught.
+    let guarded = "function guarded(n)\n  try\n    guarded(n - 1)\n  catch\n    n\n  end\nend\n";
+    assert_eq!(
+        bud.run_source::<i64>(&format!("{guarded}guarded(100)"))
+            .unwrap(),
+        92
+    );
+
+    // `return` exits the function even when the value returned isn't a tail
+    // call, such as when running outside of a function.
+    assert_eq!(
+        bud.run_source::<i64>(&format!("{sum}return sum(3, 0)\n0"))
+            .unwrap(),
+        6
+    );
+
+    // Tail calls with the wrong number of arguments fault.
+    assert!(matches!(
+        bud.run_source::<Value>("function g(n)\n  g()\nend\ng(1)")
+            .unwrap_err(),
+        Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::ArgumentCountMismatch {
+                expected: 1,
+                received: 0
+            }),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn compilation_error_locations() {
+    let source = "function double(value)\n    value * 2\nend\n\ndouble(1) + triple(2)\n";
+    let mut bud = Bud::empty();
+    let error = bud.run_source::<i64>(source).unwrap_err();
+    assert!(matches!(
+        &error,
+        Error::Compilation(CompilationError::UndefinedFunction { name, .. }) if name == "triple"
+    ));
+    assert_eq!(&source[error.location().unwrap()], "triple(2)");
+
+    let source = "a := 1\nb := (a + missing) * 2\n";
+    let mut bud = Bud::empty();
+    let error = bud.run_source::<i64>(source).unwrap_err();
+    assert!(matches!(
+        &error,
+        Error::Compilation(CompilationError::UndefinedIdentifier { name, .. }) if name == "missing"
+    ));
+    assert_eq!(&source[error.location().unwrap()], "missing");
+
+    // Errors within imported modules name the module's source.
+    let mut bud = Bud::empty().with_loader(
+        MemoryLoader::default().with_source("lib.bud", "function f()\n    g(1)\nend\n"),
+    );
+    let error = bud.run_source::<()>("import \"lib.bud\"\n").unwrap_err();
+    match &error {
+        Error::Compilation(err) => assert_eq!(err.source_name().unwrap(), "lib.bud"),
+        other => unreachable!("unexpected error {other:?}"),
+    }
+    assert_eq!(error.location(), Some(17..21));
 }
 
 #[test]
-fn nesting_if() {
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                if (if 1
-                        true
-                    end)
-                    42
-                end
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 42);
+fn bytecode() {
+    let source = "function greet(name)\n    \"hello \" + name\nend\n\nfunction count(n)\n    total := 0\n    i := n\n    loop while i > 0\n        total := total + i\n        i := i - 1\n    end\n    total\nend\n\ngreet(\"world\")\n";
+    let bytecode = Bud::empty().compile_to_bytecode::<()>(source).unwrap();
+    assert!(budvm::bytecode::is_bytecode(&bytecode));
+    let mut bud = Bud::empty();
+    let result: String = bud.run_bytecode(&bytecode).unwrap();
+    assert_eq!(result, "hello world");
+    // Functions defined by the bytecode remain callable.
+    let result: i64 = bud.run_source("count(4)").unwrap();
+    assert_eq!(result, 10);
+
+    // Every truncation is rejected without panicking.
+    for length in 0..bytecode.len() {
+        let error = Bud::empty()
+            .run_bytecode::<Value>(&bytecode[..length])
+            .unwrap_err()
+            .expect_no_pause();
+        assert!(matches!(error, Error::Bytecode(_)), "{length}: {error:?}");
+    }
+
+    let mut trailing = bytecode.clone();
+    trailing.push(0);
+    assert!(matches!(
+        Bud::empty()
+            .run_bytecode::<Value>(&trailing)
+            .unwrap_err()
+            .expect_no_pause(),
+        Error::Bytecode(BytecodeError::TrailingBytes)
+    ));
+
+    let mut bad_magic = bytecode.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(
+        Module::<Intrinsic>::from_bytecode(&bad_magic).unwrap_err(),
+        BytecodeError::InvalidHeader
+    );
+
+    let mut bad_version = bytecode.clone();
+    bad_version[4..6].copy_from_slice(&(budvm::bytecode::VERSION + 1).to_le_bytes());
+    assert_eq!(
+        Module::<Intrinsic>::from_bytecode(&bad_version).unwrap_err(),
+        BytecodeError::UnsupportedVersion(budvm::bytecode::VERSION + 1)
+    );
+
+    // Corrupting any single byte must never panic.
+    for index in 0..bytecode.len() {
+        let mut corrupted = bytecode.clone();
+        corrupted[index] ^= 0xff;
+        drop(Module::<Intrinsic>::from_bytecode(&corrupted));
+    }
 }
 
 #[test]
-fn comments() {
-    let parsed = Lexer::new("// test")
-        .collect::<Result<Vec<_>, _>>()
+fn bytecode_call_arity() {
+    // Bytecode that decodes cleanly can still call functions with the wrong
+    // number of arguments.
+    let bytecode = Bud::empty()
+        .compile_to_bytecode::<()>("function f(a)\n  a\nend\nf(1)")
         .unwrap();
-    assert_eq!(parsed.len(), 1);
-    match &parsed[0].kind {
-        TokenKind::Comment(comment) => {
-            assert_eq!(comment, "// test");
+    let mut module = Module::<Intrinsic>::from_bytecode(&bytecode).unwrap();
+    let init = module.init.as_mut().unwrap();
+    for instruction in &mut init.body.code {
+        if let budvm::ir::Instruction::Call { arg_count, .. } = instruction {
+            *arg_count = 0;
         }
-        _ => unreachable!("unexpected token kind"),
     }
+    let mut bud = Bud::empty();
+    let error = bud
+        .run_bytecode::<Value>(&module.to_bytecode())
+        .unwrap_err()
+        .expect_no_pause();
+    match error {
+        Error::Vm(budvm::Error::Verification(error)) => {
+            assert!(error.issues.iter().any(|issue| issue.kind
+                == VerificationIssueKind::ArgumentCountMismatch {
+                    expected: 1,
+                    received: 0
+                }));
+        }
+        other => unreachable!("unexpected error: {other:?}"),
+    }
+    // None of the functions were defined.
+    assert!(bud
+        .resolve_function_vtable_index(&Symbol::from("f"))
+        .is_none());
 
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                // comment alone on line
-                if true // comment after if line
-                    a := 2 // comment after expression
-                    loop // comment after loop
-                        break // comment after break
-                    end
-                    1
-                end // comment after end
-            "#,
-        )
+    // Calls that bypass verification fault instead of panicking.
+    bud.run_source::<Value>("function f(a)\n  a\nend\n")
         .unwrap();
-    assert_eq!(result, 1);
+    let vtable_index = bud.resolve_function_vtable_index(&Symbol::from("f"));
+    let call = [Instruction::Call {
+        vtable_index,
+        arg_count: 0,
+        destination: Destination::Return,
+    }];
+    let fault = bud.run::<Value>(&call, 0).unwrap_err();
+    assert!(matches!(
+        fault.kind,
+        FaultOrPause::Fault(FaultKind::ArgumentCountMismatch {
+            expected: 1,
+            received: 0
+        })
+    ));
 }
 
 #[test]
-fn nested_assignments() {
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                a := b := 4
-                a * b
-            "#,
-        )
-        .unwrap();
-    assert_eq!(result, 16);
+fn bytecode_linked_functions() {
+    let function = Function::<Intrinsic> {
+        name: Symbol::from("concat"),
+        arg_count: 1,
+        variable_count: 1,
+        code: vec![
+            Instruction::Add {
+                left: ValueOrSource::Argument(0),
+                right: ValueOrSource::Value(Value::dynamic(String::from("!"))),
+                destination: Destination::Variable(0),
+            },
+            Instruction::Return(Some(ValueOrSource::Variable(0))),
+        ],
+    };
+    let encoded = budvm::bytecode::encode_function::<BudEnvironment<()>>(&function).unwrap();
+    let decoded = budvm::bytecode::decode_function::<BudEnvironment<()>>(&encoded).unwrap();
+    assert_eq!(decoded.name, function.name);
+    assert_eq!(decoded.code.len(), 2);
+    let mut bud = Bud::empty().with_function(decoded);
+    let result: String = bud.run_source("concat(\"hi\")").unwrap();
+    assert_eq!(result, "hi!");
+
+    // Modules and functions are distinct artifacts.
+    assert_eq!(
+        Module::<Intrinsic>::from_bytecode(&encoded).unwrap_err(),
+        BytecodeError::UnexpectedArtifact
+    );
+
+    // References to variables that don't exist are rejected.
+    let invalid = Function::<Intrinsic> {
+        variable_count: 0,
+        ..function
+    };
+    let encoded = budvm::bytecode::encode_function::<BudEnvironment<()>>(&invalid).unwrap();
+    assert!(matches!(
+        budvm::bytecode::decode_function::<BudEnvironment<()>>(&encoded),
+        Err(BytecodeError::InvalidVariable { index: 0, .. })
+    ));
 }
 
 #[test]
-fn if_else_if() {
-    let result = Bud::empty()
-        .run_source::<i64>(
-            r#"
-                function test(a)
-                    if a = 0
-                        2
-                    else if a = 1
-                        3
-                    else
-                        4
-                    end
-                end
+fn verification() {
+    let mut bud = Bud::empty();
+    let add = bud
+        .define_function(Function {
+            name: Symbol::from("add"),
+            arg_count: 2,
+            variable_count: 0,
+            code: vec![Instruction::Add {
+                left: ValueOrSource::Argument(0),
+                right: ValueOrSource::Argument(1),
+                destination: Destination::Return,
+            }],
+        })
+        .unwrap();
+    bud.verify_functions().unwrap();
 
-                test(0) + test(1) + test(2)
-            "#,
+    let invalid = Function::<Intrinsic> {
+        name: Symbol::from("invalid"),
+        arg_count: 1,
+        variable_count: 1,
+        code: vec![
+            // Only one argument is pushed before calling add.
+            Instruction::Push(ValueOrSource::Argument(1)),
+            Instruction::Call {
+                vtable_index: Some(add),
+                arg_count: 1,
+                destination: Destination::Variable(1),
+            },
+            Instruction::Call {
+                vtable_index: Some(add + 100),
+                arg_count: 0,
+                destination: Destination::Stack,
+            },
+            // The loop pushes a value each iteration.
+            Instruction::Push(ValueOrSource::Variable(0)),
+            Instruction::If {
+                condition: ValueOrSource::Variable(0),
+                false_jump_to: 2,
+            },
+            Instruction::JumpTo(10),
+            Instruction::Add {
+                left: ValueOrSource::Variable(0),
+                right: ValueOrSource::Argument(0),
+                destination: Destination::Return,
+            },
+        ],
+    };
+    let error = bud.verify_function(&invalid).unwrap_err();
+    let issues = error
+        .issues
+        .iter()
+        .map(|issue| (issue.instruction_index, issue.kind.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        issues,
+        [
+            (0, VerificationIssueKind::InvalidArgumentIndex(1)),
+            (
+                1,
+                VerificationIssueKind::ArgumentCountMismatch {
+                    expected: 2,
+                    received: 1
+                }
+            ),
+            (1, VerificationIssueKind::InvalidVariableIndex(1)),
+            (2, VerificationIssueKind::InvalidVtableIndex(add + 100)),
+            (
+                2,
+                VerificationIssueKind::InconsistentStackDepth {
+                    expected: 0,
+                    received: 2
+                }
+            ),
+            (5, VerificationIssueKind::InvalidJumpTarget(10)),
+        ]
+    );
+    assert!(error
+        .issues
+        .iter()
+        .all(|issue| issue.function.as_deref() == Some("invalid")));
+}
+
+#[test]
+fn verification_top_level() {
+    // Top-level code can't recurse, and can't pop values it never pushed.
+    let bud = Bud::empty();
+    let error = bud
+        .verify(
+            &[Instruction::CallIntrinsic {
+                intrinsic: Intrinsic::NewList,
+                arg_count: 2,
+                destination: Destination::Stack,
+            }],
+            0,
         )
-        .unwrap();
-    assert_eq!(result, 9);
+        .unwrap_err();
+    assert_eq!(
+        error.issues[0].kind,
+        VerificationIssueKind::StackUnderflow {
+            required: 2,
+            available: 0
+        }
+    );
+    let error = bud
+        .verify(
+            &[Instruction::Call {
+                vtable_index: None,
+                arg_count: 0,
+                destination: Destination::Return,
+            }],
+            0,
+        )
+        .unwrap_err();
+    assert_eq!(
+        error.issues[0].kind,
+        VerificationIssueKind::NoCurrentFunction
+    );
 }
 
 #[test]
-fn logic() {
-    // To verify short circuit behavior, we have register a native function that
-    // panics when called.
-    let mut bud = Bud::empty()
-        .with_native_function("unreachable", |_args: &mut PoppedValues<'_>| {
-            unreachable!("short circuit failed")
-        });
+fn optimizer_passes() {
+    let asm = r"
+        add 40 2 $$1
+        lt 1 2 jump #never
+        jump #middle
+        push 99
+        #never
+        return void
+        #middle
+        jump #end
+        #end
+        return $$1
+        ";
+    let mut module = Module::<Intrinsic>::from_asm(asm).unwrap();
+    let expected = Module::<Intrinsic>::from_asm("load 42 $$1\nreturn $$1").unwrap();
 
-    assert!(!bud
-        .run_source::<bool>(r#"false and unreachable()"#,)
-        .unwrap());
-    assert!(bud.run_source::<bool>(r#"true and true"#,).unwrap());
-    assert!(!bud.run_source::<bool>(r#"true and false"#,).unwrap());
+    // Without optimizations, the code block is left untouched.
+    let unoptimized = Module::<Intrinsic>::from_asm(asm).unwrap();
+    module.optimize(OptimizationLevel::None);
+    assert_eq!(module.init, unoptimized.init);
 
-    assert!(bud.run_source::<bool>(r#"true or unreachable()"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"false or true"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"false or false"#,).unwrap());
+    module.optimize(OptimizationLevel::Basic);
+    assert_eq!(
+        module.init.as_ref().unwrap().body.code,
+        expected.init.as_ref().unwrap().body.code
+    );
 
-    assert!(!bud.run_source::<bool>(r#"false xor false"#,).unwrap());
-    assert!(!bud.run_source::<bool>(r#"true xor true"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"true xor false"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"false xor true"#,).unwrap());
+    let mut bud = Bud::empty();
+    assert_eq!(unoptimized.load_into::<i64, _>(&mut bud).unwrap(), 42);
+    assert_eq!(module.load_into::<i64, _>(&mut bud).unwrap(), 42);
+}
 
-    assert!(!bud.run_source::<bool>(r#"not true"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"not false"#,).unwrap());
-    assert_eq!(bud.run_source::<i64>(r#"not 0"#,).unwrap(), !0);
-    assert!(bud.run_source::<bool>(r#"not 0.0"#,).unwrap());
-    assert!(bud.run_source::<bool>(r#"not """#,).unwrap());
+#[test]
+fn optimizer_temporary_coalescing() {
+    let asm = r"
+        push 3
+        push 4
+        call sum 2 $$
 
-    assert!(bud
-        .run_source::<bool>(
-            r#"
-                a := false
-                not a
-            "#,
+        function sum @a @b
+        add @a 1 $$1
+        add $$1 @b $$2
+        mul $$2 $$1 $$3
+        add $$3 2 $$4
+        return $$4
+        ";
+    let mut module = Module::<Intrinsic>::from_asm(asm).unwrap();
+    let unoptimized = Module::<Intrinsic>::from_asm(asm).unwrap();
+    assert_eq!(module.vtable[0].body.variables, 4);
+
+    // Coalescing is only performed at the highest optimization level.
+    module.optimize(OptimizationLevel::Basic);
+    assert_eq!(module.vtable[0].body, unoptimized.vtable[0].body);
+
+    // $1 is still needed when $2 is assigned, but the other temporaries can
+    // share storage.
+    module.optimize(OptimizationLevel::Full);
+    assert_eq!(module.vtable[0].body.variables, 2);
+    assert_eq!(module.vtable[0].body.code.len(), 5);
+
+    let result: i64 = unoptimized.load_into(&mut Bud::empty()).unwrap();
+    assert_eq!(result, 34);
+    let result: i64 = module.load_into(&mut Bud::empty()).unwrap();
+    assert_eq!(result, 34);
+}
+
+#[test]
+fn optimizer_preserves_results() {
+    let sources = [
+        "1 + 2 * 3 - 4 / 2",
+        "1.5 * 2.0 + 0.25",
+        "9223372036854775807 + 1",
+        "1 / 0",
+        "1 + true",
+        "1 < \"a\"",
+        "\"a\" + \"b\"",
+        "1 << 64",
+        "(1 << 4) | (255 & 7) ^ 2",
+        "not 0 and (true or 1 / 0)",
+        "false and 1 + true",
+        "true or 1 + true",
+        "1 xor 0",
+        "if 1 < 2\n    1\nelse\n    2\nend",
+        "if 2 = 3\n    1\nelse\n    2\nend",
+        "a := 0\nloop while a < 10\n    a := a + 1\nend\na",
+        "function fib(n)\n    if n <= 2\n        1\n    else\n        this(n - 1) + this(n - 2)\n    end\nend\nfib(15)",
+        "function first(n)\n    if n > 1\n        n * 2\n    else if true\n        0\n    else\n        n\n    end\nend\nfirst(4) + first(0)",
+        "total := 0\ni := 0\nloop\n    i := i + 1\n    if i > 5\n        break\n    end\n    if i = 2\n        continue\n    end\n    total := total + i\nend\ntotal",
+    ];
+    for source in sources {
+        let unoptimized = Bud::empty()
+            .run_source::<Value>(source)
+            .map_err(|err| err.to_string());
+        for level in [OptimizationLevel::Basic, OptimizationLevel::Full] {
+            let mut bud = Bud::empty().with_optimization_level(level);
+            assert_eq!(bud.optimization_level(), level);
+            let optimized = bud
+                .run_source::<Value>(source)
+                .map_err(|err| err.to_string());
+            assert_eq!(optimized, unoptimized, "{source} at {level:?}");
+
+            let mut bud = Bud::empty().with_optimization_level(level);
+            let evaluated = bud.evaluate::<Value>(source).map_err(|err| err.to_string());
+            assert_eq!(evaluated, unoptimized, "{source} at {level:?}");
+
+            let bytecode = Bud::empty()
+                .with_optimization_level(level)
+                .compile_to_bytecode::<()>(source);
+            if let Ok(bytecode) = bytecode {
+                let loaded = Bud::empty()
+                    .run_bytecode::<Value>(&bytecode)
+                    .map_err(|err| err.to_string());
+                assert_eq!(loaded, unoptimized, "{source} at {level:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn optimizer_debug_info() {
+    let source = "function check(n)\n    if 1 > 2\n        0\n    else\n        n / (2 - 2) + true\n    end\nend\n\ncheck(1)";
+    let mut module = crate::parser::parse(source)
+        .unwrap()
+        .compile(&mut Bud::empty())
+        .unwrap();
+    let unoptimized = module.vtable[0].body.code.len();
+    module.optimize(OptimizationLevel::Full);
+    let body = &module.vtable[0].body;
+    assert!(body.code.len() < unoptimized);
+    assert_eq!(
+        body.debug_info.as_ref().unwrap().locations.len(),
+        body.code.len()
+    );
+
+    // Faults are still reported at the location of the original source.
+    let fault_location = |level| {
+        let mut bud = Bud::empty().with_optimization_level(level);
+        let error = bud.run_source::<Value>(source).unwrap_err();
+        error
+            .stack_trace()
+            .iter()
+            .find_map(|frame| frame.location.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        fault_location(OptimizationLevel::Full),
+        fault_location(OptimizationLevel::None)
+    );
+}
+
+#[test]
+fn allocation_budget() {
+    #[derive(Debug)]
+    struct Limited(AllocationBudget);
+
+    impl crate::Environment for Limited {
+        type String = String;
+        type Map = HashMap;
+        type List = List;
+
+        fn step(&mut self) -> budvm::ExecutionBehavior {
+            budvm::ExecutionBehavior::Continue
+        }
+
+        fn allocation_budget(&self) -> Option<&AllocationBudget> {
+            Some(&self.0)
+        }
+    }
+
+    fn is_out_of_memory<T: std::fmt::Debug>(result: Result<T, Error<'_, Limited, T>>) -> bool {
+        matches!(
+            result.unwrap_err(),
+            Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(FaultKind::OutOfMemory(4096)),
+                ..
+            }))
         )
-        .unwrap());
+    }
+
+    let budget = AllocationBudget::new(4096);
+    let mut bud = Bud::default_for(Limited(budget.clone()));
+    assert_eq!(bud.run_source::<String>(r#""ab" * 3"#).unwrap(), "ababab");
+
+    // A single large allocation is refused before it is made.
+    assert!(is_out_of_memory(
+        bud.run_source::<Value>(r#""x" * 1_000_000_000"#)
+    ));
+
+    // Padding is charged before it is written.
+    budget.reset();
+    assert!(is_out_of_memory(
+        bud.run_source::<Value>(r#"format("{:60000}", 1)"#)
+    ));
+
+    // Large powers are refused before they are computed.
+    budget.reset();
+    assert!(is_out_of_memory(
+        bud.run_source::<Value>("2n ** 4_000_000_000")
+    ));
+    budget.reset();
+    assert!(is_out_of_memory(
+        bud.run_source::<Value>("2.5d ** 4_000_000_000")
+    ));
+
+    // Results built from other values are charged as they are built.
+    for source in [
+        r#"format("{}", "x" * 3000)"#,
+        "s := \"x\" * 1000\nl := [s, s, s, s, s]\nl as String",
+        "s := \"x\" * 1000\nm := {1: s, 2: s, 3: s, 4: s, 5: s}\nm as String",
+        "s := \"x\" * 1000\nl := [s, s, s, s, s]\nl.join(\"\")",
+    ] {
+        budget.reset();
+        assert!(
+            is_out_of_memory(bud.run_source::<Value>(source)),
+            "{source}"
+        );
+    }
+    budget.reset();
+    assert!(is_out_of_memory(bud.run_source::<Value>(&format!(
+        "0.{}1d + 1",
+        "0".repeat(20_000)
+    ))));
+
+    // Growing a list without bound exhausts the budget.
+    budget.reset();
+    assert!(is_out_of_memory(
+        bud.run_source::<Value>("list := []\nloop\n  list.push(1)\nend")
+    ));
+
+    // The fault can be caught by the script.
+    budget.reset();
     assert_eq!(
         bud.run_source::<i64>(
-            r#"
-                a := 0
-                ~a
-            "#,
+            r#"try
+  "x" * 1_000_000_000
+  0
+catch
+  42
+end"#
         )
         .unwrap(),
-        !0
+        42
     );
-    assert!(bud
-        .run_source::<bool>(
-            r#"
-                a := 0.0
-                not a
-            "#,
-        )
-        .unwrap());
-    assert!(bud
-        .run_source::<bool>(
-            r#"
-                    a := ""
-                    not a
-                "#,
-        )
-        .unwrap());
+}
 
-    // Verify that this groups `(not true) and true` instead of `not (true and true)`.
-    assert!(!bud.run_source::<bool>(r#"not true and true"#,).unwrap());
+#[test]
+fn budget_costs() {
+    fn cost_of(source: &str) -> usize {
+        let mut bud = Bud::default_for(Budgeted::new(usize::MAX, ()));
+        bud.run_source::<Value>(source).unwrap();
+        usize::MAX - bud.environment().balance()
+    }
+
+    let source = "list := []\nloop for i := 1 to 1_000 inclusive\n  list.push(i)\nend\n";
+    // Sorting charges for each element in addition to the instruction.
+    assert_eq!(
+        cost_of(&format!("{source}list.sort()")),
+        cost_of(&format!("{source}list.count()")) + 1_000
+    );
+
+    // Built-in operations charge in proportion to the size of their inputs
+    // and results.
+    let string = "s := \"x\" * 10_000\n";
+    assert!(cost_of("\"x\" * 10_000") > cost_of("\"x\" * 10") + 9_000);
+    for operation in [
+        "s.split(\"y\")",
+        "s.chars()",
+        "s.replace(\"x\", \"y\")",
+        "format(\"{}\", s)",
+    ] {
+        assert!(
+            cost_of(&format!("{string}{operation}")) > cost_of(string) + 10_000,
+            "{operation}"
+        );
+    }
+    let map = "m := {}\nloop for i := 1 to 1_000 inclusive\n  m[i] := i\nend\n";
+    for operation in ["m.keys()", "m.values()", "m.entries()", "m.merge(m)"] {
+        assert!(
+            cost_of(&format!("{map}{operation}")) >= cost_of(map) + 1_000,
+            "{operation}"
+        );
+    }
+    let big = "a := 10n ** 1_000\n";
+    assert!(cost_of(&format!("{big}a * a")) > cost_of(&format!("{big}a + a")) + 10_000);
+
+    // Powers are charged by the size of their result.
+    assert!(cost_of("2n ** 10_000") > cost_of("2n ** 10") + 10_000);
+
+    // Instructions can be weighted differently.
+    let mut bud = Bud::default_for(Budgeted::new(usize::MAX, ()).with_costs(InstructionCosts {
+        arithmetic: 100,
+        ..InstructionCosts::default()
+    }));
+    bud.run_source::<Value>("1 + 2").unwrap();
+    let weighted = usize::MAX - bud.environment().balance();
+    assert!(weighted >= 100);
+    assert!(weighted < 100 + cost_of("1 + 2"));
 }
 
 #[test]
-fn bitwise() {
-    assert_run!("1 & 3", 1);
-    assert_run!("1 | 3", 3);
-    assert_run!("1 ^ 3", 2);
-    assert_run!("1 << 3", 8);
-    assert_run!("1 << 64", 0);
-    assert_run!("8 >> 3", 1);
-    assert_run!("1 >> 64", 0);
+fn budget_exhausted_while_sorting() {
+    let source = "list := []\nloop for i := 1 to 100 inclusive\n  list.push(-i)\nend\n";
+    let mut bud = Bud::default_for(Budgeted::new(usize::MAX, ()));
+    bud.run_source::<()>(&format!("{source}list.count()"))
+        .unwrap();
+    let before_sorting = usize::MAX - bud.environment().balance();
+
+    // Comparators are called from within the native sort, which can't be
+    // paused. Running out of budget while comparing faults instead.
+    let mut bud = Bud::default_for(Budgeted::new(before_sorting + 10, ()));
+    assert!(matches!(
+        bud.run_source::<()>(&format!("{source}list.sort(function(a, b) a - b end)")),
+        Err(Error::Vm(budvm::Error::Fault(Fault {
+            kind: FaultOrPause::Fault(FaultKind::UnresumablePause),
+            ..
+        })))
+    ));
 }
 
 #[test]
-fn conversions() {
-    assert_run!("1 as String", Value::dynamic(String::from("1")));
-    assert_run!("\"1\" as String", Value::dynamic(String::from("1")));
-    assert_run!("1 as Real", Value::Real(1.0));
-    assert_run!("1 as Integer", Value::Integer(1));
-    assert_run!("1 as Boolean", Value::Boolean(true));
-    assert_run!("0 as Boolean", Value::Boolean(false));
-    assert_run!("1.2 as Integer", Value::Integer(1));
+fn cancellation() {
+    fn kind_of(error: Error<'_, (), Value>) -> FaultKind {
+        match error {
+            Error::Vm(budvm::Error::Fault(Fault {
+                kind: FaultOrPause::Fault(kind),
+                ..
+            })) => kind,
+            other => unreachable!("unexpected error: {other:?}"),
+        }
+    }
+
+    // Scripts can't catch the fault to keep running.
+    let source = "try\n  loop\n  end\ncatch\n  42\nend";
+
+    let token = CancellationToken::new();
+    let mut bud = Bud::empty().with_cancellation(token.clone());
+    let canceller = {
+        let token = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        })
+    };
+    assert_eq!(
+        kind_of(bud.run_source::<Value>(source).unwrap_err()),
+        FaultKind::Cancelled
+    );
+    canceller.join().unwrap();
+
+    // Once reset, the same virtual machine can continue to be used.
+    token.reset();
+    assert_eq!(bud.run_source::<i64>("1 + 2").unwrap(), 3);
+
+    bud.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+    assert_eq!(
+        kind_of(bud.run_source::<Value>(source).unwrap_err()),
+        FaultKind::TimedOut
+    );
 }
@@ -0,0 +1,248 @@
+use crate::{FaultKind, Value};
+
+/// Controls how integer arithmetic behaves when the result cannot be
+/// represented by an `i64`, or when an integer is divided by zero.
+///
+/// This only affects operations where both operands are
+/// [`Value::Integer`]s. Operations on [`Value::Real`]s always follow IEEE 754
+/// rules, and [`DynamicValue`](crate::DynamicValue)s implement their own
+/// behavior.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ArithmeticMode {
+    /// [`Value::Void`] is produced instead of the result. Dividing by zero and
+    /// raising an integer to a negative power also produce [`Value::Void`].
+    #[default]
+    Void,
+    /// [`FaultKind::Overflow`] is raised. Dividing by zero raises
+    /// [`FaultKind::DivideByZero`].
+    Fault,
+    /// The result wraps around at the boundary of the integer type.
+    Wrapping,
+    /// The result is clamped to `i64::MIN` or `i64::MAX`.
+    Saturating,
+    /// The operation is performed again using reals, producing a
+    /// [`Value::Real`]. Raising an integer to a negative power also produces
+    /// a [`Value::Real`].
+    PromoteToReal,
+}
+
+impl ArithmeticMode {
+    /// Returns the result of negating `value` using this mode.
+    pub(crate) fn negate(self, value: i64) -> Result<Value, FaultKind> {
+        if let Some(negated) = value.checked_neg() {
+            return Ok(Value::Integer(negated));
+        }
+        match self {
+            ArithmeticMode::Void => Ok(Value::Void),
+            ArithmeticMode::Fault => Err(FaultKind::Overflow {
+                operator: "-",
+                operands: vec![Value::Integer(value)],
+            }),
+            ArithmeticMode::Wrapping => Ok(Value::Integer(value.wrapping_neg())),
+            ArithmeticMode::Saturating => Ok(Value::Integer(value.saturating_neg())),
+            ArithmeticMode::PromoteToReal => Ok(Value::Real(-to_real(value))),
+        }
+    }
+}
+
+/// A binary operation on integers, and the functions needed to implement each
+/// [`ArithmeticMode`].
+pub(crate) struct IntegerOperation {
+    operator: &'static str,
+    checked: fn(i64, i64) -> Option<i64>,
+    wrapping: fn(i64, i64) -> i64,
+    saturating: fn(i64, i64) -> i64,
+    real: fn(f64, f64) -> f64,
+    right: RightOperand,
+}
+
+/// The restrictions on the right hand side of an [`IntegerOperation`].
+enum RightOperand {
+    Any,
+    NonZero,
+    NonNegative(&'static str),
+}
+
+impl IntegerOperation {
+    pub const ADD: Self = Self {
+        operator: "+",
+        checked: i64::checked_add,
+        wrapping: i64::wrapping_add,
+        saturating: i64::saturating_add,
+        real: |left, right| left + right,
+        right: RightOperand::Any,
+    };
+    pub const SUB: Self = Self {
+        operator: "-",
+        checked: i64::checked_sub,
+        wrapping: i64::wrapping_sub,
+        saturating: i64::saturating_sub,
+        real: |left, right| left - right,
+        right: RightOperand::Any,
+    };
+    pub const MULTIPLY: Self = Self {
+        operator: "*",
+        checked: i64::checked_mul,
+        wrapping: i64::wrapping_mul,
+        saturating: i64::saturating_mul,
+        real: |left, right| left * right,
+        right: RightOperand::Any,
+    };
+    pub const DIVIDE: Self = Self {
+        operator: "/",
+        checked: i64::checked_div,
+        wrapping: i64::wrapping_div,
+        saturating: i64::saturating_div,
+        real: |left, right| left / right,
+        right: RightOperand::NonZero,
+    };
+    pub const INTEGER_DIVIDE: Self = Self {
+        operator: "div",
+        checked: integer_floor_div,
+        // `i64::MIN div -1` is the only quotient that overflows.
+        wrapping: |left, right| integer_floor_div(left, right).unwrap_or(i64::MIN),
+        saturating: |left, right| integer_floor_div(left, right).unwrap_or(i64::MAX),
+        real: real_floor_div,
+        right: RightOperand::NonZero,
+    };
+    pub const REMAINDER: Self = Self {
+        operator: "%",
+        checked: integer_floor_rem,
+        // The remainder never overflows once division by zero is excluded.
+        wrapping: |left, right| integer_floor_rem(left, right).unwrap_or_default(),
+        saturating: |left, right| integer_floor_rem(left, right).unwrap_or_default(),
+        real: real_floor_rem,
+        right: RightOperand::NonZero,
+    };
+    pub const POWER: Self = Self {
+        operator: "**",
+        checked: integer_pow,
+        wrapping: wrapping_pow,
+        saturating: saturating_pow,
+        real: f64::powf,
+        right: RightOperand::NonNegative("exponent"),
+    };
+
+    /// Performs this operation on two reals.
+    pub fn real(&self, left: f64, right: f64) -> f64 {
+        (self.real)(left, right)
+    }
+
+    /// Performs this operation on two integers, handling overflow as
+    /// specified by `mode`.
+    pub fn integer(&self, mode: ArithmeticMode, left: i64, right: i64) -> Result<Value, FaultKind> {
+        match self.right {
+            RightOperand::NonZero if right == 0 => {
+                return if mode == ArithmeticMode::Void {
+                    Ok(Value::Void)
+                } else {
+                    Err(FaultKind::DivideByZero(Value::Integer(left)))
+                };
+            }
+            RightOperand::NonNegative(name) if right < 0 => {
+                return match mode {
+                    ArithmeticMode::Void => Ok(Value::Void),
+                    ArithmeticMode::PromoteToReal => {
+                        Ok(Value::Real(self.real(to_real(left), to_real(right))))
+                    }
+                    _ => Err(FaultKind::ValueOutOfRange(name)),
+                };
+            }
+            _ => {}
+        }
+
+        if let Some(result) = (self.checked)(left, right) {
+            return Ok(Value::Integer(result));
+        }
+        match mode {
+            ArithmeticMode::Void => Ok(Value::Void),
+            ArithmeticMode::Fault => Err(FaultKind::Overflow {
+                operator: self.operator,
+                operands: vec![Value::Integer(left), Value::Integer(right)],
+            }),
+            ArithmeticMode::Wrapping => Ok(Value::Integer((self.wrapping)(left, right))),
+            ArithmeticMode::Saturating => Ok(Value::Integer((self.saturating)(left, right))),
+            ArithmeticMode::PromoteToReal => {
+                Ok(Value::Real(self.real(to_real(left), to_real(right))))
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // Promoting to a real is allowed to lose precision.
+fn to_real(value: i64) -> f64 {
+    value as f64
+}
+
+/// Divides `left` by `right`, rounding towards negative infinity.
+pub(crate) fn integer_floor_div(left: i64, right: i64) -> Option<i64> {
+    let quotient = left.checked_div(right)?;
+    if left % right != 0 && (left < 0) != (right < 0) {
+        quotient.checked_sub(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Returns the remainder of [`integer_floor_div()`], which has the same sign
+/// as `right`.
+pub(crate) fn integer_floor_rem(left: i64, right: i64) -> Option<i64> {
+    if right == 0 {
+        return None;
+    }
+    // `wrapping_rem` only wraps for `i64::MIN % -1`, whose remainder is 0.
+    let remainder = left.wrapping_rem(right);
+    if remainder != 0 && (remainder < 0) != (right < 0) {
+        Some(remainder + right)
+    } else {
+        Some(remainder)
+    }
+}
+
+pub(crate) fn real_floor_div(left: f64, right: f64) -> f64 {
+    (left / right).floor()
+}
+
+pub(crate) fn real_floor_rem(left: f64, right: f64) -> f64 {
+    let remainder = left % right;
+    if remainder != 0. && (remainder < 0.) != (right < 0.) {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+/// Raises `base` to `exponent`. Negative exponents aren't supported, as the
+/// result would not be an integer.
+pub(crate) fn integer_pow(base: i64, exponent: i64) -> Option<i64> {
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent),
+        // Only a few bases can be raised to such a large exponent.
+        Err(_) if exponent > 0 => match base {
+            0 | 1 => Some(base),
+            -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+            _ => None,
+        },
+        Err(_) => None,
+    }
+}
+
+fn wrapping_pow(mut base: i64, mut exponent: i64) -> i64 {
+    let mut result = 1_i64;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn saturating_pow(base: i64, exponent: i64) -> i64 {
+    integer_pow(base, exponent).unwrap_or(if base < 0 && exponent % 2 == 1 {
+        i64::MIN
+    } else {
+        i64::MAX
+    })
+}
@@ -0,0 +1,738 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Write},
+    hash::{Hash, Hasher},
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use crate::{cost::charge, memory::allocate, Decimal, DynamicValue, FaultKind, Symbol, Value};
+
+/// Each limb holds 9 decimal digits, which keeps conversions to and from
+/// strings simple and allows decimals to be rescaled cheaply.
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+
+/// An arbitrary-precision signed integer.
+///
+/// In Bud, this type's kind is `BigInteger`. Literals are written with an `n`
+/// suffix (`123n`), and other values can be converted using `as BigInteger`.
+///
+/// Arithmetic with an [`Value::Integer`] produces a `BigInteger`, and
+/// arithmetic with a [`Value::Real`] produces a real. Like integers, division
+/// using `/` truncates towards zero, while `div` and `%` round towards
+/// negative infinity. Dividing by zero raises [`FaultKind::DivideByZero`].
+///
+/// Like other numbers, a `BigInteger` is only equal to and comparable with
+/// other `BigInteger`s.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct BigInteger {
+    negative: bool,
+    /// The magnitude, stored as base-10^9 digits with the least significant
+    /// digit first. Never contains trailing zeroes.
+    limbs: Vec<u32>,
+}
+
+impl BigInteger {
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        trim(&mut limbs);
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    /// Returns true if this value is 0.
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Returns true if this value is less than 0.
+    #[must_use]
+    pub const fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Returns the absolute value of this value.
+    #[must_use]
+    pub fn abs(&self) -> Self {
+        Self {
+            negative: false,
+            limbs: self.limbs.clone(),
+        }
+    }
+
+    /// Returns this value as an `i64`, if it is within range.
+    #[must_use]
+    pub fn to_i64(&self) -> Option<i64> {
+        // Three limbs can hold any i64 value.
+        if self.limbs.len() > 3 {
+            return None;
+        }
+        let magnitude = self.limbs.iter().rev().fold(0_i128, |value, limb| {
+            value * i128::from(BASE) + i128::from(*limb)
+        });
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    /// Returns the closest `f64` to this value.
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns `value` truncated to an integer, or None if `value` is not
+    /// finite.
+    #[must_use]
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if value.is_finite() {
+            // Display never uses exponential notation for `f64`.
+            value.trunc().to_string().parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Raises this value to the power of `exponent`.
+    #[must_use]
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = Self::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    /// Returns an upper bound of the number of limbs needed to store this value
+    /// raised to `exponent`.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )] // The estimate is positive, and saturates when converted.
+    fn pow_limbs(&self, exponent: u32) -> usize {
+        match self.limbs.as_slice() {
+            // 0 and 1 never grow.
+            [] | [1] => 1,
+            [.., top] => {
+                let digits = (self.limbs.len() - 1) as f64 * BASE_DIGITS as f64
+                    + (f64::from(*top) + 1.).log10();
+                (digits * f64::from(exponent) / BASE_DIGITS as f64).ceil() as usize + 1
+            }
+        }
+    }
+
+    /// Returns an upper bound of the number of limbs needed to store this value
+    /// multiplied by 10 raised to `exponent`.
+    pub(crate) fn scaled_limbs(&self, exponent: u32) -> usize {
+        self.limbs.len() + exponent as usize / BASE_DIGITS + 1
+    }
+
+    /// Charges the budget for adding or subtracting `other`, which visits each
+    /// limb once.
+    pub(crate) fn charge_linear(&self, other: &Self) {
+        charge(self.limbs.len().saturating_add(other.limbs.len()));
+    }
+
+    /// Charges the budget for multiplying or dividing by `other`, which visits
+    /// every pair of limbs.
+    pub(crate) fn charge_quadratic(&self, other: &Self) {
+        charge(self.limbs.len().saturating_mul(other.limbs.len()));
+    }
+
+    /// Divides this value by `other`, truncating the result towards zero.
+    /// Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        self.div_rem(other).map(|(quotient, _)| quotient)
+    }
+
+    /// Divides this value by `other`, rounding the result towards negative
+    /// infinity. Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_div_floor(&self, other: &Self) -> Option<Self> {
+        self.div_rem_floor(other).map(|(quotient, _)| quotient)
+    }
+
+    /// Returns the remainder of [`BigInteger::checked_div_floor()`], which has
+    /// the same sign as `other`. Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_rem_floor(&self, other: &Self) -> Option<Self> {
+        self.div_rem_floor(other).map(|(_, remainder)| remainder)
+    }
+
+    /// Returns the truncated quotient and the remainder of dividing this value
+    /// by `other`. The remainder has the same sign as this value.
+    pub(crate) fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &other.limbs);
+        Some((
+            Self::from_parts(self.negative != other.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        ))
+    }
+
+    fn div_rem_floor(&self, other: &Self) -> Option<(Self, Self)> {
+        let (quotient, remainder) = self.div_rem(other)?;
+        if !remainder.is_zero() && remainder.negative != other.negative {
+            Some((&quotient - &Self::from(1), &remainder + other))
+        } else {
+            Some((quotient, remainder))
+        }
+    }
+
+    /// Multiplies this value by 10 raised to `exponent`.
+    pub(crate) fn scale_up(&self, exponent: u32) -> Self {
+        if exponent == 0 || self.is_zero() {
+            self.clone()
+        } else {
+            self * &Self::from(10).pow(exponent)
+        }
+    }
+
+    fn binary_operation(
+        &self,
+        other: &Value,
+        is_reverse: bool,
+        big: impl FnOnce(&Self, &Self) -> Result<Value, FaultKind>,
+        real: impl FnOnce(f64, f64) -> f64,
+    ) -> Result<Option<Value>, FaultKind> {
+        let other = match other {
+            Value::Integer(other) => Self::from(*other),
+            Value::Real(other) => {
+                let (left, right) = ordered(self.to_f64(), *other, is_reverse);
+                return Ok(Some(Value::Real(real(left, right))));
+            }
+            other => match other.as_dynamic::<Self>() {
+                Some(other) => other.clone(),
+                None => return Ok(None),
+            },
+        };
+        // No operation produces more limbs than its operands combined.
+        allocate_limbs(self.limbs.len() + other.limbs.len() + 1)?;
+        let (left, right) = ordered(self, &other, is_reverse);
+        big(left, right).map(Some)
+    }
+}
+
+/// Returns `(left, right)`, swapping them if `is_reverse` is true.
+pub(crate) fn ordered<T>(left: T, right: T, is_reverse: bool) -> (T, T) {
+    if is_reverse {
+        (right, left)
+    } else {
+        (left, right)
+    }
+}
+
+/// Returns the result of a division operation, or a
+/// [`FaultKind::DivideByZero`] containing `dividend`.
+pub(crate) fn divided<T: DynamicValue + Clone>(
+    result: Option<T>,
+    dividend: &T,
+) -> Result<Value, FaultKind> {
+    result
+        .map(Value::dynamic)
+        .ok_or_else(|| FaultKind::DivideByZero(Value::dynamic(dividend.clone())))
+}
+
+/// Charges the memory and budget needed to raise `base` to `exponent` before
+/// the result is computed.
+pub(crate) fn charge_pow(base: &BigInteger, exponent: u32) -> Result<(), FaultKind> {
+    let limbs = base.pow_limbs(exponent);
+    // Squaring the largest intermediate value dominates the work.
+    charge(limbs.saturating_mul(limbs));
+    allocate_limbs(limbs)
+}
+
+/// Charges the memory needed to store `limbs` limbs.
+pub(crate) fn allocate_limbs(limbs: usize) -> Result<(), FaultKind> {
+    allocate(limbs.saturating_mul(std::mem::size_of::<u32>()))
+}
+
+/// Returns `exponent` as a `u32`, raising a fault if it is negative or too
+/// large.
+pub(crate) fn exponent(exponent: &BigInteger) -> Result<u32, FaultKind> {
+    exponent
+        .to_i64()
+        .and_then(|exponent| u32::try_from(exponent).ok())
+        .ok_or(FaultKind::ValueOutOfRange("exponent"))
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)] // Values are always reduced below BASE.
+fn limb(value: u64) -> u32 {
+    (value % BASE) as u32
+}
+
+fn cmp_magnitude(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0;
+    for index in 0..left.len().max(right.len()) {
+        let sum = u64::from(left.get(index).copied().unwrap_or_default())
+            + u64::from(right.get(index).copied().unwrap_or_default())
+            + carry;
+        result.push(limb(sum));
+        carry = sum / BASE;
+    }
+    if carry > 0 {
+        result.push(limb(carry));
+    }
+    result
+}
+
+/// Subtracts `right` from `left`. `left` must be greater than or equal to
+/// `right`.
+fn sub_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0;
+    for (index, left) in left.iter().enumerate() {
+        let subtrahend = u64::from(right.get(index).copied().unwrap_or_default()) + borrow;
+        let left = u64::from(*left);
+        if left >= subtrahend {
+            result.push(limb(left - subtrahend));
+            borrow = 0;
+        } else {
+            result.push(limb(left + BASE - subtrahend));
+            borrow = 1;
+        }
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    if left.is_empty() || right.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0; left.len() + right.len()];
+    for (left_index, left) in left.iter().enumerate() {
+        let mut carry = 0;
+        for (right_index, right) in right.iter().enumerate() {
+            let product = u64::from(result[left_index + right_index])
+                + u64::from(*left) * u64::from(*right)
+                + carry;
+            result[left_index + right_index] = limb(product);
+            carry = product / BASE;
+        }
+        result[left_index + right.len()] = limb(carry);
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_small(left: &[u32], right: u32) -> Vec<u32> {
+    mul_magnitude(left, &[right])
+}
+
+/// Divides `left` by `right`, returning the quotient and remainder. `right`
+/// must not be empty.
+fn div_rem_magnitude(left: &[u32], right: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = Vec::with_capacity(left.len());
+    let mut remainder = Vec::<u32>::with_capacity(right.len() + 1);
+    for limb in left.iter().rev() {
+        remainder.insert(0, *limb);
+        trim(&mut remainder);
+
+        // Find the largest digit whose product with `right` fits within the
+        // current remainder.
+        let (mut low, mut high) = (0, limb_max());
+        while low < high {
+            let middle = low + (high - low + 1) / 2;
+            if cmp_magnitude(&mul_small(right, middle), &remainder) == Ordering::Greater {
+                high = middle - 1;
+            } else {
+                low = middle;
+            }
+        }
+        if low > 0 {
+            remainder = sub_magnitude(&remainder, &mul_small(right, low));
+        }
+        quotient.push(low);
+    }
+    quotient.reverse();
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+#[allow(clippy::cast_possible_truncation)] // BASE fits in a u32.
+const fn limb_max() -> u32 {
+    (BASE - 1) as u32
+}
+
+impl From<i64> for BigInteger {
+    fn from(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push(limb(magnitude));
+            magnitude /= BASE;
+        }
+        Self::from_parts(value < 0, limbs)
+    }
+}
+
+impl Ord for BigInteger {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for BigInteger {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInteger {
+    type Output = BigInteger;
+
+    fn add(self, other: Self) -> BigInteger {
+        if self.negative == other.negative {
+            return BigInteger::from_parts(self.negative, add_magnitude(&self.limbs, &other.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => {
+                BigInteger::from_parts(other.negative, sub_magnitude(&other.limbs, &self.limbs))
+            }
+            Ordering::Equal => BigInteger::default(),
+            Ordering::Greater => {
+                BigInteger::from_parts(self.negative, sub_magnitude(&self.limbs, &other.limbs))
+            }
+        }
+    }
+}
+
+impl Sub for &BigInteger {
+    type Output = BigInteger;
+
+    fn sub(self, other: Self) -> BigInteger {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInteger {
+    type Output = BigInteger;
+
+    fn mul(self, other: Self) -> BigInteger {
+        BigInteger::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.limbs, &other.limbs),
+        )
+    }
+}
+
+impl Neg for &BigInteger {
+    type Output = BigInteger;
+
+    fn neg(self) -> BigInteger {
+        BigInteger::from_parts(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Display for BigInteger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut limbs = self.limbs.iter().rev();
+        match limbs.next() {
+            Some(top) => {
+                if self.negative {
+                    f.write_char('-')?;
+                }
+                write!(f, "{top}")?;
+                for limb in limbs {
+                    write!(f, "{limb:09}")?;
+                }
+                Ok(())
+            }
+            None => f.write_char('0'),
+        }
+    }
+}
+
+/// An error parsing a [`BigInteger`] or [`Decimal`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseNumberError;
+
+impl Display for ParseNumberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid number")
+    }
+}
+
+impl std::error::Error for ParseNumberError {}
+
+impl FromStr for BigInteger {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(ParseNumberError);
+        }
+
+        let mut limbs = Vec::with_capacity(digits.len() / BASE_DIGITS + 1);
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            limbs.push(digits[start..end].parse().map_err(|_| ParseNumberError)?);
+            end = start;
+        }
+        Ok(Self::from_parts(negative, limbs))
+    }
+}
+
+impl DynamicValue for BigInteger {
+    fn is_truthy(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn kind(&self) -> Symbol {
+        Symbol::from("BigInteger")
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        self.to_i64()
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Some(self.to_f64())
+    }
+
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        match kind.as_str() {
+            "String" => Some(Value::dynamic(self.to_string())),
+            "Decimal" => Some(Value::dynamic(Decimal::from(self.clone()))),
+            _ => None,
+        }
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        other.as_dynamic::<Self>().map(|other| self == other)
+    }
+
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        other.as_dynamic::<Self>().map(|other| self.cmp(other))
+    }
+
+    fn checked_add(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_linear(right);
+                Ok(Value::dynamic(left + right))
+            },
+            |left, right| left + right,
+        )
+    }
+
+    fn checked_sub(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_linear(right);
+                Ok(Value::dynamic(left - right))
+            },
+            |left, right| left - right,
+        )
+    }
+
+    fn checked_mul(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_quadratic(right);
+                Ok(Value::dynamic(left * right))
+            },
+            |left, right| left * right,
+        )
+    }
+
+    fn checked_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_quadratic(right);
+                divided(left.checked_div(right), left)
+            },
+            |left, right| left / right,
+        )
+    }
+
+    fn checked_int_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_quadratic(right);
+                divided(left.checked_div_floor(right), left)
+            },
+            crate::arithmetic::real_floor_div,
+        )
+    }
+
+    fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |left, right| {
+                left.charge_quadratic(right);
+                divided(left.checked_rem_floor(right), left)
+            },
+            crate::arithmetic::real_floor_rem,
+        )
+    }
+
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(
+            other,
+            is_reverse,
+            |base, power| {
+                let power = exponent(power)?;
+                charge_pow(base, power)?;
+                Ok(Value::dynamic(base.pow(power)))
+            },
+            f64::powf,
+        )
+    }
+
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        charge(self.limbs.len());
+        allocate_limbs(self.limbs.len())?;
+        Ok(Some(Value::dynamic(-self)))
+    }
+
+    fn to_source(&self) -> Option<String> {
+        Some(format!("{self}n"))
+    }
+
+    fn hash<H>(&self, state: &mut H) -> bool
+    where
+        H: Hasher,
+    {
+        Hash::hash(self, state);
+        true
+    }
+}
+
+#[test]
+fn arithmetic() {
+    let values = [
+        0_i64,
+        1,
+        -1,
+        7,
+        -7,
+        999_999_999,
+        1_000_000_000,
+        -1_000_000_001,
+        123_456_789_012_345,
+        i64::MAX,
+        i64::MIN,
+    ];
+    for left in values {
+        for right in values {
+            let (big_left, big_right) = (BigInteger::from(left), BigInteger::from(right));
+            let (wide_left, wide_right) = (i128::from(left), i128::from(right));
+            assert_eq!(
+                (&big_left + &big_right).to_string(),
+                (wide_left + wide_right).to_string()
+            );
+            assert_eq!(
+                (&big_left - &big_right).to_string(),
+                (wide_left - wide_right).to_string()
+            );
+            assert_eq!(
+                (&big_left * &big_right).to_string(),
+                (wide_left * wide_right).to_string()
+            );
+            assert_eq!(big_left.cmp(&big_right), left.cmp(&right));
+            if right != 0 {
+                let quotient = wide_left / wide_right;
+                let remainder = wide_left % wide_right;
+                let floored = if remainder != 0 && (remainder < 0) != (wide_right < 0) {
+                    (quotient - 1, remainder + wide_right)
+                } else {
+                    (quotient, remainder)
+                };
+                assert_eq!(
+                    big_left.checked_div(&big_right).unwrap().to_string(),
+                    quotient.to_string()
+                );
+                assert_eq!(
+                    big_left.checked_div_floor(&big_right).unwrap().to_string(),
+                    floored.0.to_string()
+                );
+                assert_eq!(
+                    big_left.checked_rem_floor(&big_right).unwrap().to_string(),
+                    floored.1.to_string()
+                );
+            }
+        }
+        assert_eq!(BigInteger::from(left).to_i64(), Some(left));
+    }
+
+    let large = "123456789012345678901234567890"
+        .parse::<BigInteger>()
+        .unwrap();
+    let divisor = "-98765432109876543210".parse::<BigInteger>().unwrap();
+    let product = &large * &divisor;
+    assert_eq!(
+        product.to_string(),
+        "-12193263113702179522496570642237463801111263526900"
+    );
+    assert_eq!(product.checked_div(&divisor), Some(large.clone()));
+    assert_eq!(large.to_i64(), None);
+    assert_eq!(
+        BigInteger::from(2).pow(100).to_string(),
+        "1267650600228229401496703205376"
+    );
+    assert_eq!(BigInteger::from(0).checked_div(&BigInteger::from(0)), None);
+    assert_eq!("-0".parse::<BigInteger>(), Ok(BigInteger::default()));
+    assert!("1.0".parse::<BigInteger>().is_err());
+    assert!("-".parse::<BigInteger>().is_err());
+}
+
+#[test]
+fn pow_limbs() {
+    for base in [0_i64, 1, -1, 2, 999_999_999, 1_000_000_000, i64::MAX] {
+        let base = BigInteger::from(base);
+        for exponent in [0, 1, 2, 7, 100] {
+            assert!(base.pow_limbs(exponent) >= base.pow(exponent).limbs.len());
+        }
+    }
+    // The estimate is close enough to use as a memory charge.
+    let two = BigInteger::from(2);
+    assert!(two.pow_limbs(1_000) <= two.pow(1_000).limbs.len() * 2);
+}
@@ -335,6 +335,14 @@ where
         removed
     }
 
+    /// Removes all entries from this map. The allocated capacity is retained.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bins.truncate(self.bin_mask.into_count());
+        self.bins.fill(Bin::default());
+        self.free_collision_head = OptionalIndex::none();
+    }
+
     /// Returns the number of entries contained in this map.
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -344,3 +344,23 @@ fn collision_reuse() {
     map.insert(0xFF00, 3);
     assert_eq!(map.bins.len(), 9, "new bin allocated");
 }
+
+#[test]
+fn clear() {
+    let mut map = BudMap::with_hasher(BadHasher(0));
+    map.insert(0xFF, 1);
+    map.insert(0xFF00, 2);
+    assert_eq!(map.bins.len(), 9, "didn't collide");
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.bins.len(), 8, "collision bins not freed");
+    assert_eq!(map.get(&0xFF), None);
+    map.insert(0xFF00, 3);
+    map.insert(0xFF, 4);
+    assert_eq!(map.get(&0xFF), Some(&4));
+    assert_eq!(map.get(&0xFF00), Some(&3));
+    assert_eq!(
+        map.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+        [0xFF00, 0xFF]
+    );
+}
@@ -0,0 +1,2216 @@
+//! A versioned binary encoding of compiled code (`.budc`).
+//!
+//! Two kinds of artifacts can be encoded:
+//!
+//! - An intermediate representation [`Module`](ir::Module), using
+//!   [`Module::to_bytecode()`](ir::Module::to_bytecode) and
+//!   [`Module::from_bytecode()`](ir::Module::from_bytecode). Modules refer to
+//!   functions by name and can be linked into any virtual machine.
+//! - A linked [`Function`], using [`encode_function()`] and
+//!   [`decode_function()`]. Linked functions refer to other functions by vtable
+//!   index, which means they are only meaningful to a virtual machine whose
+//!   vtable matches the one the function was linked against.
+//!
+//! Every artifact begins with [`MAGIC`], followed by the little-endian
+//! [`VERSION`] of the format and a byte identifying the kind of artifact.
+//! Decoding validates the entire artifact, including that every variable,
+//! argument, and label referenced by an instruction exists. Malformed input
+//! results in a [`BytecodeError`].
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    ir, CompareAction, Comparison, DebugInfo, Destination, Environment, Function, Instruction,
+    SourceLocation, Symbol, Value, ValueKind, ValueOrSource,
+};
+
+/// The bytes every encoded artifact begins with.
+pub const MAGIC: &[u8; 4] = b"BUDC";
+
+/// The version of the encoding produced by this crate. Artifacts with any
+/// other version are rejected when decoding.
+pub const VERSION: u16 = 1;
+
+/// The maximum depth of nested modules accepted when decoding.
+const MAX_MODULE_DEPTH: usize = 64;
+
+const KIND_MODULE: u8 = 0;
+const KIND_FUNCTION: u8 = 1;
+
+/// Returns true if `bytes` begins with [`MAGIC`].
+#[must_use]
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encodes a linked function.
+///
+/// Dynamic values are only supported if they are the environment's string
+/// type. Any other dynamic value results in
+/// [`BytecodeError::UnsupportedValue`].
+pub fn encode_function<Env>(function: &Function<Env::Intrinsic>) -> Result<Vec<u8>, BytecodeError>
+where
+    Env: Environment,
+    Env::String: AsRef<str>,
+{
+    let mut encoder = Encoder::new(KIND_FUNCTION);
+    encoder.str(&function.name);
+    encoder.usize(function.arg_count);
+    encoder.usize(function.variable_count);
+    encoder.usize(function.code.len());
+    for instruction in &function.code {
+        encoder.instruction::<Env>(instruction)?;
+    }
+    Ok(encoder.finish())
+}
+
+/// Decodes and validates a linked function encoded with
+/// [`encode_function()`].
+///
+/// Vtable indices cannot be validated without the virtual machine the
+/// function will execute within. Calling an invalid vtable index results in
+/// [`FaultKind::InvalidVtableIndex`](crate::FaultKind::InvalidVtableIndex)
+/// when executed.
+pub fn decode_function<Env>(bytes: &[u8]) -> Result<Function<Env::Intrinsic>, BytecodeError>
+where
+    Env: Environment,
+{
+    let mut decoder = Decoder::new(bytes, KIND_FUNCTION)?;
+    let name = decoder.symbol()?;
+    let arg_count = decoder.usize()?;
+    let variable_count = decoder.usize()?;
+    let instruction_count = decoder.len()?;
+    let mut code = Vec::with_capacity(instruction_count);
+    for _ in 0..instruction_count {
+        code.push(decoder.instruction::<Env>()?);
+    }
+    decoder.finish()?;
+
+    let function = Function {
+        name,
+        arg_count,
+        variable_count,
+        code,
+    };
+    validate_function(&function)?;
+    Ok(function)
+}
+
+pub(crate) fn encode_module<Intrinsic>(module: &ir::Module<Intrinsic>) -> Vec<u8>
+where
+    Intrinsic: Display,
+{
+    let mut encoder = Encoder::new(KIND_MODULE);
+    encoder.module(module);
+    encoder.finish()
+}
+
+pub(crate) fn decode_module<Intrinsic>(bytes: &[u8]) -> Result<ir::Module<Intrinsic>, BytecodeError>
+where
+    Intrinsic: FromStr,
+{
+    let mut decoder = Decoder::new(bytes, KIND_MODULE)?;
+    let module = decoder.module(0)?;
+    decoder.finish()?;
+    Ok(module)
+}
+
+/// An error encoding or decoding bytecode.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BytecodeError {
+    /// The input does not begin with [`MAGIC`].
+    InvalidHeader,
+    /// The input was encoded with an unsupported version of the format.
+    UnsupportedVersion(u16),
+    /// The input contains a different kind of artifact than was requested.
+    UnexpectedArtifact,
+    /// The input ended unexpectedly.
+    UnexpectedEof,
+    /// The input contains bytes after the end of the artifact.
+    TrailingBytes,
+    /// An unknown tag was encountered while decoding.
+    InvalidTag {
+        /// The kind of value being decoded.
+        kind: &'static str,
+        /// The tag encountered.
+        tag: u8,
+    },
+    /// An encoded number is out of range.
+    InvalidNumber,
+    /// An encoded string is not valid UTF-8.
+    InvalidString,
+    /// An intrinsic could not be parsed.
+    UnknownIntrinsic(String),
+    /// Modules are nested too deeply.
+    TooDeeplyNested,
+    /// An instruction references a variable that does not exist.
+    InvalidVariable {
+        /// The function containing the instruction.
+        function: Symbol,
+        /// The index of the variable.
+        index: usize,
+    },
+    /// An instruction references an argument that does not exist.
+    InvalidArgument {
+        /// The function containing the instruction.
+        function: Symbol,
+        /// The index of the argument.
+        index: usize,
+    },
+    /// An instruction jumps to a label that is not defined.
+    InvalidLabel {
+        /// The function containing the instruction.
+        function: Symbol,
+        /// The index of the label.
+        index: usize,
+    },
+    /// A function's debug information does not match its instructions.
+    InvalidDebugInfo(Symbol),
+    /// A value cannot be encoded.
+    UnsupportedValue(ValueKind),
+}
+
+impl std::error::Error for BytecodeError {}
+
+impl Display for BytecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BytecodeError::InvalidHeader => f.write_str("input is not bud bytecode"),
+            BytecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported bytecode version {version}")
+            }
+            BytecodeError::UnexpectedArtifact => {
+                f.write_str("bytecode contains a different kind of artifact")
+            }
+            BytecodeError::UnexpectedEof => f.write_str("unexpected end of bytecode"),
+            BytecodeError::TrailingBytes => f.write_str("unexpected data after end of bytecode"),
+            BytecodeError::InvalidTag { kind, tag } => write!(f, "invalid {kind} tag: {tag}"),
+            BytecodeError::InvalidNumber => f.write_str("encoded number is out of range"),
+            BytecodeError::InvalidString => f.write_str("encoded string is not valid utf-8"),
+            BytecodeError::UnknownIntrinsic(name) => write!(f, "unknown intrinsic: {name}"),
+            BytecodeError::TooDeeplyNested => f.write_str("modules are nested too deeply"),
+            BytecodeError::InvalidVariable { function, index } => {
+                write!(f, "invalid variable index {index} in {function}")
+            }
+            BytecodeError::InvalidArgument { function, index } => {
+                write!(f, "invalid argument index {index} in {function}")
+            }
+            BytecodeError::InvalidLabel { function, index } => {
+                write!(f, "undefined label {index} in {function}")
+            }
+            BytecodeError::InvalidDebugInfo(function) => {
+                write!(f, "debug information does not match the code of {function}")
+            }
+            BytecodeError::UnsupportedValue(kind) => {
+                write!(f, "values of kind {} cannot be encoded", kind.as_str())
+            }
+        }
+    }
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn new(kind: u8) -> Self {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(kind);
+        Self { bytes }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(u8::from(value));
+    }
+
+    fn u64(&mut self, mut value: u64) {
+        // LEB128
+        loop {
+            #[allow(clippy::cast_possible_truncation)] // masked to 7 bits
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.u8(byte);
+                break;
+            }
+            self.u8(byte | 0x80);
+        }
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn optional_str(&mut self, value: Option<&str>) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            self.str(value);
+        }
+    }
+
+    fn module<Intrinsic>(&mut self, module: &ir::Module<Intrinsic>)
+    where
+        Intrinsic: Display,
+    {
+        self.optional_str(module.name.as_deref());
+        self.usize(module.vtable.len());
+        for function in &module.vtable {
+            self.ir_function(function);
+        }
+        self.usize(module.modules.len());
+        for module in &module.modules {
+            self.module(module);
+        }
+        self.bool(module.init.is_some());
+        if let Some(init) = &module.init {
+            self.ir_function(init);
+        }
+    }
+
+    fn ir_function<Intrinsic>(&mut self, function: &ir::Function<Intrinsic>)
+    where
+        Intrinsic: Display,
+    {
+        self.str(&function.name);
+        let body = &function.body;
+        self.usize(body.arguments.len());
+        for argument in &body.arguments {
+            self.str(argument);
+        }
+        self.usize(body.variables);
+        self.usize(body.code.len());
+        for instruction in &body.code {
+            self.ir_instruction(instruction);
+        }
+        self.bool(body.debug_info.is_some());
+        if let Some(debug_info) = &body.debug_info {
+            self.debug_info(debug_info);
+        }
+    }
+
+    fn debug_info(&mut self, debug_info: &DebugInfo) {
+        self.optional_str(debug_info.source.as_deref());
+        self.usize(debug_info.locations.len());
+        for location in &debug_info.locations {
+            self.bool(location.is_some());
+            if let Some(location) = location {
+                self.usize(location.range.start);
+                self.usize(location.range.end);
+                self.usize(location.line);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_lines)] // Most are straight mappings...
+    fn ir_instruction<Intrinsic>(&mut self, instruction: &ir::Instruction<Intrinsic>)
+    where
+        Intrinsic: Display,
+    {
+        match instruction {
+            ir::Instruction::Add {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(0, left, right, destination),
+            ir::Instruction::Sub {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(1, left, right, destination),
+            ir::Instruction::Multiply {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(2, left, right, destination),
+            ir::Instruction::Divide {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(3, left, right, destination),
+            ir::Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(34, left, right, destination),
+            ir::Instruction::Remainder {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(35, left, right, destination),
+            ir::Instruction::Power {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(36, left, right, destination),
+            ir::Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(4, left, right, destination),
+            ir::Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(5, left, right, destination),
+            ir::Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(6, left, right, destination),
+            ir::Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(7, left, right, destination),
+            ir::Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(8, left, right, destination),
+            ir::Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(9, left, right, destination),
+            ir::Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(10, left, right, destination),
+            ir::Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            } => self.ir_binop(11, left, right, destination),
+            ir::Instruction::LogicalNot { value, destination } => {
+                self.u8(12);
+                self.literal_or_source(value);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::BitwiseNot { value, destination } => {
+                self.u8(13);
+                self.literal_or_source(value);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::Negate { value, destination } => {
+                self.u8(37);
+                self.literal_or_source(value);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::Convert {
+                value,
+                kind,
+                destination,
+            } => {
+                self.u8(14);
+                self.literal_or_source(value);
+                self.value_kind(kind);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::If {
+                condition,
+                false_jump_to,
+            } => {
+                self.u8(15);
+                self.literal_or_source(condition);
+                self.label(false_jump_to);
+            }
+            ir::Instruction::JumpTo(label) => {
+                self.u8(16);
+                self.label(label);
+            }
+            ir::Instruction::Label(label) => {
+                self.u8(17);
+                self.label(label);
+            }
+            ir::Instruction::Compare {
+                comparison,
+                left,
+                right,
+                action,
+            } => {
+                self.u8(18);
+                self.comparison(*comparison);
+                self.literal_or_source(left);
+                self.literal_or_source(right);
+                match action {
+                    ir::CompareAction::Store(destination) => {
+                        self.u8(0);
+                        self.ir_destination(destination);
+                    }
+                    ir::CompareAction::JumpIfFalse(label) => {
+                        self.u8(1);
+                        self.label(label);
+                    }
+                }
+            }
+            ir::Instruction::Push(value) => {
+                self.u8(19);
+                self.literal_or_source(value);
+            }
+            ir::Instruction::Load { value, variable } => {
+                self.u8(20);
+                self.literal_or_source(value);
+                self.variable(variable);
+            }
+            ir::Instruction::Return(value) => {
+                self.u8(21);
+                self.bool(value.is_some());
+                if let Some(value) = value {
+                    self.literal_or_source(value);
+                }
+            }
+            ir::Instruction::Call {
+                function,
+                arg_count,
+                destination,
+            } => {
+                self.u8(22);
+                self.optional_str(function.as_deref());
+                self.usize(*arg_count);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::TailCall {
+                function,
+                arg_count,
+            } => {
+                self.u8(38);
+                self.optional_str(function.as_deref());
+                self.usize(*arg_count);
+            }
+            ir::Instruction::CallIntrinsic {
+                intrinsic,
+                arg_count,
+                destination,
+            } => {
+                self.u8(23);
+                self.str(&intrinsic.to_string());
+                self.usize(*arg_count);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::CallInstance {
+                target,
+                name,
+                arg_count,
+                destination,
+            } => {
+                self.u8(24);
+                self.bool(target.is_some());
+                if let Some(target) = target {
+                    self.literal_or_source(target);
+                }
+                self.str(name);
+                self.usize(*arg_count);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::CallValue {
+                target,
+                arg_count,
+                destination,
+            } => {
+                self.u8(25);
+                self.literal_or_source(target);
+                self.usize(*arg_count);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::NewClosure {
+                function,
+                capture_count,
+                destination,
+            } => {
+                self.u8(26);
+                self.str(function);
+                self.usize(*capture_count);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => {
+                self.u8(27);
+                self.literal_or_source(target);
+                self.literal_or_source(index);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => {
+                self.u8(28);
+                self.literal_or_source(target);
+                self.literal_or_source(index);
+                self.literal_or_source(value);
+            }
+            ir::Instruction::Iterate {
+                source,
+                destination,
+            } => {
+                self.u8(29);
+                self.literal_or_source(source);
+                self.ir_destination(destination);
+            }
+            ir::Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => {
+                self.u8(30);
+                self.literal_or_source(iterator);
+                self.ir_destination(destination);
+                self.label(done_jump_to);
+            }
+            ir::Instruction::EnterTry {
+                handler,
+                destination,
+            } => {
+                self.u8(31);
+                self.ir_destination(destination);
+                self.label(handler);
+            }
+            ir::Instruction::ExitTry => self.u8(32),
+            ir::Instruction::Throw(value) => {
+                self.u8(33);
+                self.literal_or_source(value);
+            }
+        }
+    }
+
+    fn ir_binop(
+        &mut self,
+        opcode: u8,
+        left: &ir::LiteralOrSource,
+        right: &ir::LiteralOrSource,
+        destination: &ir::Destination,
+    ) {
+        self.u8(opcode);
+        self.literal_or_source(left);
+        self.literal_or_source(right);
+        self.ir_destination(destination);
+    }
+
+    fn literal_or_source(&mut self, value: &ir::LiteralOrSource) {
+        match value {
+            ir::LiteralOrSource::Literal(literal) => {
+                self.u8(0);
+                self.literal(literal);
+            }
+            ir::LiteralOrSource::Argument(argument) => {
+                self.u8(1);
+                self.usize(argument.index);
+                self.str(&argument.name);
+            }
+            ir::LiteralOrSource::Variable(variable) => {
+                self.u8(2);
+                self.variable(variable);
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &ir::Literal) {
+        match literal {
+            ir::Literal::Void => self.u8(0),
+            ir::Literal::Integer(value) => {
+                self.u8(1);
+                self.i64(*value);
+            }
+            ir::Literal::Real(value) => {
+                self.u8(2);
+                self.f64(*value);
+            }
+            ir::Literal::Boolean(value) => {
+                self.u8(3);
+                self.bool(*value);
+            }
+            ir::Literal::String(value) => {
+                self.u8(4);
+                self.str(value);
+            }
+        }
+    }
+
+    fn variable(&mut self, variable: &ir::Variable) {
+        self.usize(variable.index);
+        self.str(&variable.name);
+    }
+
+    fn label(&mut self, label: &ir::Label) {
+        self.usize(label.index);
+        self.optional_str(label.name.as_deref());
+    }
+
+    fn ir_destination(&mut self, destination: &ir::Destination) {
+        match destination {
+            ir::Destination::Variable(variable) => {
+                self.u8(0);
+                self.variable(variable);
+            }
+            ir::Destination::Stack => self.u8(1),
+            ir::Destination::Return => self.u8(2),
+        }
+    }
+
+    fn comparison(&mut self, comparison: Comparison) {
+        self.u8(match comparison {
+            Comparison::Equal => 0,
+            Comparison::NotEqual => 1,
+            Comparison::LessThan => 2,
+            Comparison::LessThanOrEqual => 3,
+            Comparison::GreaterThan => 4,
+            Comparison::GreaterThanOrEqual => 5,
+        });
+    }
+
+    fn value_kind(&mut self, kind: &ValueKind) {
+        match kind {
+            ValueKind::Integer => self.u8(0),
+            ValueKind::Real => self.u8(1),
+            ValueKind::Boolean => self.u8(2),
+            ValueKind::Dynamic(name) => {
+                self.u8(3);
+                self.str(name);
+            }
+            ValueKind::Void => self.u8(4),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)] // Most are straight mappings...
+    fn instruction<Env>(
+        &mut self,
+        instruction: &Instruction<Env::Intrinsic>,
+    ) -> Result<(), BytecodeError>
+    where
+        Env: Environment,
+        Env::String: AsRef<str>,
+    {
+        match instruction {
+            Instruction::Add {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(0, left, right, *destination)?,
+            Instruction::Sub {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(1, left, right, *destination)?,
+            Instruction::Multiply {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(2, left, right, *destination)?,
+            Instruction::Divide {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(3, left, right, *destination)?,
+            Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(34, left, right, *destination)?,
+            Instruction::Remainder {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(35, left, right, *destination)?,
+            Instruction::Power {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(36, left, right, *destination)?,
+            Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(4, left, right, *destination)?,
+            Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(5, left, right, *destination)?,
+            Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(6, left, right, *destination)?,
+            Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(7, left, right, *destination)?,
+            Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(8, left, right, *destination)?,
+            Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(9, left, right, *destination)?,
+            Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(10, left, right, *destination)?,
+            Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            } => self.binop::<Env>(11, left, right, *destination)?,
+            Instruction::LogicalNot { value, destination } => {
+                self.u8(12);
+                self.value_or_source::<Env>(value)?;
+                self.destination(*destination);
+            }
+            Instruction::BitwiseNot { value, destination } => {
+                self.u8(13);
+                self.value_or_source::<Env>(value)?;
+                self.destination(*destination);
+            }
+            Instruction::Negate { value, destination } => {
+                self.u8(37);
+                self.value_or_source::<Env>(value)?;
+                self.destination(*destination);
+            }
+            Instruction::Convert {
+                value,
+                kind,
+                destination,
+            } => {
+                self.u8(14);
+                self.value_or_source::<Env>(value)?;
+                self.value_kind(kind);
+                self.destination(*destination);
+            }
+            Instruction::If {
+                condition,
+                false_jump_to,
+            } => {
+                self.u8(15);
+                self.value_or_source::<Env>(condition)?;
+                self.usize(*false_jump_to);
+            }
+            Instruction::JumpTo(target) => {
+                self.u8(16);
+                self.usize(*target);
+            }
+            Instruction::Compare {
+                comparison,
+                left,
+                right,
+                action,
+            } => {
+                self.u8(18);
+                self.comparison(*comparison);
+                self.value_or_source::<Env>(left)?;
+                self.value_or_source::<Env>(right)?;
+                match action {
+                    CompareAction::Store(destination) => {
+                        self.u8(0);
+                        self.destination(*destination);
+                    }
+                    CompareAction::JumpIfFalse(target) => {
+                        self.u8(1);
+                        self.usize(*target);
+                    }
+                }
+            }
+            Instruction::Push(value) => {
+                self.u8(19);
+                self.value_or_source::<Env>(value)?;
+            }
+            Instruction::Load {
+                value,
+                variable_index,
+            } => {
+                self.u8(20);
+                self.value_or_source::<Env>(value)?;
+                self.usize(*variable_index);
+            }
+            Instruction::Return(value) => {
+                self.u8(21);
+                self.bool(value.is_some());
+                if let Some(value) = value {
+                    self.value_or_source::<Env>(value)?;
+                }
+            }
+            Instruction::Call {
+                vtable_index,
+                arg_count,
+                destination,
+            } => {
+                self.u8(22);
+                self.bool(vtable_index.is_some());
+                if let Some(vtable_index) = vtable_index {
+                    self.usize(*vtable_index);
+                }
+                self.usize(*arg_count);
+                self.destination(*destination);
+            }
+            Instruction::TailCall {
+                vtable_index,
+                arg_count,
+            } => {
+                self.u8(38);
+                self.bool(vtable_index.is_some());
+                if let Some(vtable_index) = vtable_index {
+                    self.usize(*vtable_index);
+                }
+                self.usize(*arg_count);
+            }
+            Instruction::CallIntrinsic {
+                intrinsic,
+                arg_count,
+                destination,
+            } => {
+                self.u8(23);
+                self.str(&intrinsic.to_string());
+                self.usize(*arg_count);
+                self.destination(*destination);
+            }
+            Instruction::CallInstance {
+                target,
+                name,
+                arg_count,
+                destination,
+            } => {
+                self.u8(24);
+                self.bool(target.is_some());
+                if let Some(target) = target {
+                    self.value_or_source::<Env>(target)?;
+                }
+                self.str(name);
+                self.usize(*arg_count);
+                self.destination(*destination);
+            }
+            Instruction::CallValue {
+                target,
+                arg_count,
+                destination,
+            } => {
+                self.u8(25);
+                self.value_or_source::<Env>(target)?;
+                self.usize(*arg_count);
+                self.destination(*destination);
+            }
+            Instruction::NewClosure {
+                vtable_index,
+                capture_count,
+                destination,
+            } => {
+                self.u8(26);
+                self.usize(*vtable_index);
+                self.usize(*capture_count);
+                self.destination(*destination);
+            }
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => {
+                self.u8(27);
+                self.value_or_source::<Env>(target)?;
+                self.value_or_source::<Env>(index)?;
+                self.destination(*destination);
+            }
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => {
+                self.u8(28);
+                self.value_or_source::<Env>(target)?;
+                self.value_or_source::<Env>(index)?;
+                self.value_or_source::<Env>(value)?;
+            }
+            Instruction::Iterate {
+                source,
+                destination,
+            } => {
+                self.u8(29);
+                self.value_or_source::<Env>(source)?;
+                self.destination(*destination);
+            }
+            Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => {
+                self.u8(30);
+                self.value_or_source::<Env>(iterator)?;
+                self.destination(*destination);
+                self.usize(*done_jump_to);
+            }
+            Instruction::EnterTry {
+                handler,
+                destination,
+            } => {
+                self.u8(31);
+                self.destination(*destination);
+                self.usize(*handler);
+            }
+            Instruction::ExitTry => self.u8(32),
+            Instruction::Throw(value) => {
+                self.u8(33);
+                self.value_or_source::<Env>(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn binop<Env>(
+        &mut self,
+        opcode: u8,
+        left: &ValueOrSource,
+        right: &ValueOrSource,
+        destination: Destination,
+    ) -> Result<(), BytecodeError>
+    where
+        Env: Environment,
+        Env::String: AsRef<str>,
+    {
+        self.u8(opcode);
+        self.value_or_source::<Env>(left)?;
+        self.value_or_source::<Env>(right)?;
+        self.destination(destination);
+        Ok(())
+    }
+
+    fn value_or_source<Env>(&mut self, value: &ValueOrSource) -> Result<(), BytecodeError>
+    where
+        Env: Environment,
+        Env::String: AsRef<str>,
+    {
+        match value {
+            ValueOrSource::Value(value) => {
+                self.u8(0);
+                self.value::<Env>(value)?;
+            }
+            ValueOrSource::Argument(index) => {
+                self.u8(1);
+                self.usize(*index);
+            }
+            ValueOrSource::Variable(index) => {
+                self.u8(2);
+                self.usize(*index);
+            }
+        }
+        Ok(())
+    }
+
+    fn value<Env>(&mut self, value: &Value) -> Result<(), BytecodeError>
+    where
+        Env: Environment,
+        Env::String: AsRef<str>,
+    {
+        // Values share their encoding with literals.
+        match value {
+            Value::Void => self.literal(&ir::Literal::Void),
+            Value::Integer(value) => self.literal(&ir::Literal::Integer(*value)),
+            Value::Real(value) => self.literal(&ir::Literal::Real(*value)),
+            Value::Boolean(value) => self.literal(&ir::Literal::Boolean(*value)),
+            Value::Dynamic(_) => {
+                let string = value
+                    .as_dynamic::<Env::String>()
+                    .ok_or_else(|| BytecodeError::UnsupportedValue(value.kind()))?;
+                self.u8(4);
+                self.str(string.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    fn destination(&mut self, destination: Destination) {
+        match destination {
+            Destination::Variable(index) => {
+                self.u8(0);
+                self.usize(index);
+            }
+            Destination::Stack => self.u8(1),
+            Destination::Return => self.u8(2),
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], expected_kind: u8) -> Result<Self, BytecodeError> {
+        let bytes = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or(BytecodeError::InvalidHeader)?;
+        let mut decoder = Self { bytes };
+        let version = u16::from_le_bytes(decoder.array()?);
+        if version != VERSION {
+            return Err(BytecodeError::UnsupportedVersion(version));
+        }
+        if decoder.u8()? == expected_kind {
+            Ok(decoder)
+        } else {
+            Err(BytecodeError::UnexpectedArtifact)
+        }
+    }
+
+    fn finish(self) -> Result<(), BytecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(BytecodeError::TrailingBytes)
+        }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], BytecodeError> {
+        if length > self.bytes.len() {
+            return Err(BytecodeError::UnexpectedEof);
+        }
+        let (taken, remaining) = self.bytes.split_at(length);
+        self.bytes = remaining;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn tag(&mut self, kind: &'static str, count: u8) -> Result<u8, BytecodeError> {
+        let tag = self.u8()?;
+        if tag < count {
+            Ok(tag)
+        } else {
+            Err(BytecodeError::InvalidTag { kind, tag })
+        }
+    }
+
+    fn bool(&mut self) -> Result<bool, BytecodeError> {
+        Ok(self.tag("boolean", 2)? == 1)
+    }
+
+    fn u64(&mut self) -> Result<u64, BytecodeError> {
+        let mut value = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(BytecodeError::InvalidNumber);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BytecodeError::InvalidNumber)
+    }
+
+    fn usize(&mut self) -> Result<usize, BytecodeError> {
+        usize::try_from(self.u64()?).map_err(|_| BytecodeError::InvalidNumber)
+    }
+
+    /// Reads the length of a list. Each entry is encoded using at least one
+    /// byte, which allows rejecting lengths that cannot possibly be valid
+    /// before allocating space for them.
+    fn len(&mut self) -> Result<usize, BytecodeError> {
+        let length = self.usize()?;
+        if length <= self.bytes.len() {
+            Ok(length)
+        } else {
+            Err(BytecodeError::UnexpectedEof)
+        }
+    }
+
+    fn i64(&mut self) -> Result<i64, BytecodeError> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, BytecodeError> {
+        Ok(f64::from_bits(u64::from_le_bytes(self.array()?)))
+    }
+
+    fn str(&mut self) -> Result<&'a str, BytecodeError> {
+        let length = self.usize()?;
+        std::str::from_utf8(self.take(length)?).map_err(|_| BytecodeError::InvalidString)
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, BytecodeError> {
+        self.str().map(Symbol::from)
+    }
+
+    fn optional_symbol(&mut self) -> Result<Option<Symbol>, BytecodeError> {
+        if self.bool()? {
+            self.symbol().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn intrinsic<Intrinsic>(&mut self) -> Result<Intrinsic, BytecodeError>
+    where
+        Intrinsic: FromStr,
+    {
+        let name = self.str()?;
+        Intrinsic::from_str(name).map_err(|_| BytecodeError::UnknownIntrinsic(name.to_string()))
+    }
+
+    fn module<Intrinsic>(&mut self, depth: usize) -> Result<ir::Module<Intrinsic>, BytecodeError>
+    where
+        Intrinsic: FromStr,
+    {
+        if depth > MAX_MODULE_DEPTH {
+            return Err(BytecodeError::TooDeeplyNested);
+        }
+
+        let name = self.optional_symbol()?;
+        let function_count = self.len()?;
+        let mut vtable = Vec::with_capacity(function_count);
+        for _ in 0..function_count {
+            vtable.push(self.ir_function()?);
+        }
+        let module_count = self.len()?;
+        let mut modules = Vec::with_capacity(module_count);
+        for _ in 0..module_count {
+            modules.push(self.module(depth + 1)?);
+        }
+        let init = if self.bool()? {
+            Some(self.ir_function()?)
+        } else {
+            None
+        };
+        Ok(ir::Module {
+            name,
+            vtable,
+            modules,
+            init,
+        })
+    }
+
+    fn ir_function<Intrinsic>(&mut self) -> Result<ir::Function<Intrinsic>, BytecodeError>
+    where
+        Intrinsic: FromStr,
+    {
+        let name = self.symbol()?;
+        let argument_count = self.len()?;
+        let mut arguments = Vec::with_capacity(argument_count);
+        for _ in 0..argument_count {
+            arguments.push(self.symbol()?);
+        }
+        let variables = self.usize()?;
+        let instruction_count = self.len()?;
+        let mut code = Vec::with_capacity(instruction_count);
+        for _ in 0..instruction_count {
+            code.push(self.ir_instruction()?);
+        }
+        let debug_info = if self.bool()? {
+            Some(self.debug_info()?)
+        } else {
+            None
+        };
+
+        let function = ir::Function {
+            name,
+            body: ir::CodeBlock {
+                arguments,
+                variables,
+                code,
+                debug_info,
+            },
+        };
+        validate_ir_function(&function)?;
+        Ok(function)
+    }
+
+    fn debug_info(&mut self) -> Result<DebugInfo, BytecodeError> {
+        let source = self.optional_symbol()?;
+        let location_count = self.len()?;
+        let mut locations = Vec::with_capacity(location_count);
+        for _ in 0..location_count {
+            locations.push(if self.bool()? {
+                let start = self.usize()?;
+                let end = self.usize()?;
+                let line = self.usize()?;
+                Some(SourceLocation {
+                    range: start..end,
+                    line,
+                })
+            } else {
+                None
+            });
+        }
+        Ok(DebugInfo { source, locations })
+    }
+
+    #[allow(clippy::too_many_lines)] // Most are straight mappings...
+    fn ir_instruction<Intrinsic>(&mut self) -> Result<ir::Instruction<Intrinsic>, BytecodeError>
+    where
+        Intrinsic: FromStr,
+    {
+        Ok(match self.tag("instruction", 39)? {
+            opcode @ (0..=11 | 34..=36) => {
+                let left = self.literal_or_source()?;
+                let right = self.literal_or_source()?;
+                let destination = self.ir_destination()?;
+                match opcode {
+                    0 => ir::Instruction::Add {
+                        left,
+                        right,
+                        destination,
+                    },
+                    1 => ir::Instruction::Sub {
+                        left,
+                        right,
+                        destination,
+                    },
+                    2 => ir::Instruction::Multiply {
+                        left,
+                        right,
+                        destination,
+                    },
+                    3 => ir::Instruction::Divide {
+                        left,
+                        right,
+                        destination,
+                    },
+                    4 => ir::Instruction::LogicalAnd {
+                        left,
+                        right,
+                        destination,
+                    },
+                    5 => ir::Instruction::LogicalOr {
+                        left,
+                        right,
+                        destination,
+                    },
+                    6 => ir::Instruction::LogicalXor {
+                        left,
+                        right,
+                        destination,
+                    },
+                    7 => ir::Instruction::BitwiseAnd {
+                        left,
+                        right,
+                        destination,
+                    },
+                    8 => ir::Instruction::BitwiseOr {
+                        left,
+                        right,
+                        destination,
+                    },
+                    9 => ir::Instruction::BitwiseXor {
+                        left,
+                        right,
+                        destination,
+                    },
+                    10 => ir::Instruction::ShiftLeft {
+                        left,
+                        right,
+                        destination,
+                    },
+                    11 => ir::Instruction::ShiftRight {
+                        left,
+                        right,
+                        destination,
+                    },
+                    34 => ir::Instruction::IntegerDivide {
+                        left,
+                        right,
+                        destination,
+                    },
+                    35 => ir::Instruction::Remainder {
+                        left,
+                        right,
+                        destination,
+                    },
+                    _ => ir::Instruction::Power {
+                        left,
+                        right,
+                        destination,
+                    },
+                }
+            }
+            12 => ir::Instruction::LogicalNot {
+                value: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+            },
+            13 => ir::Instruction::BitwiseNot {
+                value: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+            },
+            14 => ir::Instruction::Convert {
+                value: self.literal_or_source()?,
+                kind: self.value_kind()?,
+                destination: self.ir_destination()?,
+            },
+            15 => ir::Instruction::If {
+                condition: self.literal_or_source()?,
+                false_jump_to: self.label()?,
+            },
+            16 => ir::Instruction::JumpTo(self.label()?),
+            17 => ir::Instruction::Label(self.label()?),
+            18 => ir::Instruction::Compare {
+                comparison: self.comparison()?,
+                left: self.literal_or_source()?,
+                right: self.literal_or_source()?,
+                action: match self.tag("compare action", 2)? {
+                    0 => ir::CompareAction::Store(self.ir_destination()?),
+                    _ => ir::CompareAction::JumpIfFalse(self.label()?),
+                },
+            },
+            19 => ir::Instruction::Push(self.literal_or_source()?),
+            20 => ir::Instruction::Load {
+                value: self.literal_or_source()?,
+                variable: self.variable()?,
+            },
+            21 => ir::Instruction::Return(if self.bool()? {
+                Some(self.literal_or_source()?)
+            } else {
+                None
+            }),
+            22 => ir::Instruction::Call {
+                function: self.optional_symbol()?,
+                arg_count: self.usize()?,
+                destination: self.ir_destination()?,
+            },
+            23 => ir::Instruction::CallIntrinsic {
+                intrinsic: self.intrinsic()?,
+                arg_count: self.usize()?,
+                destination: self.ir_destination()?,
+            },
+            24 => ir::Instruction::CallInstance {
+                target: if self.bool()? {
+                    Some(self.literal_or_source()?)
+                } else {
+                    None
+                },
+                name: self.symbol()?,
+                arg_count: self.usize()?,
+                destination: self.ir_destination()?,
+            },
+            25 => ir::Instruction::CallValue {
+                target: self.literal_or_source()?,
+                arg_count: self.usize()?,
+                destination: self.ir_destination()?,
+            },
+            26 => ir::Instruction::NewClosure {
+                function: self.symbol()?,
+                capture_count: self.usize()?,
+                destination: self.ir_destination()?,
+            },
+            27 => ir::Instruction::GetIndex {
+                target: self.literal_or_source()?,
+                index: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+            },
+            28 => ir::Instruction::SetIndex {
+                target: self.literal_or_source()?,
+                index: self.literal_or_source()?,
+                value: self.literal_or_source()?,
+            },
+            29 => ir::Instruction::Iterate {
+                source: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+            },
+            30 => ir::Instruction::IterateNext {
+                iterator: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+                done_jump_to: self.label()?,
+            },
+            31 => ir::Instruction::EnterTry {
+                destination: self.ir_destination()?,
+                handler: self.label()?,
+            },
+            32 => ir::Instruction::ExitTry,
+            33 => ir::Instruction::Throw(self.literal_or_source()?),
+            37 => ir::Instruction::Negate {
+                value: self.literal_or_source()?,
+                destination: self.ir_destination()?,
+            },
+            _ => ir::Instruction::TailCall {
+                function: self.optional_symbol()?,
+                arg_count: self.usize()?,
+            },
+        })
+    }
+
+    fn literal_or_source(&mut self) -> Result<ir::LiteralOrSource, BytecodeError> {
+        Ok(match self.tag("value", 3)? {
+            0 => ir::LiteralOrSource::Literal(self.literal()?),
+            1 => ir::LiteralOrSource::Argument(ir::Argument {
+                index: self.usize()?,
+                name: self.symbol()?,
+            }),
+            _ => ir::LiteralOrSource::Variable(self.variable()?),
+        })
+    }
+
+    fn literal(&mut self) -> Result<ir::Literal, BytecodeError> {
+        Ok(match self.tag("literal", 5)? {
+            0 => ir::Literal::Void,
+            1 => ir::Literal::Integer(self.i64()?),
+            2 => ir::Literal::Real(self.f64()?),
+            3 => ir::Literal::Boolean(self.bool()?),
+            _ => ir::Literal::String(self.str()?.to_string()),
+        })
+    }
+
+    fn variable(&mut self) -> Result<ir::Variable, BytecodeError> {
+        Ok(ir::Variable {
+            index: self.usize()?,
+            name: self.symbol()?,
+        })
+    }
+
+    fn label(&mut self) -> Result<ir::Label, BytecodeError> {
+        Ok(ir::Label {
+            index: self.usize()?,
+            name: self.optional_symbol()?,
+        })
+    }
+
+    fn ir_destination(&mut self) -> Result<ir::Destination, BytecodeError> {
+        Ok(match self.tag("destination", 3)? {
+            0 => ir::Destination::Variable(self.variable()?),
+            1 => ir::Destination::Stack,
+            _ => ir::Destination::Return,
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Comparison, BytecodeError> {
+        Ok(match self.tag("comparison", 6)? {
+            0 => Comparison::Equal,
+            1 => Comparison::NotEqual,
+            2 => Comparison::LessThan,
+            3 => Comparison::LessThanOrEqual,
+            4 => Comparison::GreaterThan,
+            _ => Comparison::GreaterThanOrEqual,
+        })
+    }
+
+    fn value_kind(&mut self) -> Result<ValueKind, BytecodeError> {
+        Ok(match self.tag("value kind", 5)? {
+            0 => ValueKind::Integer,
+            1 => ValueKind::Real,
+            2 => ValueKind::Boolean,
+            3 => ValueKind::Dynamic(self.symbol()?),
+            _ => ValueKind::Void,
+        })
+    }
+
+    #[allow(clippy::too_many_lines)] // Most are straight mappings...
+    fn instruction<Env>(&mut self) -> Result<Instruction<Env::Intrinsic>, BytecodeError>
+    where
+        Env: Environment,
+    {
+        Ok(match self.tag("instruction", 39)? {
+            opcode @ (0..=11 | 34..=36) => {
+                let left = self.value_or_source::<Env>()?;
+                let right = self.value_or_source::<Env>()?;
+                let destination = self.destination()?;
+                match opcode {
+                    0 => Instruction::Add {
+                        left,
+                        right,
+                        destination,
+                    },
+                    1 => Instruction::Sub {
+                        left,
+                        right,
+                        destination,
+                    },
+                    2 => Instruction::Multiply {
+                        left,
+                        right,
+                        destination,
+                    },
+                    3 => Instruction::Divide {
+                        left,
+                        right,
+                        destination,
+                    },
+                    4 => Instruction::LogicalAnd {
+                        left,
+                        right,
+                        destination,
+                    },
+                    5 => Instruction::LogicalOr {
+                        left,
+                        right,
+                        destination,
+                    },
+                    6 => Instruction::LogicalXor {
+                        left,
+                        right,
+                        destination,
+                    },
+                    7 => Instruction::BitwiseAnd {
+                        left,
+                        right,
+                        destination,
+                    },
+                    8 => Instruction::BitwiseOr {
+                        left,
+                        right,
+                        destination,
+                    },
+                    9 => Instruction::BitwiseXor {
+                        left,
+                        right,
+                        destination,
+                    },
+                    10 => Instruction::ShiftLeft {
+                        left,
+                        right,
+                        destination,
+                    },
+                    11 => Instruction::ShiftRight {
+                        left,
+                        right,
+                        destination,
+                    },
+                    34 => Instruction::IntegerDivide {
+                        left,
+                        right,
+                        destination,
+                    },
+                    35 => Instruction::Remainder {
+                        left,
+                        right,
+                        destination,
+                    },
+                    _ => Instruction::Power {
+                        left,
+                        right,
+                        destination,
+                    },
+                }
+            }
+            12 => Instruction::LogicalNot {
+                value: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+            },
+            13 => Instruction::BitwiseNot {
+                value: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+            },
+            14 => Instruction::Convert {
+                value: self.value_or_source::<Env>()?,
+                kind: self.value_kind()?,
+                destination: self.destination()?,
+            },
+            15 => Instruction::If {
+                condition: self.value_or_source::<Env>()?,
+                false_jump_to: self.usize()?,
+            },
+            16 => Instruction::JumpTo(self.usize()?),
+            // Labels are removed when linking.
+            17 => {
+                return Err(BytecodeError::InvalidTag {
+                    kind: "instruction",
+                    tag: 17,
+                })
+            }
+            18 => Instruction::Compare {
+                comparison: self.comparison()?,
+                left: self.value_or_source::<Env>()?,
+                right: self.value_or_source::<Env>()?,
+                action: match self.tag("compare action", 2)? {
+                    0 => CompareAction::Store(self.destination()?),
+                    _ => CompareAction::JumpIfFalse(self.usize()?),
+                },
+            },
+            19 => Instruction::Push(self.value_or_source::<Env>()?),
+            20 => Instruction::Load {
+                value: self.value_or_source::<Env>()?,
+                variable_index: self.usize()?,
+            },
+            21 => Instruction::Return(if self.bool()? {
+                Some(self.value_or_source::<Env>()?)
+            } else {
+                None
+            }),
+            22 => Instruction::Call {
+                vtable_index: if self.bool()? {
+                    Some(self.usize()?)
+                } else {
+                    None
+                },
+                arg_count: self.usize()?,
+                destination: self.destination()?,
+            },
+            23 => Instruction::CallIntrinsic {
+                intrinsic: self.intrinsic()?,
+                arg_count: self.usize()?,
+                destination: self.destination()?,
+            },
+            24 => Instruction::CallInstance {
+                target: if self.bool()? {
+                    Some(self.value_or_source::<Env>()?)
+                } else {
+                    None
+                },
+                name: self.symbol()?,
+                arg_count: self.usize()?,
+                destination: self.destination()?,
+            },
+            25 => Instruction::CallValue {
+                target: self.value_or_source::<Env>()?,
+                arg_count: self.usize()?,
+                destination: self.destination()?,
+            },
+            26 => Instruction::NewClosure {
+                vtable_index: self.usize()?,
+                capture_count: self.usize()?,
+                destination: self.destination()?,
+            },
+            27 => Instruction::GetIndex {
+                target: self.value_or_source::<Env>()?,
+                index: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+            },
+            28 => Instruction::SetIndex {
+                target: self.value_or_source::<Env>()?,
+                index: self.value_or_source::<Env>()?,
+                value: self.value_or_source::<Env>()?,
+            },
+            29 => Instruction::Iterate {
+                source: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+            },
+            30 => Instruction::IterateNext {
+                iterator: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+                done_jump_to: self.usize()?,
+            },
+            31 => Instruction::EnterTry {
+                destination: self.destination()?,
+                handler: self.usize()?,
+            },
+            32 => Instruction::ExitTry,
+            33 => Instruction::Throw(self.value_or_source::<Env>()?),
+            37 => Instruction::Negate {
+                value: self.value_or_source::<Env>()?,
+                destination: self.destination()?,
+            },
+            _ => Instruction::TailCall {
+                vtable_index: if self.bool()? {
+                    Some(self.usize()?)
+                } else {
+                    None
+                },
+                arg_count: self.usize()?,
+            },
+        })
+    }
+
+    fn value_or_source<Env>(&mut self) -> Result<ValueOrSource, BytecodeError>
+    where
+        Env: Environment,
+    {
+        Ok(match self.tag("value", 3)? {
+            0 => ValueOrSource::Value(self.literal()?.instantiate::<Env>()),
+            1 => ValueOrSource::Argument(self.usize()?),
+            _ => ValueOrSource::Variable(self.usize()?),
+        })
+    }
+
+    fn destination(&mut self) -> Result<Destination, BytecodeError> {
+        Ok(match self.tag("destination", 3)? {
+            0 => Destination::Variable(self.usize()?),
+            1 => Destination::Stack,
+            _ => Destination::Return,
+        })
+    }
+}
+
+#[allow(clippy::too_many_lines)] // Most are straight mappings...
+fn validate_ir_function<Intrinsic>(
+    function: &ir::Function<Intrinsic>,
+) -> Result<(), BytecodeError> {
+    let body = &function.body;
+    let defined_labels = body
+        .code
+        .iter()
+        .filter_map(|instruction| match instruction {
+            ir::Instruction::Label(label) => Some(label.index),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let check_label = |label: &ir::Label| {
+        if defined_labels.contains(&label.index) {
+            Ok(())
+        } else {
+            Err(BytecodeError::InvalidLabel {
+                function: function.name.clone(),
+                index: label.index,
+            })
+        }
+    };
+    let check_variable = |variable: &ir::Variable| {
+        if variable.index < body.variables {
+            Ok(())
+        } else {
+            Err(BytecodeError::InvalidVariable {
+                function: function.name.clone(),
+                index: variable.index,
+            })
+        }
+    };
+    let check_value = |value: &ir::LiteralOrSource| match value {
+        ir::LiteralOrSource::Literal(_) => Ok(()),
+        ir::LiteralOrSource::Argument(argument) => {
+            if argument.index < body.arguments.len() {
+                Ok(())
+            } else {
+                Err(BytecodeError::InvalidArgument {
+                    function: function.name.clone(),
+                    index: argument.index,
+                })
+            }
+        }
+        ir::LiteralOrSource::Variable(variable) => check_variable(variable),
+    };
+    let check_destination = |destination: &ir::Destination| match destination {
+        ir::Destination::Variable(variable) => check_variable(variable),
+        ir::Destination::Stack | ir::Destination::Return => Ok(()),
+    };
+
+    for instruction in &body.code {
+        match instruction {
+            ir::Instruction::Add {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::Sub {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::Multiply {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::Divide {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::Remainder {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::Power {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            }
+            | ir::Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            } => {
+                check_value(left)?;
+                check_value(right)?;
+                check_destination(destination)?;
+            }
+            ir::Instruction::LogicalNot { value, destination }
+            | ir::Instruction::BitwiseNot { value, destination }
+            | ir::Instruction::Negate { value, destination }
+            | ir::Instruction::Convert {
+                value, destination, ..
+            } => {
+                check_value(value)?;
+                check_destination(destination)?;
+            }
+            ir::Instruction::If {
+                condition,
+                false_jump_to,
+            } => {
+                check_value(condition)?;
+                check_label(false_jump_to)?;
+            }
+            ir::Instruction::JumpTo(label) => check_label(label)?,
+            ir::Instruction::Label(_)
+            | ir::Instruction::ExitTry
+            | ir::Instruction::TailCall { .. } => {}
+            ir::Instruction::Compare {
+                left,
+                right,
+                action,
+                ..
+            } => {
+                check_value(left)?;
+                check_value(right)?;
+                match action {
+                    ir::CompareAction::Store(destination) => check_destination(destination)?,
+                    ir::CompareAction::JumpIfFalse(label) => check_label(label)?,
+                }
+            }
+            ir::Instruction::Push(value) | ir::Instruction::Throw(value) => check_value(value)?,
+            ir::Instruction::Load { value, variable } => {
+                check_value(value)?;
+                check_variable(variable)?;
+            }
+            ir::Instruction::Return(value) => {
+                if let Some(value) = value {
+                    check_value(value)?;
+                }
+            }
+            ir::Instruction::CallInstance {
+                target,
+                destination,
+                ..
+            } => {
+                if let Some(target) = target {
+                    check_value(target)?;
+                }
+                check_destination(destination)?;
+            }
+            ir::Instruction::CallValue {
+                target,
+                destination,
+                ..
+            } => {
+                check_value(target)?;
+                check_destination(destination)?;
+            }
+            ir::Instruction::Call { destination, .. }
+            | ir::Instruction::CallIntrinsic { destination, .. }
+            | ir::Instruction::NewClosure { destination, .. } => {
+                check_destination(destination)?;
+            }
+            ir::Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => {
+                check_value(target)?;
+                check_value(index)?;
+                check_destination(destination)?;
+            }
+            ir::Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => {
+                check_value(target)?;
+                check_value(index)?;
+                check_value(value)?;
+            }
+            ir::Instruction::Iterate {
+                source,
+                destination,
+            } => {
+                check_value(source)?;
+                check_destination(destination)?;
+            }
+            ir::Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => {
+                check_value(iterator)?;
+                check_destination(destination)?;
+                check_label(done_jump_to)?;
+            }
+            ir::Instruction::EnterTry {
+                handler,
+                destination,
+            } => {
+                check_destination(destination)?;
+                check_label(handler)?;
+            }
+        }
+    }
+
+    if let Some(debug_info) = &body.debug_info {
+        let valid = debug_info.locations.len() == body.code.len()
+            && debug_info
+                .locations
+                .iter()
+                .flatten()
+                .all(|location| location.range.start <= location.range.end);
+        if !valid {
+            return Err(BytecodeError::InvalidDebugInfo(function.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_lines)] // Most are straight mappings...
+fn validate_function<Intrinsic>(function: &Function<Intrinsic>) -> Result<(), BytecodeError> {
+    let check_variable = |index: usize| {
+        if index < function.variable_count {
+            Ok(())
+        } else {
+            Err(BytecodeError::InvalidVariable {
+                function: function.name.clone(),
+                index,
+            })
+        }
+    };
+    let check_value = |value: &ValueOrSource| match value {
+        ValueOrSource::Value(_) => Ok(()),
+        ValueOrSource::Argument(index) => {
+            if *index < function.arg_count {
+                Ok(())
+            } else {
+                Err(BytecodeError::InvalidArgument {
+                    function: function.name.clone(),
+                    index: *index,
+                })
+            }
+        }
+        ValueOrSource::Variable(index) => check_variable(*index),
+    };
+    let check_destination = |destination: &Destination| match destination {
+        Destination::Variable(index) => check_variable(*index),
+        Destination::Stack | Destination::Return => Ok(()),
+    };
+
+    // Jumping beyond the end of a function returns from it, so jump targets
+    // are always valid.
+    for instruction in &function.code {
+        match instruction {
+            Instruction::Add {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Sub {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Multiply {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Divide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Remainder {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Power {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Compare {
+                left,
+                right,
+                action: CompareAction::Store(destination),
+                ..
+            } => {
+                check_value(left)?;
+                check_value(right)?;
+                check_destination(destination)?;
+            }
+            Instruction::Compare { left, right, .. } => {
+                check_value(left)?;
+                check_value(right)?;
+            }
+            Instruction::LogicalNot { value, destination }
+            | Instruction::BitwiseNot { value, destination }
+            | Instruction::Negate { value, destination }
+            | Instruction::Convert {
+                value, destination, ..
+            }
+            | Instruction::Iterate {
+                source: value,
+                destination,
+            }
+            | Instruction::IterateNext {
+                iterator: value,
+                destination,
+                ..
+            } => {
+                check_value(value)?;
+                check_destination(destination)?;
+            }
+            Instruction::If { condition, .. } => check_value(condition)?,
+            Instruction::JumpTo(_) | Instruction::ExitTry | Instruction::TailCall { .. } => {}
+            Instruction::Push(value) | Instruction::Throw(value) => check_value(value)?,
+            Instruction::EnterTry { destination, .. } => check_destination(destination)?,
+            Instruction::Load {
+                value,
+                variable_index,
+            } => {
+                check_value(value)?;
+                check_variable(*variable_index)?;
+            }
+            Instruction::Return(value) => {
+                if let Some(value) = value {
+                    check_value(value)?;
+                }
+            }
+            Instruction::CallInstance {
+                target,
+                destination,
+                ..
+            } => {
+                if let Some(target) = target {
+                    check_value(target)?;
+                }
+                check_destination(destination)?;
+            }
+            Instruction::CallValue {
+                target,
+                destination,
+                ..
+            } => {
+                check_value(target)?;
+                check_destination(destination)?;
+            }
+            Instruction::Call { destination, .. }
+            | Instruction::CallIntrinsic { destination, .. }
+            | Instruction::NewClosure { destination, .. } => {
+                check_destination(destination)?;
+            }
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => {
+                check_value(target)?;
+                check_value(index)?;
+                check_destination(destination)?;
+            }
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => {
+                check_value(target)?;
+                check_value(index)?;
+                check_value(value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
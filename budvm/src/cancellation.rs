@@ -0,0 +1,47 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle that stops a [`VirtualMachine`](crate::VirtualMachine) that is
+/// executing, even from another thread.
+///
+/// Once [`cancel()`](Self::cancel) has been called, any virtual machine using
+/// this token raises [`FaultKind::Cancelled`](crate::FaultKind::Cancelled)
+/// shortly before executing its next instruction. The token stays cancelled
+/// until [`reset()`](Self::reset) is called.
+///
+/// This type can be cheaply cloned, and all clones share the same state.
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Returns a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that all virtual machines using this token stop executing.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`cancel()`](Self::cancel) has been called since this
+    /// token was created or last reset.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears a previous cancellation, allowing execution to continue.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{symbol::Symbol, DynamicValue, Value};
+
+/// A function value, optionally with values captured from the scope it was
+/// created in.
+///
+/// Closures are created with [`Instruction::NewClosure`](crate::Instruction::NewClosure)
+/// and called with [`Instruction::CallValue`](crate::Instruction::CallValue).
+/// When called, the captured values are passed to the function after the
+/// arguments provided by the caller.
+///
+/// A closure refers to its function by vtable index, which means it is only
+/// meaningful within the virtual machine that created it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Closure {
+    vtable_index: usize,
+    captured: Vec<Value>,
+}
+
+impl Closure {
+    /// Returns a closure that calls the function at `vtable_index`, passing
+    /// `captured` after the arguments of each call.
+    #[must_use]
+    pub fn new(vtable_index: usize, captured: Vec<Value>) -> Self {
+        Self {
+            vtable_index,
+            captured,
+        }
+    }
+
+    /// Returns the vtable index of the function this closure calls.
+    #[must_use]
+    pub const fn vtable_index(&self) -> usize {
+        self.vtable_index
+    }
+
+    /// Returns the values captured by this closure.
+    #[must_use]
+    pub fn captured(&self) -> &[Value] {
+        &self.captured
+    }
+}
+
+impl DynamicValue for Closure {
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    fn kind(&self) -> Symbol {
+        Symbol::from("Function")
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        let other = other.as_dynamic::<Self>()?;
+        Some(self == other)
+    }
+
+    fn hash<H>(&self, state: &mut H) -> bool
+    where
+        H: std::hash::Hasher,
+    {
+        std::hash::Hash::hash(&self.vtable_index, state);
+        for value in &self.captured {
+            if !value.try_hash(state) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
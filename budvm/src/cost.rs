@@ -0,0 +1,162 @@
+use std::cell::Cell;
+
+use crate::Instruction;
+
+/// The amount of budget charged for executing each kind of [`Instruction`].
+///
+/// A [`Budgeted`](crate::Budgeted) environment charges the cost of each
+/// instruction before it is executed. The default costs charge one unit for
+/// every instruction.
+///
+/// Instructions that perform an unbounded amount of work, such as calling a
+/// native function or a method on a [`DynamicValue`](crate::DynamicValue),
+/// can charge additional costs while executing using [`charge()`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[must_use]
+pub struct InstructionCosts {
+    /// The cost of [`Instruction::Add`], [`Instruction::Sub`],
+    /// [`Instruction::Multiply`], [`Instruction::Divide`],
+    /// [`Instruction::IntegerDivide`], [`Instruction::Remainder`],
+    /// [`Instruction::Power`], and [`Instruction::Negate`].
+    pub arithmetic: usize,
+    /// The cost of the logical, bitwise, and shift instructions.
+    pub logic: usize,
+    /// The cost of [`Instruction::Compare`].
+    pub compare: usize,
+    /// The cost of [`Instruction::Push`], [`Instruction::Load`], and
+    /// [`Instruction::Convert`].
+    pub data: usize,
+    /// The cost of [`Instruction::If`], [`Instruction::JumpTo`],
+    /// [`Instruction::Return`], [`Instruction::EnterTry`],
+    /// [`Instruction::ExitTry`], and [`Instruction::Throw`]. This cost is also
+    /// charged when a function returns by reaching the end of its
+    /// instructions.
+    pub control_flow: usize,
+    /// The cost of [`Instruction::Call`], [`Instruction::TailCall`],
+    /// [`Instruction::CallValue`], and [`Instruction::NewClosure`].
+    pub call: usize,
+    /// The cost of [`Instruction::CallInstance`].
+    pub call_instance: usize,
+    /// The cost of [`Instruction::CallIntrinsic`].
+    pub call_intrinsic: usize,
+    /// The cost of [`Instruction::GetIndex`] and [`Instruction::SetIndex`].
+    pub index: usize,
+    /// The cost of [`Instruction::Iterate`] and [`Instruction::IterateNext`].
+    pub iterate: usize,
+}
+
+impl Default for InstructionCosts {
+    fn default() -> Self {
+        Self::uniform(1)
+    }
+}
+
+impl InstructionCosts {
+    /// Returns a table that charges `cost` for every instruction.
+    pub const fn uniform(cost: usize) -> Self {
+        Self {
+            arithmetic: cost,
+            logic: cost,
+            compare: cost,
+            data: cost,
+            control_flow: cost,
+            call: cost,
+            call_instance: cost,
+            call_intrinsic: cost,
+            index: cost,
+            iterate: cost,
+        }
+    }
+
+    /// Returns the cost of executing `instruction`.
+    #[must_use]
+    pub const fn cost_of<Intrinsic>(&self, instruction: &Instruction<Intrinsic>) -> usize {
+        match instruction {
+            Instruction::Add { .. }
+            | Instruction::Sub { .. }
+            | Instruction::Multiply { .. }
+            | Instruction::Divide { .. }
+            | Instruction::IntegerDivide { .. }
+            | Instruction::Remainder { .. }
+            | Instruction::Power { .. }
+            | Instruction::Negate { .. } => self.arithmetic,
+            Instruction::LogicalAnd { .. }
+            | Instruction::LogicalOr { .. }
+            | Instruction::LogicalXor { .. }
+            | Instruction::LogicalNot { .. }
+            | Instruction::BitwiseAnd { .. }
+            | Instruction::BitwiseOr { .. }
+            | Instruction::BitwiseXor { .. }
+            | Instruction::BitwiseNot { .. }
+            | Instruction::ShiftLeft { .. }
+            | Instruction::ShiftRight { .. } => self.logic,
+            Instruction::Compare { .. } => self.compare,
+            Instruction::Push(_) | Instruction::Load { .. } | Instruction::Convert { .. } => {
+                self.data
+            }
+            Instruction::If { .. }
+            | Instruction::JumpTo(_)
+            | Instruction::Return(_)
+            | Instruction::EnterTry { .. }
+            | Instruction::ExitTry
+            | Instruction::Throw(_) => self.control_flow,
+            Instruction::Call { .. }
+            | Instruction::TailCall { .. }
+            | Instruction::CallValue { .. }
+            | Instruction::NewClosure { .. } => self.call,
+            Instruction::CallInstance { .. } => self.call_instance,
+            Instruction::CallIntrinsic { .. } => self.call_intrinsic,
+            Instruction::GetIndex { .. } | Instruction::SetIndex { .. } => self.index,
+            Instruction::Iterate { .. } | Instruction::IterateNext { .. } => self.iterate,
+        }
+    }
+}
+
+thread_local! {
+    static PENDING_COST: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Charges `cost` against the budget of the virtual machine executing on the
+/// current thread. If no virtual machine is executing, this function does
+/// nothing.
+///
+/// Native functions and [`DynamicValue`](crate::DynamicValue)s should call
+/// this in proportion to the work they perform. The total charged during an
+/// instruction is passed to
+/// [`Environment::consume()`](crate::Environment::consume) once the
+/// instruction finishes.
+pub fn charge(cost: usize) {
+    PENDING_COST.with(|pending| {
+        if let Some(total) = pending.get() {
+            pending.set(Some(total.saturating_add(cost)));
+        }
+    });
+}
+
+/// Returns the total charged using [`charge()`] since the last call.
+pub(crate) fn take_charges() -> usize {
+    PENDING_COST.with(|pending| match pending.get() {
+        Some(total) => {
+            pending.set(Some(0));
+            total
+        }
+        None => 0,
+    })
+}
+
+/// Starts collecting charges on the current thread until the returned guard
+/// is dropped.
+pub(crate) fn activate() -> ActiveCharges {
+    ActiveCharges(PENDING_COST.with(|pending| pending.replace(Some(0))))
+}
+
+/// Restores the previously collected charges when dropped.
+#[must_use]
+pub(crate) struct ActiveCharges(Option<usize>);
+
+impl Drop for ActiveCharges {
+    fn drop(&mut self) {
+        let previous = self.0;
+        PENDING_COST.with(|pending| pending.set(previous));
+    }
+}
@@ -0,0 +1,34 @@
+use std::ops::Range;
+
+use crate::symbol::Symbol;
+
+/// A location within source code.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SourceLocation {
+    /// The range of bytes within the source.
+    pub range: Range<usize>,
+    /// The line `range` starts on. The first line of a source is line 1.
+    pub line: usize,
+}
+
+/// A debug table that maps a function's instructions back to the source code
+/// it was compiled from.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct DebugInfo {
+    /// The name of the source the function was compiled from, such as a file
+    /// path.
+    pub source: Option<Symbol>,
+    /// The location of each instruction, indexed by instruction index.
+    pub locations: Vec<Option<SourceLocation>>,
+}
+
+impl DebugInfo {
+    /// Returns the location of the instruction at `instruction_index`, if
+    /// known.
+    #[must_use]
+    pub fn location(&self, instruction_index: usize) -> Option<&SourceLocation> {
+        self.locations
+            .get(instruction_index)
+            .and_then(Option::as_ref)
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Write},
+    hash::{Hash, Hasher},
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use crate::{
+    big_integer::{allocate_limbs, charge_pow, divided, exponent, ordered},
+    cost::charge,
+    BigInteger, DynamicValue, FaultKind, ParseNumberError, Symbol, Value,
+};
+
+/// The number of digits after the decimal point that division results are
+/// rounded to.
+const DIVISION_SCALE: u32 = 28;
+
+/// An arbitrary-precision decimal number.
+///
+/// In Bud, this type's kind is `Decimal`. Literals are written with a `d`
+/// suffix (`12.50d`), and other values can be converted using `as Decimal`.
+///
+/// Addition, subtraction, and multiplication are exact. The result keeps the
+/// number of digits after the decimal point of its operands, which means
+/// `1.10d + 2d` is displayed as `3.10`. Division results are rounded half
+/// away from zero to 28 digits after the decimal point, with trailing zeroes
+/// removed. Dividing by zero raises [`FaultKind::DivideByZero`].
+///
+/// Arithmetic with an [`Value::Integer`], a [`BigInteger`], or a
+/// [`Value::Real`] produces a `Decimal`. Reals are converted using their
+/// shortest representation, which means `0.1` is converted to exactly `0.1`.
+///
+/// Like other numbers, a `Decimal` is only equal to and comparable with other
+/// `Decimal`s. Two decimals are equal if they represent the same number,
+/// regardless of how many digits follow the decimal point.
+#[derive(Debug, Clone, Default)]
+pub struct Decimal {
+    mantissa: BigInteger,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Returns the decimal equal to `mantissa` divided by 10 raised to
+    /// `scale`.
+    #[must_use]
+    pub const fn new(mantissa: BigInteger, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    /// Returns the digits of this decimal, without the decimal point.
+    #[must_use]
+    pub const fn mantissa(&self) -> &BigInteger {
+        &self.mantissa
+    }
+
+    /// Returns the number of digits after the decimal point.
+    #[must_use]
+    pub const fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Returns true if this value is 0.
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    /// Returns this value with trailing zeroes after the decimal point
+    /// removed.
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let ten = BigInteger::from(10);
+        let mut normalized = self.clone();
+        while normalized.scale > 0 {
+            match normalized.mantissa.div_rem(&ten) {
+                Some((quotient, remainder)) if remainder.is_zero() => {
+                    normalized.mantissa = quotient;
+                    normalized.scale -= 1;
+                }
+                _ => break,
+            }
+        }
+        normalized
+    }
+
+    /// Returns this value with the fractional digits removed.
+    #[must_use]
+    pub fn trunc(&self) -> BigInteger {
+        self.mantissa
+            .checked_div(&BigInteger::from(1).scale_up(self.scale))
+            .unwrap_or_default()
+    }
+
+    /// Returns the closest `f64` to this value.
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the shortest decimal that converts back to `value`, or None if
+    /// `value` is not finite.
+    #[must_use]
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if value.is_finite() {
+            // Display never uses exponential notation for `f64`.
+            value.to_string().parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the mantissas of `self` and `other` rescaled to the same
+    /// scale, along with the scale.
+    fn aligned(&self, other: &Self) -> (BigInteger, BigInteger, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            self.mantissa.scale_up(scale - self.scale),
+            other.mantissa.scale_up(scale - other.scale),
+            scale,
+        )
+    }
+
+    /// Divides this value by `other`, rounding the result to 28 digits after
+    /// the decimal point. Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        let scale = DIVISION_SCALE.max(self.scale).max(other.scale);
+        let numerator = self.mantissa.scale_up(scale + other.scale - self.scale);
+        let (quotient, remainder) = numerator.div_rem(&other.mantissa)?;
+        // Round half away from zero.
+        let quotient = if &remainder.abs() + &remainder.abs() >= other.mantissa.abs() {
+            let one = BigInteger::from(1);
+            if numerator.is_negative() == other.mantissa.is_negative() {
+                &quotient + &one
+            } else {
+                &quotient - &one
+            }
+        } else {
+            quotient
+        };
+        Some(Self::new(quotient, scale).normalized())
+    }
+
+    /// Divides this value by `other`, rounding the result towards negative
+    /// infinity to an integer. Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_div_floor(&self, other: &Self) -> Option<Self> {
+        let (left, right, _) = self.aligned(other);
+        left.checked_div_floor(&right).map(Self::from)
+    }
+
+    /// Returns the remainder of [`Decimal::checked_div_floor()`], which has
+    /// the same sign as `other`. Returns None if `other` is 0.
+    #[must_use]
+    pub fn checked_rem_floor(&self, other: &Self) -> Option<Self> {
+        let (left, right, scale) = self.aligned(other);
+        left.checked_rem_floor(&right)
+            .map(|remainder| Self::new(remainder, scale))
+    }
+
+    /// Raises this value to the power of `exponent`. Negative exponents
+    /// divide 1 by the result. Returns None if the result can't be
+    /// represented.
+    #[must_use]
+    pub fn checked_pow(&self, exponent: i64) -> Option<Self> {
+        let power = u32::try_from(exponent.unsigned_abs()).ok()?;
+        let result = Self::new(self.mantissa.pow(power), self.scale.checked_mul(power)?);
+        if exponent < 0 {
+            Self::from(1).checked_div(&result)
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Returns upper bounds of the number of limbs in the mantissas of this
+    /// value and `other` once rescaled for an operation. Division rescales the
+    /// mantissas further than any other operation.
+    fn rescaled_limbs(&self, other: &Self) -> (usize, usize) {
+        let scale = DIVISION_SCALE.max(self.scale).max(other.scale);
+        (
+            self.mantissa
+                .scaled_limbs(scale.saturating_add(other.scale) - self.scale),
+            other
+                .mantissa
+                .scaled_limbs(scale.saturating_add(self.scale) - other.scale),
+        )
+    }
+
+    /// Charges the budget for adding or subtracting `other`.
+    fn charge_linear(&self, other: &Self) {
+        let (left, right) = self.rescaled_limbs(other);
+        charge(left.saturating_add(right));
+    }
+
+    /// Charges the budget for multiplying or dividing by `other`.
+    fn charge_quadratic(&self, other: &Self) {
+        let (left, right) = self.rescaled_limbs(other);
+        charge(left.saturating_mul(right));
+    }
+
+    fn binary_operation(
+        &self,
+        other: &Value,
+        is_reverse: bool,
+        op: impl FnOnce(&Self, &Self) -> Result<Value, FaultKind>,
+    ) -> Result<Option<Value>, FaultKind> {
+        let other = match other {
+            Value::Integer(other) => Self::from(*other),
+            Value::Real(real) => Self::from_f64(*real).ok_or_else(|| {
+                FaultKind::invalid_type(
+                    "`@received-value` can't be represented as a Decimal",
+                    other.clone(),
+                )
+            })?,
+            other => {
+                if let Some(other) = other.as_dynamic::<Self>() {
+                    other.clone()
+                } else if let Some(other) = other.as_dynamic::<BigInteger>() {
+                    Self::from(other.clone())
+                } else {
+                    return Ok(None);
+                }
+            }
+        };
+        let (self_limbs, other_limbs) = self.rescaled_limbs(&other);
+        allocate_limbs(self_limbs.saturating_add(other_limbs))?;
+        let (left, right) = ordered(self, &other, is_reverse);
+        op(left, right).map(Some)
+    }
+}
+
+impl From<BigInteger> for Decimal {
+    fn from(value: BigInteger) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self::from(BigInteger::from(value))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Equal decimals can have different scales.
+        let normalized = self.normalized();
+        Hash::hash(&normalized.mantissa, state);
+        Hash::hash(&normalized.scale, state);
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (left, right, _) = self.aligned(other);
+        left.cmp(&right)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Self) -> Decimal {
+        let (left, right, scale) = self.aligned(other);
+        Decimal::new(&left + &right, scale)
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Self) -> Decimal {
+        let (left, right, scale) = self.aligned(other);
+        Decimal::new(&left - &right, scale)
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Self) -> Decimal {
+        Decimal::new(&self.mantissa * &other.mantissa, self.scale + other.scale)
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-&self.mantissa, self.scale)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.abs().to_string();
+        let scale = usize::try_from(self.scale).unwrap_or(usize::MAX);
+        if self.mantissa.is_negative() {
+            f.write_char('-')?;
+        }
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+
+        if digits.len() > scale {
+            let (whole, fraction) = digits.split_at(digits.len() - scale);
+            write!(f, "{whole}.{fraction}")
+        } else {
+            f.write_str("0.")?;
+            for _ in digits.len()..scale {
+                f.write_char('0')?;
+            }
+            f.write_str(&digits)
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        // Digits are required on both sides of the decimal point.
+        if whole.strip_prefix('-').unwrap_or(whole).is_empty()
+            || s.ends_with('.')
+            || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+        {
+            return Err(ParseNumberError);
+        }
+        let mantissa = format!("{whole}{fraction}").parse::<BigInteger>()?;
+        let scale = u32::try_from(fraction.len()).map_err(|_| ParseNumberError)?;
+        Ok(Self::new(mantissa, scale))
+    }
+}
+
+impl DynamicValue for Decimal {
+    fn is_truthy(&self) -> bool {
+        !self.is_zero()
+    }
+
+    fn kind(&self) -> Symbol {
+        Symbol::from("Decimal")
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        self.trunc().to_i64()
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Some(self.to_f64())
+    }
+
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        match kind.as_str() {
+            "String" => Some(Value::dynamic(self.to_string())),
+            "BigInteger" => Some(Value::dynamic(self.trunc())),
+            _ => None,
+        }
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        other.as_dynamic::<Self>().map(|other| self == other)
+    }
+
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        other.as_dynamic::<Self>().map(|other| self.cmp(other))
+    }
+
+    fn checked_add(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_linear(right);
+            Ok(Value::dynamic(left + right))
+        })
+    }
+
+    fn checked_sub(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_linear(right);
+            Ok(Value::dynamic(left - right))
+        })
+    }
+
+    fn checked_mul(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_quadratic(right);
+            Ok(Value::dynamic(left * right))
+        })
+    }
+
+    fn checked_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_quadratic(right);
+            divided(left.checked_div(right), left)
+        })
+    }
+
+    fn checked_int_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_quadratic(right);
+            divided(left.checked_div_floor(right), left)
+        })
+    }
+
+    fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |left, right| {
+            left.charge_quadratic(right);
+            divided(left.checked_rem_floor(right), left)
+        })
+    }
+
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.binary_operation(other, is_reverse, |base, power| {
+            let power = power.normalized();
+            if power.scale > 0 {
+                return Err(FaultKind::ValueOutOfRange("exponent"));
+            }
+            let magnitude = exponent(&power.mantissa.abs())?;
+            charge_pow(&base.mantissa, magnitude)?;
+            let magnitude = i64::from(magnitude);
+            let power = if power.mantissa.is_negative() {
+                -magnitude
+            } else {
+                magnitude
+            };
+            base.checked_pow(power)
+                .map(Value::dynamic)
+                .ok_or(FaultKind::ValueOutOfRange("exponent"))
+        })
+    }
+
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        charge(self.mantissa.scaled_limbs(0));
+        allocate_limbs(self.mantissa.scaled_limbs(0))?;
+        Ok(Some(Value::dynamic(-self)))
+    }
+
+    fn to_source(&self) -> Option<String> {
+        Some(format!("{self}d"))
+    }
+
+    fn hash<H>(&self, state: &mut H) -> bool
+    where
+        H: Hasher,
+    {
+        Hash::hash(self, state);
+        true
+    }
+}
+
+#[test]
+fn arithmetic() {
+    let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+    assert_eq!((&decimal("1.10") + &decimal("2")).to_string(), "3.10");
+    assert_eq!((&decimal("0.1") + &decimal("0.2")).to_string(), "0.3");
+    assert_eq!((&decimal("1.5") - &decimal("2.25")).to_string(), "-0.75");
+    assert_eq!((&decimal("-0.05") * &decimal("0.5")).to_string(), "-0.025");
+    assert_eq!(
+        decimal("1").checked_div(&decimal("3")).unwrap().to_string(),
+        "0.3333333333333333333333333333"
+    );
+    assert_eq!(
+        decimal("-2")
+            .checked_div(&decimal("3"))
+            .unwrap()
+            .to_string(),
+        "-0.6666666666666666666666666667"
+    );
+    assert_eq!(
+        decimal("10.00")
+            .checked_div(&decimal("4"))
+            .unwrap()
+            .to_string(),
+        "2.5"
+    );
+    assert_eq!(decimal("1").checked_div(&decimal("0.00")), None);
+    assert_eq!(
+        decimal("-7.5")
+            .checked_div_floor(&decimal("2"))
+            .unwrap()
+            .to_string(),
+        "-4"
+    );
+    assert_eq!(
+        decimal("-7.5")
+            .checked_rem_floor(&decimal("2"))
+            .unwrap()
+            .to_string(),
+        "0.5"
+    );
+    assert_eq!(decimal("1.5").checked_pow(2).unwrap().to_string(), "2.25");
+    assert_eq!(decimal("2").checked_pow(-2).unwrap().to_string(), "0.25");
+    assert_eq!(decimal("1.50"), decimal("1.5"));
+    assert!(decimal("-1.5") < decimal("-1.25"));
+    assert_eq!(decimal("-12.75").trunc(), BigInteger::from(-12));
+    assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+    assert_eq!(Decimal::from_f64(f64::NAN), None);
+    assert_eq!(decimal("-0.5").to_f64().to_string(), "-0.5");
+    assert!("1.".parse::<Decimal>().is_err());
+    assert!(".5".parse::<Decimal>().is_err());
+    assert!("1.-5".parse::<Decimal>().is_err());
+}
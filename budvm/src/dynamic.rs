@@ -32,6 +32,15 @@ pub trait DynamicValue: Send + Sync + Debug + 'static {
         None
     }
 
+    /// Returns this value as an `f64`, if possible.
+    ///
+    /// Implementing this function enables this type to be converted to a
+    /// `Real` using `as Real`.
+    #[must_use]
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
     /// Converts this value to the given kind, if possible.
     ///
     /// If this function returns None, the virtual machine will return a fault
@@ -116,6 +125,76 @@ pub trait DynamicValue: Send + Sync + Debug + 'static {
         Ok(None)
     }
 
+    /// Attempts to compute the result of dividing self and other, rounding
+    /// towards negative infinity.
+    ///
+    /// If `is_reverse` is false, the operation being requested is `self` div
+    /// `other`. If `is_reverse` is true, the operation being requested is
+    /// `other` div `self`.
+    #[allow(unused_variables)]
+    fn checked_int_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        Ok(None)
+    }
+
+    /// Attempts to compute the remainder of dividing self and other.
+    ///
+    /// If `is_reverse` is false, the operation being requested is `self` %
+    /// `other`. If `is_reverse` is true, the operation being requested is
+    /// `other` % `self`.
+    #[allow(unused_variables)]
+    fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        Ok(None)
+    }
+
+    /// Attempts to compute the result of raising self to the power of other.
+    ///
+    /// If `is_reverse` is false, the operation being requested is `self` **
+    /// `other`. If `is_reverse` is true, the operation being requested is
+    /// `other` ** `self`.
+    #[allow(unused_variables)]
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        Ok(None)
+    }
+
+    /// Attempts to compute the negation of self.
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        Ok(None)
+    }
+
+    /// Returns the value stored at `index`.
+    ///
+    /// Implementing this function enables this type to be read using the index
+    /// operator, such as `value[index]`.
+    #[allow(unused_variables)]
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        Err(FaultKind::NotIndexable(ValueKind::Dynamic(self.kind())))
+    }
+
+    /// Stores `value` at `index`.
+    ///
+    /// Implementing this function enables this type to be assigned to using
+    /// the index operator, such as `value[index] := 1`.
+    #[allow(unused_variables)]
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        Err(FaultKind::NotIndexable(ValueKind::Dynamic(self.kind())))
+    }
+
+    /// Returns an iterator over the contents of this value.
+    ///
+    /// Implementing this function enables this type to be used in loops, such
+    /// as `loop for item in value`. The returned value must implement
+    /// [`DynamicValue::next_item()`]. [`ValueIterator`](crate::ValueIterator)
+    /// can be used to wrap any Rust iterator.
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        Err(FaultKind::NotIterable(ValueKind::Dynamic(self.kind())))
+    }
+
+    /// Advances this iterator, returning the next value. Returns `None` when
+    /// no values remain.
+    fn next_item(&self) -> Result<Option<Value>, FaultKind> {
+        Err(FaultKind::NotIterable(ValueKind::Dynamic(self.kind())))
+    }
+
     /// Calls a function by `name` with `args`.
     #[allow(unused_variables)]
     fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
@@ -265,6 +344,12 @@ impl Dynamic {
         self.0.as_i64()
     }
 
+    /// Returns the result of [`DynamicValue::as_f64()`] for the wrapped value.
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        self.0.as_f64()
+    }
+
     /// Returns the result of [`DynamicValue::convert()`] for the wrapped value.
     #[must_use]
     pub fn convert(&self, kind: &Symbol) -> Option<Value> {
@@ -316,6 +401,57 @@ impl Dynamic {
         self.0.checked_div(other, is_reverse)
     }
 
+    /// Returns the result of [`DynamicValue::checked_int_div()`] for the
+    /// wrapped value.
+    pub fn checked_int_div(
+        &self,
+        other: &Value,
+        is_reverse: bool,
+    ) -> Result<Option<Value>, FaultKind> {
+        self.0.checked_int_div(other, is_reverse)
+    }
+
+    /// Returns the result of [`DynamicValue::checked_rem()`] for the wrapped
+    /// value.
+    pub fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.0.checked_rem(other, is_reverse)
+    }
+
+    /// Returns the result of [`DynamicValue::checked_pow()`] for the wrapped
+    /// value.
+    pub fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.0.checked_pow(other, is_reverse)
+    }
+
+    /// Returns the result of [`DynamicValue::checked_neg()`] for the wrapped
+    /// value.
+    pub fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        self.0.checked_neg()
+    }
+
+    /// Returns the result of [`DynamicValue::get_index()`] for the wrapped
+    /// value.
+    pub fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        self.0.get_index(index)
+    }
+
+    /// Invokes [`DynamicValue::set_index()`] with the given parameters.
+    pub fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        self.0.set_index(index, value)
+    }
+
+    /// Returns the result of [`DynamicValue::iterate()`] for the wrapped
+    /// value.
+    pub fn iterate(&self) -> Result<Value, FaultKind> {
+        self.0.iterate()
+    }
+
+    /// Returns the result of [`DynamicValue::next_item()`] for the wrapped
+    /// value.
+    pub fn next_item(&self) -> Result<Option<Value>, FaultKind> {
+        self.0.next_item()
+    }
+
     /// Invokes [`DynamicValue::call`] with the given parameters.
     pub fn call(&mut self, name: &Symbol, args: PoppedValues<'_>) -> Result<Value, FaultKind> {
         self.0.call(name, args)
@@ -352,6 +488,7 @@ trait UnboxableDynamicValue: Debug + Send + Sync {
 
     fn is_truthy(&self) -> bool;
     fn as_i64(&self) -> Option<i64>;
+    fn as_f64(&self) -> Option<f64>;
     fn convert(&self, kind: &Symbol) -> Option<Value>;
     fn kind(&self) -> Symbol;
     fn partial_eq(&self, other: &Value) -> Option<bool>;
@@ -360,9 +497,17 @@ trait UnboxableDynamicValue: Debug + Send + Sync {
     fn checked_sub(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
     fn checked_mul(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
     fn checked_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
+    fn checked_int_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
+    fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind>;
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind>;
     fn to_source(&self) -> Option<String>;
     fn hash(&self, state: &mut dyn Hasher) -> bool;
     fn call(&self, name: &Symbol, arguments: PoppedValues<'_>) -> Result<Value, FaultKind>;
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind>;
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind>;
+    fn iterate(&self) -> Result<Value, FaultKind>;
+    fn next_item(&self) -> Result<Option<Value>, FaultKind>;
 }
 
 #[derive(Clone)]
@@ -404,6 +549,10 @@ where
     fn as_i64(&self) -> Option<i64> {
         self.value().as_i64()
     }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.value().as_f64()
+    }
     fn convert(&self, kind: &Symbol) -> Option<Value> {
         self.value().convert(kind)
     }
@@ -440,6 +589,22 @@ where
         self.value().checked_div(other, is_reverse)
     }
 
+    fn checked_int_div(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.value().checked_int_div(other, is_reverse)
+    }
+
+    fn checked_rem(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.value().checked_rem(other, is_reverse)
+    }
+
+    fn checked_pow(&self, other: &Value, is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        self.value().checked_pow(other, is_reverse)
+    }
+
+    fn checked_neg(&self) -> Result<Option<Value>, FaultKind> {
+        self.value().checked_neg()
+    }
+
     fn hash(&self, mut state: &mut dyn Hasher) -> bool {
         self.value().hash(&mut state)
     }
@@ -451,6 +616,22 @@ where
     fn call(&self, name: &Symbol, mut arguments: PoppedValues<'_>) -> Result<Value, FaultKind> {
         self.value().call(name, &mut arguments)
     }
+
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        self.value().get_index(index)
+    }
+
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        self.value().set_index(index, value)
+    }
+
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        self.value().iterate()
+    }
+
+    fn next_item(&self) -> Result<Option<Value>, FaultKind> {
+        self.value().next_item()
+    }
 }
 
 impl<T> Debug for DynamicValueData<T>
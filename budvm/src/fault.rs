@@ -0,0 +1,66 @@
+use crate::{symbol::Symbol, DynamicValue, FaultKind, PoppedValues, Value, ValueKind};
+
+/// A fault that was caught by an exception handler.
+///
+/// When a fault other than [`FaultKind::Thrown`] is caught by
+/// [`Instruction::EnterTry`](crate::Instruction::EnterTry), it is converted to
+/// this type so that the code handling it can inspect what went wrong.
+///
+/// Throwing this value raises the original fault again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultValue(FaultKind);
+
+impl FaultValue {
+    /// Returns a new instance wrapping `kind`.
+    #[must_use]
+    pub const fn new(kind: FaultKind) -> Self {
+        Self(kind)
+    }
+
+    /// Returns the fault that was caught.
+    #[must_use]
+    pub const fn kind(&self) -> &FaultKind {
+        &self.0
+    }
+
+    /// Returns the contained fault.
+    #[must_use]
+    pub fn into_kind(self) -> FaultKind {
+        self.0
+    }
+}
+
+impl DynamicValue for FaultValue {
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    fn kind(&self) -> Symbol {
+        Symbol::from("Fault")
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        other.as_dynamic::<Self>().map(|other| self == other)
+    }
+
+    fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
+        match name.as_str() {
+            "kind" => {
+                args.verify_empty()?;
+                Ok(Value::dynamic(String::from(self.0.name())))
+            }
+            "message" => {
+                args.verify_empty()?;
+                Ok(Value::dynamic(self.0.to_string()))
+            }
+            _ => Err(FaultKind::UnknownFunction {
+                kind: ValueKind::Dynamic(DynamicValue::kind(self)),
+                name: name.clone(),
+            }),
+        }
+    }
+
+    fn to_source(&self) -> Option<String> {
+        None
+    }
+}
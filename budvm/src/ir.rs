@@ -4,7 +4,7 @@
 //! variables, labels, and function calls.
 use std::{
     borrow::{Borrow, BorrowMut},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     fmt::{Display, Write},
     marker::PhantomData,
@@ -13,17 +13,20 @@ use std::{
 };
 
 use crate::{
-    symbol::Symbol, Comparison, Environment, Error, FromStack, Noop, StringLiteralDisplay, Value,
-    ValueKind, ValueOrSource, VirtualMachine,
+    bytecode::{self, BytecodeError},
+    symbol::Symbol,
+    Comparison, DebugInfo, Environment, Error, FromStack, Noop, SourceLocation,
+    StringLiteralDisplay, Value, ValueKind, ValueOrSource, VirtualMachine,
 };
 
 pub mod asm;
+pub mod optimizer;
 
 /// A label that can be jumped to.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Label {
     pub(crate) index: usize,
-    name: Option<Symbol>,
+    pub(crate) name: Option<Symbol>,
 }
 
 impl Display for Label {
@@ -39,8 +42,8 @@ impl Display for Label {
 /// A reference to a local variable.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Variable {
-    index: usize,
-    name: Symbol,
+    pub(crate) index: usize,
+    pub(crate) name: Symbol,
 }
 
 impl Variable {
@@ -49,6 +52,12 @@ impl Variable {
     pub fn index(&self) -> usize {
         self.index
     }
+
+    /// Returns the name of this variable.
+    #[must_use]
+    pub const fn name(&self) -> &Symbol {
+        &self.name
+    }
 }
 
 impl Display for Variable {
@@ -60,8 +69,8 @@ impl Display for Variable {
 /// A reference to an argument passed to a function.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Argument {
-    index: usize,
-    name: Symbol,
+    pub(crate) index: usize,
+    pub(crate) name: Symbol,
 }
 
 impl Argument {
@@ -129,6 +138,46 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result to be stored in.
         destination: Destination,
     },
+    /// Divides `left` by `right`, rounding the result towards negative
+    /// infinity, and places the result in `destination`.
+    ///
+    /// If this operation causes an overflow or `right` is an integer zero,
+    /// [`Value::Void`] will be stored in the destination instead.
+    IntegerDivide {
+        /// The left hand side of the operation.
+        left: LiteralOrSource,
+        /// The right hand side of the operation.
+        right: LiteralOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
+    /// Places the remainder of dividing `left` by `right` in `destination`.
+    /// The remainder has the same sign as `right`, which makes this the
+    /// counterpart of [`Instruction::IntegerDivide`].
+    ///
+    /// If `right` is an integer zero, [`Value::Void`] will be stored in the
+    /// destination instead.
+    Remainder {
+        /// The left hand side of the operation.
+        left: LiteralOrSource,
+        /// The right hand side of the operation.
+        right: LiteralOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
+    /// Raises `left` to the power of `right` and places the result in
+    /// `destination`.
+    ///
+    /// If this operation causes an overflow or `right` is a negative integer,
+    /// [`Value::Void`] will be stored in the destination instead.
+    Power {
+        /// The left hand side of the operation.
+        left: LiteralOrSource,
+        /// The right hand side of the operation.
+        right: LiteralOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
     /// Performs a logical and of `left` and `right` and places the result in
     /// `destination`. This operation always results in a [`Value::Boolean`].
     ///
@@ -253,6 +302,16 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result to be stored in.
         destination: Destination,
     },
+    /// Negates `value`, storing the result in `destination`.
+    ///
+    /// If this operation causes an overflow, [`Value::Void`] will be stored in
+    /// the destination instead.
+    Negate {
+        /// The value to negate.
+        value: LiteralOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
     /// Converts a value to another type, storing the result in `destination`.
     ///
     /// If `value` cannot be converted, a fault will be returned.
@@ -360,6 +419,21 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result of the call.
         destination: Destination,
     },
+    /// Calls a function in place of the current function, returning the
+    /// called function's result from the current frame.
+    ///
+    /// The arguments on the stack replace the current frame's arguments and
+    /// variables, allowing recursion in tail position to execute without
+    /// growing the call stack.
+    TailCall {
+        /// The name of the function to call. If None, the current function is
+        /// called recursively.
+        function: Option<Symbol>,
+
+        /// The number of arguments on the stack that should be used as
+        /// arguments to this call.
+        arg_count: usize,
+    },
     /// Calls an intrinsic runtime function.
     ///
     /// When calling a function, values on the stack are "passed" to the
@@ -403,6 +477,85 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result of the call.
         destination: Destination,
     },
+    /// Calls a function value, such as a [`Closure`](crate::Closure).
+    ///
+    /// When calling a function, values on the stack are "passed" to the
+    /// function being pushed to the stack before calling the function. To
+    /// ensure the correct number of arguments are taken even when variable
+    /// argument lists are supported, the number of arguments is passed and
+    /// controls the baseline of the stack.
+    ///
+    /// Upon returning from a function call, the arguments will no longer be on
+    /// the stack. The value returned from the function (or [`Value::Void`] if
+    /// no value was returned) will be placed in `destination`.
+    CallValue {
+        /// The function value to call.
+        target: LiteralOrSource,
+        /// The number of arguments on the stack that should be used as
+        /// arguments to this call.
+        arg_count: usize,
+        /// The destination for the result of the call.
+        destination: Destination,
+    },
+    /// Creates a [`Closure`](crate::Closure) for `function`, capturing
+    /// `capture_count` values from the stack, and stores it in `destination`.
+    NewClosure {
+        /// The name of the function the closure calls.
+        function: Symbol,
+        /// The number of values on the stack to capture.
+        capture_count: usize,
+        /// The destination for the created closure.
+        destination: Destination,
+    },
+    /// Reads the value stored at `index` within `target` and places it in
+    /// `destination`.
+    GetIndex {
+        /// The value being indexed.
+        target: LiteralOrSource,
+        /// The index to read.
+        index: LiteralOrSource,
+        /// The destination for the value read.
+        destination: Destination,
+    },
+    /// Stores `value` at `index` within `target`.
+    SetIndex {
+        /// The value being indexed.
+        target: LiteralOrSource,
+        /// The index to store `value` at.
+        index: LiteralOrSource,
+        /// The value to store.
+        value: LiteralOrSource,
+    },
+    /// Creates an iterator over `source` and places it in `destination`.
+    Iterate {
+        /// The value to iterate over.
+        source: LiteralOrSource,
+        /// The destination for the created iterator.
+        destination: Destination,
+    },
+    /// Advances `iterator`, storing the produced value in `destination`. If
+    /// the iterator has no more values, jumps to `done_jump_to`.
+    IterateNext {
+        /// The iterator to advance.
+        iterator: LiteralOrSource,
+        /// The destination for the produced value.
+        destination: Destination,
+        /// The label to jump to when the iterator is finished.
+        done_jump_to: Label,
+    },
+    /// Registers an exception handler that jumps to `handler` after storing
+    /// the caught value in `destination`. The handler remains active until
+    /// [`Instruction::ExitTry`] is executed.
+    EnterTry {
+        /// The label to jump to when a fault is caught.
+        handler: Label,
+        /// The destination for the caught value.
+        destination: Destination,
+    },
+    /// Removes the most recently registered exception handler.
+    ExitTry,
+    /// Raises a fault containing the provided value.
+    Throw(LiteralOrSource),
 }
 
 impl<Intrinsic> Display for Instruction<Intrinsic>
@@ -432,6 +585,21 @@ where
                 right,
                 destination,
             } => write!(f, "div {left} {right} {destination}"),
+            Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            } => write!(f, "idiv {left} {right} {destination}"),
+            Instruction::Remainder {
+                left,
+                right,
+                destination,
+            } => write!(f, "rem {left} {right} {destination}"),
+            Instruction::Power {
+                left,
+                right,
+                destination,
+            } => write!(f, "pow {left} {right} {destination}"),
             Instruction::LogicalAnd {
                 left,
                 right,
@@ -478,6 +646,9 @@ where
             Instruction::BitwiseNot { value, destination } => {
                 write!(f, "bitnot {value} {destination}")
             }
+            Instruction::Negate { value, destination } => {
+                write!(f, "neg {value} {destination}")
+            }
             Instruction::Convert {
                 value,
                 kind,
@@ -517,6 +688,16 @@ where
                     write!(f, "recurse {arg_count} {destination}")
                 }
             }
+            Instruction::TailCall {
+                function,
+                arg_count,
+            } => {
+                if let Some(function) = function {
+                    write!(f, "tailcall {function} {arg_count}")
+                } else {
+                    write!(f, "tailrecurse {arg_count}")
+                }
+            }
             Instruction::CallIntrinsic {
                 intrinsic,
                 arg_count,
@@ -536,6 +717,41 @@ where
                     write!(f, "invoke $ {name} {arg_count} {destination}")
                 }
             }
+            Instruction::CallValue {
+                target,
+                arg_count,
+                destination,
+            } => write!(f, "callvalue {target} {arg_count} {destination}"),
+            Instruction::NewClosure {
+                function,
+                capture_count,
+                destination,
+            } => write!(f, "closure {function} {capture_count} {destination}"),
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => write!(f, "getindex {target} {index} {destination}"),
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => write!(f, "setindex {target} {index} {value}"),
+            Instruction::Iterate {
+                source,
+                destination,
+            } => write!(f, "iterate {source} {destination}"),
+            Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => write!(f, "iternext {iterator} {destination} {done_jump_to}"),
+            Instruction::EnterTry {
+                handler,
+                destination,
+            } => write!(f, "try {destination} {handler}"),
+            Instruction::ExitTry => f.write_str("endtry"),
+            Instruction::Throw(value) => write!(f, "throw {value}"),
         }
     }
 }
@@ -776,7 +992,15 @@ pub struct CodeBlockBuilder<Intrinsic> {
     temporary_variables: usize,
     scope: HashMap<Symbol, ScopeSymbol>,
     loops: LoopLabels,
+    try_depth: usize,
     variables: HashMap<Symbol, Variable>,
+    variable_count: usize,
+    free_variables: Vec<usize>,
+    block_scopes: Vec<Vec<(Symbol, Option<ScopeSymbol>)>>,
+    out_of_scope: HashSet<Symbol>,
+    source: Option<Symbol>,
+    location: Option<SourceLocation>,
+    locations: Vec<Option<SourceLocation>>,
 }
 
 impl<Intrinsic> Default for CodeBlockBuilder<Intrinsic> {
@@ -789,7 +1013,15 @@ impl<Intrinsic> Default for CodeBlockBuilder<Intrinsic> {
             temporary_variables: 0,
             scope: HashMap::default(),
             loops: LoopLabels::default(),
+            try_depth: 0,
             variables: HashMap::default(),
+            variable_count: 0,
+            free_variables: Vec::default(),
+            block_scopes: Vec::default(),
+            out_of_scope: HashSet::default(),
+            source: None,
+            location: None,
+            locations: Vec::default(),
         }
     }
 }
@@ -816,6 +1048,24 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
         self.args.iter().find(|arg| &arg.name == name).cloned()
     }
 
+    /// Registers `name` as a function that can be referenced by this code
+    /// block. Arguments and variables with the same name take precedence.
+    pub fn add_function(&mut self, name: impl Into<Symbol>) {
+        let name = name.into();
+        self.scope
+            .entry(name.clone())
+            .or_insert(ScopeSymbol::Function(name));
+    }
+
+    /// Returns an iterator over the functions that can be referenced by this
+    /// code block.
+    pub fn functions(&self) -> impl Iterator<Item = &Symbol> + '_ {
+        self.scope.values().filter_map(|symbol| match symbol {
+            ScopeSymbol::Function(name) => Some(name),
+            ScopeSymbol::Argument(_) | ScopeSymbol::Variable(_) => None,
+        })
+    }
+
     /// Adds a new symbol to this code block.
     fn add_symbol(&mut self, symbol: impl Into<Symbol>, value: ScopeSymbol) {
         self.scope.insert(symbol.into(), value);
@@ -847,6 +1097,30 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
     /// Push an instruction.
     pub fn push(&mut self, operation: Instruction<Intrinsic>) {
         self.ops.push(operation);
+        self.locations.push(self.location.clone());
+    }
+
+    /// Sets the name of the source this code block is being compiled from.
+    pub fn set_source(&mut self, source: Option<Symbol>) {
+        self.source = source;
+    }
+
+    /// Returns the name of the source this code block is being compiled from.
+    #[must_use]
+    pub const fn source(&self) -> Option<&Symbol> {
+        self.source.as_ref()
+    }
+
+    /// Sets the source location of the instructions pushed after this call.
+    /// Returns the previous location, allowing it to be restored.
+    pub fn set_location(&mut self, location: Option<SourceLocation>) -> Option<SourceLocation> {
+        std::mem::replace(&mut self.location, location)
+    }
+
+    /// Returns the source location of the instructions being pushed.
+    #[must_use]
+    pub const fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
     }
 
     /// Label the next instruction as `label`.
@@ -867,6 +1141,30 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
         self.loops.find(name)
     }
 
+    /// Registers an exception handler that jumps to `handler`, storing the
+    /// caught value in `destination`.
+    pub fn enter_try(&mut self, handler: Label, destination: Destination) {
+        self.try_depth += 1;
+        self.push(Instruction::EnterTry {
+            handler,
+            destination,
+        });
+    }
+
+    /// Removes the exception handler most recently registered with
+    /// [`Self::enter_try()`].
+    pub fn exit_try(&mut self) {
+        self.try_depth = self.try_depth.saturating_sub(1);
+        self.push(Instruction::ExitTry);
+    }
+
+    /// Returns the number of exception handlers registered at the current
+    /// location.
+    #[must_use]
+    pub const fn try_depth(&self) -> usize {
+        self.try_depth
+    }
+
     /// Adds the appropriate instruction to store `value` into `destination`.
     pub fn store_into_destination(&mut self, value: LiteralOrSource, destination: Destination) {
         match destination {
@@ -901,6 +1199,7 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
                 );
                 Ok(())
             }
+            _ if self.is_out_of_scope(symbol) => Err(LinkError::OutOfScope(symbol.clone())),
             _ => Err(LinkError::UndefinedIdentifier(symbol.clone())),
         }
     }
@@ -908,47 +1207,144 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
     /// Looks up an existing location for a variable with the provided `name`.
     /// If an existing location is not found, new space will be allocated for
     /// it and returned.
+    ///
+    /// Variables allocated while a block scope is active are only visible
+    /// until [`Self::end_scope()`] is called.
     pub fn variable_index_from_name(&mut self, name: &Symbol) -> Variable {
-        let new_index = self.variables.len();
-        let variable = self
-            .variables
-            .entry(name.clone())
-            .or_insert_with(|| Variable {
-                index: new_index,
+        if let Some(variable) = self.variables.get(name) {
+            return variable.clone();
+        }
+
+        self.declare_variable(name)
+    }
+
+    /// Allocates new space for a variable with the provided `name`, even if a
+    /// variable with the same name is already visible.
+    ///
+    /// When declared while a block scope is active, any symbol with the same
+    /// name is visible again once [`Self::end_scope()`] is called.
+    pub fn declare_variable(&mut self, name: &Symbol) -> Variable {
+        let variable = if let Some(index) = self.free_variables.pop() {
+            let variable = Variable {
+                index,
                 name: name.clone(),
-            })
-            .clone();
-        if variable.index == new_index {
-            self.add_symbol(name.clone(), ScopeSymbol::Variable(variable.clone()));
+            };
+            // The slot still contains the value of the variable that went
+            // out of scope.
+            self.push(Instruction::Load {
+                value: LiteralOrSource::Literal(Literal::Void),
+                variable: variable.clone(),
+            });
+            variable
+        } else {
+            self.allocate_variable(name.clone())
+        };
+        self.variables.insert(name.clone(), variable.clone());
+        let previous = self
+            .scope
+            .insert(name.clone(), ScopeSymbol::Variable(variable.clone()));
+        if let Some(block) = self.block_scopes.last_mut() {
+            block.push((name.clone(), previous));
         }
+        self.out_of_scope.remove(name);
         variable
     }
 
+    fn allocate_variable(&mut self, name: Symbol) -> Variable {
+        let index = self.variable_count;
+        self.variable_count += 1;
+        Variable { index, name }
+    }
+
     /// Creates a new temporary variable.
     ///
     /// Internally this simply uses a counter to create a new variable each time
-    /// this is called named `$1`, `$2`, and so on.
+    /// this is called named `$1`, `$2`, and so on. Temporary variables are
+    /// never scoped to a block.
     pub fn new_temporary_variable(&mut self) -> Variable {
         self.temporary_variables += 1;
-        let variable = self.variable_index_from_name(&Symbol::from(
+        let variable = self.allocate_variable(Symbol::from(
             format!("${}", self.temporary_variables).as_str(),
         ));
+        self.variables
+            .insert(variable.name.clone(), variable.clone());
+        self.add_symbol(
+            variable.name.clone(),
+            ScopeSymbol::Variable(variable.clone()),
+        );
         variable
     }
 
+    /// Begins a block scope. Variables introduced until the matching call to
+    /// [`Self::end_scope()`] are only visible within the block.
+    pub fn begin_scope(&mut self) {
+        self.block_scopes.push(Vec::new());
+    }
+
+    /// Ends the block scope most recently started with
+    /// [`Self::begin_scope()`]. The variables introduced within the block are
+    /// no longer visible, and their storage will be reused by variables
+    /// introduced later.
+    pub fn end_scope(&mut self) {
+        let introduced = self.block_scopes.pop().unwrap_or_default();
+        for (name, previous) in introduced.into_iter().rev() {
+            if let Some(variable) = self.variables.remove(&name) {
+                self.free_variables.push(variable.index);
+            }
+            if let Some(ScopeSymbol::Variable(shadowed)) = &previous {
+                self.variables.insert(name.clone(), shadowed.clone());
+            }
+            if let Some(previous) = previous {
+                self.scope.insert(name, previous);
+            } else {
+                self.scope.remove(&name);
+                self.out_of_scope.insert(name);
+            }
+        }
+    }
+
+    /// Returns true if `name` is a variable whose block scope has ended, and
+    /// no other symbol with the same name is visible.
+    #[must_use]
+    pub fn is_out_of_scope(&self, name: &Symbol) -> bool {
+        self.out_of_scope.contains(name) && !self.scope.contains_key(name)
+    }
+
+    /// Returns the storage of variables whose block scope has ended and that
+    /// hasn't been reused. Each variable is named `$unusedN`, where `N` is its
+    /// index.
+    pub fn unused_variables(&self) -> impl Iterator<Item = Variable> + '_ {
+        self.free_variables.iter().map(|&index| Variable {
+            index,
+            name: Symbol::from(format!("$unused{index}").as_str()),
+        })
+    }
+
     /// Returns the completed code block.
     #[must_use]
     pub fn finish(self) -> CodeBlock<Intrinsic> {
+        let debug_info = if self.source.is_some() || self.locations.iter().any(Option::is_some) {
+            Some(DebugInfo {
+                source: self.source,
+                locations: self.locations,
+            })
+        } else {
+            None
+        };
         CodeBlock {
             arguments: self.args.into_iter().map(|arg| arg.name).collect(),
-            variables: self.variables.len(),
+            variables: self.variable_count,
             code: self.ops,
+            debug_info,
         }
     }
 
     /// Begins a loop with the given `name`. The result of the loop will be
     /// stored in `result`. If the loop does not return a result, the
     /// destination will be untouched.
+    ///
+    /// The loop is a block scope: variables introduced within it are only
+    /// visible until the returned [`LoopScope`] is dropped.
     pub fn begin_loop(
         &mut self,
         name: Option<Symbol>,
@@ -956,11 +1352,13 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
     ) -> LoopScope<'_, Self, Intrinsic> {
         let break_label = self.new_label();
         let continue_label = self.new_label();
+        self.begin_scope();
         self.loops.begin(LoopInfo {
             name,
             break_label: break_label.clone(),
             continue_label: continue_label.clone(),
             loop_result: result,
+            try_depth: self.try_depth,
         });
         LoopScope {
             owner: self,
@@ -970,7 +1368,7 @@ impl<Intrinsic> CodeBlockBuilder<Intrinsic> {
         }
     }
 
-    /// Returns the collection of known variables.
+    /// Returns the collection of variables that are currently visible.
     #[must_use]
     pub fn variables(&self) -> &HashMap<Symbol, Variable> {
         &self.variables
@@ -1053,6 +1451,7 @@ where
 {
     fn drop(&mut self) {
         self.loops.exit_block();
+        self.end_scope();
     }
 }
 
@@ -1093,6 +1492,9 @@ pub struct LoopInfo {
     pub continue_label: Label,
     /// The desination to store the loops result into.
     pub loop_result: Destination,
+    /// The number of exception handlers registered when the loop began.
+    /// Jumping out of the loop must remove any handlers registered since.
+    pub try_depth: usize,
 }
 
 /// A block of intermediate instructions.
@@ -1104,6 +1506,9 @@ pub struct CodeBlock<Intrinsic> {
     pub variables: usize,
     /// The list of instructions.
     pub code: Vec<Instruction<Intrinsic>>,
+    /// The debug table for this block, indexed by the instructions in
+    /// [`Self::code`].
+    pub debug_info: Option<DebugInfo>,
 }
 
 impl<Intrinsic> CodeBlock<Intrinsic> {
@@ -1141,6 +1546,31 @@ impl<Intrinsic> CodeBlock<Intrinsic> {
                 code: instructions,
             })
     }
+
+    /// Optimizes this code block using the passes for `level`.
+    ///
+    /// See [`optimizer`] for a description of each optimization.
+    pub fn optimize(&mut self, level: optimizer::OptimizationLevel) {
+        optimizer::Optimizer::for_level(level).optimize(self);
+    }
+
+    /// Returns the debug table for the linked form of this code block.
+    #[must_use]
+    pub fn linked_debug_info(&self) -> Option<DebugInfo> {
+        let debug_info = self.debug_info.as_ref()?;
+        // Labels are removed when linking.
+        let locations = self
+            .code
+            .iter()
+            .zip(&debug_info.locations)
+            .filter(|(op, _)| !matches!(op, Instruction::Label(_)))
+            .map(|(_, location)| location.clone())
+            .collect();
+        Some(DebugInfo {
+            source: debug_info.source.clone(),
+            locations,
+        })
+    }
 }
 
 impl<Intrinsic> Display for CodeBlock<Intrinsic>
@@ -1207,6 +1637,33 @@ where
             right: right.instantiate::<S::Environment>(),
             destination: destination.into(),
         },
+        Instruction::IntegerDivide {
+            left,
+            right,
+            destination,
+        } => crate::Instruction::IntegerDivide {
+            left: left.instantiate::<S::Environment>(),
+            right: right.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
+        Instruction::Remainder {
+            left,
+            right,
+            destination,
+        } => crate::Instruction::Remainder {
+            left: left.instantiate::<S::Environment>(),
+            right: right.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
+        Instruction::Power {
+            left,
+            right,
+            destination,
+        } => crate::Instruction::Power {
+            left: left.instantiate::<S::Environment>(),
+            right: right.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
         Instruction::LogicalOr {
             left,
             right,
@@ -1287,6 +1744,10 @@ where
             value: value.instantiate::<S::Environment>(),
             destination: destination.into(),
         },
+        Instruction::Negate { value, destination } => crate::Instruction::Negate {
+            value: value.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
         Instruction::Convert {
             value,
             kind,
@@ -1347,6 +1808,23 @@ where
                 destination: destination.into(),
             }
         }
+        Instruction::TailCall {
+            function,
+            arg_count,
+        } => {
+            let vtable_index = function
+                .as_ref()
+                .map(|symbol| {
+                    scope
+                        .resolve_function_vtable_index(symbol)
+                        .ok_or_else(|| LinkError::UndefinedFunction(symbol.clone()))
+                })
+                .transpose()?;
+            crate::Instruction::TailCall {
+                vtable_index,
+                arg_count: *arg_count,
+            }
+        }
         Instruction::CallInstance {
             target,
             name,
@@ -1369,6 +1847,71 @@ where
             arg_count: *arg_count,
             destination: destination.into(),
         },
+        Instruction::CallValue {
+            target,
+            arg_count,
+            destination,
+        } => crate::Instruction::CallValue {
+            target: target.instantiate::<S::Environment>(),
+            arg_count: *arg_count,
+            destination: destination.into(),
+        },
+        Instruction::NewClosure {
+            function,
+            capture_count,
+            destination,
+        } => crate::Instruction::NewClosure {
+            vtable_index: scope
+                .resolve_function_vtable_index(function)
+                .ok_or_else(|| LinkError::UndefinedFunction(function.clone()))?,
+            capture_count: *capture_count,
+            destination: destination.into(),
+        },
+        Instruction::GetIndex {
+            target,
+            index,
+            destination,
+        } => crate::Instruction::GetIndex {
+            target: target.instantiate::<S::Environment>(),
+            index: index.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
+        Instruction::SetIndex {
+            target,
+            index,
+            value,
+        } => crate::Instruction::SetIndex {
+            target: target.instantiate::<S::Environment>(),
+            index: index.instantiate::<S::Environment>(),
+            value: value.instantiate::<S::Environment>(),
+        },
+        Instruction::Iterate {
+            source,
+            destination,
+        } => crate::Instruction::Iterate {
+            source: source.instantiate::<S::Environment>(),
+            destination: destination.into(),
+        },
+        Instruction::IterateNext {
+            iterator,
+            destination,
+            done_jump_to,
+        } => crate::Instruction::IterateNext {
+            iterator: iterator.instantiate::<S::Environment>(),
+            destination: destination.into(),
+            done_jump_to: labels[done_jump_to.index].expect("label not inserted"),
+        },
+        Instruction::EnterTry {
+            handler,
+            destination,
+        } => crate::Instruction::EnterTry {
+            handler: labels[handler.index].expect("label not inserted"),
+            destination: destination.into(),
+        },
+        Instruction::ExitTry => crate::Instruction::ExitTry,
+        Instruction::Throw(value) => {
+            crate::Instruction::Throw(value.instantiate::<S::Environment>())
+        }
     }))
 }
 
@@ -1390,10 +1933,28 @@ pub trait Scope {
         function: crate::Function<<Self::Environment as Environment>::Intrinsic>,
     ) -> Option<usize>;
 
+    /// Returns the vtable index that the next function passed to
+    /// [`define_function()`](Self::define_function) will be assigned, if it is
+    /// known in advance.
+    ///
+    /// When known, [`Module::link_into()`] allows functions to call functions
+    /// defined later in the same module. The default implementation returns
+    /// `None`.
+    fn next_vtable_index(&self) -> Option<usize> {
+        None
+    }
+
     /// Defines a persistent variable.
     ///
     /// This is used to enable interactive sessions.
     fn define_persistent_variable(&mut self, name: Symbol, variable: Variable);
+
+    /// Associates `debug_info` with the function at `vtable_index`.
+    ///
+    /// The default implementation discards the debug information.
+    fn define_debug_info(&mut self, vtable_index: usize, debug_info: DebugInfo) {
+        let _ = (vtable_index, debug_info);
+    }
 }
 
 impl Scope for () {
@@ -1488,6 +2049,9 @@ where
         let vtable_index = scope
             .define_function(function)
             .ok_or(LinkError::InvalidScopeOperation)?;
+        if let Some(debug_info) = self.body.linked_debug_info() {
+            scope.define_debug_info(vtable_index, debug_info);
+        }
         Ok(vtable_index)
     }
 }
@@ -1512,9 +2076,14 @@ where
 /// A collection of functions and modules.
 #[derive(Debug, PartialEq)]
 pub struct Module<Intrinsic> {
+    /// The name of this module. Submodules must be named, as their functions
+    /// are linked using names qualified by the module path (`module.function`).
+    pub name: Option<Symbol>,
     /// A list of functions defined in the module.
     pub vtable: Vec<Function<Intrinsic>>,
     /// A list of submodules.
+    ///
+    /// The initialization functions of submodules are not executed.
     pub modules: Vec<Module<Intrinsic>>,
     /// The initialization function of this module, if any.
     pub init: Option<Function<Intrinsic>>,
@@ -1523,6 +2092,7 @@ pub struct Module<Intrinsic> {
 impl<Intrinsic> Default for Module<Intrinsic> {
     fn default() -> Self {
         Self {
+            name: None,
             vtable: Vec::default(),
             modules: Vec::default(),
             init: None,
@@ -1541,36 +2111,124 @@ where
         init: Option<Function<Intrinsic>>,
     ) -> Self {
         Self {
+            name: None,
             vtable,
             modules,
             init,
         }
     }
 
+    /// Sets the name of this module and returns self. This is a builder-style
+    /// function.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<Symbol>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
     /// Returns a module parsed from Bud Assembly (`budasm`).
     pub fn from_asm(assembly: &str) -> Result<Self, asm::AsmError> {
         asm::Parser::parse(assembly)
     }
 
-    /// Runs all code in this unit in the passed context.
-    pub fn load_into<'a, Output: FromStack, Env>(
+    /// Returns this module encoded as [bytecode](crate::bytecode).
+    #[must_use]
+    pub fn to_bytecode(&self) -> Vec<u8> {
+        bytecode::encode_module(self)
+    }
+
+    /// Returns a module decoded from [bytecode](crate::bytecode) produced by
+    /// [`Module::to_bytecode()`].
+    ///
+    /// The bytecode is fully validated before being returned.
+    pub fn from_bytecode(bytes: &[u8]) -> Result<Self, BytecodeError> {
+        bytecode::decode_module(bytes)
+    }
+
+    /// Optimizes every function in this module and its submodules using the
+    /// passes for `level`.
+    pub fn optimize(&mut self, level: optimizer::OptimizationLevel) {
+        optimizer::Optimizer::for_level(level).optimize_module(self);
+    }
+
+    /// Links all functions in this module and its submodules into `scope`.
+    ///
+    /// Submodules are linked first. Their functions are registered using
+    /// names qualified by their module path, such as `outer.inner.function`.
+    /// When linking a function within a submodule, names are resolved
+    /// starting with the innermost module and ending with `scope` itself.
+    ///
+    /// If [`Scope::next_vtable_index()`] is known, functions can call any
+    /// function in the module, including those defined after them.
+    pub fn link_into<S, E>(&self, scope: &mut S) -> Result<(), LinkError>
+    where
+        S: Scope<Environment = E>,
+        E: Environment<Intrinsic = Intrinsic>,
+    {
+        let mut scope = ModuleScope {
+            scope,
+            path: Vec::new(),
+            reserved: HashMap::new(),
+            next_index: None,
+        };
+        // When the indexes functions will be assigned are known, every
+        // function in the module can be resolved before it is linked.
+        if let Some(next_index) = scope.scope.next_vtable_index() {
+            let mut index = next_index;
+            self.reserve_functions(&mut scope, &mut index)?;
+            scope.next_index = Some(next_index);
+        }
+        self.link_functions(&mut scope)
+    }
+
+    /// Assigns each function in this module and its submodules the vtable
+    /// index it will be defined at by [`Self::link_functions()`].
+    fn reserve_functions<S>(
         &self,
-        context: &'a mut VirtualMachine<Env>,
-    ) -> Result<Output, Error<'a, Env, Output>>
+        scope: &mut ModuleScope<'_, S>,
+        next_index: &mut usize,
+    ) -> Result<(), LinkError> {
+        for module in &self.modules {
+            let name = module
+                .name
+                .clone()
+                .ok_or(LinkError::InvalidScopeOperation)?;
+            scope.path.push(name);
+            let result = module.reserve_functions(scope, next_index);
+            scope.path.pop();
+            result?;
+        }
+
+        for function in &self.vtable {
+            let name = scope.qualified_name(&function.name);
+            scope.reserved.insert(name, *next_index);
+            *next_index += 1;
+        }
+
+        Ok(())
+    }
+
+    fn link_functions<S, E>(&self, scope: &mut ModuleScope<'_, S>) -> Result<(), LinkError>
     where
-        Env: Environment<Intrinsic = Intrinsic>,
+        S: Scope<Environment = E>,
+        E: Environment<Intrinsic = Intrinsic>,
     {
-        // // Process all modules first
-        // for _module in &self.modules {
-        //     todo!()
-        // }
+        for module in &self.modules {
+            let name = module
+                .name
+                .clone()
+                .ok_or(LinkError::InvalidScopeOperation)?;
+            scope.path.push(name);
+            let result = module.link_functions(scope);
+            scope.path.pop();
+            result?;
+        }
 
-        // Compile each function
         for (index, function) in self.vtable.iter().enumerate() {
             if env::var("PRINT_IR").is_ok() {
                 println!(
                     "function #{index} - {}({})",
-                    function.name,
+                    scope.qualified_name(&function.name),
                     function
                         .body
                         .arguments
@@ -1580,15 +2238,81 @@ where
                         .join(", ")
                 );
             }
-            function.link_into(context)?;
+            function.link_into(scope)?;
         }
 
-        // Execute the module initializer if it exists
-        if let Some(init) = &self.init {
-            if env::var("PRINT_IR").is_ok() {
-                println!("function init");
+        Ok(())
+    }
+
+    /// Runs all code in this unit in the passed context.
+    pub fn load_into<'a, Output: FromStack, Env>(
+        &self,
+        context: &'a mut VirtualMachine<Env>,
+    ) -> Result<Output, Error<'a, Env, Output>>
+    where
+        Env: Environment<Intrinsic = Intrinsic>,
+    {
+        self.link_into(context)?;
+        let init = self.link_init(context)?;
+        Self::run_init(init, context)
+    }
+
+    /// Runs all code in this unit in the passed context after statically
+    /// verifying it. See [`VirtualMachine::verify()`] for the checks
+    /// performed.
+    ///
+    /// No code is executed unless every linked function passes verification.
+    /// If linking or verification fails, the functions linked are removed
+    /// from `context`. This should be used for modules from untrusted
+    /// sources, such as [bytecode](crate::bytecode).
+    pub fn load_verified_into<'a, Output: FromStack, Env>(
+        &self,
+        context: &'a mut VirtualMachine<Env>,
+    ) -> Result<Output, Error<'a, Env, Output>>
+    where
+        Env: Environment<Intrinsic = Intrinsic>,
+    {
+        let checkpoint = context.checkpoint();
+        let init = match self
+            .link_into(context)
+            .and_then(|()| self.link_init(context))
+        {
+            Ok(init) => init,
+            Err(err) => {
+                context.rollback(checkpoint);
+                return Err(Error::from(err));
             }
-            let vtable_index = init.link_into(context)?;
+        };
+        context.verify_since(checkpoint)?;
+        Self::run_init(init, context)
+    }
+
+    /// Links the module initializer, if it exists, returning its vtable
+    /// index.
+    fn link_init<Env>(&self, context: &mut VirtualMachine<Env>) -> Result<Option<usize>, LinkError>
+    where
+        Env: Environment<Intrinsic = Intrinsic>,
+    {
+        self.init
+            .as_ref()
+            .map(|init| {
+                if env::var("PRINT_IR").is_ok() {
+                    println!("function init");
+                }
+                init.link_into(context)
+            })
+            .transpose()
+    }
+
+    fn run_init<Output: FromStack, Env>(
+        init: Option<usize>,
+        context: &mut VirtualMachine<Env>,
+    ) -> Result<Output, Error<'_, Env, Output>>
+    where
+        Env: Environment<Intrinsic = Intrinsic>,
+    {
+        // Execute the module initializer if it exists
+        if let Some(vtable_index) = init {
             context
                 .run(
                     vec![crate::Instruction::Call {
@@ -1605,6 +2329,86 @@ where
     }
 }
 
+/// A [`Scope`] that links functions into a module nested within another
+/// scope.
+struct ModuleScope<'a, S> {
+    scope: &'a mut S,
+    path: Vec<Symbol>,
+    /// The vtable indexes of the functions in the module being linked.
+    reserved: HashMap<Symbol, usize>,
+    /// The vtable index the next defined function must be assigned for
+    /// `reserved` to remain accurate.
+    next_index: Option<usize>,
+}
+
+impl<S> ModuleScope<'_, S> {
+    fn qualified_name(&self, name: &Symbol) -> Symbol {
+        self.qualified_name_at(self.path.len(), name)
+    }
+
+    fn qualified_name_at(&self, depth: usize, name: &Symbol) -> Symbol {
+        if depth == 0 {
+            name.clone()
+        } else {
+            let mut qualified = String::new();
+            for module in &self.path[..depth] {
+                qualified.push_str(module);
+                qualified.push('.');
+            }
+            qualified.push_str(name);
+            Symbol::from(qualified.as_str())
+        }
+    }
+}
+
+impl<S> Scope for ModuleScope<'_, S>
+where
+    S: Scope,
+{
+    type Environment = S::Environment;
+
+    fn resolve_function_vtable_index(&self, name: &Symbol) -> Option<usize> {
+        (0..=self.path.len()).rev().find_map(|depth| {
+            let name = self.qualified_name_at(depth, name);
+            self.reserved
+                .get(&name)
+                .copied()
+                .or_else(|| self.scope.resolve_function_vtable_index(&name))
+        })
+    }
+
+    fn map_each_symbol(&self, callback: &mut impl FnMut(Symbol, ScopeSymbolKind)) {
+        self.scope.map_each_symbol(callback);
+    }
+
+    fn define_function(
+        &mut self,
+        mut function: crate::Function<<Self::Environment as Environment>::Intrinsic>,
+    ) -> Option<usize> {
+        function.name = self.qualified_name(&function.name);
+        let vtable_index = self.scope.define_function(function)?;
+        if let Some(expected) = self.next_index {
+            if vtable_index != expected {
+                return None;
+            }
+            self.next_index = Some(expected + 1);
+        }
+        Some(vtable_index)
+    }
+
+    fn next_vtable_index(&self) -> Option<usize> {
+        self.scope.next_vtable_index()
+    }
+
+    fn define_persistent_variable(&mut self, name: Symbol, variable: Variable) {
+        self.scope.define_persistent_variable(name, variable);
+    }
+
+    fn define_debug_info(&mut self, vtable_index: usize, debug_info: DebugInfo) {
+        self.scope.define_debug_info(vtable_index, debug_info);
+    }
+}
+
 impl<Intrinsic> Display for Module<Intrinsic>
 where
     Intrinsic: Display,
@@ -1640,6 +2444,8 @@ pub enum LinkError {
     InvalidLabel(Label),
     /// An invalid operation for the provided [`Scope`] was attempted.
     InvalidScopeOperation,
+    /// A variable was referenced after the block it was defined in ended.
+    OutOfScope(Symbol),
 }
 
 impl Display for LinkError {
@@ -1654,6 +2460,9 @@ impl Display for LinkError {
             LinkError::UndefinedIdentifier(symbol) => {
                 write!(f, "undefined identifier: {symbol}")
             }
+            LinkError::OutOfScope(symbol) => {
+                write!(f, "variable out of scope: {symbol}")
+            }
             LinkError::InvalidLabel(label) => {
                 if let Some(name) = &label.name {
                     write!(f, "invalid label: #{}", name)
@@ -1664,26 +2473,3 @@ impl Display for LinkError {
         }
     }
 }
-
-// #[test]
-// fn optimizations() {
-//     let unit = crate::parser::parse(
-//         r#"function fibonacci(n)
-//             if n <= 2
-//                 1
-//             else
-//                 this(n - 1) + this(n - 2)
-//             end
-//         end
-//         "#,
-//     )
-//     .unwrap()
-//     .compile();
-//     println!("Unoptimized code:\n{}", unit.vtable[0].body);
-//     println!("Unoptimized length: {}", unit.vtable[0].body.code.len());
-//     let mut graph = crate::optimizer::CodeGraph::new(&unit.vtable[0].body.code);
-//     println!("Graph:\n {graph}");
-//     let dag = graph.dags();
-//     println!("Dag:\n {dag:?}");
-//     // dag.optimize(&mut graph);
-// }
@@ -131,6 +131,16 @@ impl<'a> Iterator for Lexer<'a> {
                         match literal.contents {
                             Numeric::Integer(integer) => TokenKind::Integer(integer),
                             Numeric::Real(real) => TokenKind::Real(real),
+                            // Bud Assembly has no literals for these types.
+                            Numeric::BigInteger(_) | Numeric::Decimal(_) => {
+                                return Some(Err(AsmError::UnexpectedChar {
+                                    character: self.source[literal.last_offset..]
+                                        .chars()
+                                        .next()
+                                        .expect("suffix present"),
+                                    offset: literal.last_offset,
+                                }))
+                            }
                         },
                         offset..literal.last_offset + 1,
                     )));
@@ -219,13 +229,16 @@ where
                     } else {
                         match &**symbol {
                             "function" => self.parse_function()?,
-                            "add" | "sub" | "mul" | "div" | "and" | "or" | "xor" | "bitor"
-                            | "bitand" | "bitxor" | "shl" | "shr" => self.parse_binop(symbol)?,
+                            "add" | "sub" | "mul" | "div" | "idiv" | "rem" | "pow" | "and"
+                            | "or" | "xor" | "bitor" | "bitand" | "bitxor" | "shl" | "shr" => {
+                                self.parse_binop(symbol)?;
+                            }
                             "eq" | "neq" | "lt" | "lte" | "gt" | "gte" => {
                                 self.parse_comparison(symbol)?;
                             }
                             "not" => self.parse_not()?,
                             "bitnot" => self.parse_bitnot()?,
+                            "neg" => self.parse_neg()?,
                             "convert" => self.parse_convert()?,
                             "ifnot" => self.parse_ifnot()?,
                             "jump" => self.parse_jump()?,
@@ -233,8 +246,26 @@ where
                             "load" => self.parse_load()?,
                             "call" => self.parse_call()?,
                             "recurse" => self.parse_call_with_name(None)?,
+                            "tailcall" => self.parse_tail_call()?,
+                            "tailrecurse" => self.parse_tail_call_with_name(None)?,
                             "intrinsic" => self.parse_intrinsic()?,
                             "invoke" => self.parse_invoke()?,
+                            "callvalue" => self.parse_call_value()?,
+                            "closure" => self.parse_closure()?,
+                            "getindex" => self.parse_get_index()?,
+                            "setindex" => self.parse_set_index()?,
+                            "iterate" => self.parse_iterate()?,
+                            "iternext" => self.parse_iterate_next()?,
+                            "try" => self.parse_enter_try()?,
+                            "endtry" => {
+                                self.expect_end_of_line()?;
+                                self.current_function.push(Instruction::ExitTry);
+                            }
+                            "throw" => {
+                                let value = self.expect_literal_or_source()?;
+                                self.expect_end_of_line()?;
+                                self.current_function.push(Instruction::Throw(value));
+                            }
                             "return" => {
                                 let value = if self.next_is_end_of_line() {
                                     None
@@ -284,6 +315,21 @@ where
                 right,
                 destination,
             },
+            "idiv" => Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            },
+            "rem" => Instruction::Remainder {
+                left,
+                right,
+                destination,
+            },
+            "pow" => Instruction::Power {
+                left,
+                right,
+                destination,
+            },
             "and" => Instruction::LogicalAnd {
                 left,
                 right,
@@ -581,6 +627,14 @@ where
         Ok(())
     }
 
+    fn parse_neg(&mut self) -> Result<(), AsmError> {
+        let value = self.expect_literal_or_source()?;
+        let destination = self.expect_destination()?;
+        self.current_function
+            .push(Instruction::Negate { value, destination });
+        Ok(())
+    }
+
     fn parse_convert(&mut self) -> Result<(), AsmError> {
         let value = self.expect_literal_or_source()?;
         let (kind, _) = self.expect_identifier("value kind")?;
@@ -651,6 +705,22 @@ where
         Ok(())
     }
 
+    fn parse_tail_call(&mut self) -> Result<(), AsmError> {
+        let (function, _) = self.expect_identifier("function name")?;
+        self.parse_tail_call_with_name(Some(function))
+    }
+
+    fn parse_tail_call_with_name(&mut self, function: Option<Symbol>) -> Result<(), AsmError> {
+        let arg_count = self.expect_arg_count()?;
+
+        self.current_function.push(Instruction::TailCall {
+            function,
+            arg_count,
+        });
+
+        Ok(())
+    }
+
     fn parse_intrinsic(&mut self) -> Result<(), AsmError> {
         let (name, range) = self.expect_identifier("intrinsic name")?;
         let intrinsic = <Intrinsic as FromStr>::from_str(&name).map_err(|_| {
@@ -696,6 +766,100 @@ where
 
         Ok(())
     }
+
+    fn parse_call_value(&mut self) -> Result<(), AsmError> {
+        let target = self.expect_literal_or_source()?;
+        let arg_count = self.expect_arg_count()?;
+        let destination = self.expect_destination()?;
+
+        self.current_function.push(Instruction::CallValue {
+            target,
+            arg_count,
+            destination,
+        });
+
+        Ok(())
+    }
+
+    fn parse_closure(&mut self) -> Result<(), AsmError> {
+        let (function, _) = self.expect_identifier("function name")?;
+        let capture_count = self.expect_arg_count()?;
+        let destination = self.expect_destination()?;
+
+        self.current_function.push(Instruction::NewClosure {
+            function,
+            capture_count,
+            destination,
+        });
+
+        Ok(())
+    }
+
+    fn parse_get_index(&mut self) -> Result<(), AsmError> {
+        let target = self.expect_literal_or_source()?;
+        let index = self.expect_literal_or_source()?;
+        let destination = self.expect_destination()?;
+
+        self.current_function.push(Instruction::GetIndex {
+            target,
+            index,
+            destination,
+        });
+
+        Ok(())
+    }
+
+    fn parse_set_index(&mut self) -> Result<(), AsmError> {
+        let target = self.expect_literal_or_source()?;
+        let index = self.expect_literal_or_source()?;
+        let value = self.expect_literal_or_source()?;
+
+        self.current_function.push(Instruction::SetIndex {
+            target,
+            index,
+            value,
+        });
+
+        Ok(())
+    }
+
+    fn parse_iterate(&mut self) -> Result<(), AsmError> {
+        let source = self.expect_literal_or_source()?;
+        let destination = self.expect_destination()?;
+
+        self.current_function.push(Instruction::Iterate {
+            source,
+            destination,
+        });
+
+        Ok(())
+    }
+
+    fn parse_iterate_next(&mut self) -> Result<(), AsmError> {
+        let iterator = self.expect_literal_or_source()?;
+        let destination = self.expect_destination()?;
+        let done_jump_to = self.expect_label()?;
+
+        self.current_function.push(Instruction::IterateNext {
+            iterator,
+            destination,
+            done_jump_to,
+        });
+
+        Ok(())
+    }
+
+    fn parse_enter_try(&mut self) -> Result<(), AsmError> {
+        let destination = self.expect_destination()?;
+        let handler = self.expect_label()?;
+
+        self.current_function.push(Instruction::EnterTry {
+            handler,
+            destination,
+        });
+
+        Ok(())
+    }
 }
 
 #[test]
@@ -732,7 +896,7 @@ fn roundtrip_all_instructions() {
     let a_label = block.named_label("a_label");
     block.label(a_label.clone());
     block.push(Instruction::Add {
-        left: LiteralOrSource::Argument(arg1),
+        left: LiteralOrSource::Argument(arg1.clone()),
         right: LiteralOrSource::Variable(var1.clone()),
         destination: Destination::Variable(var1.clone()),
     });
@@ -751,6 +915,21 @@ fn roundtrip_all_instructions() {
         right: LiteralOrSource::Literal(Literal::Integer(0)),
         destination: Destination::Stack,
     });
+    block.push(Instruction::IntegerDivide {
+        left: LiteralOrSource::Literal(Literal::Integer(7)),
+        right: LiteralOrSource::Literal(Literal::Integer(2)),
+        destination: Destination::Stack,
+    });
+    block.push(Instruction::Remainder {
+        left: LiteralOrSource::Literal(Literal::Integer(7)),
+        right: LiteralOrSource::Literal(Literal::Integer(-2)),
+        destination: Destination::Stack,
+    });
+    block.push(Instruction::Power {
+        left: LiteralOrSource::Literal(Literal::Real(2.0)),
+        right: LiteralOrSource::Literal(Literal::Real(0.5)),
+        destination: Destination::Stack,
+    });
     block.push(Instruction::LogicalAnd {
         left: LiteralOrSource::Literal(Literal::Void),
         right: LiteralOrSource::Literal(Literal::Void),
@@ -799,6 +978,10 @@ fn roundtrip_all_instructions() {
         value: LiteralOrSource::Literal(Literal::Void),
         destination: Destination::Stack,
     });
+    block.push(Instruction::Negate {
+        value: LiteralOrSource::Argument(arg1.clone()),
+        destination: Destination::Stack,
+    });
     block.push(Instruction::Convert {
         value: LiteralOrSource::Literal(Literal::Void),
         kind: ValueKind::Boolean,
@@ -828,7 +1011,7 @@ fn roundtrip_all_instructions() {
         comparison: Comparison::Equal,
         left: LiteralOrSource::Literal(Literal::Void),
         right: LiteralOrSource::Literal(Literal::Void),
-        action: CompareAction::JumpIfFalse(a_label),
+        action: CompareAction::JumpIfFalse(a_label.clone()),
     });
     block.push(Instruction::Compare {
         comparison: Comparison::NotEqual,
@@ -878,6 +1061,14 @@ fn roundtrip_all_instructions() {
         arg_count: 1,
         destination: Destination::Stack,
     });
+    block.push(Instruction::TailCall {
+        function: None,
+        arg_count: 1,
+    });
+    block.push(Instruction::TailCall {
+        function: Some(Symbol::from("test")),
+        arg_count: 1,
+    });
     block.push(Instruction::CallInstance {
         target: None,
         name: Symbol::from("test"),
@@ -885,7 +1076,7 @@ fn roundtrip_all_instructions() {
         destination: Destination::Stack,
     });
     block.push(Instruction::CallInstance {
-        target: Some(LiteralOrSource::Variable(var1)),
+        target: Some(LiteralOrSource::Variable(var1.clone())),
         name: Symbol::from("test"),
         arg_count: 1,
         destination: Destination::Stack,
@@ -895,6 +1086,41 @@ fn roundtrip_all_instructions() {
         arg_count: 1,
         destination: Destination::Stack,
     });
+    block.push(Instruction::CallValue {
+        target: LiteralOrSource::Argument(arg1.clone()),
+        arg_count: 1,
+        destination: Destination::Stack,
+    });
+    block.push(Instruction::NewClosure {
+        function: Symbol::from("test"),
+        capture_count: 1,
+        destination: Destination::Stack,
+    });
+    block.push(Instruction::GetIndex {
+        target: LiteralOrSource::Variable(var1.clone()),
+        index: LiteralOrSource::Literal(Literal::Integer(0)),
+        destination: Destination::Variable(var1.clone()),
+    });
+    block.push(Instruction::SetIndex {
+        target: LiteralOrSource::Argument(arg1.clone()),
+        index: LiteralOrSource::Literal(Literal::String(String::from("key"))),
+        value: LiteralOrSource::Variable(var1.clone()),
+    });
+    block.push(Instruction::Iterate {
+        source: LiteralOrSource::Argument(arg1),
+        destination: Destination::Variable(var1.clone()),
+    });
+    block.push(Instruction::IterateNext {
+        iterator: LiteralOrSource::Variable(var1.clone()),
+        destination: Destination::Variable(var1.clone()),
+        done_jump_to: a_label.clone(),
+    });
+    block.push(Instruction::EnterTry {
+        handler: a_label,
+        destination: Destination::Variable(var1.clone()),
+    });
+    block.push(Instruction::ExitTry);
+    block.push(Instruction::Throw(LiteralOrSource::Variable(var1)));
     let test_func = Function::new("test", block.finish());
 
     let manually_built = Module::new(vec![test_func], Vec::new(), Some(init));
@@ -0,0 +1,1112 @@
+//! Optimization passes over [`CodeBlock`]s.
+//!
+//! An [`Optimizer`] runs a pipeline of [`Pass`]es over a code block until none
+//! of them make any further changes. [`Optimizer::for_level()`] returns the
+//! pipeline used for each [`OptimizationLevel`]:
+//!
+//! - [`ConstantFolding`] evaluates operations whose operands are all literals.
+//! - [`DeadCodeElimination`] removes unreachable instructions, unused labels,
+//!   and jumps to the next instruction.
+//! - [`JumpThreading`] retargets jumps whose destination immediately jumps or
+//!   returns.
+//! - [`TemporaryCoalescing`] shares storage between temporary variables whose
+//!   values are never needed at the same time.
+//!
+//! Every pass preserves the observable behavior of the code block, including
+//! which faults are raised, and keeps the code block's [`DebugInfo`] in sync
+//! with its instructions.
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    ir::{
+        CodeBlock, CompareAction, Destination, Instruction, Label, Literal, LiteralOrSource,
+        Module, Variable,
+    },
+    Comparison, Value,
+};
+
+/// The maximum number of times the passes of an [`Optimizer`] are repeated.
+const MAX_ITERATIONS: usize = 16;
+
+/// How aggressively code is optimized.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub enum OptimizationLevel {
+    /// No optimizations are performed.
+    #[default]
+    None,
+    /// Constant folding, dead code elimination, and jump threading are
+    /// performed.
+    Basic,
+    /// All optimizations in [`OptimizationLevel::Basic`] are performed, and
+    /// temporary variables are coalesced.
+    Full,
+}
+
+/// An optimization performed on a [`CodeBlock`].
+pub trait Pass<Intrinsic> {
+    /// Optimizes `block`, returning true if any changes were made.
+    fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) -> bool;
+}
+
+/// A pipeline of [`Pass`]es.
+pub struct Optimizer<Intrinsic> {
+    passes: Vec<Box<dyn Pass<Intrinsic>>>,
+}
+
+impl<Intrinsic> Default for Optimizer<Intrinsic> {
+    fn default() -> Self {
+        Self { passes: Vec::new() }
+    }
+}
+
+impl<Intrinsic> Optimizer<Intrinsic> {
+    /// Returns an optimizer that performs the passes for `level`.
+    #[must_use]
+    pub fn for_level(level: OptimizationLevel) -> Self {
+        let optimizer = Self::default();
+        match level {
+            OptimizationLevel::None => optimizer,
+            OptimizationLevel::Basic => optimizer
+                .with_pass(ConstantFolding)
+                .with_pass(JumpThreading)
+                .with_pass(DeadCodeElimination),
+            OptimizationLevel::Full => {
+                Self::for_level(OptimizationLevel::Basic).with_pass(TemporaryCoalescing)
+            }
+        }
+    }
+
+    /// Adds `pass` to the end of this pipeline and returns self. This is a
+    /// builder-style function.
+    #[must_use]
+    pub fn with_pass(mut self, pass: impl Pass<Intrinsic> + 'static) -> Self {
+        self.push_pass(pass);
+        self
+    }
+
+    /// Adds `pass` to the end of this pipeline.
+    pub fn push_pass(&mut self, pass: impl Pass<Intrinsic> + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    /// Optimizes `block` by running each pass in order, repeating the pipeline
+    /// until no pass makes any changes.
+    pub fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) {
+        for _ in 0..MAX_ITERATIONS {
+            let mut changed = false;
+            for pass in &mut self.passes {
+                changed |= pass.optimize(block);
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Optimizes every function in `module` and its submodules, including
+    /// their initializers.
+    pub fn optimize_module(&mut self, module: &mut Module<Intrinsic>) {
+        for function in &mut module.vtable {
+            self.optimize(&mut function.body);
+        }
+        for module in &mut module.modules {
+            self.optimize_module(module);
+        }
+        if let Some(init) = &mut module.init {
+            self.optimize(&mut init.body);
+        }
+    }
+}
+
+/// Evaluates operations whose operands are all literals.
+///
+/// Only operations that are evaluated the same way regardless of the
+/// [`Environment`](crate::Environment) are folded. Operations that would raise
+/// a fault are left untouched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantFolding;
+
+impl<Intrinsic> Pass<Intrinsic> for ConstantFolding {
+    fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) -> bool {
+        let mut removed = vec![false; block.code.len()];
+        let mut changed = false;
+        for (index, instruction) in block.code.iter_mut().enumerate() {
+            match fold(instruction) {
+                Folded::Unchanged => {}
+                Folded::Removed => {
+                    removed[index] = true;
+                    changed = true;
+                }
+                Folded::Replaced(replacement) => {
+                    *instruction = replacement;
+                    changed = true;
+                }
+            }
+        }
+        remove_instructions(block, &removed);
+        changed
+    }
+}
+
+enum Folded<Intrinsic> {
+    Unchanged,
+    Removed,
+    Replaced(Instruction<Intrinsic>),
+}
+
+impl<Intrinsic> From<Option<Instruction<Intrinsic>>> for Folded<Intrinsic> {
+    fn from(instruction: Option<Instruction<Intrinsic>>) -> Self {
+        instruction.map_or(Folded::Unchanged, Folded::Replaced)
+    }
+}
+
+#[allow(clippy::too_many_lines)] // One arm per instruction.
+fn fold<Intrinsic>(instruction: &Instruction<Intrinsic>) -> Folded<Intrinsic> {
+    match instruction {
+        Instruction::Add {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(left, right, destination, i64::checked_add, |a, b| a + b),
+        Instruction::Sub {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(left, right, destination, i64::checked_sub, |a, b| a - b),
+        Instruction::Multiply {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(left, right, destination, i64::checked_mul, |a, b| a * b),
+        Instruction::Divide {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(left, right, destination, i64::checked_div, |a, b| a / b),
+        Instruction::IntegerDivide {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(
+            left,
+            right,
+            destination,
+            crate::arithmetic::integer_floor_div,
+            crate::arithmetic::real_floor_div,
+        ),
+        Instruction::Remainder {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(
+            left,
+            right,
+            destination,
+            crate::arithmetic::integer_floor_rem,
+            crate::arithmetic::real_floor_rem,
+        ),
+        Instruction::Power {
+            left,
+            right,
+            destination,
+        } => fold_arithmetic(
+            left,
+            right,
+            destination,
+            crate::arithmetic::integer_pow,
+            f64::powf,
+        ),
+        Instruction::LogicalAnd {
+            left,
+            right,
+            destination,
+        } => {
+            // The right hand side is only evaluated if the left is truthy.
+            let result = match (literal_value(left), literal_value(right)) {
+                (Some(left), _) if left.is_falsey() => Some(false),
+                (Some(_), Some(right)) => Some(right.is_truthy()),
+                _ => None,
+            };
+            store(result.map(Literal::Boolean), destination).into()
+        }
+        Instruction::LogicalOr {
+            left,
+            right,
+            destination,
+        } => {
+            // The right hand side is only evaluated if the left is falsey.
+            let result = match (literal_value(left), literal_value(right)) {
+                (Some(left), _) if left.is_truthy() => Some(true),
+                (Some(_), Some(right)) => Some(right.is_truthy()),
+                _ => None,
+            };
+            store(result.map(Literal::Boolean), destination).into()
+        }
+        Instruction::LogicalXor {
+            left,
+            right,
+            destination,
+        } => {
+            let result = literal_value(left)
+                .zip(literal_value(right))
+                .map(|(left, right)| Literal::Boolean(left.is_truthy() ^ right.is_truthy()));
+            store(result, destination).into()
+        }
+        Instruction::LogicalNot { value, destination } => {
+            let result = literal_value(value).map(|value| Literal::Boolean(value.is_falsey()));
+            store(result, destination).into()
+        }
+        Instruction::BitwiseAnd {
+            left,
+            right,
+            destination,
+        } => fold_integer(left, right, destination, |a, b| Some(a & b)),
+        Instruction::BitwiseOr {
+            left,
+            right,
+            destination,
+        } => fold_integer(left, right, destination, |a, b| Some(a | b)),
+        Instruction::BitwiseXor {
+            left,
+            right,
+            destination,
+        } => fold_integer(left, right, destination, |a, b| Some(a ^ b)),
+        Instruction::ShiftLeft {
+            left,
+            right,
+            destination,
+        } => fold_integer(left, right, destination, |a, b| match u32::try_from(b) {
+            Ok(b) if b < 64 => Some(a << b),
+            Ok(_) => Some(0),
+            Err(_) => None,
+        }),
+        Instruction::ShiftRight {
+            left,
+            right,
+            destination,
+        } => fold_integer(left, right, destination, |a, b| match u32::try_from(b) {
+            Ok(b) if b < 64 => Some(a >> b),
+            Ok(_) => Some(0),
+            Err(_) => None,
+        }),
+        Instruction::BitwiseNot { value, destination } => {
+            let result = match literal_value(value) {
+                Some(Value::Integer(value)) => Some(Literal::Integer(!value)),
+                _ => None,
+            };
+            store(result, destination).into()
+        }
+        Instruction::Negate { value, destination } => {
+            let result = match literal_value(value) {
+                Some(Value::Integer(value)) => value.checked_neg().map(Literal::Integer),
+                Some(Value::Real(value)) => Some(Literal::Real(-value)),
+                _ => None,
+            };
+            store(result, destination).into()
+        }
+        Instruction::If {
+            condition,
+            false_jump_to,
+        } => match literal_value(condition) {
+            Some(condition) if condition.is_truthy() => Folded::Removed,
+            Some(_) => Folded::Replaced(Instruction::JumpTo(false_jump_to.clone())),
+            None => Folded::Unchanged,
+        },
+        Instruction::Compare {
+            comparison,
+            left,
+            right,
+            action,
+        } => {
+            let result = literal_value(left)
+                .zip(literal_value(right))
+                .and_then(|(left, right)| compare(*comparison, &left, &right));
+            match (result, action) {
+                (None, _) => Folded::Unchanged,
+                (Some(result), CompareAction::Store(destination)) => {
+                    store(Some(Literal::Boolean(result)), destination).into()
+                }
+                (Some(true), CompareAction::JumpIfFalse(_)) => Folded::Removed,
+                (Some(false), CompareAction::JumpIfFalse(label)) => {
+                    Folded::Replaced(Instruction::JumpTo(label.clone()))
+                }
+            }
+        }
+        Instruction::Convert { .. }
+        | Instruction::JumpTo(_)
+        | Instruction::Label(_)
+        | Instruction::Push(_)
+        | Instruction::Load { .. }
+        | Instruction::Return(_)
+        | Instruction::Call { .. }
+        | Instruction::TailCall { .. }
+        | Instruction::CallIntrinsic { .. }
+        | Instruction::CallInstance { .. }
+        | Instruction::CallValue { .. }
+        | Instruction::NewClosure { .. }
+        | Instruction::GetIndex { .. }
+        | Instruction::SetIndex { .. }
+        | Instruction::Iterate { .. }
+        | Instruction::IterateNext { .. }
+        | Instruction::EnterTry { .. }
+        | Instruction::ExitTry
+        | Instruction::Throw(_) => Folded::Unchanged,
+    }
+}
+
+fn fold_arithmetic<Intrinsic>(
+    left: &LiteralOrSource,
+    right: &LiteralOrSource,
+    destination: &Destination,
+    integer: impl FnOnce(i64, i64) -> Option<i64>,
+    real: impl FnOnce(f64, f64) -> f64,
+) -> Folded<Intrinsic> {
+    // The result of integer overflow depends on the virtual machine's
+    // ArithmeticMode, so those operations are left to be evaluated at runtime.
+    let result = match (literal_value(left), literal_value(right)) {
+        (Some(Value::Integer(left)), Some(Value::Integer(right))) => {
+            integer(left, right).map(Literal::Integer)
+        }
+        (Some(Value::Real(left)), Some(Value::Real(right))) => {
+            Some(Literal::Real(real(left, right)))
+        }
+        _ => None,
+    };
+    store(result, destination).into()
+}
+
+fn fold_integer<Intrinsic>(
+    left: &LiteralOrSource,
+    right: &LiteralOrSource,
+    destination: &Destination,
+    op: impl FnOnce(i64, i64) -> Option<i64>,
+) -> Folded<Intrinsic> {
+    let result = match (literal_value(left), literal_value(right)) {
+        (Some(Value::Integer(left)), Some(Value::Integer(right))) => {
+            op(left, right).map(Literal::Integer)
+        }
+        _ => None,
+    };
+    store(result, destination).into()
+}
+
+fn compare(comparison: Comparison, left: &Value, right: &Value) -> Option<bool> {
+    // Ordering comparisons between incompatible values raise faults.
+    let ordering = || left.partial_cmp(right);
+    match comparison {
+        Comparison::Equal => Some(left == right),
+        Comparison::NotEqual => Some(left != right),
+        Comparison::LessThan => ordering().map(|ordering| ordering == Ordering::Less),
+        Comparison::LessThanOrEqual => ordering().map(|ordering| ordering != Ordering::Greater),
+        Comparison::GreaterThan => ordering().map(|ordering| ordering == Ordering::Greater),
+        Comparison::GreaterThanOrEqual => ordering().map(|ordering| ordering != Ordering::Less),
+    }
+}
+
+/// Returns the value of `value` if it is a literal that can be evaluated
+/// without an environment.
+fn literal_value(value: &LiteralOrSource) -> Option<Value> {
+    match value {
+        LiteralOrSource::Literal(Literal::Void) => Some(Value::Void),
+        LiteralOrSource::Literal(Literal::Integer(value)) => Some(Value::Integer(*value)),
+        LiteralOrSource::Literal(Literal::Real(value)) => Some(Value::Real(*value)),
+        LiteralOrSource::Literal(Literal::Boolean(value)) => Some(Value::Boolean(*value)),
+        LiteralOrSource::Literal(Literal::String(_))
+        | LiteralOrSource::Argument(_)
+        | LiteralOrSource::Variable(_) => None,
+    }
+}
+
+/// Returns an instruction that stores `value` in `destination`.
+///
+/// There is no instruction that only sets the return value, so results
+/// destined for [`Destination::Return`] are not folded.
+fn store<Intrinsic>(
+    value: Option<Literal>,
+    destination: &Destination,
+) -> Option<Instruction<Intrinsic>> {
+    let value = LiteralOrSource::Literal(value?);
+    match destination {
+        Destination::Variable(variable) => Some(Instruction::Load {
+            value,
+            variable: variable.clone(),
+        }),
+        Destination::Stack => Some(Instruction::Push(value)),
+        Destination::Return => None,
+    }
+}
+
+/// Removes unreachable instructions, labels that are never jumped to, and
+/// jumps to the instruction that would have executed next.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeadCodeElimination;
+
+impl<Intrinsic> Pass<Intrinsic> for DeadCodeElimination {
+    fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) -> bool {
+        let positions = label_positions(&block.code);
+        let mut reachable = vec![false; block.code.len()];
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            if index >= block.code.len() || reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            pending.extend(successors(index, &block.code[index], &positions));
+        }
+
+        let mut jumped_to = vec![false; block.code.len()];
+        for (index, instruction) in block.code.iter().enumerate() {
+            if reachable[index] {
+                if let Some(label) = jump_target(instruction) {
+                    if let Some(position) = positions.get(&label.index) {
+                        jumped_to[*position] = true;
+                    }
+                }
+            }
+        }
+
+        let removed = block
+            .code
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| match instruction {
+                Instruction::Label(_) => !jumped_to[index],
+                Instruction::JumpTo(label)
+                | Instruction::If {
+                    false_jump_to: label,
+                    ..
+                } => !reachable[index] || jumps_to_next(&block.code, index, label),
+                _ => !reachable[index],
+            })
+            .collect::<Vec<_>>();
+        remove_instructions(block, &removed)
+    }
+}
+
+/// Returns true if the only instructions between `index` and the definition
+/// of `label` are other labels.
+fn jumps_to_next<Intrinsic>(code: &[Instruction<Intrinsic>], index: usize, label: &Label) -> bool {
+    for instruction in &code[index + 1..] {
+        match instruction {
+            Instruction::Label(next) if next.index == label.index => return true,
+            Instruction::Label(_) => {}
+            _ => break,
+        }
+    }
+    false
+}
+
+/// Retargets jumps whose destination immediately jumps elsewhere, and
+/// replaces unconditional jumps to a return with the return itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JumpThreading;
+
+impl<Intrinsic> Pass<Intrinsic> for JumpThreading {
+    fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) -> bool {
+        let positions = label_positions(&block.code);
+        let mut changed = false;
+        for index in 0..block.code.len() {
+            let label = if let Some(label) = jump_target(&block.code[index]) {
+                label
+            } else {
+                continue;
+            };
+            let threaded = match final_destination(&block.code, &positions, label) {
+                Some(Instruction::Return(value))
+                    if matches!(block.code[index], Instruction::JumpTo(_)) =>
+                {
+                    Instruction::Return(value.clone())
+                }
+                Some(Instruction::JumpTo(target)) if target.index != label.index => {
+                    Instruction::JumpTo(target.clone())
+                }
+                _ => continue,
+            };
+            match (&mut block.code[index], threaded) {
+                (
+                    Instruction::If {
+                        false_jump_to: label,
+                        ..
+                    }
+                    | Instruction::Compare {
+                        action: CompareAction::JumpIfFalse(label),
+                        ..
+                    }
+                    | Instruction::IterateNext {
+                        done_jump_to: label,
+                        ..
+                    }
+                    | Instruction::EnterTry { handler: label, .. },
+                    Instruction::JumpTo(target),
+                ) => *label = target,
+                (instruction, threaded) => *instruction = threaded,
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Returns the last unconditional jump in the chain of jumps starting at
+/// `label`, or the first instruction after `label` if it isn't a jump. Returns
+/// `None` if the jumps form a loop.
+fn final_destination<'a, Intrinsic>(
+    code: &'a [Instruction<Intrinsic>],
+    positions: &HashMap<usize, usize>,
+    label: &Label,
+) -> Option<&'a Instruction<Intrinsic>> {
+    let mut visited = vec![label.index];
+    let mut destination = None;
+    loop {
+        let position = *positions.get(visited.last()?)?;
+        let instruction = code[position..]
+            .iter()
+            .find(|instruction| !matches!(instruction, Instruction::Label(_)))?;
+        match instruction {
+            Instruction::JumpTo(next) if visited.contains(&next.index) => return None,
+            Instruction::JumpTo(next) => {
+                visited.push(next.index);
+                destination = Some(instruction);
+            }
+            _ => return destination.or(Some(instruction)),
+        }
+    }
+}
+
+/// Stores temporary variables whose values are never needed at the same time
+/// in the same variable, reducing the number of variables a code block needs.
+///
+/// Temporary variables are the variables created by
+/// [`CodeBlockBuilder::new_temporary_variable()`](crate::ir::CodeBlockBuilder::new_temporary_variable).
+/// Named variables are never changed, as they may be persisted between
+/// interactive evaluations.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemporaryCoalescing;
+
+impl<Intrinsic> Pass<Intrinsic> for TemporaryCoalescing {
+    fn optimize(&mut self, block: &mut CodeBlock<Intrinsic>) -> bool {
+        let live_out = live_variables(block);
+
+        let mut temporaries = Vec::new();
+        for instruction in &mut block.code {
+            for variable in variables_mut(instruction) {
+                if variable.name.starts_with('$') && !temporaries.contains(&variable.index) {
+                    temporaries.push(variable.index);
+                }
+            }
+        }
+        temporaries.sort_unstable();
+
+        // Temporaries that may be read before being assigned rely on their
+        // initial value, and can't share storage.
+        let live_on_entry = live_in(block, 0, &live_out);
+        let mut interferes = HashMap::<usize, Vec<usize>>::new();
+        for (index, instruction) in block.code.iter().enumerate() {
+            for defined in defined_variables(instruction) {
+                for live in &live_out[index] {
+                    if *live != defined {
+                        interferes.entry(defined).or_default().push(*live);
+                        interferes.entry(*live).or_default().push(defined);
+                    }
+                }
+            }
+        }
+
+        let mut assigned = HashMap::<usize, usize>::new();
+        for temporary in &temporaries {
+            if live_on_entry.contains(temporary) {
+                continue;
+            }
+            let conflicts = interferes.get(temporary).map_or(&[][..], Vec::as_slice);
+            let slot = temporaries
+                .iter()
+                .copied()
+                .find(|slot| {
+                    !live_on_entry.contains(slot)
+                        && assigned.iter().all(|(other, other_slot)| {
+                            other_slot != slot || !conflicts.contains(other)
+                        })
+                })
+                .unwrap_or(*temporary);
+            assigned.insert(*temporary, slot);
+        }
+
+        let mut changed = false;
+        for instruction in &mut block.code {
+            for variable in variables_mut(instruction) {
+                if let Some(slot) = assigned.get(&variable.index) {
+                    if *slot != variable.index {
+                        variable.index = *slot;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut variables = 0;
+        for instruction in &mut block.code {
+            for variable in variables_mut(instruction) {
+                variables = variables.max(variable.index + 1);
+            }
+        }
+        if variables < block.variables {
+            block.variables = variables;
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Returns the variables that are live after each instruction executes.
+fn live_variables<Intrinsic>(block: &CodeBlock<Intrinsic>) -> Vec<Vec<usize>> {
+    let positions = label_positions(&block.code);
+    // Any instruction may fault, which can transfer control to any exception
+    // handler.
+    let handlers = block
+        .code
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::EnterTry { handler, .. } => positions.get(&handler.index).copied(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let successors = block
+        .code
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| {
+            let mut successors = successors(index, instruction, &positions);
+            successors.extend(&handlers);
+            successors
+        })
+        .collect::<Vec<_>>();
+    let mut live_out = vec![Vec::new(); block.code.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in (0..block.code.len()).rev() {
+            let mut live = Vec::new();
+            for successor in &successors[index] {
+                for variable in live_in(block, *successor, &live_out) {
+                    if !live.contains(&variable) {
+                        live.push(variable);
+                    }
+                }
+            }
+            if live.len() != live_out[index].len() {
+                live_out[index] = live;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+/// Returns the variables that are live before the instruction at `index`
+/// executes.
+fn live_in<Intrinsic>(
+    block: &CodeBlock<Intrinsic>,
+    index: usize,
+    live_out: &[Vec<usize>],
+) -> Vec<usize> {
+    let instruction = if let Some(instruction) = block.code.get(index) {
+        instruction
+    } else {
+        return Vec::new();
+    };
+    let defined = defined_variables(instruction);
+    let mut live = used_variables(instruction);
+    for variable in &live_out[index] {
+        if !defined.contains(variable) && !live.contains(variable) {
+            live.push(*variable);
+        }
+    }
+    live
+}
+
+/// Returns the indices of the instructions that may execute after the
+/// instruction at `index`.
+fn successors<Intrinsic>(
+    index: usize,
+    instruction: &Instruction<Intrinsic>,
+    positions: &HashMap<usize, usize>,
+) -> Vec<usize> {
+    let target = |label: &Label| positions.get(&label.index).copied();
+    match instruction {
+        Instruction::Return(_) | Instruction::TailCall { .. } | Instruction::Throw(_) => Vec::new(),
+        Instruction::JumpTo(label) => target(label).into_iter().collect(),
+        Instruction::If {
+            false_jump_to: label,
+            ..
+        }
+        | Instruction::Compare {
+            action: CompareAction::JumpIfFalse(label),
+            ..
+        }
+        | Instruction::IterateNext {
+            done_jump_to: label,
+            ..
+        }
+        | Instruction::EnterTry { handler: label, .. } => [Some(index + 1), target(label)]
+            .into_iter()
+            .flatten()
+            .collect(),
+        _ => vec![index + 1],
+    }
+}
+
+fn jump_target<Intrinsic>(instruction: &Instruction<Intrinsic>) -> Option<&Label> {
+    match instruction {
+        Instruction::JumpTo(label)
+        | Instruction::If {
+            false_jump_to: label,
+            ..
+        }
+        | Instruction::Compare {
+            action: CompareAction::JumpIfFalse(label),
+            ..
+        }
+        | Instruction::IterateNext {
+            done_jump_to: label,
+            ..
+        }
+        | Instruction::EnterTry { handler: label, .. } => Some(label),
+        _ => None,
+    }
+}
+
+/// Returns the position of each label within `code`, keyed by label index.
+fn label_positions<Intrinsic>(code: &[Instruction<Intrinsic>]) -> HashMap<usize, usize> {
+    code.iter()
+        .enumerate()
+        .filter_map(|(position, instruction)| match instruction {
+            Instruction::Label(label) => Some((label.index, position)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn used_variables<Intrinsic>(instruction: &Instruction<Intrinsic>) -> Vec<usize> {
+    let mut used = Vec::new();
+    let mut value = |value: &LiteralOrSource| {
+        if let LiteralOrSource::Variable(variable) = value {
+            used.push(variable.index);
+        }
+    };
+    match instruction {
+        Instruction::Add { left, right, .. }
+        | Instruction::Sub { left, right, .. }
+        | Instruction::Multiply { left, right, .. }
+        | Instruction::Divide { left, right, .. }
+        | Instruction::IntegerDivide { left, right, .. }
+        | Instruction::Remainder { left, right, .. }
+        | Instruction::Power { left, right, .. }
+        | Instruction::LogicalAnd { left, right, .. }
+        | Instruction::LogicalOr { left, right, .. }
+        | Instruction::LogicalXor { left, right, .. }
+        | Instruction::BitwiseAnd { left, right, .. }
+        | Instruction::BitwiseOr { left, right, .. }
+        | Instruction::BitwiseXor { left, right, .. }
+        | Instruction::ShiftLeft { left, right, .. }
+        | Instruction::ShiftRight { left, right, .. }
+        | Instruction::Compare { left, right, .. }
+        | Instruction::GetIndex {
+            target: left,
+            index: right,
+            ..
+        } => {
+            value(left);
+            value(right);
+        }
+        Instruction::SetIndex {
+            target,
+            index,
+            value: v,
+        } => {
+            value(target);
+            value(index);
+            value(v);
+        }
+        // The destination keeps its previous value when the iterator is
+        // finished.
+        Instruction::IterateNext {
+            iterator,
+            destination: Destination::Variable(variable),
+            ..
+        } => {
+            value(iterator);
+            used.push(variable.index);
+        }
+        // The destination keeps its previous value unless a fault is caught.
+        Instruction::EnterTry {
+            destination: Destination::Variable(variable),
+            ..
+        } => used.push(variable.index),
+        Instruction::LogicalNot { value: v, .. }
+        | Instruction::BitwiseNot { value: v, .. }
+        | Instruction::Negate { value: v, .. }
+        | Instruction::Convert { value: v, .. }
+        | Instruction::If { condition: v, .. }
+        | Instruction::Push(v)
+        | Instruction::Load { value: v, .. }
+        | Instruction::Return(Some(v))
+        | Instruction::CallInstance {
+            target: Some(v), ..
+        }
+        | Instruction::CallValue { target: v, .. }
+        | Instruction::Iterate { source: v, .. }
+        | Instruction::IterateNext { iterator: v, .. }
+        | Instruction::Throw(v) => value(v),
+        Instruction::JumpTo(_)
+        | Instruction::Label(_)
+        | Instruction::EnterTry { .. }
+        | Instruction::ExitTry
+        | Instruction::Return(None)
+        | Instruction::Call { .. }
+        | Instruction::TailCall { .. }
+        | Instruction::CallIntrinsic { .. }
+        | Instruction::CallInstance { target: None, .. }
+        | Instruction::NewClosure { .. } => {}
+    }
+    used
+}
+
+fn defined_variables<Intrinsic>(instruction: &Instruction<Intrinsic>) -> Vec<usize> {
+    match instruction {
+        Instruction::Add { destination, .. }
+        | Instruction::Sub { destination, .. }
+        | Instruction::Multiply { destination, .. }
+        | Instruction::Divide { destination, .. }
+        | Instruction::IntegerDivide { destination, .. }
+        | Instruction::Remainder { destination, .. }
+        | Instruction::Power { destination, .. }
+        | Instruction::LogicalAnd { destination, .. }
+        | Instruction::LogicalOr { destination, .. }
+        | Instruction::LogicalXor { destination, .. }
+        | Instruction::BitwiseAnd { destination, .. }
+        | Instruction::BitwiseOr { destination, .. }
+        | Instruction::BitwiseXor { destination, .. }
+        | Instruction::ShiftLeft { destination, .. }
+        | Instruction::ShiftRight { destination, .. }
+        | Instruction::LogicalNot { destination, .. }
+        | Instruction::BitwiseNot { destination, .. }
+        | Instruction::Negate { destination, .. }
+        | Instruction::Convert { destination, .. }
+        | Instruction::Call { destination, .. }
+        | Instruction::CallIntrinsic { destination, .. }
+        | Instruction::CallValue { destination, .. }
+        | Instruction::NewClosure { destination, .. }
+        | Instruction::GetIndex { destination, .. }
+        | Instruction::Iterate { destination, .. }
+        | Instruction::IterateNext { destination, .. }
+        | Instruction::EnterTry { destination, .. }
+        | Instruction::Compare {
+            action: CompareAction::Store(destination),
+            ..
+        } => match destination {
+            Destination::Variable(variable) => vec![variable.index],
+            Destination::Stack | Destination::Return => Vec::new(),
+        },
+        Instruction::CallInstance {
+            target,
+            destination,
+            ..
+        } => {
+            // Calling a function on a value may mutate it in place.
+            let mut defined = Vec::new();
+            if let Some(LiteralOrSource::Variable(variable)) = target {
+                defined.push(variable.index);
+            }
+            if let Destination::Variable(variable) = destination {
+                defined.push(variable.index);
+            }
+            defined
+        }
+        Instruction::Load { variable, .. } => vec![variable.index],
+        Instruction::If { .. }
+        | Instruction::JumpTo(_)
+        | Instruction::Label(_)
+        | Instruction::Compare { .. }
+        | Instruction::Push(_)
+        | Instruction::SetIndex { .. }
+        | Instruction::ExitTry
+        | Instruction::Throw(_)
+        | Instruction::Return(_)
+        | Instruction::TailCall { .. } => Vec::new(),
+    }
+}
+
+#[allow(clippy::too_many_lines)] // One arm per instruction.
+fn variables_mut<Intrinsic>(instruction: &mut Instruction<Intrinsic>) -> Vec<&mut Variable> {
+    let mut variables = Vec::new();
+    let (values, destination): (Vec<&mut LiteralOrSource>, Option<&mut Destination>) =
+        match instruction {
+            Instruction::Add {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Sub {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Multiply {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Divide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Remainder {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Power {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Compare {
+                left,
+                right,
+                action: CompareAction::Store(destination),
+                ..
+            }
+            | Instruction::GetIndex {
+                target: left,
+                index: right,
+                destination,
+            } => (vec![left, right], Some(destination)),
+            Instruction::Compare { left, right, .. } => (vec![left, right], None),
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => (vec![target, index, value], None),
+            Instruction::LogicalNot { value, destination }
+            | Instruction::BitwiseNot { value, destination }
+            | Instruction::Negate { value, destination }
+            | Instruction::Convert {
+                value, destination, ..
+            }
+            | Instruction::CallValue {
+                target: value,
+                destination,
+                ..
+            }
+            | Instruction::Iterate {
+                source: value,
+                destination,
+            }
+            | Instruction::IterateNext {
+                iterator: value,
+                destination,
+                ..
+            } => (vec![value], Some(destination)),
+            Instruction::CallInstance {
+                target,
+                destination,
+                ..
+            } => (target.iter_mut().collect(), Some(destination)),
+            Instruction::If {
+                condition: value, ..
+            }
+            | Instruction::Push(value)
+            | Instruction::Return(Some(value))
+            | Instruction::Throw(value) => (vec![value], None),
+            Instruction::Load { value, variable } => {
+                variables.push(variable);
+                (vec![value], None)
+            }
+            Instruction::Call { destination, .. }
+            | Instruction::CallIntrinsic { destination, .. }
+            | Instruction::NewClosure { destination, .. }
+            | Instruction::EnterTry { destination, .. } => (Vec::new(), Some(destination)),
+            Instruction::JumpTo(_)
+            | Instruction::Label(_)
+            | Instruction::ExitTry
+            | Instruction::Return(None)
+            | Instruction::TailCall { .. } => (Vec::new(), None),
+        };
+    for value in values {
+        if let LiteralOrSource::Variable(variable) = value {
+            variables.push(variable);
+        }
+    }
+    if let Some(Destination::Variable(variable)) = destination {
+        variables.push(variable);
+    }
+    variables
+}
+
+/// Removes each instruction whose corresponding entry in `removed` is true,
+/// along with its debug information. Returns true if any instructions were
+/// removed.
+fn remove_instructions<Intrinsic>(block: &mut CodeBlock<Intrinsic>, removed: &[bool]) -> bool {
+    if !removed.contains(&true) {
+        return false;
+    }
+    let mut index = 0;
+    block.code.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+    if let Some(debug_info) = &mut block.debug_info {
+        let mut index = 0;
+        debug_info.locations.retain(|_| {
+            index += 1;
+            !removed.get(index - 1).copied().unwrap_or(false)
+        });
+    }
+    true
+}
@@ -0,0 +1,74 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{symbol::Symbol, DynamicValue, FaultKind, PoppedValues, Value};
+
+/// An iterator over a sequence of [`Value`]s.
+///
+/// This type wraps any Rust iterator, making it easy to implement
+/// [`DynamicValue::iterate()`]. Clones of an iterator share the same position,
+/// allowing iterators to be cheaply moved around by reference.
+#[derive(Clone)]
+pub struct ValueIterator(Arc<Mutex<Box<dyn Iterator<Item = Value> + Send>>>);
+
+impl ValueIterator {
+    /// Returns a new iterator that produces the values from `iterator`.
+    pub fn new<I>(iterator: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+        I::IntoIter: Send + 'static,
+    {
+        Self(Arc::new(Mutex::new(Box::new(iterator.into_iter()))))
+    }
+
+    /// Advances this iterator, returning the next value if one is available.
+    #[must_use]
+    pub fn next_value(&self) -> Option<Value> {
+        let mut iterator = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        iterator.next()
+    }
+}
+
+impl Debug for ValueIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueIterator").finish_non_exhaustive()
+    }
+}
+
+impl DynamicValue for ValueIterator {
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    fn kind(&self) -> Symbol {
+        Symbol::from("Iterator")
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        let other = other.as_dynamic::<Self>()?;
+        Some(Arc::ptr_eq(&self.0, &other.0))
+    }
+
+    fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
+        match name.as_str() {
+            "next" => {
+                args.verify_empty()?;
+                Ok(self.next_value().unwrap_or_default())
+            }
+            _ => Err(FaultKind::UnknownFunction {
+                kind: super::ValueKind::Dynamic(self.kind()),
+                name: name.clone(),
+            }),
+        }
+    }
+
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        Ok(Value::dynamic(self.clone()))
+    }
+
+    fn next_item(&self) -> Result<Option<Value>, FaultKind> {
+        Ok(self.next_value())
+    }
+}
@@ -10,6 +10,8 @@ use std::{
     str::CharIndices,
 };
 
+use crate::{BigInteger, Decimal, ParseNumberError};
+
 /// An iterator adapter thatallows peeking up to two positions ahead.
 pub struct DoublePeekable<I>
 where
@@ -276,6 +278,10 @@ pub fn decode_string_literal_contents(
 }
 
 /// Decodes all valid numeric literal formats supported by Bud and Bud Assembly.
+///
+/// Integers followed by an `n` are decoded as a [`Numeric::BigInteger`].
+/// Integers and reals followed by a `d` are decoded as a
+/// [`Numeric::Decimal`].
 pub fn decode_numeric_literal(
     chars: &mut DoublePeekable<CharIndices<'_>>,
     source: &str,
@@ -309,6 +315,13 @@ pub fn decode_numeric_literal(
             Cow::Borrowed(source)
         };
 
+        if let Some(suffix_offset) = take_suffix(chars, 'd') {
+            return Ok(NumericLiteral {
+                contents: Numeric::Decimal(source.parse()?),
+                last_offset: suffix_offset,
+            });
+        }
+
         let value = source.parse::<f64>()?;
 
         return Ok(NumericLiteral {
@@ -323,6 +336,17 @@ pub fn decode_numeric_literal(
     } else {
         Cow::Borrowed(source)
     };
+    if let Some(suffix_offset) = take_suffix(chars, 'n') {
+        return Ok(NumericLiteral {
+            contents: Numeric::BigInteger(source.parse()?),
+            last_offset: suffix_offset,
+        });
+    } else if let Some(suffix_offset) = take_suffix(chars, 'd') {
+        return Ok(NumericLiteral {
+            contents: Numeric::Decimal(source.parse()?),
+            last_offset: suffix_offset,
+        });
+    }
     let value = source.parse::<i64>()?;
     Ok(NumericLiteral {
         contents: Numeric::Integer(value),
@@ -330,6 +354,20 @@ pub fn decode_numeric_literal(
     })
 }
 
+/// Consumes `suffix` if it is the next character and it isn't the start of an
+/// identifier. Returns the offset of the suffix if it was consumed.
+fn take_suffix(chars: &mut DoublePeekable<CharIndices<'_>>, suffix: char) -> Option<usize> {
+    let is_suffix = chars.peek().map_or(false, |(_, ch)| *ch == suffix)
+        && !chars
+            .peek_second()
+            .map_or(false, |(_, ch)| ch.is_alphanumeric() || *ch == '_');
+    if is_suffix {
+        chars.next().map(|(offset, _)| offset)
+    } else {
+        None
+    }
+}
+
 /// A parsed numeric literal.
 pub struct NumericLiteral {
     /// The value that was parsed.
@@ -344,6 +382,10 @@ pub enum Numeric {
     Integer(i64),
     /// A real number (floating point).
     Real(f64),
+    /// An arbitrary-precision integer.
+    BigInteger(BigInteger),
+    /// An arbitrary-precision decimal.
+    Decimal(Decimal),
 }
 
 /// An error while decoding a numeric literal.
@@ -353,6 +395,8 @@ pub enum DecodeNumericError {
     Float(ParseFloatError),
     /// An error from parsing an integer value.
     Integer(ParseIntError),
+    /// An error from parsing a [`BigInteger`] or [`Decimal`] value.
+    Number(ParseNumberError),
 }
 
 impl DecodeNumericError {
@@ -368,6 +412,7 @@ impl Display for DecodeNumericError {
         match self {
             DecodeNumericError::Float(err) => Display::fmt(err, f),
             DecodeNumericError::Integer(err) => Display::fmt(err, f),
+            DecodeNumericError::Number(err) => Display::fmt(err, f),
         }
     }
 }
@@ -378,6 +423,12 @@ impl From<ParseFloatError> for DecodeNumericError {
     }
 }
 
+impl From<ParseNumberError> for DecodeNumericError {
+    fn from(err: ParseNumberError) -> Self {
+        Self::Number(err)
+    }
+}
+
 impl From<ParseIntError> for DecodeNumericError {
     fn from(err: ParseIntError) -> Self {
         Self::Integer(err)
@@ -16,34 +16,64 @@
 use std::{
     any::{type_name, Any},
     cmp::Ordering,
-    collections::{HashMap as StdHashMap, VecDeque},
+    collections::HashMap as StdHashMap,
     fmt::{Debug, Display, Write},
     hash::{Hash, Hasher},
     marker::PhantomData,
-    ops::{Add, Bound, Deref, Div, Index, IndexMut, Mul, RangeBounds, Sub},
+    ops::{Bound, Deref, Index, IndexMut, RangeBounds},
     str::FromStr,
     sync::Arc,
+    time::Instant,
     vec,
 };
 
+mod arithmetic;
+mod big_integer;
 /// A `HashMap` implementation that provides a defined iteration order.
 pub mod budmap;
+pub mod bytecode;
+mod cancellation;
+mod closure;
+mod cost;
+mod debug;
+mod decimal;
 mod dynamic;
+mod fault;
 pub mod ir;
+mod iterator;
 pub mod lexer_util;
 mod list;
 mod map;
+mod memory;
+mod record;
 mod string;
 mod symbol;
+mod verify;
 
-use crate::ir::{Scope, ScopeSymbolKind};
+use crate::{
+    arithmetic::IntegerOperation,
+    ir::{Scope, ScopeSymbolKind},
+    verify::Verifier,
+};
 
 pub use self::{
+    arithmetic::ArithmeticMode,
+    big_integer::{BigInteger, ParseNumberError},
+    cancellation::CancellationToken,
+    closure::Closure,
+    cost::{charge, InstructionCosts},
+    debug::{DebugInfo, SourceLocation},
+    decimal::Decimal,
     dynamic::{Dynamic, DynamicValue},
+    fault::FaultValue,
+    iterator::ValueIterator,
     list::List,
     map::HashMap,
+    memory::{allocate, AllocationBudget},
+    record::Record,
     string::StringLiteralDisplay,
     symbol::Symbol,
+    verify::{VerificationError, VerificationIssue, VerificationIssueKind},
 };
 
 /// A virtual machine instruction.
@@ -54,8 +84,8 @@ pub use self::{
 pub enum Instruction<Intrinsic> {
     /// Adds `left` and `right` and places the result in `destination`.
     ///
-    /// If this operation causes an overflow, [`Value::Void`] will be stored in
-    /// the destination instead.
+    /// Integer overflow is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
     Add {
         /// The left hand side of the operation.
         left: ValueOrSource,
@@ -66,8 +96,8 @@ pub enum Instruction<Intrinsic> {
     },
     /// Subtracts `right` from `left` and places the result in `destination`.
     ///
-    /// If this operation causes an overflow, [`Value::Void`] will be stored in
-    /// the destination instead.
+    /// Integer overflow is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
     Sub {
         /// The left hand side of the operation.
         left: ValueOrSource,
@@ -78,8 +108,8 @@ pub enum Instruction<Intrinsic> {
     },
     /// Multiply `left` by `right` and places the result in `destination`.
     ///
-    /// If this operation causes an overflow, [`Value::Void`] will be stored in
-    /// the destination instead.
+    /// Integer overflow is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
     Multiply {
         /// The left hand side of the operation.
         left: ValueOrSource,
@@ -90,8 +120,8 @@ pub enum Instruction<Intrinsic> {
     },
     /// Divides `left` by `right` and places the result in `destination`.
     ///
-    /// If this operation causes an overflow, [`Value::Void`] will be stored in
-    /// the destination instead.
+    /// Integer overflow is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
     Divide {
         /// The left hand side of the operation.
         left: ValueOrSource,
@@ -100,6 +130,46 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result to be stored in.
         destination: Destination,
     },
+    /// Divides `left` by `right`, rounding the result towards negative
+    /// infinity, and places the result in `destination`.
+    ///
+    /// Integer overflow and division by zero are handled according to the
+    /// virtual machine's [`ArithmeticMode`].
+    IntegerDivide {
+        /// The left hand side of the operation.
+        left: ValueOrSource,
+        /// The right hand side of the operation.
+        right: ValueOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
+    /// Places the remainder of dividing `left` by `right` in `destination`.
+    /// The remainder has the same sign as `right`, which makes this the
+    /// counterpart of [`Instruction::IntegerDivide`].
+    ///
+    /// Division by zero is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
+    Remainder {
+        /// The left hand side of the operation.
+        left: ValueOrSource,
+        /// The right hand side of the operation.
+        right: ValueOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
+    /// Raises `left` to the power of `right` and places the result in
+    /// `destination`.
+    ///
+    /// Integer overflow and negative integer exponents are handled according
+    /// to the virtual machine's [`ArithmeticMode`].
+    Power {
+        /// The left hand side of the operation.
+        left: ValueOrSource,
+        /// The right hand side of the operation.
+        right: ValueOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
     /// Performs a logical and of `left` and `right` and places the result in
     /// `destination`. This operation always results in a [`Value::Boolean`].
     ///
@@ -250,6 +320,16 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result to be stored in.
         destination: Destination,
     },
+    /// Negates `value`, storing the result in `destination`.
+    ///
+    /// Integer overflow is handled according to the virtual machine's
+    /// [`ArithmeticMode`].
+    Negate {
+        /// The value to negate.
+        value: ValueOrSource,
+        /// The destination for the result to be stored in.
+        destination: Destination,
+    },
     /// Converts a value to another type, storing the result in `destination`.
     ///
     /// If `value` cannot be converted, a fault will be returned.
@@ -330,6 +410,10 @@ pub enum Instruction<Intrinsic> {
     /// Upon returning from a function call, the arguments will no longer be on
     /// the stack. The value returned from the function (or [`Value::Void`] if
     /// no value was returned) will be placed in `destination`.
+    ///
+    /// Calls do not consume native stack space. If the virtual machine's
+    /// maximum call depth has been reached, [`FaultKind::CallDepthExceeded`]
+    /// is raised instead.
     Call {
         /// The vtable index within the current module of the function to call.
         /// If None, the current function is called recursively.
@@ -346,6 +430,26 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result of the call.
         destination: Destination,
     },
+    /// Calls a function in place of the current function, returning the
+    /// called function's result from the current frame.
+    ///
+    /// The arguments on the stack replace the current frame's arguments and
+    /// variables, and any exception handlers registered by the current frame
+    /// are removed. Because no frame is added, tail calls do not count
+    /// towards the virtual machine's maximum call depth.
+    TailCall {
+        /// The vtable index within the current module of the function to call.
+        /// If None, the current function is called recursively.
+        ///
+        /// If a vtable index is provided but is beyond the number of functions
+        /// registered to the current module, [`FaultKind::InvalidVtableIndex`]
+        /// will be returned.
+        vtable_index: Option<usize>,
+
+        /// The number of arguments on the stack that should be used as
+        /// arguments to this call.
+        arg_count: usize,
+    },
     /// Calls an intrinsic runtime function.
     ///
     /// When calling a function, values on the stack are "passed" to the
@@ -378,6 +482,9 @@ pub enum Instruction<Intrinsic> {
     /// Upon returning from a function call, the arguments will no longer be on
     /// the stack. The value returned from the function (or [`Value::Void`] if
     /// no value was returned) will be placed in `destination`.
+    ///
+    /// When the target is a [`Record`], the function named `<kind>.<name>` is
+    /// called with the record as its first argument, if it exists.
     CallInstance {
         /// The target of the function call. If None, the value on the stack
         /// prior to the arguments is the target of the call.
@@ -393,6 +500,134 @@ pub enum Instruction<Intrinsic> {
         /// The destination for the result of the call.
         destination: Destination,
     },
+    /// Calls a function value, such as a [`Closure`].
+    ///
+    /// When calling a function, values on the stack are "passed" to the
+    /// function being pushed to the stack before calling the function. To
+    /// ensure the correct number of arguments are taken even when variable
+    /// argument lists are supported, the number of arguments is passed and
+    /// controls the baseline of the stack.
+    ///
+    /// Any values captured by the closure are passed to the function after
+    /// the arguments from the stack. If the function expects a different
+    /// number of arguments, [`FaultKind::ArgumentCountMismatch`] will be
+    /// returned.
+    ///
+    /// Upon returning from a function call, the arguments will no longer be on
+    /// the stack. The value returned from the function (or [`Value::Void`] if
+    /// no value was returned) will be placed in `destination`.
+    CallValue {
+        /// The function value to call.
+        target: ValueOrSource,
+
+        /// The number of arguments on the stack that should be used as
+        /// arguments to this call.
+        arg_count: usize,
+
+        /// The destination for the result of the call.
+        destination: Destination,
+    },
+    /// Creates a [`Closure`] for the function at `vtable_index`, capturing
+    /// `capture_count` values from the stack, and stores it in `destination`.
+    NewClosure {
+        /// The vtable index within the current module of the function the
+        /// closure calls.
+        ///
+        /// If the vtable index is beyond the number of functions registered to
+        /// the current module, [`FaultKind::InvalidVtableIndex`] will be
+        /// returned.
+        vtable_index: usize,
+
+        /// The number of values on the stack to capture.
+        capture_count: usize,
+
+        /// The destination for the created closure.
+        destination: Destination,
+    },
+    /// Reads the value stored at `index` within `target` and places it in
+    /// `destination`.
+    ///
+    /// Only dynamic values implementing [`DynamicValue::get_index()`] support
+    /// indexing. For all other values, [`FaultKind::NotIndexable`] will be
+    /// returned.
+    GetIndex {
+        /// The value being indexed.
+        target: ValueOrSource,
+        /// The index to read.
+        index: ValueOrSource,
+        /// The destination for the value read.
+        destination: Destination,
+    },
+    /// Stores `value` at `index` within `target`.
+    ///
+    /// Only dynamic values implementing [`DynamicValue::set_index()`] support
+    /// indexing. For all other values, [`FaultKind::NotIndexable`] will be
+    /// returned.
+    SetIndex {
+        /// The value being indexed.
+        target: ValueOrSource,
+        /// The index to store `value` at.
+        index: ValueOrSource,
+        /// The value to store.
+        value: ValueOrSource,
+    },
+    /// Creates an iterator over `source` and places it in `destination`.
+    ///
+    /// Only dynamic values implementing [`DynamicValue::iterate()`] support
+    /// iteration. For all other values, [`FaultKind::NotIterable`] will be
+    /// returned.
+    Iterate {
+        /// The value to iterate over.
+        source: ValueOrSource,
+        /// The destination for the created iterator.
+        destination: Destination,
+    },
+    /// Advances `iterator`, storing the produced value in `destination`.
+    ///
+    /// If a value was produced, the virtual machine continues executing the
+    /// next instruction in sequence.
+    ///
+    /// If the iterator has no more values, nothing is stored in `destination`
+    /// and the virtual machine jumps to number `done_jump_to`. This number is
+    /// the absolute number from the start of the set of instructions being
+    /// executed.
+    IterateNext {
+        /// The iterator to advance.
+        iterator: ValueOrSource,
+        /// The destination for the produced value.
+        destination: Destination,
+        /// The 0-based index of the instruction to jump to when the iterator
+        /// is finished. This index is relative to the begining of the set of
+        /// instructions being executed.
+        done_jump_to: usize,
+    },
+    /// Registers an exception handler for the current stack frame.
+    ///
+    /// Until a matching [`Instruction::ExitTry`] is executed, any fault that
+    /// occurs in this stack frame or in a function called by it will be
+    /// caught by this handler. When a fault is caught, the stack is restored
+    /// to the depth it had when this instruction was executed, the caught
+    /// value is stored in `destination`, and the virtual machine jumps to
+    /// number `handler`.
+    ///
+    /// Values raised by [`Instruction::Throw`] are caught as-is. All other
+    /// faults are caught as a [`FaultValue`].
+    EnterTry {
+        /// The 0-based index of the instruction to jump to when a fault is
+        /// caught. This index is relative to the begining of the set of
+        /// instructions being executed.
+        handler: usize,
+        /// The destination for the caught value.
+        destination: Destination,
+    },
+    /// Removes the most recently registered exception handler of the current
+    /// stack frame.
+    ExitTry,
+    /// Raises [`FaultKind::Thrown`] with the provided value.
+    ///
+    /// If the value is a [`FaultValue`], the fault it contains is raised
+    /// again instead.
+    Throw(ValueOrSource),
 }
 
 impl<Intrinsic> Display for Instruction<Intrinsic>
@@ -422,6 +657,21 @@ where
                 right,
                 destination,
             } => write!(f, "div {left} {right} {destination}"),
+            Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            } => write!(f, "idiv {left} {right} {destination}"),
+            Instruction::Remainder {
+                left,
+                right,
+                destination,
+            } => write!(f, "rem {left} {right} {destination}"),
+            Instruction::Power {
+                left,
+                right,
+                destination,
+            } => write!(f, "pow {left} {right} {destination}"),
             Instruction::LogicalAnd {
                 left,
                 right,
@@ -468,6 +718,9 @@ where
             Instruction::BitwiseNot { value, destination } => {
                 write!(f, "bitnot {value} {destination}")
             }
+            Instruction::Negate { value, destination } => {
+                write!(f, "neg {value} {destination}")
+            }
             Instruction::Convert {
                 value,
                 kind,
@@ -509,6 +762,16 @@ where
                     write!(f, "recurse {arg_count} {destination}")
                 }
             }
+            Instruction::TailCall {
+                vtable_index,
+                arg_count,
+            } => {
+                if let Some(vtable_index) = vtable_index {
+                    write!(f, "tailcall #{vtable_index} {arg_count}")
+                } else {
+                    write!(f, "tailrecurse {arg_count}")
+                }
+            }
             Instruction::CallInstance {
                 target,
                 name,
@@ -528,6 +791,41 @@ where
             } => {
                 write!(f, "intrinsic {intrinsic} {arg_count} {destination}")
             }
+            Instruction::CallValue {
+                target,
+                arg_count,
+                destination,
+            } => write!(f, "callvalue {target} {arg_count} {destination}"),
+            Instruction::NewClosure {
+                vtable_index,
+                capture_count,
+                destination,
+            } => write!(f, "closure #{vtable_index} {capture_count} {destination}"),
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => write!(f, "getindex {target} {index} {destination}"),
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => write!(f, "setindex {target} {index} {value}"),
+            Instruction::Iterate {
+                source,
+                destination,
+            } => write!(f, "iterate {source} {destination}"),
+            Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => write!(f, "iternext {iterator} {destination} jump {done_jump_to}"),
+            Instruction::EnterTry {
+                handler,
+                destination,
+            } => write!(f, "try {destination} jump {handler}"),
+            Instruction::ExitTry => f.write_str("endtry"),
+            Instruction::Throw(value) => write!(f, "throw {value}"),
         }
     }
 }
@@ -773,6 +1071,51 @@ impl Value {
         }
     }
 
+    /// Returns the value stored at `index` within this value.
+    ///
+    /// Only dynamic values support indexing. See
+    /// [`DynamicValue::get_index()`].
+    pub fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        match self {
+            Value::Dynamic(value) => value.get_index(index),
+            _ => Err(FaultKind::NotIndexable(self.kind())),
+        }
+    }
+
+    /// Stores `value` at `index` within this value.
+    ///
+    /// Only dynamic values support indexing. See
+    /// [`DynamicValue::set_index()`].
+    pub fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        match self {
+            Value::Dynamic(dynamic) => dynamic.set_index(index, value),
+            _ => Err(FaultKind::NotIndexable(self.kind())),
+        }
+    }
+
+    /// Returns an iterator over the contents of this value.
+    ///
+    /// Only dynamic values support iteration. See
+    /// [`DynamicValue::iterate()`].
+    pub fn iterate(&self) -> Result<Value, FaultKind> {
+        match self {
+            Value::Dynamic(value) => value.iterate(),
+            _ => Err(FaultKind::NotIterable(self.kind())),
+        }
+    }
+
+    /// Advances this iterator, returning the next value. Returns `None` when
+    /// no values remain.
+    ///
+    /// Only dynamic values support iteration. See
+    /// [`DynamicValue::next_item()`].
+    pub fn next_item(&self) -> Result<Option<Value>, FaultKind> {
+        match self {
+            Value::Dynamic(value) => value.next_item(),
+            _ => Err(FaultKind::NotIterable(self.kind())),
+        }
+    }
+
     /// Converts this value to another kind, if possible.
     #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
     pub fn convert<Env>(&self, kind: &ValueKind, environment: &Env) -> Result<Self, FaultKind>
@@ -800,10 +1143,37 @@ impl Value {
             ValueKind::Real => match self {
                 Value::Integer(value) => Some(Value::Real(*value as f64)),
                 Value::Real(value) => Some(Value::Real(*value)),
+                Value::Dynamic(value) => value.as_f64().map(Value::Real),
                 _ => None,
             },
             ValueKind::Boolean => Some(Value::Boolean(self.is_truthy())),
-            ValueKind::Dynamic(kind) => Some(environment.convert(self, kind)?),
+            ValueKind::Dynamic(kind) => match self {
+                // Dynamic values may know how to convert themselves, such as a
+                // Record converting to a Map.
+                Value::Dynamic(value) => {
+                    if let Some(converted) = value.convert(kind) {
+                        Some(converted)
+                    } else {
+                        // Converting to source can't report exhausting the
+                        // allocation budget until it has returned.
+                        memory::take_refused()?;
+                        Some(environment.convert(self, kind)?)
+                    }
+                }
+                Value::Integer(value) if kind == "BigInteger" => {
+                    Some(Value::dynamic(BigInteger::from(*value)))
+                }
+                Value::Integer(value) if kind == "Decimal" => {
+                    Some(Value::dynamic(Decimal::from(*value)))
+                }
+                Value::Real(value) if kind == "BigInteger" => {
+                    BigInteger::from_f64(*value).map(Value::dynamic)
+                }
+                Value::Real(value) if kind == "Decimal" => {
+                    Decimal::from_f64(*value).map(Value::dynamic)
+                }
+                _ => Some(environment.convert(self, kind)?),
+            },
             ValueKind::Void => None,
         };
 
@@ -884,6 +1254,19 @@ impl Value {
         self.try_hash(&mut NullHasher)
     }
 
+    /// Returns this value formatted as Bud source code, if it can be
+    /// represented as source.
+    #[must_use]
+    pub fn to_source(&self) -> Option<String> {
+        match self {
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Real(value) => Some(format!("{value:?}")),
+            Value::Boolean(value) => Some(value.to_string()),
+            Value::Dynamic(value) => value.to_source(),
+            Value::Void => None,
+        }
+    }
+
     /// Attempts to compute a hash over this value. Returns true if the value
     /// contained supports hashing.
     ///
@@ -1163,12 +1546,14 @@ impl Display for ValueKind {
 struct Module<Intrinsic> {
     contents: StdHashMap<Symbol, ModuleItem>,
     vtable: Vec<VtableEntry<Intrinsic>>,
+    debug_info: StdHashMap<usize, DebugInfo>,
 }
 impl<Intrinsic> Default for Module<Intrinsic> {
     fn default() -> Self {
         Self {
             contents: StdHashMap::default(),
             vtable: Vec::default(),
+            debug_info: StdHashMap::default(),
         }
     }
 }
@@ -1203,6 +1588,27 @@ impl<Intrinsic> Module<Intrinsic> {
     ) -> usize {
         self.define_vtable_entry(name, VtableEntry::NativeFunction(Arc::new(function)))
     }
+
+    fn fault_stack_frame(
+        &self,
+        vtable_index: Option<usize>,
+        instruction_index: usize,
+    ) -> FaultStackFrame {
+        let function = vtable_index
+            .and_then(|index| self.vtable.get(index))
+            .and_then(|entry| match entry {
+                VtableEntry::Function(function) => Some(function.name.clone()),
+                VtableEntry::NativeFunction(_) => None,
+            });
+        let debug_info = vtable_index.and_then(|index| self.debug_info.get(&index));
+        FaultStackFrame {
+            vtable_index,
+            instruction_index,
+            function,
+            source: debug_info.and_then(|debug| debug.source.clone()),
+            location: debug_info.and_then(|debug| debug.location(instruction_index).cloned()),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1258,6 +1664,12 @@ where
     }
 }
 
+/// The functions defined in a [`VirtualMachine`] at a point in time.
+pub(crate) struct Checkpoint {
+    vtable_length: usize,
+    contents: StdHashMap<Symbol, ModuleItem>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum ModuleItem {
     Function(usize),
@@ -1363,7 +1775,7 @@ enum ModuleItem {
 ///   conditionally.
 ///
 ///
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VirtualMachine<Env>
 where
     Env: Environment,
@@ -1374,6 +1786,23 @@ where
     persistent_variables: Vec<Symbol>,
     local_module: Module<Env::Intrinsic>,
     environment: Env,
+    arithmetic_mode: ArithmeticMode,
+    maximum_call_depth: usize,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
+}
+
+/// The maximum number of nested function calls a [`VirtualMachine`] allows by
+/// default.
+pub const DEFAULT_MAXIMUM_CALL_DEPTH: usize = 100_000;
+
+impl<Env> Default for VirtualMachine<Env>
+where
+    Env: Environment + Default,
+{
+    fn default() -> Self {
+        Self::default_for(Env::default())
+    }
 }
 
 impl VirtualMachine<()> {
@@ -1399,6 +1828,10 @@ where
             stack: Stack::new(initial_stack_capacity, maximum_stack_capacity),
             local_module: Module::default(),
             persistent_variables: Vec::new(),
+            arithmetic_mode: ArithmeticMode::default(),
+            maximum_call_depth: DEFAULT_MAXIMUM_CALL_DEPTH,
+            cancellation: None,
+            deadline: None,
         }
     }
 
@@ -1417,6 +1850,101 @@ where
         &mut self.environment
     }
 
+    /// Sets how integer overflow and division by zero are handled and returns
+    /// self. This is a builder-style function.
+    ///
+    /// By default, [`ArithmeticMode::Void`] is used.
+    #[must_use]
+    pub fn with_arithmetic_mode(mut self, mode: ArithmeticMode) -> Self {
+        self.set_arithmetic_mode(mode);
+        self
+    }
+
+    /// Sets how integer overflow and division by zero are handled.
+    pub fn set_arithmetic_mode(&mut self, mode: ArithmeticMode) {
+        self.arithmetic_mode = mode;
+    }
+
+    /// Returns how integer overflow and division by zero are handled.
+    #[must_use]
+    pub const fn arithmetic_mode(&self) -> ArithmeticMode {
+        self.arithmetic_mode
+    }
+
+    /// Sets the maximum number of nested function calls and returns self.
+    /// This is a builder-style function.
+    ///
+    /// Calling a function while this many calls are already executing raises
+    /// [`FaultKind::CallDepthExceeded`]. By default,
+    /// [`DEFAULT_MAXIMUM_CALL_DEPTH`] is used.
+    #[must_use]
+    pub fn with_maximum_call_depth(mut self, maximum_call_depth: usize) -> Self {
+        self.set_maximum_call_depth(maximum_call_depth);
+        self
+    }
+
+    /// Sets the maximum number of nested function calls.
+    ///
+    /// Calling a function while this many calls are already executing raises
+    /// [`FaultKind::CallDepthExceeded`].
+    pub fn set_maximum_call_depth(&mut self, maximum_call_depth: usize) {
+        self.maximum_call_depth = maximum_call_depth;
+    }
+
+    /// Returns the maximum number of nested function calls.
+    #[must_use]
+    pub const fn maximum_call_depth(&self) -> usize {
+        self.maximum_call_depth
+    }
+
+    /// Sets the token that can cancel execution and returns self. This is a
+    /// builder-style function.
+    ///
+    /// After [`CancellationToken::cancel()`] is called, executing code raises
+    /// [`FaultKind::Cancelled`].
+    #[must_use]
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.set_cancellation(Some(cancellation));
+        self
+    }
+
+    /// Sets the token that can cancel execution.
+    ///
+    /// After [`CancellationToken::cancel()`] is called, executing code raises
+    /// [`FaultKind::Cancelled`].
+    pub fn set_cancellation(&mut self, cancellation: Option<CancellationToken>) {
+        self.cancellation = cancellation;
+    }
+
+    /// Returns the token that can cancel execution.
+    #[must_use]
+    pub const fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Sets the time at which execution is stopped and returns self. This is
+    /// a builder-style function.
+    ///
+    /// Code executing after `deadline` raises [`FaultKind::TimedOut`].
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.set_deadline(Some(deadline));
+        self
+    }
+
+    /// Sets the time at which execution is stopped.
+    ///
+    /// Code executing after `deadline` raises [`FaultKind::TimedOut`].
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Returns the time at which execution is stopped.
+    #[must_use]
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
     /// Returns a list of persistent variables defined with
     /// [`Scope::define_persistent_variable()`]
     pub fn persistent_variables(&self) -> &[Symbol] {
@@ -1482,6 +2010,94 @@ where
         }
     }
 
+    /// Statically verifies `instructions` as if they were executed by
+    /// [`VirtualMachine::run()`] with `variable_count` variables.
+    ///
+    /// Instructions that pass verification will not fault with
+    /// [`FaultKind::InvalidVariableIndex`], [`FaultKind::InvalidArgumentIndex`],
+    /// [`FaultKind::InvalidVtableIndex`], or [`FaultKind::StackUnderflow`], and
+    /// every [`Instruction::Call`] passes the number of arguments the called
+    /// function expects. All issues found are returned together.
+    pub fn verify(
+        &self,
+        instructions: &[Instruction<Env::Intrinsic>],
+        variable_count: usize,
+    ) -> Result<(), VerificationError> {
+        let mut issues = Vec::new();
+        Verifier {
+            function: None,
+            code: instructions,
+            arg_count: 0,
+            variable_count,
+            vtable: &self.local_module.vtable,
+        }
+        .verify(&mut issues);
+        VerificationError::from_issues(issues)
+    }
+
+    /// Statically verifies `function` against the functions defined in this
+    /// virtual machine. See [`VirtualMachine::verify()`] for the checks
+    /// performed.
+    ///
+    /// Call this before [`VirtualMachine::define_function()`] to reject
+    /// untrusted functions before they can be executed. Functions that call
+    /// each other can be verified after all of them have been defined using
+    /// [`VirtualMachine::verify_functions()`].
+    pub fn verify_function(
+        &self,
+        function: &Function<Env::Intrinsic>,
+    ) -> Result<(), VerificationError> {
+        let mut issues = Vec::new();
+        Verifier::for_function(function, &self.local_module.vtable).verify(&mut issues);
+        VerificationError::from_issues(issues)
+    }
+
+    /// Statically verifies every function defined in this virtual machine.
+    /// See [`VirtualMachine::verify()`] for the checks performed.
+    pub fn verify_functions(&self) -> Result<(), VerificationError> {
+        self.verify_functions_from(0)
+    }
+
+    /// Statically verifies the functions defined at or after `vtable_index`.
+    fn verify_functions_from(&self, vtable_index: usize) -> Result<(), VerificationError> {
+        let mut issues = Vec::new();
+        for entry in &self.local_module.vtable[vtable_index..] {
+            if let VtableEntry::Function(function) = entry {
+                Verifier::for_function(function, &self.local_module.vtable).verify(&mut issues);
+            }
+        }
+        VerificationError::from_issues(issues)
+    }
+
+    /// Returns the current set of defined functions, allowing functions
+    /// defined afterwards to be verified and removed if needed.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            vtable_length: self.local_module.vtable.len(),
+            contents: self.local_module.contents.clone(),
+        }
+    }
+
+    /// Verifies the functions defined since `checkpoint` was taken. If
+    /// verification fails, the functions are removed.
+    pub(crate) fn verify_since(&mut self, checkpoint: Checkpoint) -> Result<(), VerificationError> {
+        let result = self.verify_functions_from(checkpoint.vtable_length);
+        if result.is_err() {
+            self.rollback(checkpoint);
+        }
+        result
+    }
+
+    /// Removes every function defined since `checkpoint` was taken.
+    pub(crate) fn rollback(&mut self, checkpoint: Checkpoint) {
+        let module = &mut self.local_module;
+        module.vtable.truncate(checkpoint.vtable_length);
+        module
+            .debug_info
+            .retain(|vtable_index, _| *vtable_index < checkpoint.vtable_length);
+        module.contents = checkpoint.contents;
+    }
+
     /// Runs a set of instructions, allocating space for `variable_count`
     /// variables to be used by `instructions`. When this function returns, the
     /// stack space for the variables will be removed.
@@ -1528,21 +2144,22 @@ where
         let variables_offset = return_offset
             .checked_sub(variable_count)
             .ok_or(FaultKind::StackUnderflow)?;
-        let returned_value = match (StackFrame {
-            module: &self.local_module,
-            stack: &mut self.stack,
-            environment: &mut self.environment,
-            return_offset,
-            destination: Destination::Return,
-            variables_offset,
-            arg_offset: 0,
-            return_value: None,
-            vtable_index: None,
-            operation_index: 0,
-            _output: PhantomData,
-        }
-        .execute_operations(&instructions))
-        {
+        let result = StackFrame::new(
+            self,
+            &instructions,
+            vec![SuspendedFrame {
+                return_offset,
+                arg_offset: 0,
+                variables_offset,
+                return_value: None,
+                vtable_index: None,
+                operation_index: 0,
+                destination: Destination::Return,
+                handlers: Vec::new(),
+            }],
+        )
+        .execute_operations(0);
+        let returned_value = match result {
             Err(Fault {
                 kind: FaultOrPause::Pause(paused_evaluation),
                 stack,
@@ -1571,24 +2188,10 @@ where
     fn resume<'a, Output: FromStack>(
         &'a mut self,
         operations: Instructions<'a, Env::Intrinsic>,
-        mut paused_stack: VecDeque<PausedFrame>,
+        paused_stack: Vec<SuspendedFrame>,
     ) -> Result<Output, Fault<'a, Env, Output>> {
-        let first_frame = paused_stack.pop_front().expect("at least one frame");
-        let value = match (StackFrame {
-            module: &self.local_module,
-            stack: &mut self.stack,
-            environment: &mut self.environment,
-            return_offset: first_frame.return_offset,
-            destination: first_frame.destination,
-            variables_offset: first_frame.variables_offset,
-            arg_offset: first_frame.arg_offset,
-            return_value: first_frame.return_value,
-            vtable_index: first_frame.vtable_index,
-            operation_index: first_frame.operation_index,
-            _output: PhantomData,
-        }
-        .resume_executing_execute_operations(&operations, paused_stack))
-        {
+        let result = StackFrame::new(self, &operations, paused_stack).execute_operations(0);
+        let value = match result {
             Ok(value) => value,
             Err(Fault {
                 kind: FaultOrPause::Pause(paused_evaluation),
@@ -1714,6 +2317,16 @@ where
         Some(self.local_module.define_function(function))
     }
 
+    fn next_vtable_index(&self) -> Option<usize> {
+        Some(self.local_module.vtable.len())
+    }
+
+    fn define_debug_info(&mut self, vtable_index: usize, debug_info: DebugInfo) {
+        self.local_module
+            .debug_info
+            .insert(vtable_index, debug_info);
+    }
+
     fn define_persistent_variable(&mut self, name: Symbol, variable: crate::ir::Variable) {
         if variable.index() >= self.persistent_variables.len() {
             self.persistent_variables
@@ -1729,6 +2342,9 @@ enum FlowControl {
     JumpTo(usize),
 }
 
+/// The executor of Bud code. The fields describe the frame that is currently
+/// executing, while the frames of the functions waiting on a call to return
+/// are kept in `callers` rather than on the native stack.
 #[derive(Debug)]
 struct StackFrame<'a, Env, Output>
 where
@@ -1737,6 +2353,20 @@ where
     module: &'a Module<Env::Intrinsic>,
     stack: &'a mut Stack,
     environment: &'a mut Env,
+    arithmetic_mode: ArithmeticMode,
+    maximum_call_depth: usize,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    // The number of instructions to execute before checking for cancellation.
+    instructions_until_interrupt_check: u32,
+    // The number of native operations waiting on Bud code they called.
+    native_depth: usize,
+    // The instructions executed by frames without a vtable index.
+    root: &'a [Instruction<Env::Intrinsic>],
+    // The frames waiting on a call to return, outermost first.
+    callers: Vec<SuspendedFrame>,
+
+    code: &'a [Instruction<Env::Intrinsic>],
     // Each stack frame cannot pop below this offset.
     return_offset: usize,
     destination: Destination,
@@ -1746,111 +2376,130 @@ where
 
     vtable_index: Option<usize>,
     operation_index: usize,
+    handlers: Vec<Handler>,
 
     _output: PhantomData<Output>,
 }
 
-impl<'a, Env, Output> StackFrame<'a, Env, Output>
-where
-    Env: Environment,
-{
-    fn resume_executing_execute_operations(
-        &mut self,
-        operations: &[Instruction<Env::Intrinsic>],
-        mut resume_from: VecDeque<PausedFrame>,
-    ) -> Result<Value, Fault<'static, Env, Output>> {
-        if let Some(call_to_resume) = resume_from.pop_front() {
-            // We were calling a function when this happened. We need to finish the call.
-            let vtable_index = call_to_resume
-                .vtable_index
-                .expect("can only resume a called function");
-            let function = match &self.module.vtable[vtable_index] {
-                VtableEntry::Function(function) => function,
-                VtableEntry::NativeFunction(_) => unreachable!("cannot resume a native function"),
-            };
-            let mut running_frame = StackFrame {
-                module: self.module,
-                stack: self.stack,
-                environment: self.environment,
-                return_offset: call_to_resume.return_offset,
-                destination: call_to_resume.destination,
-                variables_offset: call_to_resume.variables_offset,
-                arg_offset: call_to_resume.arg_offset,
-                return_value: call_to_resume.return_value,
-                vtable_index: call_to_resume.vtable_index,
-                operation_index: call_to_resume.operation_index,
-                _output: PhantomData,
-            };
-            let returned_value = match running_frame
-                .resume_executing_execute_operations(&function.code, resume_from)
-            {
-                Ok(value) => value,
-                Err(Fault {
-                    kind: FaultOrPause::Pause(mut paused),
-                    stack,
-                }) => {
-                    paused.stack.push_front(PausedFrame {
-                        return_offset: self.return_offset,
-                        arg_offset: self.arg_offset,
-                        variables_offset: self.variables_offset,
-                        return_value: self.return_value.take(),
-                        vtable_index: self.vtable_index,
-                        operation_index: self.operation_index,
-                        destination: self.destination,
-                    });
-                    return Err(Fault {
-                        kind: FaultOrPause::Pause(paused),
-                        stack,
-                    });
-                }
-                Err(err) => return Err(err),
-            };
+/// The number of instructions executed between checks for cancellation and
+/// deadlines.
+const INTERRUPT_CHECK_INTERVAL: u32 = 256;
 
-            self.clean_stack_after_call(
-                call_to_resume.arg_offset,
-                call_to_resume.destination,
-                returned_value,
-            )?;
+/// The maximum number of native operations, such as sorting with a
+/// comparator, that can be waiting on Bud code they called. Unlike Bud calls,
+/// each of these consumes native stack space.
+const MAXIMUM_NATIVE_CALL_DEPTH: usize = 64;
 
-            // The call that was executing when we paused has finished, we can
-            // now resume executing our frame's instructions.
-        }
+/// An exception handler registered by [`Instruction::EnterTry`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Handler {
+    operation_index: usize,
+    destination: Destination,
+    stack_length: usize,
+}
 
-        self.execute_operations(operations)
+impl<'a, Env, Output> StackFrame<'a, Env, Output>
+where
+    Env: Environment,
+{
+    /// Returns an executor for `vm` that continues executing the last of
+    /// `frames`. The other frames are waiting on a call to return.
+    fn new(
+        vm: &'a mut VirtualMachine<Env>,
+        root: &'a [Instruction<Env::Intrinsic>],
+        mut frames: Vec<SuspendedFrame>,
+    ) -> Self {
+        let frame = frames.pop().expect("at least one frame");
+        let mut executor = Self {
+            module: &vm.local_module,
+            stack: &mut vm.stack,
+            environment: &mut vm.environment,
+            arithmetic_mode: vm.arithmetic_mode,
+            maximum_call_depth: vm.maximum_call_depth,
+            cancellation: vm.cancellation.clone(),
+            deadline: vm.deadline,
+            instructions_until_interrupt_check: 0,
+            native_depth: 0,
+            root,
+            callers: frames,
+            code: root,
+            return_offset: 0,
+            destination: Destination::Return,
+            variables_offset: 0,
+            arg_offset: 0,
+            return_value: None,
+            vtable_index: None,
+            operation_index: 0,
+            handlers: Vec::new(),
+            _output: PhantomData,
+        };
+        executor.restore(frame);
+        executor
+    }
+
+    /// Removes the state of the currently executing frame.
+    fn suspend(&mut self) -> SuspendedFrame {
+        SuspendedFrame {
+            return_offset: self.return_offset,
+            arg_offset: self.arg_offset,
+            variables_offset: self.variables_offset,
+            return_value: self.return_value.take(),
+            vtable_index: self.vtable_index,
+            operation_index: self.operation_index,
+            destination: self.destination,
+            handlers: std::mem::take(&mut self.handlers),
+        }
     }
+
+    /// Makes `frame` the currently executing frame.
+    fn restore(&mut self, frame: SuspendedFrame) {
+        let module = self.module;
+        self.code = match frame.vtable_index {
+            Some(vtable_index) => match &module.vtable[vtable_index] {
+                VtableEntry::Function(function) => &function.code,
+                VtableEntry::NativeFunction(_) => unreachable!("native functions have no frame"),
+            },
+            None => self.root,
+        };
+        self.return_offset = frame.return_offset;
+        self.arg_offset = frame.arg_offset;
+        self.variables_offset = frame.variables_offset;
+        self.return_value = frame.return_value;
+        self.vtable_index = frame.vtable_index;
+        self.operation_index = frame.operation_index;
+        self.destination = frame.destination;
+        self.handlers = frame.handlers;
+    }
+
+    /// Executes until the frame at `base_depth` returns, including any
+    /// functions called along the way.
     fn execute_operations(
         &mut self,
-        operations: &[Instruction<Env::Intrinsic>],
+        base_depth: usize,
     ) -> Result<Value, Fault<'static, Env, Output>> {
+        let _allocation_budget = memory::activate(self.environment.allocation_budget().cloned());
+        let _charges = cost::activate();
         loop {
-            if matches!(self.environment.step(), ExecutionBehavior::Pause) {
-                let mut stack = VecDeque::new();
-                stack.push_front(PausedFrame {
-                    return_offset: self.return_offset,
-                    arg_offset: self.arg_offset,
-                    variables_offset: self.variables_offset,
-                    return_value: self.return_value.take(),
-                    vtable_index: self.vtable_index,
-                    operation_index: self.operation_index,
-                    destination: self.destination,
-                });
-                return Err(Fault {
-                    kind: FaultOrPause::Pause(PausedExecution {
-                        context: None,
-                        operations: None,
-                        stack,
-                        _return: PhantomData,
-                    }),
-                    stack: vec![FaultStackFrame {
-                        vtable_index: self.vtable_index,
-                        instruction_index: self.operation_index,
-                    }],
-                });
+            let code = self.code;
+            let operation = code.get(self.operation_index);
+            if matches!(
+                self.environment.step_instruction(operation),
+                ExecutionBehavior::Pause
+            ) {
+                return Err(self.pause(base_depth));
             }
 
-            let operation = operations.get(self.operation_index);
-            let operation = if let Some(operation) = operation {
-                operation
+            let flow = if let Some(operation) = operation {
+                self.operation_index += 1;
+                let flow = match self.check_interrupted() {
+                    Ok(()) => self.execute_operation(operation),
+                    Err(kind) => Err(Fault::from(kind)),
+                };
+                let charged = cost::take_charges();
+                if charged > 0 {
+                    self.environment.consume(charged);
+                }
+                flow
             } else {
                 // Implicit return;
                 let return_value = self.return_value.take().unwrap_or_else(|| {
@@ -1860,42 +2509,175 @@ where
                         Value::Void
                     }
                 });
-                return Ok(return_value);
+                Ok(Some(FlowControl::Return(return_value)))
             };
-            self.operation_index += 1;
-            match self.execute_operation(operation) {
-                Ok(None) => {}
+            let result = match flow {
+                Ok(None) => Ok(()),
                 Ok(Some(FlowControl::JumpTo(op_index))) => {
                     self.operation_index = op_index;
+                    Ok(())
                 }
                 Ok(Some(FlowControl::Return(value))) => {
-                    return Ok(value);
-                }
-                Err(mut fault) => {
-                    if let FaultOrPause::Pause(paused_frame) = &mut fault.kind {
-                        paused_frame.stack.push_front(PausedFrame {
-                            return_offset: self.return_offset,
-                            arg_offset: self.arg_offset,
-                            variables_offset: self.variables_offset,
-                            return_value: self.return_value.take(),
-                            vtable_index: self.vtable_index,
-                            operation_index: self.operation_index,
-                            destination: self.destination,
-                        });
+                    if self.callers.len() == base_depth {
+                        return Ok(value);
                     }
-                    fault.stack.insert(
-                        0,
-                        FaultStackFrame {
-                            vtable_index: self.vtable_index,
-                            instruction_index: self.operation_index - 1,
-                        },
-                    );
-                    return Err(fault);
+                    self.return_to_caller(value)
+                }
+                Err(fault) => Err(fault),
+            };
+            if let Err(fault) = result {
+                self.unwind(fault, base_depth)?;
+            }
+        }
+    }
+
+    /// Returns an error if execution has been cancelled or has run past its
+    /// deadline. Because reading the clock is comparatively expensive, this is
+    /// only checked once every [`INTERRUPT_CHECK_INTERVAL`] instructions.
+    fn check_interrupted(&mut self) -> Result<(), FaultKind> {
+        if self.instructions_until_interrupt_check > 0 {
+            self.instructions_until_interrupt_check -= 1;
+            return Ok(());
+        }
+        self.instructions_until_interrupt_check = INTERRUPT_CHECK_INTERVAL - 1;
+
+        if matches!(&self.cancellation, Some(cancellation) if cancellation.is_cancelled()) {
+            Err(FaultKind::Cancelled)
+        } else if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+            Err(FaultKind::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Suspends every frame above `base_depth`, returning them as a paused
+    /// execution.
+    fn pause(&mut self, base_depth: usize) -> Fault<'static, Env, Output> {
+        let mut fault_stack = self.callers[base_depth..]
+            .iter()
+            .map(|frame| {
+                self.module
+                    .fault_stack_frame(frame.vtable_index, frame.operation_index - 1)
+            })
+            .collect::<Vec<_>>();
+        fault_stack.push(
+            self.module
+                .fault_stack_frame(self.vtable_index, self.operation_index),
+        );
+        let current = self.suspend();
+        let stack = self
+            .callers
+            .drain(base_depth..)
+            .chain(std::iter::once(current))
+            .collect();
+        Fault {
+            kind: FaultOrPause::Pause(PausedExecution {
+                context: None,
+                operations: None,
+                stack,
+                _return: PhantomData,
+            }),
+            stack: fault_stack,
+        }
+    }
+
+    /// Returns from the current frame, storing `value` in the destination
+    /// the caller requested.
+    fn return_to_caller(&mut self, value: Value) -> Result<(), Fault<'static, Env, Output>> {
+        let arg_offset = self.arg_offset;
+        let destination = self.destination;
+        let caller = self.callers.pop().expect("a caller is waiting");
+        self.restore(caller);
+        self.clean_stack_after_call(arg_offset, destination, value)
+    }
+
+    /// Executes the function that was just called until it returns to the
+    /// calling frame. This is used by native operations that call back into
+    /// Bud code.
+    fn finish_call(&mut self) -> Result<(), Fault<'static, Env, Output>> {
+        let result = if self.native_depth < MAXIMUM_NATIVE_CALL_DEPTH {
+            self.native_depth += 1;
+            let result = self.execute_operations(self.callers.len());
+            self.native_depth -= 1;
+            result
+        } else {
+            Err(Fault::from(FaultKind::CallDepthExceeded(
+                MAXIMUM_NATIVE_CALL_DEPTH,
+            )))
+        };
+        match result {
+            Ok(value) => self.return_to_caller(value),
+            Err(fault) => {
+                let caller = self.callers.pop().expect("a caller is waiting");
+                self.restore(caller);
+                match fault {
+                    Fault {
+                        kind: FaultOrPause::Pause(_),
+                        stack,
+                    } => Err(Fault {
+                        kind: FaultOrPause::Fault(FaultKind::UnresumablePause),
+                        stack,
+                    }),
+                    fault => Err(fault),
                 }
             }
         }
     }
 
+    /// Removes frames until `fault` is caught. If no frame above `base_depth`
+    /// catches the fault, it is returned while the frame at `base_depth` is
+    /// the current frame.
+    fn unwind(
+        &mut self,
+        mut fault: Fault<'static, Env, Output>,
+        base_depth: usize,
+    ) -> Result<(), Fault<'static, Env, Output>> {
+        loop {
+            fault = match self.catch(fault) {
+                Ok(()) => return Ok(()),
+                Err(fault) => fault,
+            };
+            fault.stack.insert(
+                0,
+                self.module
+                    .fault_stack_frame(self.vtable_index, self.operation_index - 1),
+            );
+            if self.callers.len() == base_depth {
+                return Err(fault);
+            }
+            let caller = self.callers.pop().expect("checked length");
+            self.restore(caller);
+        }
+    }
+
+    /// Attempts to catch `fault` using the most recently registered exception
+    /// handler. If the fault was caught, execution continues at the handler.
+    fn catch(
+        &mut self,
+        fault: Fault<'static, Env, Output>,
+    ) -> Result<(), Fault<'static, Env, Output>> {
+        let handler = match (&fault.kind, self.handlers.last()) {
+            // Scripts must not be able to prevent themselves from being
+            // stopped.
+            (FaultOrPause::Fault(FaultKind::Cancelled | FaultKind::TimedOut), _) => {
+                return Err(fault)
+            }
+            (FaultOrPause::Fault(_), Some(handler)) => *handler,
+            _ => return Err(fault),
+        };
+        let caught = match fault.kind {
+            FaultOrPause::Fault(FaultKind::Thrown(value)) => value,
+            FaultOrPause::Fault(kind) => Value::dynamic(FaultValue::new(kind)),
+            FaultOrPause::Pause(_) => unreachable!("pauses are never caught"),
+        };
+
+        self.handlers.pop();
+        self.stack.truncate(handler.stack_length);
+        *self.resolve_value_source_mut(handler.destination)? = caught;
+        self.operation_index = handler.operation_index;
+        Ok(())
+    }
+
     #[allow(clippy::too_many_lines)]
     fn execute_operation(
         &mut self,
@@ -1925,6 +2707,21 @@ where
                 right,
                 destination,
             } => self.checked_div(left, right, *destination),
+            Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            } => self.checked_int_div(left, right, *destination),
+            Instruction::Remainder {
+                left,
+                right,
+                destination,
+            } => self.checked_rem(left, right, *destination),
+            Instruction::Power {
+                left,
+                right,
+                destination,
+            } => self.checked_pow(left, right, *destination),
             Instruction::LogicalAnd {
                 left,
                 right,
@@ -1985,6 +2782,7 @@ where
                 value: left,
                 destination,
             } => self.bitnot(left, *destination),
+            Instruction::Negate { value, destination } => self.negate(value, *destination),
             Instruction::Convert {
                 value,
                 kind,
@@ -2032,6 +2830,10 @@ where
                 arg_count,
                 destination,
             } => self.call(*vtable_index, *arg_count, *destination),
+            Instruction::TailCall {
+                vtable_index,
+                arg_count,
+            } => self.tail_call(*vtable_index, *arg_count),
             Instruction::CallIntrinsic {
                 intrinsic,
                 arg_count,
@@ -2043,6 +2845,58 @@ where
                 arg_count,
                 destination,
             } => self.call_instance(target.as_ref(), name, *arg_count, *destination),
+            Instruction::CallValue {
+                target,
+                arg_count,
+                destination,
+            } => self.call_value(target, *arg_count, *destination),
+            Instruction::NewClosure {
+                vtable_index,
+                capture_count,
+                destination,
+            } => self.new_closure(*vtable_index, *capture_count, *destination),
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => self.get_index(target, index, *destination),
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => self.set_index(target, index, value),
+            Instruction::Iterate {
+                source,
+                destination,
+            } => self.iterate(source, *destination),
+            Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => self.iterate_next(iterator, *destination, *done_jump_to),
+            Instruction::EnterTry {
+                handler,
+                destination,
+            } => {
+                self.handlers.push(Handler {
+                    operation_index: *handler,
+                    destination: *destination,
+                    stack_length: self.stack.len(),
+                });
+                Ok(None)
+            }
+            Instruction::ExitTry => {
+                self.handlers.pop();
+                Ok(None)
+            }
+            Instruction::Throw(value) => {
+                let value = self.resolve_value_or_source(value)?;
+                let kind = match value.as_dynamic::<FaultValue>() {
+                    Some(fault) => fault.kind().clone(),
+                    None => FaultKind::Thrown(value.clone()),
+                };
+                Err(Fault::from(kind))
+            }
         }
     }
 
@@ -2252,31 +3106,42 @@ where
 
         match function {
             VtableEntry::Function(function) => {
-                assert_eq!(function.arg_count, arg_count);
+                if function.arg_count != arg_count {
+                    return Err(Fault::from(FaultKind::ArgumentCountMismatch {
+                        expected: function.arg_count,
+                        received: arg_count,
+                    }));
+                }
+
+                if self.callers.len() >= self.maximum_call_depth {
+                    return Err(Fault::from(FaultKind::CallDepthExceeded(
+                        self.maximum_call_depth,
+                    )));
+                }
 
                 let variables_offset = self.stack.len();
                 let return_offset = variables_offset + function.variable_count;
-                let arg_offset = variables_offset - function.arg_count;
+                let arg_offset = variables_offset
+                    .checked_sub(function.arg_count)
+                    .ok_or_else(Fault::stack_underflow)?;
                 if function.variable_count > 0 {
                     self.stack.grow_to(return_offset)?;
                 }
 
-                let mut frame = StackFrame {
-                    module: self.module,
-                    stack: self.stack,
-                    environment: self.environment,
+                // The called function executes once the current instruction
+                // finishes, and returns to this frame when it completes.
+                let caller = self.suspend();
+                self.callers.push(caller);
+                self.restore(SuspendedFrame {
                     return_offset,
-                    destination,
-                    variables_offset,
                     arg_offset,
+                    variables_offset,
                     return_value: None,
                     vtable_index: Some(vtable_index),
                     operation_index: 0,
-                    _output: PhantomData,
-                };
-                let returned_value = frame.execute_operations(&function.code)?;
-
-                self.clean_stack_after_call(arg_offset, destination, returned_value)?;
+                    destination,
+                    handlers: Vec::new(),
+                });
 
                 Ok(None)
             }
@@ -2305,6 +3170,61 @@ where
         }
     }
 
+    fn tail_call(
+        &mut self,
+        vtable_index: Option<usize>,
+        arg_count: usize,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let vtable_index = vtable_index
+            .or(self.vtable_index)
+            .ok_or(FaultKind::InvalidVtableIndex)?;
+        let module = self.module;
+        let function = module
+            .vtable
+            .get(vtable_index)
+            .ok_or(FaultKind::InvalidVtableIndex)?;
+
+        match function {
+            VtableEntry::Function(function) => {
+                if function.arg_count != arg_count {
+                    return Err(Fault::from(FaultKind::ArgumentCountMismatch {
+                        expected: function.arg_count,
+                        received: arg_count,
+                    }));
+                }
+
+                let args_start = match self.stack.len().checked_sub(arg_count) {
+                    Some(args_start) if args_start >= self.return_offset => args_start,
+                    _ => return Err(Fault::stack_underflow()),
+                };
+
+                // Move the new arguments down to where this frame's arguments
+                // begin, discarding this frame's arguments and variables.
+                self.stack.remove_range(self.arg_offset..args_start);
+                let variables_offset = self.arg_offset + arg_count;
+                let return_offset = variables_offset + function.variable_count;
+                self.stack.grow_to(return_offset)?;
+
+                self.code = &function.code;
+                self.variables_offset = variables_offset;
+                self.return_offset = return_offset;
+                self.return_value = None;
+                self.vtable_index = Some(vtable_index);
+                self.operation_index = 0;
+                self.handlers.clear();
+
+                Ok(None)
+            }
+            VtableEntry::NativeFunction(_) => {
+                self.call(Some(vtable_index), arg_count, Destination::Return)?;
+                Ok(Some(FlowControl::Return(
+                    self.return_value.take().unwrap_or_default(),
+                )))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_lines)] // One arm per kind of target.
     fn call_instance(
         &mut self,
         target: Option<&ValueOrSource>,
@@ -2373,12 +3293,44 @@ where
             _ => return Err(Fault::stack_underflow()),
         };
 
+        // Records dispatch to the function named after their kind, which
+        // receives the record as its first argument.
+        if let Some(record) = self.stack[stack_index].as_dynamic::<Record>() {
+            let method = Symbol::from(format!("{}.{name}", record.kind()).as_str());
+            if let Some(ModuleItem::Function(vtable_index)) = self.module.contents.get(&method) {
+                return self.call_record_method(
+                    *vtable_index,
+                    stack_index,
+                    target.is_some(),
+                    arg_count,
+                    destination,
+                );
+            }
+        }
+
         // Pull the target out of its current location.
         let mut target_value = Value::Void;
         std::mem::swap(&mut target_value, &mut self.stack[stack_index]);
+        // Sorting a list with a comparator requires calling back into the
+        // virtual machine, which dynamic values are unable to do.
+        let comparator = if name.as_str() == "sort"
+            && arg_count == 1
+            && target_value.as_dynamic::<List>().is_some()
+            && self.stack.top()?.as_dynamic::<Closure>().is_some()
+        {
+            Some(self.stack.pop()?)
+        } else {
+            None
+        };
         // Call without resolving any errors
-        let result = match &mut target_value {
-            Value::Dynamic(value) => value.call(name, self.stack.pop_n(arg_count)),
+        let result = match (&mut target_value, comparator) {
+            (Value::Dynamic(_), Some(comparator)) => self.sort_with_comparator(
+                target_value.as_dynamic::<List>().expect("checked above"),
+                &comparator,
+            ),
+            (Value::Dynamic(value), None) => value
+                .call(name, self.stack.pop_n(arg_count))
+                .map_err(Fault::from),
 
             _ => {
                 return Err(Fault::from(FaultKind::invalid_type(
@@ -2415,6 +3367,130 @@ where
         Ok(None)
     }
 
+    fn call_record_method(
+        &mut self,
+        vtable_index: usize,
+        stack_index: usize,
+        copy_target: bool,
+        arg_count: usize,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        match self.module.vtable.get(vtable_index) {
+            Some(VtableEntry::Function(function)) if function.arg_count != arg_count + 1 => {
+                // The record itself isn't counted, as it isn't passed
+                // explicitly.
+                return Err(Fault::from(FaultKind::ArgumentCountMismatch {
+                    expected: function.arg_count.saturating_sub(1),
+                    received: arg_count,
+                }));
+            }
+            Some(_) => {}
+            None => return Err(Fault::from(FaultKind::InvalidVtableIndex)),
+        }
+
+        if copy_target {
+            // The record must be placed before the arguments.
+            let record = self.stack[stack_index].clone();
+            let args = self.stack.pop_n(arg_count).collect::<Vec<_>>();
+            self.stack.push(record)?;
+            for arg in args {
+                self.stack.push(arg)?;
+            }
+        }
+
+        self.call(Some(vtable_index), arg_count + 1, destination)
+    }
+
+    fn sort_with_comparator(
+        &mut self,
+        list: &List,
+        comparator: &Value,
+    ) -> Result<Value, Fault<'static, Env, Output>> {
+        let comparator = ValueOrSource::Value(comparator.clone());
+        list.sort_by(|lhs, rhs| {
+            self.stack.push(lhs.clone())?;
+            self.stack.push(rhs.clone())?;
+            let depth = self.callers.len();
+            self.call_value(&comparator, 2, Destination::Stack)?;
+            if self.callers.len() > depth {
+                self.finish_call()?;
+            }
+            let ordering = self.stack.pop()?;
+            ordering.as_i64().map(|ordering| ordering.cmp(&0)).ok_or_else(|| {
+                Fault::from(FaultKind::invalid_type(
+                    "sort comparator must return an integer. Found `@received-value` (@received-type)",
+                    ordering,
+                ))
+            })
+        })?;
+        Ok(Value::Void)
+    }
+
+    fn call_value(
+        &mut self,
+        target: &ValueOrSource,
+        arg_count: usize,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let target = self.resolve_value_or_source(target)?;
+        let closure = match target.as_dynamic::<Closure>() {
+            Some(closure) => closure.clone(),
+            None => {
+                return Err(Fault::from(FaultKind::invalid_type(
+                    "@received-type is not a function",
+                    target.clone(),
+                )))
+            }
+        };
+
+        // Verify the argument list is valid.
+        match self.stack.len().checked_sub(arg_count) {
+            Some(arg_offset) if arg_offset >= self.return_offset => {}
+            _ => return Err(Fault::stack_underflow()),
+        }
+
+        let total_args = arg_count + closure.captured().len();
+        match self.module.vtable.get(closure.vtable_index()) {
+            Some(VtableEntry::Function(function)) if function.arg_count != total_args => {
+                return Err(Fault::from(FaultKind::ArgumentCountMismatch {
+                    expected: function.arg_count,
+                    received: total_args,
+                }));
+            }
+            Some(_) => {}
+            None => return Err(Fault::from(FaultKind::InvalidVtableIndex)),
+        }
+
+        // Captured values are passed after the caller's arguments.
+        for value in closure.captured() {
+            self.stack.push(value.clone())?;
+        }
+
+        self.call(Some(closure.vtable_index()), total_args, destination)
+    }
+
+    fn new_closure(
+        &mut self,
+        vtable_index: usize,
+        capture_count: usize,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        if vtable_index >= self.module.vtable.len() {
+            return Err(Fault::from(FaultKind::InvalidVtableIndex));
+        }
+
+        match self.stack.len().checked_sub(capture_count) {
+            Some(capture_offset) if capture_offset >= self.return_offset => {}
+            _ => return Err(Fault::stack_underflow()),
+        }
+        let captured = self.stack.pop_n(capture_count).collect();
+
+        *self.resolve_value_source_mut(destination)? =
+            Value::dynamic(Closure::new(vtable_index, captured));
+
+        Ok(None)
+    }
+
     fn intrinsic(
         &mut self,
         intrinsic: &Env::Intrinsic,
@@ -2472,6 +3548,36 @@ where
         Ok(None)
     }
 
+    fn negate(
+        &mut self,
+        value: &ValueOrSource,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let value = self.resolve_value_or_source(value)?;
+        let negated = match value {
+            Value::Integer(value) => self.arithmetic_mode.negate(*value)?,
+            Value::Real(value) => Value::Real(-value),
+            Value::Dynamic(dynamic) => match dynamic.checked_neg()? {
+                Some(negated) => negated,
+                None => {
+                    return Err(Fault::invalid_type(
+                        "`@received-value` (@received-type) does not support negation",
+                        value.clone(),
+                    ))
+                }
+            },
+            other => {
+                return Err(Fault::invalid_type(
+                    "`@received-value` (@received-type) does not support negation",
+                    other.clone(),
+                ))
+            }
+        };
+        *self.resolve_value_source_mut(destination)? = negated;
+
+        Ok(None)
+    }
+
     fn convert(
         &mut self,
         value: &ValueOrSource,
@@ -2484,6 +3590,59 @@ where
         Ok(None)
     }
 
+    fn get_index(
+        &mut self,
+        target: &ValueOrSource,
+        index: &ValueOrSource,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let target = self.resolve_value_or_source(target)?;
+        let index = self.resolve_value_or_source(index)?;
+        *self.resolve_value_source_mut(destination)? = target.get_index(index)?;
+
+        Ok(None)
+    }
+
+    fn set_index(
+        &mut self,
+        target: &ValueOrSource,
+        index: &ValueOrSource,
+        value: &ValueOrSource,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let target = self.resolve_value_or_source(target)?;
+        let index = self.resolve_value_or_source(index)?;
+        let value = self.resolve_value_or_source(value)?;
+        target.set_index(index.clone(), value.clone())?;
+
+        Ok(None)
+    }
+
+    fn iterate(
+        &mut self,
+        source: &ValueOrSource,
+        destination: Destination,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let source = self.resolve_value_or_source(source)?;
+        *self.resolve_value_source_mut(destination)? = source.iterate()?;
+
+        Ok(None)
+    }
+
+    fn iterate_next(
+        &mut self,
+        iterator: &ValueOrSource,
+        destination: Destination,
+        done_jump_to: usize,
+    ) -> Result<Option<FlowControl>, Fault<'static, Env, Output>> {
+        let iterator = self.resolve_value_or_source(iterator)?;
+        if let Some(value) = iterator.next_item()? {
+            *self.resolve_value_source_mut(destination)? = value;
+            Ok(None)
+        } else {
+            Ok(Some(FlowControl::JumpTo(done_jump_to)))
+        }
+    }
+
     fn extract_integer(value: &Value) -> Result<i64, Fault<'static, Env, Output>> {
         if let Some(value) = value.as_i64() {
             Ok(value)
@@ -2569,7 +3728,7 @@ where
 }
 
 macro_rules! checked_op {
-    ($name:ident, $unchecked_name:ident, $fullname:literal) => {
+    ($name:ident, $operation:expr, $fullname:literal) => {
         impl<'a, Env, Output> StackFrame<'a, Env, Output>
         where
             Env: Environment,
@@ -2590,10 +3749,10 @@ macro_rules! checked_op {
 
                 let produced_value = match (left_value, right_value) {
                     (Value::Integer(left), Value::Integer(right)) => {
-                        left.$name(*right).map_or(Value::Void, Value::Integer)
+                        $operation.integer(self.arithmetic_mode, *left, *right)?
                     }
                     (Value::Real(left), Value::Real(right)) => {
-                        Value::Real(left.$unchecked_name(*right))
+                        Value::Real($operation.real(*left, *right))
                     }
                     (Value::Dynamic(left), right) => {
                         if let Some(value) = left.$name(right, false)? {
@@ -2658,10 +3817,21 @@ macro_rules! checked_op {
     };
 }
 
-checked_op!(checked_add, add, "add");
-checked_op!(checked_sub, sub, "subtract");
-checked_op!(checked_mul, mul, "multiply");
-checked_op!(checked_div, div, "divide");
+checked_op!(checked_add, IntegerOperation::ADD, "add");
+checked_op!(checked_sub, IntegerOperation::SUB, "subtract");
+checked_op!(checked_mul, IntegerOperation::MULTIPLY, "multiply");
+checked_op!(checked_div, IntegerOperation::DIVIDE, "divide");
+checked_op!(
+    checked_int_div,
+    IntegerOperation::INTEGER_DIVIDE,
+    "integer divide"
+);
+checked_op!(
+    checked_rem,
+    IntegerOperation::REMAINDER,
+    "take the remainder of"
+);
+checked_op!(checked_pow, IntegerOperation::POWER, "raise");
 
 /// An unexpected event occurred while executing the virtual machine.
 #[derive(Debug, PartialEq)]
@@ -2822,9 +3992,85 @@ pub enum FaultKind {
     ValueCannotBeHashed(Value),
     /// A value was encountered that was out of range of valid values.
     ValueOutOfRange(&'static str),
+    /// A value of this kind was indexed, but it does not support indexing.
+    NotIndexable(ValueKind),
+    /// A value of this kind was iterated, but it does not support iteration.
+    NotIterable(ValueKind),
+    /// Execution was paused while Bud code was being called from within a
+    /// native operation, such as a sort comparator. Execution cannot be
+    /// resumed from within these operations.
+    UnresumablePause,
+    /// A value was raised using [`Instruction::Throw`].
+    Thrown(Value),
+    /// A function was called with a different number of arguments than it
+    /// expects.
+    ArgumentCountMismatch {
+        /// The number of arguments the function expects.
+        expected: usize,
+        /// The number of arguments passed to the function.
+        received: usize,
+    },
+    /// A value did not match any of the patterns it was compared against.
+    NoMatch(Value),
+    /// An integer operation overflowed while the virtual machine was using
+    /// [`ArithmeticMode::Fault`].
+    Overflow {
+        /// The operator that overflowed, as written in Bud.
+        operator: &'static str,
+        /// The operands of the operation, in order.
+        operands: Vec<Value>,
+    },
+    /// An integer was divided by zero while the virtual machine was using an
+    /// [`ArithmeticMode`] other than [`ArithmeticMode::Void`]. Contains the
+    /// value being divided.
+    DivideByZero(Value),
+    /// A function was called while the maximum number of nested calls were
+    /// already executing. Contains the maximum call depth.
+    CallDepthExceeded(usize),
+    /// An allocation would have exceeded the environment's
+    /// [`AllocationBudget`]. Contains the budget in bytes.
+    OutOfMemory(usize),
+    /// Execution was stopped by a [`CancellationToken`]. This fault cannot be
+    /// caught by [`Instruction::EnterTry`].
+    Cancelled,
+    /// Execution continued past the virtual machine's deadline. This fault
+    /// cannot be caught by [`Instruction::EnterTry`].
+    TimedOut,
 }
 
 impl FaultKind {
+    /// Returns the name of this kind of fault.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            FaultKind::StackOverflow => "StackOverflow",
+            FaultKind::StackUnderflow => "StackUnderflow",
+            FaultKind::InvalidVariableIndex => "InvalidVariableIndex",
+            FaultKind::InvalidArgumentIndex => "InvalidArgumentIndex",
+            FaultKind::InvalidVtableIndex => "InvalidVtableIndex",
+            FaultKind::UnknownFunction { .. } => "UnknownFunction",
+            FaultKind::TypeMismatch { .. } => "TypeMismatch",
+            FaultKind::InvalidType { .. } => "InvalidType",
+            FaultKind::Dynamic(_) => "Dynamic",
+            FaultKind::ArgumentMissing(_) => "ArgumentMissing",
+            FaultKind::TooManyArguments(_) => "TooManyArguments",
+            FaultKind::ValueCannotBeHashed(_) => "ValueCannotBeHashed",
+            FaultKind::ValueOutOfRange(_) => "ValueOutOfRange",
+            FaultKind::NotIndexable(_) => "NotIndexable",
+            FaultKind::NotIterable(_) => "NotIterable",
+            FaultKind::UnresumablePause => "UnresumablePause",
+            FaultKind::Thrown(_) => "Thrown",
+            FaultKind::ArgumentCountMismatch { .. } => "ArgumentCountMismatch",
+            FaultKind::NoMatch(_) => "NoMatch",
+            FaultKind::Overflow { .. } => "Overflow",
+            FaultKind::DivideByZero(_) => "DivideByZero",
+            FaultKind::CallDepthExceeded(_) => "CallDepthExceeded",
+            FaultKind::OutOfMemory(_) => "OutOfMemory",
+            FaultKind::Cancelled => "Cancelled",
+            FaultKind::TimedOut => "TimedOut",
+        }
+    }
+
     /// An invalid type was encountered.
     ///
     /// These patterns will be replaced in `message` before being Displayed:
@@ -2854,7 +4100,7 @@ impl FaultKind {
     }
 
     /// Returns a [`FaultKind::Dynamic`].
-    pub fn dynamic<T: Debug + Display + 'static>(fault: T) -> Self {
+    pub fn dynamic<T: Debug + Display + Send + Sync + 'static>(fault: T) -> Self {
         Self::Dynamic(DynamicFault::new(fault))
     }
 }
@@ -2891,15 +4137,17 @@ impl Display for FaultKind {
             } => {
                 let message = message.replace("@expected", expected.as_str());
                 let message = message.replace("@received-type", received.kind().as_str());
+                let message = message.replace("@received-kind", received.kind().as_str());
                 let message = message.replace("@received-value", &received.to_string());
                 f.write_str(&message)
             }
             FaultKind::InvalidType { message, received } => {
                 let message = message.replace("@received-type", received.kind().as_str());
+                let message = message.replace("@received-kind", received.kind().as_str());
                 let message = message.replace("@received-value", &received.to_string());
                 f.write_str(&message)
             }
-            FaultKind::Dynamic(dynamic) => dynamic.fmt(f),
+            FaultKind::Dynamic(dynamic) => Display::fmt(dynamic, f),
             FaultKind::ArgumentMissing(missing) => write!(f, "missing argument `{missing}`"),
             FaultKind::TooManyArguments(extra) => write!(f, "unexpected argument `{extra}`"),
             FaultKind::ValueCannotBeHashed(value) => {
@@ -2910,6 +4158,39 @@ impl Display for FaultKind {
                 )
             }
             FaultKind::ValueOutOfRange(what) => write!(f, "`{what}` is out of valid range"),
+            FaultKind::NotIndexable(kind) => {
+                write!(f, "{} does not support indexing", kind.as_str())
+            }
+            FaultKind::NotIterable(kind) => {
+                write!(f, "{} does not support iteration", kind.as_str())
+            }
+            FaultKind::UnresumablePause => {
+                f.write_str("execution cannot be paused while called from a native operation")
+            }
+            FaultKind::Thrown(value) => write!(f, "uncaught value: `{value}` ({})", value.kind()),
+            FaultKind::ArgumentCountMismatch { expected, received } => write!(
+                f,
+                "function expects {expected} argument(s), but {received} were passed"
+            ),
+            FaultKind::NoMatch(value) => {
+                write!(f, "no pattern matched `{value}` ({})", value.kind())
+            }
+            FaultKind::Overflow { operator, operands } => match operands.as_slice() {
+                [operand] => write!(f, "integer overflow evaluating `{operator}{operand}`"),
+                [left, right] => {
+                    write!(f, "integer overflow evaluating `{left} {operator} {right}`")
+                }
+                _ => write!(f, "integer overflow evaluating `{operator}`"),
+            },
+            FaultKind::DivideByZero(value) => write!(f, "`{value}` was divided by zero"),
+            FaultKind::CallDepthExceeded(maximum) => {
+                write!(f, "the maximum call depth of {maximum} was exceeded")
+            }
+            FaultKind::OutOfMemory(budget) => {
+                write!(f, "the allocation budget of {budget} bytes was exhausted")
+            }
+            FaultKind::Cancelled => f.write_str("execution was cancelled"),
+            FaultKind::TimedOut => f.write_str("execution did not finish before its deadline"),
         }
     }
 }
@@ -2924,6 +4205,29 @@ pub struct FaultStackFrame {
     /// The index of the instruction that was executing when this fault was
     /// raised.
     pub instruction_index: usize,
+    /// The name of the function being executed, if known.
+    pub function: Option<Symbol>,
+    /// The name of the source the function was compiled from, if known.
+    pub source: Option<Symbol>,
+    /// The source location of the instruction being executed, if the function
+    /// was linked with [`DebugInfo`].
+    pub location: Option<SourceLocation>,
+}
+
+impl Display for FaultStackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "at {function}")?,
+            None => f.write_str("at <unknown>")?,
+        }
+
+        match (&self.source, &self.location) {
+            (Some(source), Some(location)) => write!(f, " ({source}:{})", location.line),
+            (None, Some(location)) => write!(f, " (line {})", location.line),
+            (Some(source), None) => write!(f, " ({source})"),
+            (None, None) => write!(f, " (instruction #{})", self.instruction_index),
+        }
+    }
 }
 
 /// A paused code execution.
@@ -2934,7 +4238,7 @@ where
 {
     context: Option<&'a mut VirtualMachine<Env>>,
     operations: Option<Instructions<'a, Env::Intrinsic>>,
-    stack: VecDeque<PausedFrame>,
+    stack: Vec<SuspendedFrame>,
     _return: PhantomData<ReturnType>,
 }
 
@@ -2972,8 +4276,10 @@ where
     }
 }
 
+/// The state of a frame that isn't executing, either because it is waiting on
+/// a call to return or because execution was paused.
 #[derive(Debug, Eq, PartialEq)]
-struct PausedFrame {
+struct SuspendedFrame {
     return_offset: usize,
     arg_offset: usize,
     variables_offset: usize,
@@ -2982,6 +4288,7 @@ struct PausedFrame {
     vtable_index: Option<usize>,
     operation_index: usize,
     destination: Destination,
+    handlers: Vec<Handler>,
 }
 
 /// A type that can be constructed from popping from the virtual machine stack.
@@ -3101,6 +4408,35 @@ pub trait Environment: 'static {
     /// instruction as the one when [`ExecutionBehavior::Pause`] was called.
     fn step(&mut self) -> ExecutionBehavior;
 
+    /// Called once before each instruction is executed with the instruction
+    /// that is about to be executed. The instruction is None when the current
+    /// function is about to return because it has no more instructions.
+    ///
+    /// The returned behavior is handled the same as [`step()`](Self::step).
+    /// The provided implementation calls [`step()`](Self::step).
+    fn step_instruction(
+        &mut self,
+        _instruction: Option<&Instruction<Self::Intrinsic>>,
+    ) -> ExecutionBehavior {
+        self.step()
+    }
+
+    /// Called after an instruction is executed with the total cost charged
+    /// using [`charge()`] while executing it.
+    ///
+    /// The provided implementation does nothing.
+    fn consume(&mut self, _cost: usize) {}
+
+    /// Returns the budget that allocations made by the built-in `String`,
+    /// `List`, and `HashMap` types are charged against while executing.
+    /// Memory that is released is not credited back to the budget.
+    ///
+    /// The provided implementation returns None, which does not limit
+    /// allocations.
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        None
+    }
+
     /// Converts `value` to a custom type supported by the runtime.
     ///
     /// The provided implementation supports the `String` type.
@@ -3169,12 +4505,25 @@ impl Environment for () {
 
 /// An [`Environment`] that allows executing an amount of instructions before
 /// pausing the virtual machine.
+///
+/// Each instruction is charged according to the environment's
+/// [`InstructionCosts`]. Additional costs reported using [`charge()`] are
+/// deducted after the instruction that reported them finishes. If this
+/// overdraws the budget, the virtual machine pauses before the next
+/// instruction until enough budget has been added to repay the overdraft.
+///
+/// Native operations that call back into Bud code, such as sorting with a
+/// comparator, can't be paused partway through. If the budget runs out while
+/// one of these callbacks is executing, the virtual machine faults with
+/// [`FaultKind::UnresumablePause`] instead of pausing.
 #[derive(Debug, Default)]
 #[must_use]
 pub struct Budgeted<Env> {
     /// The wrapped environment.
     pub env: Env,
     remaining_steps: usize,
+    overdraft: usize,
+    costs: InstructionCosts,
 }
 
 impl Budgeted<()> {
@@ -3190,30 +4539,85 @@ impl<Env> Budgeted<Env> {
         Self {
             env,
             remaining_steps: initial_budget,
+            overdraft: 0,
+            costs: InstructionCosts::uniform(1),
         }
     }
 
+    /// Sets the costs charged for each instruction and returns self.
+    pub const fn with_costs(mut self, costs: InstructionCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Returns the costs charged for each instruction.
+    pub const fn costs(&self) -> &InstructionCosts {
+        &self.costs
+    }
+
     /// Returns the current balance of the budget.
     #[must_use]
     pub const fn balance(&self) -> usize {
         self.remaining_steps
     }
 
-    /// Returns the current balance of the budget.
+    /// Returns the amount charged beyond the budget that must be repaid
+    /// before execution can continue.
+    #[must_use]
+    pub const fn overdraft(&self) -> usize {
+        self.overdraft
+    }
+
+    /// Charges a single unit against the budget. Returns true if the budget
+    /// had enough balance.
     #[must_use]
     pub fn charge(&mut self) -> bool {
-        if self.remaining_steps > 0 {
-            self.remaining_steps -= 1;
+        self.charge_cost(1)
+    }
+
+    /// Charges `cost` against the budget. If the budget does not have enough
+    /// balance or is overdrawn, nothing is charged and false is returned.
+    #[must_use]
+    pub fn charge_cost(&mut self, cost: usize) -> bool {
+        if self.overdraft == 0 && self.remaining_steps >= cost {
+            self.remaining_steps -= cost;
             true
         } else {
             false
         }
     }
 
+    /// Charges the cost of executing `instruction`, or the cost of returning
+    /// if `instruction` is None. Returns true if the budget had enough
+    /// balance.
+    #[must_use]
+    pub fn charge_instruction<Intrinsic>(
+        &mut self,
+        instruction: Option<&Instruction<Intrinsic>>,
+    ) -> bool {
+        let cost = match instruction {
+            Some(instruction) => self.costs.cost_of(instruction),
+            None => self.costs.control_flow,
+        };
+        self.charge_cost(cost)
+    }
+
+    /// Deducts `cost` from the budget, even if it exceeds the balance. Any
+    /// amount beyond the balance is recorded as an overdraft.
+    pub fn spend(&mut self, cost: usize) {
+        let overdrawn = cost.saturating_sub(self.remaining_steps);
+        self.remaining_steps = self.remaining_steps.saturating_sub(cost);
+        self.overdraft = self.overdraft.saturating_add(overdrawn);
+    }
+
     /// Adds an additional budget. This value will saturate `usize` instead of
-    /// panicking or overflowing.
+    /// panicking or overflowing. Any overdraft is repaid first.
     pub fn add_budget(&mut self, additional_budget: usize) {
-        self.remaining_steps = self.remaining_steps.saturating_add(additional_budget);
+        let repaid = additional_budget.min(self.overdraft);
+        self.overdraft -= repaid;
+        self.remaining_steps = self
+            .remaining_steps
+            .saturating_add(additional_budget - repaid);
     }
 }
 
@@ -3233,6 +4637,27 @@ where
         }
     }
 
+    #[inline]
+    fn step_instruction(
+        &mut self,
+        instruction: Option<&Instruction<Self::Intrinsic>>,
+    ) -> ExecutionBehavior {
+        if self.charge_instruction(instruction) {
+            self.env.step_instruction(instruction)
+        } else {
+            ExecutionBehavior::Pause
+        }
+    }
+
+    fn consume(&mut self, cost: usize) {
+        self.spend(cost);
+        self.env.consume(cost);
+    }
+
+    fn allocation_budget(&self) -> Option<&AllocationBudget> {
+        self.env.allocation_budget()
+    }
+
     fn intrinsic(
         &mut self,
         intrinsic: &Self::Intrinsic,
@@ -3389,6 +4814,61 @@ fn budget_with_frames() {
     assert_eq!(output, 30);
 }
 
+#[test]
+fn budget_costs() {
+    let mut context =
+        VirtualMachine::default_for(Budgeted::new(1, ()).with_costs(InstructionCosts {
+            arithmetic: 3,
+            ..InstructionCosts::default()
+        }))
+        .with_native_function("work", |args: &mut PoppedValues<'_>| {
+            args.verify_empty()?;
+            charge(10);
+            Ok(Value::Integer(1))
+        });
+    let fault = context
+        .run::<i64>(
+            &[
+                Instruction::Call {
+                    vtable_index: Some(0),
+                    arg_count: 0,
+                    destination: Destination::Variable(0),
+                },
+                Instruction::Add {
+                    left: ValueOrSource::Variable(0),
+                    right: ValueOrSource::Value(Value::Integer(2)),
+                    destination: Destination::Return,
+                },
+            ],
+            1,
+        )
+        .unwrap_err();
+
+    // The call fit in the budget, but the work performed by the native
+    // function overdrew it.
+    let mut pending = match fault.kind {
+        FaultOrPause::Pause(pending) => pending,
+        FaultOrPause::Fault(error) => unreachable!("unexpected error: {error}"),
+    };
+    assert_eq!(pending.environment().balance(), 0);
+    assert_eq!(pending.environment().overdraft(), 10);
+
+    // Repaying the overdraft isn't enough to execute the addition.
+    pending.environment_mut().add_budget(12);
+    assert_eq!(pending.environment().overdraft(), 0);
+    assert_eq!(pending.environment().balance(), 2);
+    let mut pending = match pending.resume().unwrap_err().kind {
+        FaultOrPause::Pause(pending) => pending,
+        FaultOrPause::Fault(error) => unreachable!("unexpected error: {error}"),
+    };
+    assert_eq!(pending.environment().balance(), 2);
+
+    // The addition costs 3, and returning from the end of the code costs 1.
+    pending.environment_mut().add_budget(2);
+    assert_eq!(pending.resume().unwrap(), 3);
+    assert_eq!(context.environment().balance(), 0);
+}
+
 /// A block of code that can be executed on the virtual machine.
 #[derive(Debug)]
 pub struct CodeBlock<Intrinsic> {
@@ -3421,6 +4901,16 @@ impl<Intrinsic> CodeBlock<Intrinsic> {
     {
         vm.run(&self.code, self.variables)
     }
+
+    /// Statically verifies this code block as if it were executed by
+    /// [`CodeBlock::execute_in()`]. See [`VirtualMachine::verify()`] for the
+    /// checks performed.
+    pub fn verify_in<Env>(&self, vm: &VirtualMachine<Env>) -> Result<(), VerificationError>
+    where
+        Env: Environment<Intrinsic = Intrinsic>,
+    {
+        vm.verify(&self.code, self.variables)
+    }
 }
 
 /// Displays a [`CodeBlock`] with optional indentation.
@@ -3574,7 +5064,7 @@ impl Stack {
     #[inline]
     pub fn top(&self) -> Result<&Value, FaultKind> {
         if self.length > 0 {
-            Ok(&self.values[self.length])
+            Ok(&self.values[self.length - 1])
         } else {
             Err(FaultKind::StackUnderflow)
         }
@@ -3802,9 +5292,15 @@ impl PartialEq for DynamicFault {
     }
 }
 
+impl Display for DynamicFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 impl DynamicFault {
     /// Returns a new instance containing the provided error.
-    pub fn new<T: Debug + Display + 'static>(error: T) -> Self {
+    pub fn new<T: Debug + Display + Send + Sync + 'static>(error: T) -> Self {
         Self(Arc::new(DynamicErrorContents(Some(error))))
     }
 
@@ -3851,7 +5347,7 @@ where
     }
 }
 
-trait AnyDynamicError: Debug + Display {
+trait AnyDynamicError: Debug + Display + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_opt_any_mut(&mut self) -> &mut dyn Any;
 
@@ -3861,7 +5357,7 @@ trait AnyDynamicError: Debug + Display {
 
 impl<T> AnyDynamicError for DynamicErrorContents<T>
 where
-    T: Debug + Display + 'static,
+    T: Debug + Display + Send + Sync + 'static,
 {
     fn as_any(&self) -> &dyn Any {
         self.0.as_ref().expect("value taken")
@@ -3991,6 +5487,161 @@ fn function_needs_extra_cleanup() {
     assert!(context.stack.is_empty());
 }
 
+#[test]
+fn tail_call() {
+    // Sums the integers from 1 to n using an accumulator.
+    let sum = Function {
+        name: Symbol::from("sum"),
+        arg_count: 2,
+        variable_count: 0,
+        code: vec![
+            Instruction::If {
+                condition: ValueOrSource::Argument(0),
+                false_jump_to: 4,
+            },
+            Instruction::Sub {
+                left: ValueOrSource::Argument(0),
+                right: ValueOrSource::Value(Value::Integer(1)),
+                destination: Destination::Stack,
+            },
+            Instruction::Add {
+                left: ValueOrSource::Argument(1),
+                right: ValueOrSource::Argument(0),
+                destination: Destination::Stack,
+            },
+            Instruction::TailCall {
+                vtable_index: None,
+                arg_count: 2,
+            },
+            Instruction::Return(Some(ValueOrSource::Argument(1))),
+        ],
+    };
+    let mut context = VirtualMachine::empty()
+        .with_maximum_call_depth(1)
+        .with_function(sum);
+    assert_eq!(
+        context
+            .run::<i64>(
+                &[
+                    Instruction::Push(ValueOrSource::Value(Value::Integer(1_000))),
+                    Instruction::Push(ValueOrSource::Value(Value::Integer(0))),
+                    Instruction::Call {
+                        vtable_index: Some(0),
+                        arg_count: 2,
+                        destination: Destination::Stack,
+                    }
+                ],
+                0
+            )
+            .unwrap(),
+        500_500
+    );
+
+    assert!(context.stack.is_empty());
+}
+
+#[test]
+fn allocation_budget() {
+    #[derive(Debug)]
+    struct Limited(AllocationBudget);
+
+    impl Environment for Limited {
+        type String = String;
+        type Intrinsic = Noop;
+
+        fn intrinsic(
+            &mut self,
+            _intrinsic: &Self::Intrinsic,
+            _args: PoppedValues<'_>,
+        ) -> Result<Value, FaultKind> {
+            Ok(Value::Void)
+        }
+
+        fn step(&mut self) -> ExecutionBehavior {
+            ExecutionBehavior::Continue
+        }
+
+        fn allocation_budget(&self) -> Option<&AllocationBudget> {
+            Some(&self.0)
+        }
+    }
+
+    let repeat = |count: i64| {
+        [Instruction::Multiply {
+            left: ValueOrSource::Value(Value::dynamic(String::from("abc"))),
+            right: ValueOrSource::Value(Value::Integer(count)),
+            destination: Destination::Return,
+        }]
+    };
+
+    let budget = AllocationBudget::new(1024);
+    let mut context = VirtualMachine::default_for(Limited(budget.clone()));
+    let result = context.run::<Value>(&repeat(10), 0).unwrap();
+    assert_eq!(
+        result.as_dynamic::<String>().map(String::as_str),
+        Some("abcabcabcabcabcabcabcabcabcabc")
+    );
+    assert_eq!(budget.allocated(), 30);
+
+    let too_large = repeat(1_000_000_000);
+    let fault = context.run::<Value>(&too_large, 0).unwrap_err();
+    assert!(matches!(
+        fault.kind,
+        FaultOrPause::Fault(FaultKind::OutOfMemory(1024))
+    ));
+    assert_eq!(budget.allocated(), 30);
+
+    // Released memory is not credited back to the budget.
+    context.run::<Value>(&repeat(10), 0).unwrap();
+    assert_eq!(budget.allocated(), 60);
+
+    // Allocations outside of execution are not charged.
+    assert!(allocate(1_000_000).is_ok());
+    assert_eq!(budget.allocated(), 60);
+
+    budget.reset();
+    assert_eq!(budget.remaining(), 1024);
+}
+
+#[test]
+fn cancellation() {
+    // An infinite loop wrapped in an exception handler that returns 42.
+    let code = [
+        Instruction::EnterTry {
+            handler: 2,
+            destination: Destination::Variable(0),
+        },
+        Instruction::JumpTo(1),
+        Instruction::Return(Some(ValueOrSource::Value(Value::Integer(42)))),
+    ];
+    let assert_interrupted = |context: &mut VirtualMachine<()>, expected: FaultKind| {
+        let fault = context.run::<i64>(&code, 1).unwrap_err();
+        match fault.kind {
+            FaultOrPause::Fault(kind) => assert_eq!(kind, expected),
+            FaultOrPause::Pause(_) => unreachable!("unexpected pause"),
+        }
+    };
+
+    let token = CancellationToken::new();
+    let mut context = VirtualMachine::empty().with_cancellation(token.clone());
+    token.cancel();
+    assert_interrupted(&mut context, FaultKind::Cancelled);
+
+    token.reset();
+    let thread_token = token.clone();
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        thread_token.cancel();
+    });
+    assert_interrupted(&mut context, FaultKind::Cancelled);
+    canceller.join().unwrap();
+
+    token.reset();
+    context.set_cancellation(None);
+    context.set_deadline(Some(Instant::now() + std::time::Duration::from_millis(50)));
+    assert_interrupted(&mut context, FaultKind::TimedOut);
+}
+
 /// All errors that can be encountered executing Bud code.
 #[derive(Debug, PartialEq)]
 pub enum Error<'a, Env, ReturnType>
@@ -3999,6 +5650,10 @@ where
 {
     /// An error occurred while linking an [`Module`](ir::Module).
     Link(ir::LinkError),
+    /// A [`Module`](ir::Module) loaded with
+    /// [`Module::load_verified_into()`](ir::Module::load_verified_into)
+    /// failed verification.
+    Verification(VerificationError),
     /// A fault occurred while running the virtual machine.
     Fault(Fault<'a, Env, ReturnType>),
 }
@@ -4010,6 +5665,7 @@ where
     fn clone(&self) -> Self {
         match self {
             Self::Link(arg0) => Self::Link(arg0.clone()),
+            Self::Verification(arg0) => Self::Verification(arg0.clone()),
             Self::Fault(arg0) => Self::Fault(arg0.clone()),
         }
     }
@@ -4031,6 +5687,7 @@ where
     pub fn expect_no_pause(self) -> Error<'static, Env, ReturnType> {
         match self {
             Error::Link(compilation) => Error::Link(compilation),
+            Error::Verification(error) => Error::Verification(error),
             Error::Fault(Fault {
                 kind: FaultOrPause::Fault(fault),
                 stack,
@@ -4057,6 +5714,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Link(err) => write!(f, "link error: {err}"),
+            Error::Verification(err) => write!(f, "verification error: {err}"),
             Error::Fault(err) => write!(f, "vm fault: {err}"),
         }
     }
@@ -4070,6 +5728,15 @@ where
     }
 }
 
+impl<'a, Env, ReturnType> From<VerificationError> for Error<'a, Env, ReturnType>
+where
+    Env: Environment,
+{
+    fn from(err: VerificationError) -> Self {
+        Self::Verification(err)
+    }
+}
+
 impl<'a, Env, ReturnType> From<Fault<'a, Env, ReturnType>> for Error<'a, Env, ReturnType>
 where
     Env: Environment,
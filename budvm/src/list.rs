@@ -1,10 +1,16 @@
 use std::{
     cmp::Ordering,
     collections::VecDeque,
-    sync::{Mutex, MutexGuard, PoisonError},
+    fmt::Write,
+    sync::{Mutex, MutexGuard, PoisonError, TryLockError},
 };
 
-use crate::{symbol::Symbol, DynamicValue, FaultKind, PoppedValues, Value};
+use crate::{
+    cost::charge,
+    memory::{allocate, allocate_values, take_refused, try_allocate},
+    symbol::Symbol,
+    DynamicValue, FaultKind, PoppedValues, Value, ValueIterator,
+};
 
 /// A List type for Bud, which wraps a [`VecDeque<Value>`].
 ///
@@ -28,6 +34,16 @@ impl List {
         self.0.lock().unwrap_or_else(PoisonError::into_inner)
     }
 
+    /// Locks the list unless it is already locked, such as while converting a
+    /// list that contains itself.
+    fn try_list(&self) -> Option<MutexGuard<'_, VecDeque<Value>>> {
+        match self.0.try_lock() {
+            Ok(list) => Some(list),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
     /// Extracts the contained collection type.
     pub fn into_inner(self) -> VecDeque<Value> {
         self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
@@ -75,6 +91,28 @@ impl List {
         let mut list = self.list();
         list.remove(index)
     }
+
+    /// Sorts the values in this list using a stable sort and the ordering
+    /// returned by `compare`.
+    ///
+    /// The list is not locked while `compare` is invoked, which allows
+    /// `compare` to interact with this list. While sorting, the list will
+    /// appear empty. If `compare` returns an error, sorting is aborted and the
+    /// values are restored in an unspecified order.
+    pub fn sort_by<E>(
+        &self,
+        compare: impl FnMut(&Value, &Value) -> Result<Ordering, E>,
+    ) -> Result<(), E> {
+        let values = Vec::from(std::mem::take(&mut *self.list()));
+        let (sorted, result) = match sort_values(values, compare) {
+            Ok(sorted) => (sorted, Ok(())),
+            Err((unsorted, err)) => (unsorted, Err(err)),
+        };
+        let mut list = self.list();
+        let appended = std::mem::replace(&mut *list, VecDeque::from(sorted));
+        list.extend(appended);
+        result
+    }
 }
 
 impl DynamicValue for List {
@@ -86,8 +124,19 @@ impl DynamicValue for List {
         Symbol::from("List")
     }
 
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        if kind == "String" {
+            self.to_source().map(Value::dynamic)
+        } else {
+            None
+        }
+    }
+
     fn partial_eq(&self, other: &Value) -> Option<bool> {
         let other = other.as_dynamic::<Self>()?;
+        if std::ptr::eq(self, other) {
+            return Some(true);
+        }
         let lhs = self.list();
         let rhs = other.list();
 
@@ -96,6 +145,9 @@ impl DynamicValue for List {
 
     fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
         let other = other.as_dynamic::<Self>()?;
+        if std::ptr::eq(self, other) {
+            return Some(Ordering::Equal);
+        }
         let other = other.list();
         let mut other = other.iter();
 
@@ -117,6 +169,7 @@ impl DynamicValue for List {
         })
     }
 
+    #[allow(clippy::too_many_lines)] // One arm per method.
     fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
         match name.as_str() {
             "count" => {
@@ -128,6 +181,7 @@ impl DynamicValue for List {
             "push" => {
                 let arg = args.next_argument("value")?;
                 args.verify_empty()?;
+                allocate_values(1)?;
                 let mut list = self.list();
                 list.push_back(arg.clone());
                 Ok(arg)
@@ -140,6 +194,7 @@ impl DynamicValue for List {
             "push_front" => {
                 let arg = args.next_argument("value")?;
                 args.verify_empty()?;
+                allocate_values(1)?;
                 let mut list = self.list();
                 list.push_front(arg.clone());
                 Ok(arg)
@@ -150,14 +205,161 @@ impl DynamicValue for List {
                 Ok(list.pop_front().unwrap_or_default())
             }
             "remove" => {
-                let index = args.next_argument("value")?;
+                let index = list_index(&args.next_argument("index")?)?;
+                args.verify_empty()?;
+                self.remove(index)
+                    .ok_or(FaultKind::ValueOutOfRange("index"))
+            }
+            "get" => {
+                let index = list_index(&args.next_argument("index")?)?;
+                args.verify_empty()?;
+                self.get(index).ok_or(FaultKind::ValueOutOfRange("index"))
+            }
+            "set" => {
+                let index = args.next_argument("index")?;
+                let value = args.next_argument("value")?;
+                args.verify_empty()?;
+                self.set_index(index, value.clone())?;
+                Ok(value)
+            }
+            "insert" => {
+                let index = list_index(&args.next_argument("index")?)?;
+                let value = args.next_argument("value")?;
+                args.verify_empty()?;
+                let mut list = self.list();
+                if index > list.len() {
+                    return Err(FaultKind::ValueOutOfRange("index"));
+                }
+                allocate_values(1)?;
+                list.insert(index, value.clone());
+                Ok(value)
+            }
+            "slice" => {
+                let start = list_index(&args.next_argument("start")?)?;
+                let end = args.next().map(|end| list_index(&end)).transpose()?;
+                args.verify_empty()?;
+                let list = self.list();
+                let end = end.unwrap_or(list.len());
+                if start > end || end > list.len() {
+                    return Err(FaultKind::ValueOutOfRange("index"));
+                }
+                allocate_values(end - start)?;
+                Ok(Value::dynamic(
+                    list.range(start..end).cloned().collect::<List>(),
+                ))
+            }
+            "contains" => {
+                let value = args.next_argument("value")?;
                 args.verify_empty()?;
-                let index = index.as_i64().ok_or_else(|| {
-                    FaultKind::invalid_type("index must be an integer", index.clone())
+                let list = self.list();
+                charge(list.len());
+                Ok(Value::Boolean(list.contains(&value)))
+            }
+            "index_of" => {
+                let value = args.next_argument("value")?;
+                args.verify_empty()?;
+                let list = self.list();
+                charge(list.len());
+                let position = list.iter().position(|entry| entry == &value);
+                match position {
+                    Some(position) => i64::try_from(position)
+                        .map(Value::Integer)
+                        .map_err(|_| FaultKind::ValueOutOfRange("index")),
+                    None => Ok(Value::Void),
+                }
+            }
+            "sort" => {
+                // Sorting with a comparator requires calling back into the
+                // virtual machine, which is handled before the call reaches
+                // this type.
+                if let Some(comparator) = args.next() {
+                    return Err(FaultKind::invalid_type(
+                        "sort comparator must be a function. Found `@received-value` (@received-type)",
+                        comparator,
+                    ));
+                }
+                charge(self.len());
+                self.sort_by(|lhs, rhs| {
+                    lhs.partial_cmp(rhs).ok_or_else(|| {
+                        FaultKind::type_mismatch(
+                            "invalid comparison between @expected and `@received-value` (@received-type)",
+                            lhs.kind(),
+                            rhs.clone(),
+                        )
+                    })
                 })?;
-                Ok(self
-                    .remove(usize::try_from(index).unwrap_or(usize::MAX))
-                    .unwrap_or_default())
+                Ok(Value::Void)
+            }
+            "reverse" => {
+                args.verify_empty()?;
+                let mut list = self.list();
+                charge(list.len());
+                list.make_contiguous().reverse();
+                Ok(Value::Void)
+            }
+            "clear" => {
+                args.verify_empty()?;
+                self.list().clear();
+                Ok(Value::Void)
+            }
+            "join" => {
+                let separator = args.next_argument("separator")?;
+                args.verify_empty()?;
+                let separator = separator.as_dynamic::<String>().ok_or_else(|| {
+                    FaultKind::invalid_type(
+                        "separator must be a string. Found `@received-value` (@received-type)",
+                        separator.clone(),
+                    )
+                })?;
+                let list = self.list();
+                charge(list.len());
+                let mut joined = String::new();
+                let mut converted = String::new();
+                for (index, value) in list.iter().enumerate() {
+                    let value = if let Some(value) = value.as_dynamic::<String>() {
+                        value
+                    } else {
+                        converted.clear();
+                        write!(converted, "{value}").expect("error writing to string");
+                        take_refused()?;
+                        &converted
+                    };
+                    if index > 0 {
+                        allocate(separator.len() + value.len())?;
+                        joined.push_str(separator);
+                    } else {
+                        allocate(value.len())?;
+                    }
+                    joined.push_str(value);
+                }
+                Ok(Value::dynamic(joined))
+            }
+            "concat" => {
+                let other = args.next_argument("other")?;
+                args.verify_empty()?;
+                let other = expect_list(&other)?;
+                let combined_length = self.len().saturating_add(other.len());
+                charge(combined_length);
+                allocate_values(combined_length)?;
+                // Copy the other list before locking this one, as the two
+                // lists may be the same value.
+                let mut combined = other.list().clone();
+                let mut list = self.list().clone();
+                list.append(&mut combined);
+                Ok(Value::dynamic(List(Mutex::new(list))))
+            }
+            "extend" => {
+                let other = args.next_argument("values")?;
+                args.verify_empty()?;
+                let iterator = other.iterate()?;
+                let mut values = Vec::new();
+                while let Some(value) = iterator.next_item()? {
+                    charge(1);
+                    allocate_values(1)?;
+                    values.push(value);
+                }
+                self.list().extend(values);
+                Ok(Value::Void)
             }
             _ => Err(FaultKind::UnknownFunction {
                 kind: super::ValueKind::Dynamic(self.kind()),
@@ -166,8 +368,56 @@ impl DynamicValue for List {
         }
     }
 
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        let index = list_index(index)?;
+        self.get(index).ok_or(FaultKind::ValueOutOfRange("index"))
+    }
+
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        let index = list_index(&index)?;
+        let mut list = self.list();
+        let entry = list
+            .get_mut(index)
+            .ok_or(FaultKind::ValueOutOfRange("index"))?;
+        *entry = value;
+        Ok(())
+    }
+
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        let list = self.list();
+        allocate_values(list.len())?;
+        Ok(Value::dynamic(ValueIterator::new(list.clone())))
+    }
+
+    fn checked_add(&self, other: &Value, _is_reverse: bool) -> Result<Option<Value>, FaultKind> {
+        if let Some(other) = other.as_dynamic::<Self>() {
+            // Like String, is_reverse can be ignored because two lists always
+            // produce a result.
+            allocate_values(self.len().saturating_add(other.len()))?;
+            let mut combined = other.list().clone();
+            let mut list = self.list().clone();
+            list.append(&mut combined);
+            Ok(Some(Value::dynamic(List(Mutex::new(list)))))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn to_source(&self) -> Option<String> {
-        None
+        let list = self.try_list()?;
+        try_allocate(2)?;
+        let mut source = String::from("[");
+        for (index, value) in list.iter().enumerate() {
+            let value = value.to_source()?;
+            // Charge for the separator as well.
+            try_allocate(value.len() + 2)?;
+            if index > 0 {
+                source.push_str(", ");
+            }
+            source.push_str(&value);
+        }
+        source.push(']');
+        Some(source)
     }
 
     fn hash<H>(&self, state: &mut H) -> bool
@@ -185,6 +435,65 @@ impl DynamicValue for List {
     }
 }
 
+/// Converts `index` into a position within a list. Negative indices are
+/// converted to a position that is always out of bounds.
+fn list_index(index: &Value) -> Result<usize, FaultKind> {
+    let index = index
+        .as_i64()
+        .ok_or_else(|| FaultKind::invalid_type("index must be an integer", index.clone()))?;
+    Ok(usize::try_from(index).unwrap_or(usize::MAX))
+}
+
+fn expect_list(value: &Value) -> Result<&List, FaultKind> {
+    value.as_dynamic::<List>().ok_or_else(|| {
+        FaultKind::invalid_type(
+            "expected a list. Found `@received-value` (@received-type)",
+            value.clone(),
+        )
+    })
+}
+
+/// Sorts `values` using a stable merge sort with a fallible `compare`
+/// function.
+///
+/// Unlike the standard library's sorting functions, `compare` is allowed to
+/// fail. If it does, the values are returned in an unspecified order alongside
+/// the error.
+fn sort_values<E>(
+    mut values: Vec<Value>,
+    mut compare: impl FnMut(&Value, &Value) -> Result<Ordering, E>,
+) -> Result<Vec<Value>, (Vec<Value>, E)> {
+    let mut width = 1;
+    let mut scratch = Vec::with_capacity(values.len());
+    while width < values.len() {
+        let mut remaining = values.drain(..).peekable();
+        while remaining.peek().is_some() {
+            let left = remaining.by_ref().take(width).collect::<Vec<_>>();
+            let right = remaining.by_ref().take(width).collect::<Vec<_>>();
+            let mut left = left.into_iter().peekable();
+            let mut right = right.into_iter().peekable();
+            while let (Some(lhs), Some(rhs)) = (left.peek(), right.peek()) {
+                match compare(lhs, rhs) {
+                    Ok(Ordering::Greater) => scratch.extend(right.next()),
+                    Ok(_) => scratch.extend(left.next()),
+                    Err(err) => {
+                        scratch.extend(left);
+                        scratch.extend(right);
+                        scratch.extend(remaining);
+                        return Err((scratch, err));
+                    }
+                }
+            }
+            scratch.extend(left);
+            scratch.extend(right);
+        }
+        drop(remaining);
+        std::mem::swap(&mut values, &mut scratch);
+        width *= 2;
+    }
+    Ok(values)
+}
+
 impl FromIterator<Value> for List {
     fn from_iter<T: IntoIterator<Item = Value>>(iter: T) -> Self {
         Self(Mutex::new(VecDeque::from_iter(iter)))
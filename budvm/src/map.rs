@@ -2,10 +2,16 @@ use std::{
     collections::hash_map::RandomState,
     fmt::Debug,
     hash::BuildHasher,
-    sync::{Mutex, MutexGuard, PoisonError},
+    sync::{Mutex, MutexGuard, PoisonError, TryLockError},
 };
 
-use crate::{budmap::BudMap, symbol::Symbol, DynamicValue, Value};
+use crate::{
+    budmap::BudMap,
+    cost::charge,
+    memory::{allocate_values, try_allocate},
+    symbol::Symbol,
+    DynamicValue, List, Value, ValueIterator,
+};
 
 use super::{FaultKind, PoppedValues};
 
@@ -93,6 +99,16 @@ where
         self.0.lock().unwrap_or_else(PoisonError::into_inner)
     }
 
+    /// Locks the map unless it is already locked, such as while converting a
+    /// map that contains itself.
+    fn try_map(&self) -> Option<MutexGuard<'_, BudMap<Value, Value, State>>> {
+        match self.0.try_lock() {
+            Ok(map) => Some(map),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
     /// Returns the number of items contained.
     pub fn len(&self) -> usize {
         self.map().len()
@@ -109,8 +125,12 @@ where
     /// function checks that `key.implements_hash()` returns true. If it does
     /// not, [`FaultKind::ValueCannotBeHashed`] will be returned.
     pub fn insert(&self, k: Value, v: Value) -> Result<Option<Value>, FaultKind> {
+        let k = check_hashable(k)?;
         let mut map = self.map();
-        Ok(map.insert(check_hashable(k)?, v))
+        if map.get(&k).is_none() {
+            allocate_values(2)?;
+        }
+        Ok(map.insert(k, v))
     }
 
     /// Returns the value associated with `key`, if present.
@@ -125,6 +145,36 @@ where
         map.remove(key)
     }
 
+    /// Returns true if this map contains a value for `key`.
+    pub fn contains_key(&self, key: &Value) -> bool {
+        let map = self.map();
+        map.get(key).is_some()
+    }
+
+    /// Removes all entries from this map.
+    pub fn clear(&self) {
+        self.map().clear();
+    }
+
+    /// Returns the keys of this map, in the order they are stored.
+    pub fn keys(&self) -> List {
+        self.map().iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    /// Returns the values of this map, in the order they are stored.
+    pub fn values(&self) -> List {
+        self.map().iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    /// Returns a list of `[key, value]` lists for each entry in this map, in
+    /// the order they are stored.
+    pub fn entries(&self) -> List {
+        self.map()
+            .iter()
+            .map(|(key, value)| Value::dynamic(List::from_iter([key.clone(), value.clone()])))
+            .collect()
+    }
+
     /// Extracts the contained collection type.
     pub fn into_inner(self) -> BudMap<Value, Value, State> {
         self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
@@ -140,6 +190,7 @@ impl<'a> TryFrom<PoppedValues<'a>> for HashMap<RandomState> {
                 "odd number of arguments passed to map constructor",
             ));
         }
+        allocate_values(values.len())?;
         let mut map = BudMap::with_capacity(values.len() / 2);
 
         while let Some(key) = values.next() {
@@ -181,8 +232,19 @@ where
         Symbol::from("Map")
     }
 
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        if kind == "String" {
+            self.to_source().map(Value::dynamic)
+        } else {
+            None
+        }
+    }
+
     fn partial_eq(&self, other: &Value) -> Option<bool> {
         if let Some(other) = other.as_dynamic::<Self>() {
+            if std::ptr::eq(self, other) {
+                return Some(true);
+            }
             let lhs = self.map();
             let rhs = other.map();
             if lhs.len() == rhs.len() {
@@ -200,6 +262,7 @@ where
         }
     }
 
+    #[allow(clippy::too_many_lines)] // One arm per method.
     fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
         match name.as_str() {
             "count" => {
@@ -241,6 +304,81 @@ where
 
                 Ok(contained_value)
             }
+            "get_or" => {
+                let key = args.next_argument("key")?;
+                let default = args.next_argument("default")?;
+                args.verify_empty()?;
+
+                Ok(self.get(&key).unwrap_or(default))
+            }
+            "contains_key" => {
+                let key = args.next_argument("key")?;
+                args.verify_empty()?;
+
+                Ok(Value::Boolean(self.contains_key(&key)))
+            }
+            "keys" => {
+                args.verify_empty()?;
+                charge(self.len());
+                allocate_values(self.len())?;
+                Ok(Value::dynamic(self.keys()))
+            }
+            "values" => {
+                args.verify_empty()?;
+                charge(self.len());
+                allocate_values(self.len())?;
+                Ok(Value::dynamic(self.values()))
+            }
+            "entries" => {
+                args.verify_empty()?;
+                // Each entry is a list containing the key and value.
+                charge(self.len());
+                allocate_values(self.len().saturating_mul(3))?;
+                Ok(Value::dynamic(self.entries()))
+            }
+            "clear" => {
+                args.verify_empty()?;
+                self.clear();
+                Ok(Value::Void)
+            }
+            "merge" => {
+                let other = args.next_argument("other")?;
+                args.verify_empty()?;
+                let other = other.as_dynamic::<Self>().ok_or_else(|| {
+                    FaultKind::invalid_type(
+                        "expected a map. Found `@received-value` (@received-kind)",
+                        other.clone(),
+                    )
+                })?;
+                // Copy the entries before locking this map, as the two maps
+                // may be the same value.
+                let entries = other
+                    .map()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+                charge(entries.len());
+                let mut map = self.map();
+                for (key, value) in entries {
+                    if map.get(&key).is_none() {
+                        allocate_values(2)?;
+                    }
+                    map.insert(key, value);
+                }
+                Ok(Value::Void)
+            }
+            "remove_all" => {
+                let keys = args.next_argument("keys")?.iterate()?;
+                args.verify_empty()?;
+                let mut removed = 0;
+                while let Some(key) = keys.next_item()? {
+                    charge(1);
+                    if self.remove(&key).is_some() {
+                        removed += 1;
+                    }
+                }
+                Ok(Value::Integer(removed))
+            }
             _ => Err(FaultKind::UnknownFunction {
                 kind: super::ValueKind::Dynamic(self.kind()),
                 name: name.clone(),
@@ -248,7 +386,39 @@ where
         }
     }
 
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        Ok(self.get(index).unwrap_or_default())
+    }
+
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        self.insert(index, value)?;
+        Ok(())
+    }
+
+    fn iterate(&self) -> Result<Value, FaultKind> {
+        allocate_values(self.len().saturating_mul(3))?;
+        Ok(Value::dynamic(ValueIterator::new(
+            self.entries().into_inner(),
+        )))
+    }
+
     fn to_source(&self) -> Option<String> {
-        None
+        let map = self.try_map()?;
+        try_allocate(2)?;
+        let mut source = String::from("{");
+        for (index, (key, value)) in map.iter().enumerate() {
+            let key = key.to_source()?;
+            let value = value.to_source()?;
+            // Charge for the separators as well.
+            try_allocate(key.len() + value.len() + 4)?;
+            if index > 0 {
+                source.push_str(", ");
+            }
+            source.push_str(&key);
+            source.push_str(": ");
+            source.push_str(&value);
+        }
+        source.push('}');
+        Some(source)
     }
 }
@@ -0,0 +1,165 @@
+use std::{
+    cell::{Cell, RefCell},
+    mem::size_of,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{FaultKind, Value};
+
+/// The number of bytes charged for each [`Value`] stored in a collection.
+pub(crate) const VALUE_SIZE: usize = size_of::<Value>();
+
+/// A budget of bytes that the built-in `String`, `List`, and `HashMap` types
+/// may allocate while a virtual machine is executing.
+///
+/// An [`Environment`](crate::Environment) enables accounting by returning a
+/// budget from
+/// [`Environment::allocation_budget()`](crate::Environment::allocation_budget).
+/// Each allocation made by a built-in operation is charged against the budget.
+/// If a charge would exceed the budget, [`FaultKind::OutOfMemory`] is raised.
+/// Allocations that can grow without bound, such as repeating a string or
+/// pushing to a list, are charged before they are made. Values are charged
+/// using the size of [`Value`] rather than any memory they refer to.
+///
+/// This is not a limit on the memory in use: charges are never credited back
+/// when values are dropped, cleared, or removed from a collection. A script
+/// that repeatedly allocates and discards values will exhaust its budget even
+/// though it never holds more than a small amount of memory at once. Size the
+/// budget for the total amount a single execution may allocate, and use
+/// [`reset()`](Self::reset) to clear the charges, such as between executions.
+///
+/// This type can be cheaply cloned, and all clones share the same accounting.
+#[derive(Debug, Clone)]
+pub struct AllocationBudget(Arc<Accounting>);
+
+#[derive(Debug)]
+struct Accounting {
+    budget: AtomicUsize,
+    allocated: AtomicUsize,
+}
+
+impl AllocationBudget {
+    /// Returns a new budget that allows allocating a total of `budget` bytes.
+    #[must_use]
+    pub fn new(budget: usize) -> Self {
+        Self(Arc::new(Accounting {
+            budget: AtomicUsize::new(budget),
+            allocated: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the total number of bytes that can be allocated.
+    #[must_use]
+    pub fn budget(&self) -> usize {
+        self.0.budget.load(Ordering::Relaxed)
+    }
+
+    /// Sets the total number of bytes that can be allocated.
+    pub fn set_budget(&self, budget: usize) {
+        self.0.budget.store(budget, Ordering::Relaxed);
+    }
+
+    /// Returns the number of bytes that have been charged.
+    #[must_use]
+    pub fn allocated(&self) -> usize {
+        self.0.allocated.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes that can be charged before
+    /// [`FaultKind::OutOfMemory`] is raised.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.budget().saturating_sub(self.allocated())
+    }
+
+    /// Clears all charges made against this budget.
+    pub fn reset(&self) {
+        self.0.allocated.store(0, Ordering::Relaxed);
+    }
+
+    /// Charges `bytes` against this budget. If the total charged would exceed
+    /// the budget, nothing is charged and [`FaultKind::OutOfMemory`] is
+    /// returned.
+    pub fn charge(&self, bytes: usize) -> Result<(), FaultKind> {
+        let budget = self.budget();
+        self.0
+            .allocated
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |allocated| {
+                allocated
+                    .checked_add(bytes)
+                    .filter(|allocated| *allocated <= budget)
+            })
+            .map(|_| ())
+            .map_err(|_| FaultKind::OutOfMemory(budget))
+    }
+}
+
+thread_local! {
+    static ACTIVE_BUDGET: RefCell<Option<AllocationBudget>> = const { RefCell::new(None) };
+    static REFUSED: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Charges `bytes` against the [`AllocationBudget`] of the virtual machine
+/// executing on the current thread. If no virtual machine is executing or its
+/// environment has no budget, this function does nothing.
+///
+/// Built-in types call this before allocating. Custom
+/// [`DynamicValue`](crate::DynamicValue)s can call this to have their own
+/// allocations budgeted.
+pub fn allocate(bytes: usize) -> Result<(), FaultKind> {
+    ACTIVE_BUDGET.with(|active| match &*active.borrow() {
+        Some(budget) => budget.charge(bytes),
+        None => Ok(()),
+    })
+}
+
+/// Charges `bytes` like [`allocate()`] from operations that can't return a
+/// fault, such as [`DynamicValue::to_source()`](crate::DynamicValue::to_source).
+/// If the charge is refused, None is returned and the fault is raised by the
+/// next call to [`take_refused()`].
+pub(crate) fn try_allocate(bytes: usize) -> Option<()> {
+    if allocate(bytes).is_ok() {
+        Some(())
+    } else {
+        ACTIVE_BUDGET.with(|active| {
+            let budget = active.borrow().as_ref().map(AllocationBudget::budget);
+            REFUSED.with(|refused| refused.set(budget));
+        });
+        None
+    }
+}
+
+/// Returns [`FaultKind::OutOfMemory`] if a charge made using
+/// [`try_allocate()`] has been refused since the last call.
+pub(crate) fn take_refused() -> Result<(), FaultKind> {
+    match REFUSED.with(Cell::take) {
+        Some(budget) => Err(FaultKind::OutOfMemory(budget)),
+        None => Ok(()),
+    }
+}
+
+/// Charges the memory needed to store `count` values.
+pub(crate) fn allocate_values(count: usize) -> Result<(), FaultKind> {
+    allocate(count.saturating_mul(VALUE_SIZE))
+}
+
+/// Makes `budget` the budget charged by [`allocate()`] on the current thread
+/// until the returned guard is dropped.
+pub(crate) fn activate(budget: Option<AllocationBudget>) -> ActiveBudget {
+    ActiveBudget(ACTIVE_BUDGET.with(|active| active.replace(budget)))
+}
+
+/// Restores the previously active [`AllocationBudget`] when dropped.
+#[must_use]
+pub(crate) struct ActiveBudget(Option<AllocationBudget>);
+
+impl Drop for ActiveBudget {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE_BUDGET.with(|active| *active.borrow_mut() = previous);
+        REFUSED.with(|refused| refused.set(None));
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+use crate::{memory::try_allocate, symbol::Symbol, DynamicValue, FaultKind, HashMap, Value};
+
+/// An instance of a user-defined record type, such as one declared with
+/// `struct` or `enum` in Bud.
+///
+/// A record has a fixed list of named fields. Fields are read and written
+/// using the index operator with either the field's name as a `String` or the
+/// field's 0-based position as an `Integer`.
+///
+/// Records created from an enum also have a variant, which is the name of the
+/// enum variant the record was created with.
+///
+/// When a record is the target of
+/// [`Instruction::CallInstance`](crate::Instruction::CallInstance), the
+/// virtual machine first looks for a function named `<kind>.<name>`. If one is
+/// found, it is called with the record as its first argument.
+///
+/// This type uses a [`Mutex`] for interior mutability.
+#[derive(Debug)]
+pub struct Record {
+    kind: Symbol,
+    variant: Option<Symbol>,
+    fields: Mutex<Vec<(Symbol, Value)>>,
+}
+
+impl Clone for Record {
+    fn clone(&self) -> Self {
+        Self {
+            kind: self.kind.clone(),
+            variant: self.variant.clone(),
+            fields: Mutex::new(self.fields().clone()),
+        }
+    }
+}
+
+impl Record {
+    /// Returns a new record of type `kind` containing `fields`.
+    pub fn new(kind: impl Into<Symbol>, fields: impl IntoIterator<Item = (Symbol, Value)>) -> Self {
+        Self {
+            kind: kind.into(),
+            variant: None,
+            fields: Mutex::new(fields.into_iter().collect()),
+        }
+    }
+
+    /// Sets the enum variant of this record and returns self.
+    #[must_use]
+    pub fn with_variant(mut self, variant: impl Into<Symbol>) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+
+    /// Returns the enum variant of this record, if it has one.
+    #[must_use]
+    pub const fn variant(&self) -> Option<&Symbol> {
+        self.variant.as_ref()
+    }
+
+    /// Returns the number of fields this record contains.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.fields().len()
+    }
+
+    /// Returns true if this record contains no fields.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields().is_empty()
+    }
+
+    fn fields(&self) -> MutexGuard<'_, Vec<(Symbol, Value)>> {
+        self.fields.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the fields unless they are already locked, such as while
+    /// converting a record that contains itself.
+    fn try_fields(&self) -> Option<MutexGuard<'_, Vec<(Symbol, Value)>>> {
+        match self.fields.try_lock() {
+            Ok(fields) => Some(fields),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Returns the names of this record's fields, in the order they were
+    /// declared.
+    #[must_use]
+    pub fn field_names(&self) -> Vec<Symbol> {
+        self.fields().iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Returns the value of the field `name`, if present.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.fields()
+            .iter()
+            .find_map(|(field, value)| (field == name).then(|| value.clone()))
+    }
+
+    /// Stores `value` in the field `name`. Returns false if this record has no
+    /// field named `name`.
+    pub fn set(&self, name: &str, value: Value) -> bool {
+        let mut fields = self.fields();
+        if let Some((_, contained)) = fields.iter_mut().find(|(field, _)| field == name) {
+            *contained = value;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn field_index(&self, index: &Value) -> Result<usize, FaultKind> {
+        let position = if let Value::Integer(position) = index {
+            usize::try_from(*position)
+                .ok()
+                .filter(|position| *position < self.len())
+        } else if let Some(name) = index.as_dynamic::<String>() {
+            self.fields()
+                .iter()
+                .position(|(field, _)| field == name.as_str())
+        } else {
+            return Err(FaultKind::invalid_type(
+                "fields are accessed by name or position. Found `@received-value` (@received-kind)",
+                index.clone(),
+            ));
+        };
+        position.ok_or_else(|| self.unknown_field(index.clone()))
+    }
+
+    fn unknown_field(&self, index: Value) -> FaultKind {
+        FaultKind::invalid_type(
+            format!("{} has no field @received-value", self.name()),
+            index,
+        )
+    }
+
+    /// Returns the name of this record's type, including its variant.
+    fn name(&self) -> String {
+        match &self.variant {
+            Some(variant) => format!("{}.{variant}", self.kind),
+            None => self.kind.to_string(),
+        }
+    }
+}
+
+impl DynamicValue for Record {
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    fn kind(&self) -> Symbol {
+        self.kind.clone()
+    }
+
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        match kind.as_str() {
+            "String" => self.to_source().map(Value::dynamic),
+            "Map" => {
+                let map = HashMap::new();
+                for (name, value) in self.fields().iter() {
+                    map.insert(Value::dynamic(name.to_string()), value.clone())
+                        .ok()?;
+                }
+                Some(Value::dynamic(map))
+            }
+            _ => None,
+        }
+    }
+
+    fn partial_eq(&self, other: &Value) -> Option<bool> {
+        let other = other.as_dynamic::<Self>()?;
+        if std::ptr::eq(self, other) {
+            return Some(true);
+        }
+        if self.kind != other.kind || self.variant != other.variant {
+            return Some(false);
+        }
+        Some(*self.fields() == *other.fields())
+    }
+
+    fn get_index(&self, index: &Value) -> Result<Value, FaultKind> {
+        let index = self.field_index(index)?;
+        Ok(self.fields()[index].1.clone())
+    }
+
+    fn set_index(&self, index: Value, value: Value) -> Result<(), FaultKind> {
+        let index = self.field_index(&index)?;
+        self.fields()[index].1 = value;
+        Ok(())
+    }
+
+    fn to_source(&self) -> Option<String> {
+        let fields = self.try_fields()?;
+        if self.variant.is_some() && fields.is_empty() {
+            // Variants without fields are referred to without parentheses.
+            return Some(self.name());
+        }
+        let mut source = format!("{}(", self.name());
+        try_allocate(source.len() + 1)?;
+        for (index, (_, value)) in fields.iter().enumerate() {
+            let value = value.to_source()?;
+            // Charge for the separator as well.
+            try_allocate(value.len() + 2)?;
+            if index > 0 {
+                source.push_str(", ");
+            }
+            source.push_str(&value);
+        }
+        source.push(')');
+        Some(source)
+    }
+}
@@ -4,7 +4,12 @@ use std::{
     hash::Hash,
 };
 
-use crate::{symbol::Symbol, DynamicValue, FaultKind, PoppedValues, Value, ValueKind};
+use crate::{
+    cost::charge,
+    memory::{allocate, allocate_values},
+    symbol::Symbol,
+    BigInteger, Decimal, DynamicValue, FaultKind, List, PoppedValues, Value, ValueKind,
+};
 
 /// A [`Display`] implementor that converts a string value to its literal form
 /// including wrapping double quotes.
@@ -23,6 +28,12 @@ impl<'a> Display for StringLiteralDisplay<'a> {
         f.write_char('"')?;
         for ch in self.0.chars() {
             match ch {
+                // Braces are escaped because they begin interpolated
+                // expressions in Bud string literals.
+                '"' | '\\' | '{' => {
+                    f.write_char('\\')?;
+                    f.write_char(ch)?;
+                }
                 ch if ch.is_alphanumeric() || ch == ' ' || ch.is_ascii_punctuation() => {
                     f.write_char(ch)?;
                 }
@@ -54,6 +65,14 @@ impl DynamicValue for String {
         Symbol::from("String")
     }
 
+    fn convert(&self, kind: &Symbol) -> Option<Value> {
+        match kind.as_str() {
+            "BigInteger" => self.trim().parse::<BigInteger>().ok().map(Value::dynamic),
+            "Decimal" => self.trim().parse::<Decimal>().ok().map(Value::dynamic),
+            _ => None,
+        }
+    }
+
     fn partial_eq(&self, other: &Value) -> Option<bool> {
         other.as_dynamic::<Self>().map(|other| self == other)
     }
@@ -62,27 +81,162 @@ impl DynamicValue for String {
         other.as_dynamic::<Self>().map(|other| self.cmp(other))
     }
 
+    #[allow(clippy::too_many_lines)] // One arm per method.
     fn call(&self, name: &Symbol, args: &mut PoppedValues<'_>) -> Result<Value, FaultKind> {
         match name.as_str() {
-            "replace" => {
-                let needle = args.next_argument("pattern")?;
-                let needle = needle.as_dynamic::<Self>().ok_or_else(|| {
+            "len" => {
+                args.verify_empty()?;
+                let len = i64::try_from(self.chars().count())
+                    .map_err(|_| FaultKind::ValueOutOfRange("len"))?;
+                Ok(Value::Integer(len))
+            }
+            "chars" => {
+                args.verify_empty()?;
+                let char_count = self.chars().count();
+                charge(char_count);
+                allocate(self.len())?;
+                allocate_values(char_count)?;
+                Ok(Value::dynamic(
+                    self.chars()
+                        .map(|ch| Value::dynamic(String::from(ch)))
+                        .collect::<List>(),
+                ))
+            }
+            "split" => {
+                let separator = string_argument(args, "separator")?;
+                args.verify_empty()?;
+                if separator.is_empty() {
+                    return Err(FaultKind::invalid_type(
+                        "separator must not be empty",
+                        Value::dynamic(separator),
+                    ));
+                }
+                charge(self.len());
+                string_list(self.split(separator.as_str()))
+            }
+            "lines" => {
+                args.verify_empty()?;
+                charge(self.len());
+                string_list(self.lines())
+            }
+            "trim" => {
+                args.verify_empty()?;
+                let trimmed = self.trim();
+                allocate(trimmed.len())?;
+                Ok(Value::dynamic(String::from(trimmed)))
+            }
+            "trim_start" => {
+                args.verify_empty()?;
+                let trimmed = self.trim_start();
+                allocate(trimmed.len())?;
+                Ok(Value::dynamic(String::from(trimmed)))
+            }
+            "trim_end" => {
+                args.verify_empty()?;
+                let trimmed = self.trim_end();
+                allocate(trimmed.len())?;
+                Ok(Value::dynamic(String::from(trimmed)))
+            }
+            "upper" => {
+                args.verify_empty()?;
+                charge(self.len());
+                allocate(self.len())?;
+                Ok(Value::dynamic(self.to_uppercase()))
+            }
+            "lower" => {
+                args.verify_empty()?;
+                charge(self.len());
+                allocate(self.len())?;
+                Ok(Value::dynamic(self.to_lowercase()))
+            }
+            "starts_with" => {
+                let prefix = string_argument(args, "prefix")?;
+                args.verify_empty()?;
+                Ok(Value::Boolean(self.starts_with(prefix.as_str())))
+            }
+            "ends_with" => {
+                let suffix = string_argument(args, "suffix")?;
+                args.verify_empty()?;
+                Ok(Value::Boolean(self.ends_with(suffix.as_str())))
+            }
+            "contains" => {
+                let pattern = string_argument(args, "pattern")?;
+                args.verify_empty()?;
+                charge(self.len());
+                Ok(Value::Boolean(self.contains(pattern.as_str())))
+            }
+            "find" => {
+                let pattern = string_argument(args, "pattern")?;
+                args.verify_empty()?;
+                charge(self.len());
+                match self.find(pattern.as_str()) {
+                    Some(byte_offset) => {
+                        let index = i64::try_from(self[..byte_offset].chars().count())
+                            .map_err(|_| FaultKind::ValueOutOfRange("index"))?;
+                        Ok(Value::Integer(index))
+                    }
+                    None => Ok(Value::Void),
+                }
+            }
+            "substring" => {
+                let start = char_index(&args.next_argument("start")?)?;
+                let end = args.next().map(|end| char_index(&end)).transpose()?;
+                args.verify_empty()?;
+                charge(self.len());
+                let char_count = self.chars().count();
+                let end = end.unwrap_or(char_count);
+                if start > end || end > char_count {
+                    return Err(FaultKind::ValueOutOfRange("index"));
+                }
+                let substring = self
+                    .chars()
+                    .skip(start)
+                    .take(end - start)
+                    .collect::<String>();
+                allocate(substring.len())?;
+                Ok(Value::dynamic(substring))
+            }
+            "repeat" => {
+                let count = args.next_argument("count")?;
+                args.verify_empty()?;
+                let count = count.as_i64().ok_or_else(|| {
                     FaultKind::invalid_type(
-                        "search pattern must be a string. Found `@received-value` (@received-kind)",
-                        needle.clone(),
+                        "count must be an integer. Found `@received-value` (@received-kind)",
+                        count.clone(),
                     )
                 })?;
-                let replacement = args.next_argument("replacement")?;
-                let replacement = replacement.as_dynamic::<Self>().ok_or_else(|| {
+                self.checked_mul(&Value::Integer(count), false)?
+                    .ok_or(FaultKind::ValueOutOfRange("count"))
+            }
+            "parse_integer" => {
+                args.verify_empty()?;
+                self.trim().parse::<i64>().map(Value::Integer).map_err(|_| {
                     FaultKind::invalid_type(
-                        "replacement must be a string. Found `@received-value` (@received-kind)",
-                        replacement.clone(),
+                        "`@received-value` is not a valid integer",
+                        Value::dynamic(self.clone()),
                     )
-                })?;
-
+                })
+            }
+            "parse_real" => {
+                args.verify_empty()?;
+                self.trim().parse::<f64>().map(Value::Real).map_err(|_| {
+                    FaultKind::invalid_type(
+                        "`@received-value` is not a valid real number",
+                        Value::dynamic(self.clone()),
+                    )
+                })
+            }
+            "replace" => {
+                let needle = string_argument(args, "pattern")?;
+                let replacement = string_argument(args, "replacement")?;
                 args.verify_empty()?;
 
-                Ok(Value::dynamic(self.replace(needle, replacement)))
+                let matches = self.matches(needle.as_str()).count();
+                let replaced_length = (self.len() - matches * needle.len())
+                    .saturating_add(matches.saturating_mul(replacement.len()));
+                charge(self.len().saturating_add(replaced_length));
+                allocate(replaced_length)?;
+                Ok(Value::dynamic(self.replace(&needle, &replacement)))
             }
             _ => Err(FaultKind::UnknownFunction {
                 kind: ValueKind::Dynamic(self.kind()),
@@ -100,6 +254,8 @@ impl DynamicValue for String {
             // We can ignore is_reverse because when both lhs and rhs are the
             // same dynamic type, we never return Ok(None), so the reverse
             // operation will not be attempted.
+            charge(self.len().saturating_add(other.len()));
+            allocate(self.len().saturating_add(other.len()))?;
             let combined = [self.as_str(), other.as_str()].join("");
             Ok(Some(Value::dynamic(combined)))
         } else {
@@ -113,6 +269,8 @@ impl DynamicValue for String {
             .and_then(|repeat| usize::try_from(repeat).ok())
         {
             if let Some(total_length) = self.len().checked_mul(repeat) {
+                charge(total_length);
+                allocate(total_length)?;
                 let mut repeated = String::with_capacity(total_length);
                 for _ in 0..repeat {
                     repeated.push_str(self);
@@ -132,3 +290,40 @@ impl DynamicValue for String {
         true
     }
 }
+
+/// Returns the next argument as a String, or returns a
+/// [`FaultKind::InvalidType`] if it is not a string.
+fn string_argument(args: &mut PoppedValues<'_>, name: &str) -> Result<String, FaultKind> {
+    let value = args.next_argument(name)?;
+    match value.into_dynamic::<String>() {
+        Ok(value) => Ok(value),
+        Err(value) => Err(FaultKind::invalid_type(
+            format!("{name} must be a string. Found `@received-value` (@received-kind)"),
+            value,
+        )),
+    }
+}
+
+/// Converts `index` into a character position. Negative indices are converted
+/// to a position that is always out of bounds.
+fn char_index(index: &Value) -> Result<usize, FaultKind> {
+    let index = index.as_i64().ok_or_else(|| {
+        FaultKind::invalid_type(
+            "index must be an integer. Found `@received-value` (@received-kind)",
+            index.clone(),
+        )
+    })?;
+    Ok(usize::try_from(index).unwrap_or(usize::MAX))
+}
+
+fn string_list<'a>(parts: impl Iterator<Item = &'a str>) -> Result<Value, FaultKind> {
+    let parts = parts.collect::<Vec<_>>();
+    allocate(parts.iter().map(|part| part.len()).sum())?;
+    allocate_values(parts.len())?;
+    Ok(Value::dynamic(
+        parts
+            .into_iter()
+            .map(|part| Value::dynamic(String::from(part)))
+            .collect::<List>(),
+    ))
+}
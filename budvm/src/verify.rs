@@ -0,0 +1,640 @@
+use std::fmt::Display;
+
+use crate::{
+    CompareAction, Destination, Function, Instruction, Symbol, ValueOrSource, VtableEntry,
+};
+
+/// The result of statically verifying instructions before executing them.
+///
+/// Verification checks that every variable, argument, vtable index, and jump
+/// target used by the instructions is valid, that each function call passes
+/// the number of arguments the called function expects, and that the stack
+/// never underflows and has a consistent depth regardless of which path was
+/// taken to reach an instruction.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerificationError {
+    /// Every issue found, in the order of the functions and instructions
+    /// that contain them.
+    pub issues: Vec<VerificationIssue>,
+}
+
+impl VerificationError {
+    pub(crate) fn from_issues(issues: Vec<VerificationIssue>) -> Result<(), Self> {
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(Self { issues })
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "verification found {} issue(s)", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An issue found while verifying instructions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerificationIssue {
+    /// The name of the function containing the instruction, if the
+    /// instructions being verified belong to a function.
+    pub function: Option<Symbol>,
+    /// The index of the instruction.
+    pub instruction_index: usize,
+    /// The kind of issue found.
+    pub kind: VerificationIssueKind,
+}
+
+impl Display for VerificationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(function) = &self.function {
+            write!(f, "{function}:{}: {}", self.instruction_index, self.kind)
+        } else {
+            write!(f, "{}: {}", self.instruction_index, self.kind)
+        }
+    }
+}
+
+/// A kind of [`VerificationIssue`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VerificationIssueKind {
+    /// A variable index is out of range.
+    InvalidVariableIndex(usize),
+    /// An argument index is out of range.
+    InvalidArgumentIndex(usize),
+    /// A vtable index does not refer to a defined function.
+    InvalidVtableIndex(usize),
+    /// A recursive [`Instruction::Call`] or [`Instruction::TailCall`] was made
+    /// outside of a function.
+    NoCurrentFunction,
+    /// A jump targets an instruction beyond the end of the instructions.
+    InvalidJumpTarget(usize),
+    /// A function was called with a different number of arguments than it
+    /// expects.
+    ArgumentCountMismatch {
+        /// The number of arguments the function expects.
+        expected: usize,
+        /// The number of arguments passed to the function.
+        received: usize,
+    },
+    /// A closure captures more values than its function accepts arguments.
+    TooManyCaptures {
+        /// The number of arguments the function accepts.
+        arg_count: usize,
+        /// The number of values captured.
+        capture_count: usize,
+    },
+    /// An instruction pops more values than have been pushed.
+    StackUnderflow {
+        /// The number of values the instruction pops.
+        required: usize,
+        /// The number of values available.
+        available: usize,
+    },
+    /// An instruction can be reached with different numbers of values on the
+    /// stack.
+    InconsistentStackDepth {
+        /// The depth the instruction was first reached with.
+        expected: usize,
+        /// The conflicting depth.
+        received: usize,
+    },
+}
+
+impl Display for VerificationIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationIssueKind::InvalidVariableIndex(index) => {
+                write!(f, "invalid variable index {index}")
+            }
+            VerificationIssueKind::InvalidArgumentIndex(index) => {
+                write!(f, "invalid argument index {index}")
+            }
+            VerificationIssueKind::InvalidVtableIndex(index) => {
+                write!(f, "invalid vtable index {index}")
+            }
+            VerificationIssueKind::NoCurrentFunction => {
+                f.write_str("recursive call outside of a function")
+            }
+            VerificationIssueKind::InvalidJumpTarget(target) => {
+                write!(f, "jump to invalid instruction {target}")
+            }
+            VerificationIssueKind::ArgumentCountMismatch { expected, received } => {
+                write!(
+                    f,
+                    "expected {expected} arguments, but {received} are passed"
+                )
+            }
+            VerificationIssueKind::TooManyCaptures {
+                arg_count,
+                capture_count,
+            } => write!(
+                f,
+                "{capture_count} values captured for a function accepting {arg_count} arguments"
+            ),
+            VerificationIssueKind::StackUnderflow {
+                required,
+                available,
+            } => write!(
+                f,
+                "{required} values are popped, but only {available} are on the stack"
+            ),
+            VerificationIssueKind::InconsistentStackDepth { expected, received } => write!(
+                f,
+                "reached with {received} values on the stack, but previously {expected}"
+            ),
+        }
+    }
+}
+
+/// The instructions being verified and the context they execute within.
+pub(crate) struct Verifier<'a, Intrinsic> {
+    pub function: Option<&'a Function<Intrinsic>>,
+    pub code: &'a [Instruction<Intrinsic>],
+    pub arg_count: usize,
+    pub variable_count: usize,
+    pub vtable: &'a [VtableEntry<Intrinsic>],
+}
+
+impl<'a, Intrinsic> Verifier<'a, Intrinsic> {
+    pub fn for_function(
+        function: &'a Function<Intrinsic>,
+        vtable: &'a [VtableEntry<Intrinsic>],
+    ) -> Self {
+        Self {
+            function: Some(function),
+            code: &function.code,
+            arg_count: function.arg_count,
+            variable_count: function.variable_count,
+            vtable,
+        }
+    }
+
+    /// Verifies the instructions, appending all issues found to `issues`.
+    pub fn verify(&self, issues: &mut Vec<VerificationIssue>) {
+        let first_issue = issues.len();
+        let mut report = |instruction_index: usize, kind: VerificationIssueKind| {
+            issues.push(VerificationIssue {
+                function: self.function.map(|function| function.name.clone()),
+                instruction_index,
+                kind,
+            });
+        };
+
+        for (index, instruction) in self.code.iter().enumerate() {
+            self.verify_indices(instruction, &mut |kind| report(index, kind));
+        }
+        self.verify_stack(&mut report);
+
+        // Issues are reported in instruction order, regardless of the order
+        // the instructions were visited.
+        issues[first_issue..].sort_by_key(|issue| issue.instruction_index);
+    }
+
+    #[allow(clippy::too_many_lines)] // Most are straight mappings...
+    fn verify_indices(
+        &self,
+        instruction: &Instruction<Intrinsic>,
+        report: &mut impl FnMut(VerificationIssueKind),
+    ) {
+        let mut values = Vec::new();
+        let mut destinations = Vec::new();
+        let mut jump_targets = Vec::new();
+        match instruction {
+            Instruction::Add {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Sub {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Multiply {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Divide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::IntegerDivide {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Remainder {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::Power {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::LogicalXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseAnd {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseOr {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::BitwiseXor {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftLeft {
+                left,
+                right,
+                destination,
+            }
+            | Instruction::ShiftRight {
+                left,
+                right,
+                destination,
+            } => {
+                values.push(left);
+                values.push(right);
+                destinations.push(*destination);
+            }
+            Instruction::LogicalNot {
+                value: v,
+                destination,
+            }
+            | Instruction::BitwiseNot {
+                value: v,
+                destination,
+            }
+            | Instruction::Negate {
+                value: v,
+                destination,
+            }
+            | Instruction::Convert {
+                value: v,
+                destination,
+                ..
+            } => {
+                values.push(v);
+                destinations.push(*destination);
+            }
+            Instruction::If {
+                condition,
+                false_jump_to,
+            } => {
+                values.push(condition);
+                jump_targets.push(*false_jump_to);
+            }
+            Instruction::JumpTo(target) => jump_targets.push(*target),
+            Instruction::Compare {
+                left,
+                right,
+                action,
+                ..
+            } => {
+                values.push(left);
+                values.push(right);
+                match action {
+                    CompareAction::Store(destination) => destinations.push(*destination),
+                    CompareAction::JumpIfFalse(target) => jump_targets.push(*target),
+                }
+            }
+            Instruction::Push(v) => values.push(v),
+            Instruction::Load {
+                value: v,
+                variable_index,
+            } => {
+                values.push(v);
+                destinations.push(Destination::Variable(*variable_index));
+            }
+            Instruction::Return(v) => {
+                if let Some(v) = v {
+                    values.push(v);
+                }
+            }
+            Instruction::Call {
+                vtable_index,
+                arg_count,
+                ..
+            }
+            | Instruction::TailCall {
+                vtable_index,
+                arg_count,
+            } => {
+                if let Some(vtable_index) = vtable_index {
+                    match self.vtable.get(*vtable_index) {
+                        Some(VtableEntry::Function(function))
+                            if function.arg_count != *arg_count =>
+                        {
+                            report(VerificationIssueKind::ArgumentCountMismatch {
+                                expected: function.arg_count,
+                                received: *arg_count,
+                            });
+                        }
+                        Some(_) => {}
+                        None => report(VerificationIssueKind::InvalidVtableIndex(*vtable_index)),
+                    }
+                } else if let Some(function) = self.function {
+                    if function.arg_count != *arg_count {
+                        report(VerificationIssueKind::ArgumentCountMismatch {
+                            expected: function.arg_count,
+                            received: *arg_count,
+                        });
+                    }
+                } else {
+                    report(VerificationIssueKind::NoCurrentFunction);
+                }
+                if let Instruction::Call { destination, .. } = instruction {
+                    destinations.push(*destination);
+                }
+            }
+            Instruction::CallIntrinsic { destination, .. } => destinations.push(*destination),
+            Instruction::CallInstance {
+                target,
+                destination,
+                ..
+            } => {
+                if let Some(target) = target {
+                    values.push(target);
+                }
+                destinations.push(*destination);
+            }
+            Instruction::CallValue {
+                target,
+                destination,
+                ..
+            } => {
+                values.push(target);
+                destinations.push(*destination);
+            }
+            Instruction::NewClosure {
+                vtable_index,
+                capture_count,
+                destination,
+            } => {
+                match self.vtable.get(*vtable_index) {
+                    Some(VtableEntry::Function(function))
+                        if function.arg_count < *capture_count =>
+                    {
+                        report(VerificationIssueKind::TooManyCaptures {
+                            arg_count: function.arg_count,
+                            capture_count: *capture_count,
+                        });
+                    }
+                    Some(_) => {}
+                    None => report(VerificationIssueKind::InvalidVtableIndex(*vtable_index)),
+                }
+                destinations.push(*destination);
+            }
+            Instruction::GetIndex {
+                target,
+                index,
+                destination,
+            } => {
+                values.push(target);
+                values.push(index);
+                destinations.push(*destination);
+            }
+            Instruction::SetIndex {
+                target,
+                index,
+                value,
+            } => {
+                values.extend([target, index, value]);
+            }
+            Instruction::Iterate {
+                source,
+                destination,
+            } => {
+                values.push(source);
+                destinations.push(*destination);
+            }
+            Instruction::IterateNext {
+                iterator,
+                destination,
+                done_jump_to,
+            } => {
+                values.push(iterator);
+                destinations.push(*destination);
+                jump_targets.push(*done_jump_to);
+            }
+            Instruction::EnterTry {
+                handler,
+                destination,
+            } => {
+                destinations.push(*destination);
+                jump_targets.push(*handler);
+            }
+            Instruction::ExitTry => {}
+            Instruction::Throw(value) => values.push(value),
+        }
+
+        for value in values {
+            match value {
+                ValueOrSource::Argument(index) if *index >= self.arg_count => {
+                    report(VerificationIssueKind::InvalidArgumentIndex(*index));
+                }
+                ValueOrSource::Variable(index) if *index >= self.variable_count => {
+                    report(VerificationIssueKind::InvalidVariableIndex(*index));
+                }
+                _ => {}
+            }
+        }
+        for destination in destinations {
+            if let Destination::Variable(index) = destination {
+                if index >= self.variable_count {
+                    report(VerificationIssueKind::InvalidVariableIndex(index));
+                }
+            }
+        }
+        // Jumping to the end of the instructions is an implicit return.
+        for target in jump_targets {
+            if target > self.code.len() {
+                report(VerificationIssueKind::InvalidJumpTarget(target));
+            }
+        }
+    }
+
+    /// Walks every reachable instruction, tracking the number of values this
+    /// frame has pushed onto the stack.
+    fn verify_stack(&self, report: &mut impl FnMut(usize, VerificationIssueKind)) {
+        let mut depths = vec![None; self.code.len()];
+        let mut inconsistent = vec![false; self.code.len()];
+        let mut pending = vec![(0, 0)];
+        while let Some((index, depth)) = pending.pop() {
+            let instruction = if let Some(instruction) = self.code.get(index) {
+                instruction
+            } else {
+                // Implicit return.
+                continue;
+            };
+            match depths[index] {
+                None => depths[index] = Some(depth),
+                Some(expected) => {
+                    if expected != depth && !inconsistent[index] {
+                        inconsistent[index] = true;
+                        report(
+                            index,
+                            VerificationIssueKind::InconsistentStackDepth {
+                                expected,
+                                received: depth,
+                            },
+                        );
+                    }
+                    continue;
+                }
+            }
+
+            let (popped, pushed) = stack_effect(instruction);
+            let depth = if let Some(remaining) = depth.checked_sub(popped) {
+                remaining + pushed
+            } else {
+                report(
+                    index,
+                    VerificationIssueKind::StackUnderflow {
+                        required: popped,
+                        available: depth,
+                    },
+                );
+                pushed
+            };
+
+            match instruction {
+                Instruction::Return(_) | Instruction::TailCall { .. } | Instruction::Throw(_) => {}
+                Instruction::JumpTo(target) => pending.push((*target, depth)),
+                Instruction::If { false_jump_to, .. }
+                | Instruction::Compare {
+                    action: CompareAction::JumpIfFalse(false_jump_to),
+                    ..
+                } => {
+                    pending.push((*false_jump_to, depth));
+                    pending.push((index + 1, depth));
+                }
+                // Nothing is stored when the iterator is finished.
+                Instruction::IterateNext { done_jump_to, .. } => {
+                    pending.push((*done_jump_to, depth - pushed));
+                    pending.push((index + 1, depth));
+                }
+                // When a fault is caught, the stack is restored to the depth
+                // it had when the handler was registered before the caught
+                // value is stored.
+                Instruction::EnterTry {
+                    handler,
+                    destination,
+                } => {
+                    let caught = usize::from(matches!(destination, Destination::Stack));
+                    pending.push((*handler, depth + caught));
+                    pending.push((index + 1, depth));
+                }
+                _ => pending.push((index + 1, depth)),
+            }
+        }
+    }
+}
+
+/// Returns the number of values `instruction` pops from and pushes onto the
+/// stack.
+fn stack_effect<Intrinsic>(instruction: &Instruction<Intrinsic>) -> (usize, usize) {
+    let pushes = |destination: &Destination| usize::from(matches!(destination, Destination::Stack));
+    match instruction {
+        Instruction::Add { destination, .. }
+        | Instruction::Sub { destination, .. }
+        | Instruction::Multiply { destination, .. }
+        | Instruction::Divide { destination, .. }
+        | Instruction::IntegerDivide { destination, .. }
+        | Instruction::Remainder { destination, .. }
+        | Instruction::Power { destination, .. }
+        | Instruction::LogicalAnd { destination, .. }
+        | Instruction::LogicalOr { destination, .. }
+        | Instruction::LogicalXor { destination, .. }
+        | Instruction::BitwiseAnd { destination, .. }
+        | Instruction::BitwiseOr { destination, .. }
+        | Instruction::BitwiseXor { destination, .. }
+        | Instruction::ShiftLeft { destination, .. }
+        | Instruction::ShiftRight { destination, .. }
+        | Instruction::LogicalNot { destination, .. }
+        | Instruction::BitwiseNot { destination, .. }
+        | Instruction::Negate { destination, .. }
+        | Instruction::Convert { destination, .. }
+        | Instruction::GetIndex { destination, .. }
+        | Instruction::Iterate { destination, .. }
+        | Instruction::IterateNext { destination, .. }
+        | Instruction::Compare {
+            action: CompareAction::Store(destination),
+            ..
+        } => (0, pushes(destination)),
+        Instruction::Push(_) => (0, 1),
+        Instruction::Call {
+            arg_count,
+            destination,
+            ..
+        }
+        | Instruction::CallIntrinsic {
+            arg_count,
+            destination,
+            ..
+        }
+        | Instruction::CallInstance {
+            target: Some(_),
+            arg_count,
+            destination,
+            ..
+        }
+        | Instruction::CallValue {
+            arg_count,
+            destination,
+            ..
+        }
+        | Instruction::NewClosure {
+            capture_count: arg_count,
+            destination,
+            ..
+        } => (*arg_count, pushes(destination)),
+        // The target is the value beneath the arguments.
+        Instruction::CallInstance {
+            target: None,
+            arg_count,
+            destination,
+            ..
+        } => (arg_count.saturating_add(1), pushes(destination)),
+        Instruction::TailCall { arg_count, .. } => (*arg_count, 0),
+        Instruction::If { .. }
+        | Instruction::JumpTo(_)
+        | Instruction::Compare { .. }
+        | Instruction::Load { .. }
+        | Instruction::SetIndex { .. }
+        | Instruction::EnterTry { .. }
+        | Instruction::ExitTry
+        | Instruction::Throw(_)
+        | Instruction::Return(_) => (0, 0),
+    }
+}